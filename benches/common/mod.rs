@@ -0,0 +1,45 @@
+//! the system allocator, keeping count, installed for every bench that
+//! includes this module
+
+// each bench only looks at some of the counts
+#![allow(dead_code)]
+
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+/// the system allocator, keeping count of allocations and the bytes in use
+struct Counting;
+static ALLOCATIONS: AtomicUsize = AtomicUsize::new(0);
+static IN_USE: AtomicUsize = AtomicUsize::new(0);
+static PEAK: AtomicUsize = AtomicUsize::new(0);
+unsafe impl GlobalAlloc for Counting {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        let in_use = IN_USE.fetch_add(layout.size(), Ordering::Relaxed) + layout.size();
+        PEAK.fetch_max(in_use, Ordering::Relaxed);
+        System.alloc(layout)
+    }
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        IN_USE.fetch_sub(layout.size(), Ordering::Relaxed);
+        System.dealloc(ptr, layout)
+    }
+}
+#[global_allocator]
+static GLOBAL: Counting = Counting;
+
+/// allocations made so far
+pub fn allocations() -> usize {
+    ALLOCATIONS.load(Ordering::Relaxed)
+}
+/// bytes allocated and not yet freed
+pub fn in_use() -> usize {
+    IN_USE.load(Ordering::Relaxed)
+}
+/// the most bytes in use at once since the last reset_peak
+pub fn peak() -> usize {
+    PEAK.load(Ordering::Relaxed)
+}
+/// start measuring the peak again from what's in use now
+pub fn reset_peak() {
+    PEAK.store(in_use(), Ordering::Relaxed);
+}
@@ -0,0 +1,47 @@
+//! round-tripping a 500x200 region through a file with 'o' and then 'i',
+//! counting how long it takes and how many allocations it makes
+//!
+//! run with `cargo bench --bench io`
+
+mod common;
+
+use refunge::{Arguments, Befunge};
+use std::time::Instant;
+
+const RUNS: u32 = 50;
+
+fn main() {
+    let path = std::env::temp_dir().join("refunge-bench-io.txt");
+    // 0gnirts, so the name pops off the stack the right way around
+    let name: String = path.to_str().unwrap().chars().rev().collect();
+    // o from (0, 0) to (499, 199), then i back in at (0, 0)
+    let code = format!("aa*5*1-aa*2*1-0000\"{name}\"o0000\"{name}\"i@");
+    let filler: String = (0..500)
+        .map(|x| b"0123456789 abc"[x % 14] as char)
+        .collect();
+    let program = [code]
+        .into_iter()
+        .chain(vec![filler; 199])
+        .collect::<Vec<_>>()
+        .join("\n");
+
+    let allocations = common::allocations();
+    let start = Instant::now();
+    for _ in 0..RUNS {
+        let args = Arguments {
+            quiet: true,
+            ..Default::default()
+        };
+        let mut befunge = Befunge::from_text(&program, args).unwrap();
+        befunge.capture_output();
+        befunge.run(|| true);
+    }
+    let elapsed = start.elapsed();
+    let allocations = common::allocations() - allocations;
+    std::fs::remove_file(path).unwrap_or(());
+    println!(
+        "500x200 region through o and i: {:?} and {} allocations per run",
+        elapsed / RUNS,
+        allocations / RUNS as usize
+    );
+}
@@ -0,0 +1,50 @@
+//! loading a 10 MB program from a file and running it (it ends right away),
+//! counting how long it takes and the most memory it holds at once
+//!
+//! run with `cargo bench --bench load`
+
+mod common;
+
+use refunge::{Arguments, Befunge};
+use std::time::Instant;
+
+const RUNS: u32 = 5;
+const SIZE: usize = 10 * 1024 * 1024;
+
+fn main() {
+    let path = std::env::temp_dir().join("refunge-bench-load.bf");
+    let row: String = (0..1000)
+        .map(|x| b"0123456789 abc"[x % 14] as char)
+        .collect();
+    let rows = SIZE / (row.len() + 1);
+    let program = ["@".to_string()]
+        .into_iter()
+        .chain(vec![row; rows - 1])
+        .collect::<Vec<_>>()
+        .join("\n");
+    std::fs::write(&path, program).unwrap();
+
+    let mut peak = 0;
+    let start = Instant::now();
+    for _ in 0..RUNS {
+        let before = common::in_use();
+        common::reset_peak();
+        let args = Arguments {
+            quiet: true,
+            file: Some(path.to_string_lossy().into_owned()),
+            ..Default::default()
+        };
+        let mut befunge = Befunge::new(args).unwrap();
+        befunge.capture_output();
+        befunge.run(|| true);
+        drop(befunge);
+        peak = peak.max(common::peak() - before);
+    }
+    let elapsed = start.elapsed();
+    std::fs::remove_file(path).unwrap_or(());
+    println!(
+        "10 MB program: {:?} to load and run, {} MB of memory at most",
+        elapsed / RUNS,
+        peak / (1024 * 1024)
+    );
+}
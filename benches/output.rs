@@ -0,0 +1,44 @@
+//! printing a million characters in quiet mode, one ',' at a time, through
+//! the same buffered writer that stdout gets, against keeping them in memory
+//!
+//! run with `cargo bench --bench output`
+
+use refunge::{Arguments, Befunge};
+use std::path::Path;
+use std::time::{Duration, Instant};
+
+const RUNS: u32 = 3;
+const CHARS: u64 = 1_000_000;
+
+/// how long the program takes per run, written to path or captured if there's none
+fn time(program: &str, path: Option<&Path>) -> Duration {
+    let start = Instant::now();
+    for _ in 0..RUNS {
+        let args = Arguments {
+            quiet: true,
+            output: path.map(|path| path.to_string_lossy().into_owned()),
+            ..Default::default()
+        };
+        let mut befunge = Befunge::from_text(program, args).unwrap();
+        if path.is_none() {
+            befunge.capture_output();
+        }
+        befunge.run(|| true);
+        befunge.flush();
+    }
+    start.elapsed() / RUNS
+}
+
+fn main() {
+    let path = std::env::temp_dir().join("refunge-bench-output.txt");
+    // count down from a million, printing an 'a' each time around
+    let program = "aa*:*aa**  v\n -1,\"a\"_:@#<";
+
+    let written = time(program, Some(&path));
+    let size = std::fs::metadata(&path).unwrap().len();
+    std::fs::remove_file(&path).unwrap_or(());
+    assert_eq!(size, CHARS);
+    let captured = time(program, None);
+    println!("{CHARS} characters, written out: {written:?} per run");
+    println!("{CHARS} characters, captured:    {captured:?} per run");
+}
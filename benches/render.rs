@@ -0,0 +1,39 @@
+//! drawing a 200x60 grid with a dozen IPs on it, the way the tui does every frame,
+//! counting how long it takes and how many allocations it makes
+//!
+//! run with `cargo bench --bench render`
+
+mod common;
+
+use ratatui::{backend::TestBackend, Terminal};
+use refunge::{FungeGrid, FungeVector, InstructionPointer};
+use std::collections::VecDeque;
+use std::time::Instant;
+
+const FRAMES: usize = 200;
+
+fn main() {
+    let row: String = (0..200)
+        .map(|x| b"0123456789+-*/"[x % 14] as char)
+        .collect();
+    let grid = FungeGrid::new(vec![row; 60].join("\n"));
+    let ips: VecDeque<InstructionPointer> = (0..12)
+        .map(|n| InstructionPointer::new(FungeVector(n * 16, n * 5), FungeVector(1, 0), n as usize))
+        .collect();
+    let mut terminal = Terminal::new(TestBackend::new(202, 62)).unwrap();
+
+    let allocations = common::allocations();
+    let start = Instant::now();
+    for _ in 0..FRAMES {
+        terminal
+            .draw(|f| f.render_widget(grid.clone().highlights(ips.clone()), f.size()))
+            .unwrap();
+    }
+    let elapsed = start.elapsed();
+    let allocations = common::allocations() - allocations;
+    println!(
+        "200x60 grid, 12 ips: {:?} and {} allocations per frame",
+        elapsed / FRAMES as u32,
+        allocations / FRAMES
+    );
+}
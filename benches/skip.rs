@@ -0,0 +1,54 @@
+//! skipping a 100,000 cell gap of spaces, the old way (walking the cells
+//! one at a time) against the skip index, and then a whole program doing it
+//!
+//! run with `cargo bench --bench skip`
+
+use refunge::{Arguments, Befunge, FungeGrid, FungeVector};
+use std::hint::black_box;
+use std::time::{Duration, Instant};
+
+const WIDTH: usize = 100_000;
+const PASSES: u32 = 1000;
+
+/// how long f takes per run, averaged over PASSES runs
+fn time(f: impl Fn()) -> Duration {
+    let start = Instant::now();
+    for _ in 0..PASSES {
+        f();
+    }
+    start.elapsed() / PASSES
+}
+
+fn main() {
+    let program = format!("1+{}", " ".repeat(WIDTH - 2));
+    let grid = FungeGrid::new(program.clone());
+    let (start, east) = (FungeVector(1, 0), FungeVector(1, 0));
+
+    let walk = time(|| {
+        let mut pos = grid.wrap(start, east, 1);
+        while grid.char_at(pos) == ' ' {
+            pos = grid.wrap(pos, east, 1);
+        }
+        black_box(pos);
+    });
+    let skip = time(|| {
+        black_box(grid.next_non_space(start, east));
+    });
+    println!("1x{WIDTH} gap, walking:  {walk:?}");
+    println!("1x{WIDTH} gap, skipping: {skip:?}");
+
+    let args = Arguments {
+        quiet: true,
+        max_ticks: Some(2 * PASSES),
+        ..Default::default()
+    };
+    let mut befunge = Befunge::from_text(&program, args).unwrap();
+    befunge.capture_output();
+    let start = Instant::now();
+    befunge.run(|| true);
+    println!(
+        "1x{WIDTH} program, {} passes: {:?}",
+        PASSES,
+        start.elapsed()
+    );
+}
@@ -0,0 +1,191 @@
+//! ANSI escape sequences in program output, kept out of the output pane
+//! where they'd scramble the screen, or turned into styles with --ansi
+//!
+//! output itself (stdout in quiet mode, and the --output file) keeps them as
+//! they were written
+
+use ratatui::style::{Color, Modifier, Style};
+use std::borrow::Cow;
+
+const ESC: char = '\x1b';
+
+/// a piece of text split up by escape sequences
+enum Piece<'t> {
+    Text(&'t str),
+    /// a complete SGR sequence, with what's between the [ and the m
+    Sgr(&'t str),
+    /// any other sequence, or the start of one that hasn't been finished yet
+    Other,
+}
+
+/// text split into runs of text and the sequences between them
+fn pieces(text: &str) -> impl Iterator<Item = Piece<'_>> {
+    let mut rest = text;
+    std::iter::from_fn(move || {
+        if rest.is_empty() {
+            return None;
+        }
+        let Some(rest_after) = rest.strip_prefix(ESC) else {
+            let end = rest.find(ESC).unwrap_or(rest.len());
+            let (text, after) = rest.split_at(end);
+            rest = after;
+            return Some(Piece::Text(text));
+        };
+        let mut chars = rest_after.char_indices();
+        let (end, piece) = match chars.next() {
+            // CSI, up to a final byte from @ to ~
+            Some((_, '[')) => match chars.find(|(_, c)| ('\x40'..='\x7e').contains(c)) {
+                Some((at, 'm')) => (at + 1, Piece::Sgr(&rest_after[1..at])),
+                Some((at, _)) => (at + 1, Piece::Other),
+                None => (rest_after.len(), Piece::Other),
+            },
+            // OSC, up to a BEL or ESC \
+            Some((_, ']')) => {
+                let mut last = ']';
+                let end = chars.find(|&(_, c)| {
+                    let done = c == '\x07' || (c == '\\' && last == ESC);
+                    last = c;
+                    done
+                });
+                (end.map_or(rest_after.len(), |(at, _)| at + 1), Piece::Other)
+            }
+            // anything else is just the one character after the ESC
+            Some((_, c)) => (c.len_utf8(), Piece::Other),
+            None => (0, Piece::Other),
+        };
+        rest = &rest_after[end..];
+        Some(piece)
+    })
+}
+
+/// text without any escape sequences, including one cut off at the end
+///
+/// ```
+/// use refunge::ansi;
+///
+/// assert_eq!(ansi::strip("\x1b[31mred\x1b[0m \x1b[2;5Hmoved\x1b[3"), "red moved");
+/// assert_eq!(ansi::strip("\x1b]0;title\x07plain"), "plain");
+/// ```
+pub fn strip(text: &str) -> Cow<'_, str> {
+    if !text.contains(ESC) {
+        return Cow::Borrowed(text);
+    }
+    let mut stripped = String::with_capacity(text.len());
+    for piece in pieces(text) {
+        if let Piece::Text(text) = piece {
+            stripped.push_str(text);
+        }
+    }
+    Cow::Owned(stripped)
+}
+
+/// text split into runs in the style the SGR sequences before them pick,
+/// starting from and updating sgr, with every other sequence dropped
+///
+/// the styles only hold what the sequences set, to be patched over whatever
+/// the text is drawn in anyway
+///
+/// ```
+/// use ratatui::style::{Color, Modifier, Style};
+/// use refunge::ansi;
+///
+/// let mut sgr = Style::default();
+/// let runs = ansi::styled("\x1b[1;31mred\x1b[0m \x1b[2;5H\x1b[38;5;4mblue\x1b[", &mut sgr);
+/// let red = Style::default().fg(Color::Red).add_modifier(Modifier::BOLD);
+/// assert_eq!(runs, [(red, "red"), (Style::default(), " "), (Style::default().fg(Color::Blue), "blue")]);
+/// // the color carries on into whatever comes next
+/// assert_eq!(ansi::styled("still blue", &mut sgr), [(Style::default().fg(Color::Blue), "still blue")]);
+/// ```
+pub fn styled<'t>(text: &'t str, sgr: &mut Style) -> Vec<(Style, &'t str)> {
+    let mut runs = vec![];
+    for piece in pieces(text) {
+        match piece {
+            Piece::Text(text) => runs.push((*sgr, text)),
+            Piece::Sgr(params) => apply(sgr, params),
+            Piece::Other => {}
+        }
+    }
+    runs
+}
+
+/// the style the SGR sequences in text leave things in, starting from sgr
+pub fn style_after(text: &str, mut sgr: Style) -> Style {
+    for piece in pieces(text) {
+        if let Piece::Sgr(params) = piece {
+            apply(&mut sgr, params);
+        }
+    }
+    sgr
+}
+
+/// update sgr by the ;-separated parameters of an SGR sequence
+fn apply(sgr: &mut Style, params: &str) {
+    let mut params = params
+        .split([';', ':'])
+        .map(|p| p.parse::<u8>().unwrap_or(0));
+    // no parameters at all means reset, just like a single 0
+    let mut next = params.next();
+    while let Some(param) = next {
+        match param {
+            0 => *sgr = Style::default(),
+            1 => *sgr = sgr.add_modifier(Modifier::BOLD),
+            2 => *sgr = sgr.add_modifier(Modifier::DIM),
+            3 => *sgr = sgr.add_modifier(Modifier::ITALIC),
+            4 => *sgr = sgr.add_modifier(Modifier::UNDERLINED),
+            5 | 6 => *sgr = sgr.add_modifier(Modifier::SLOW_BLINK),
+            7 => *sgr = sgr.add_modifier(Modifier::REVERSED),
+            9 => *sgr = sgr.add_modifier(Modifier::CROSSED_OUT),
+            22 => *sgr = sgr.remove_modifier(Modifier::BOLD | Modifier::DIM),
+            23 => *sgr = sgr.remove_modifier(Modifier::ITALIC),
+            24 => *sgr = sgr.remove_modifier(Modifier::UNDERLINED),
+            25 => *sgr = sgr.remove_modifier(Modifier::SLOW_BLINK),
+            27 => *sgr = sgr.remove_modifier(Modifier::REVERSED),
+            29 => *sgr = sgr.remove_modifier(Modifier::CROSSED_OUT),
+            30..=37 => sgr.fg = Some(Color::Indexed(param - 30)),
+            38 => sgr.fg = extended(&mut params),
+            39 => sgr.fg = None,
+            40..=47 => sgr.bg = Some(Color::Indexed(param - 40)),
+            48 => sgr.bg = extended(&mut params),
+            49 => sgr.bg = None,
+            90..=97 => sgr.fg = Some(Color::Indexed(param - 90 + 8)),
+            100..=107 => sgr.bg = Some(Color::Indexed(param - 100 + 8)),
+            _ => {}
+        }
+        next = params.next();
+    }
+    // the first 16 colors go by name, so they follow the terminal's theme
+    for color in [&mut sgr.fg, &mut sgr.bg].into_iter().flatten() {
+        if let Color::Indexed(n @ 0..=15) = *color {
+            *color = NAMED[n as usize];
+        }
+    }
+}
+
+/// the color after a 38 or 48: 5 and an index, or 2 and red, green, blue
+fn extended(params: &mut impl Iterator<Item = u8>) -> Option<Color> {
+    match params.next()? {
+        5 => params.next().map(Color::Indexed),
+        2 => Some(Color::Rgb(params.next()?, params.next()?, params.next()?)),
+        _ => None,
+    }
+}
+
+/// the 16 colors SGR picks with 30-37 and 90-97
+const NAMED: [Color; 16] = [
+    Color::Black,
+    Color::Red,
+    Color::Green,
+    Color::Yellow,
+    Color::Blue,
+    Color::Magenta,
+    Color::Cyan,
+    Color::Gray,
+    Color::DarkGray,
+    Color::LightRed,
+    Color::LightGreen,
+    Color::LightYellow,
+    Color::LightBlue,
+    Color::LightMagenta,
+    Color::LightCyan,
+    Color::White,
+];
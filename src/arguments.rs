@@ -1,18 +1,196 @@
+use crate::fingerprint::FingerprintFilter;
+use crate::grid::{GridMode, TabMode, WrapMode};
+use crate::vector::FungeVector;
+use log::LevelFilter;
+use std::time::Duration;
+
 /// command-line arguments for Refunge
 #[derive(clap::Parser, Default)]
+#[command(args_conflicts_with_subcommands = true, subcommand_negates_reqs = true)]
 pub struct Arguments {
+    #[command(subcommand)]
+    pub command: Option<Command>,
+
     /// run in quiet mode (no tui)
     #[arg(short, long)]
     pub quiet: bool,
     /// start on the first non-# line
     #[arg(short, long)]
     pub script: bool,
-    /// Target file
-    pub file: String,
+    /// start IP 0 at this cell instead, as x,y (must be inside the grid)
+    #[arg(long, value_parser = parse_vector, conflicts_with = "script")]
+    pub start: Option<FungeVector>,
+    /// start IP 0 heading this way instead of east, as dx,dy
+    #[arg(long, value_parser = parse_delta, allow_hyphen_values = true)]
+    pub delta: Option<FungeVector>,
+    /// Target file, or - to read the program from stdin
+    /// (input instructions then reflect once stdin is exhausted)
+    #[arg(required_unless_present = "eval")]
+    pub file: Option<String>,
+    /// run the given code instead of a file, repeat for more lines
+    #[arg(short, long, conflicts_with = "file")]
+    pub eval: Vec<String>,
+    /// arguments for the program, after a --, which 'y' reports after its name
+    ///
+    /// ```
+    /// use clap::Parser;
+    /// use refunge::{Arguments, Befunge};
+    ///
+    /// // print each string of y's cell 19, separated by |
+    /// let program = ["a9+y>:#v_$:#v_@", r#"    ^ ,<,"|"<"#];
+    /// let mut command_line = vec!["refunge", "-q"];
+    /// command_line.extend(program.iter().flat_map(|line| ["-e", line]));
+    /// command_line.extend(["--", "one", "two"]);
+    /// let mut befunge = Befunge::new(Arguments::parse_from(command_line)).unwrap();
+    /// befunge.capture_output();
+    /// befunge.run(|| true);
+    /// assert_eq!(befunge.output(), "<eval>|one|two");
+    /// ```
+    #[arg(last = true)]
+    pub program_args: Vec<String>,
+
+    /// values pushed onto IP 0's stack before the first tick, bottom to top, like 5,0x41,'c',"hi"
+    /// (strings go on as 0gnirts, so they read back in order)
+    #[arg(long, value_parser = StackInit::parse, allow_hyphen_values = true)]
+    pub stack_init: Option<StackInit>,
+
+    /// seed for the rng shared by all IPs ('?' directions), random if not given
+    #[arg(long)]
+    pub seed: Option<u64>,
+
+    /// answer & and ~ from this file before asking for input
+    #[arg(long)]
+    pub input_file: Option<String>,
+    /// answer ~ with a single key as soon as it's pressed, without waiting
+    /// for enter, when stdin is a terminal
+    #[arg(long, requires = "quiet")]
+    pub unbuffered_input: bool,
+
+    /// write program output to this file instead of stdout (or alongside the tui)
+    #[arg(short, long)]
+    pub output: Option<String>,
+    /// most bytes of output a program may write: quiet runs stop there,
+    /// the tui drops the rest and says so
+    #[arg(long, value_name = "BYTES")]
+    pub max_output: Option<usize>,
+
+    /// start each line of output with the id of the IP that wrote it, like [1]
+    #[arg(long, requires = "quiet")]
+    pub tag_output: bool,
+    /// print newline-delimited json events instead of raw output, and answer
+    /// input requests from stdin a line at a time, see the stream module
+    #[arg(long, requires = "quiet", conflicts_with_all = ["expect", "tag_output"])]
+    pub json: bool,
+
+    /// don't echo answered & and ~ input into the output pane or --trace
+    #[arg(long)]
+    pub no_echo_input: bool,
+
+    /// print execution statistics to stderr after ending
+    #[arg(long)]
+    pub stats: bool,
+
+    /// log every executed instruction to this file
+    /// as tab separated tick, IP id, x,y, instruction, and stack depth after,
+    /// plus tick, IP id, "input", and the value (or none) for answered & and ~,
+    /// and tick, IP id, "event", and what happened for spawns, deaths, file I/O and reflections
+    #[arg(long)]
+    pub trace: Option<String>,
+
+    /// stop after this much wall-clock time, like 500ms, 5s, or 2m (pauses instead in the tui)
+    #[arg(long, value_parser = parse_duration)]
+    pub time_limit: Option<Duration>,
+
+    /// reload and restart whenever the source file changes
+    #[arg(short, long, conflicts_with = "quiet")]
+    pub watch: bool,
+
+    /// compare program output against this file instead of printing it,
+    /// failing with a diff on mismatch
+    #[arg(long, requires = "quiet")]
+    pub expect: Option<String>,
+    /// ignore trailing whitespace on each line when using --expect
+    #[arg(long, requires = "expect")]
+    pub ignore_trailing_space: bool,
+
+    /// place another file into the grid at startup, as path@x,y
+    #[arg(long, value_parser = parse_overlay)]
+    pub overlay: Vec<Overlay>,
+    /// treat overlays overwriting existing cells as an error instead of a warning,
+    /// and stop with an error once an IP is stuck running one cell with a zero delta
+    #[arg(long)]
+    pub strict: bool,
+
+    /// write per-cell and per-instruction execution counts to this csv file
+    #[arg(long)]
+    pub profile: Option<String>,
+
+    /// how much interpreter diagnostics to show: off, error, warn, info, debug, or trace
+    #[arg(long, value_parser = parse_log_level)]
+    pub log_level: Option<LevelFilter>,
+    /// also write interpreter diagnostics to this file
+    #[arg(long)]
+    pub log_file: Option<String>,
+    /// warn the first time an IP reflects off each cell, instead of only
+    /// counting it for --stats and --profile
+    #[arg(long)]
+    pub warn_reflect: bool,
+
+    /// limit which fingerprints can be loaded, as allow=NULL,ROMA or deny=0x4d4f4455
+    #[arg(long, value_parser = FingerprintFilter::parse)]
+    pub fingerprints: Option<FingerprintFilter>,
+    /// the handprint 'y' reports, in hex (RFNG, 0x52464e47, by default)
+    #[arg(long, value_parser = parse_handprint)]
+    pub handprint: Option<i32>,
+    /// the version number 'y' reports, instead of refunge's own
+    #[arg(long)]
+    pub report_version: Option<i32>,
+    /// enable refunge's own instructions outside the spec (l), which reflect otherwise
+    #[arg(long)]
+    pub extensions: bool,
+
+    /// save the seed, inputs, and clock readings of this run to a session file
+    #[arg(long, conflicts_with = "replay")]
+    pub record: Option<String>,
+    /// rerun a session saved by --record, failing if the program diverges from it
+    #[arg(long, conflicts_with = "seed")]
+    pub replay: Option<String>,
+
+    /// save a snapshot of the interpreter here when the run stops (s also saves one in the tui)
+    #[arg(long)]
+    pub snapshot_out: Option<String>,
+    /// pick up from a snapshot saved with --snapshot-out, instead of the start of the program
+    #[arg(long)]
+    pub snapshot_in: Option<String>,
+
+    /// pause when an IP reaches this cell, as x,y (quiet mode stops there instead)
+    #[arg(long = "break", value_parser = parse_vector)]
+    pub breakpoints: Vec<FungeVector>,
+    /// pause the moment the output ends with this text (quiet mode stops there instead,
+    /// exiting with status 3)
+    #[arg(long, value_name = "TEXT")]
+    pub break_on_output: Vec<String>,
+
+    /// draw the tui in plain ASCII, for terminals that garble box drawing and arrows
+    /// (the default when the locale isn't UTF-8)
+    #[arg(long, alias = "no-unicode", conflicts_with = "quiet")]
+    pub ascii: bool,
+
+    /// turn ANSI color codes in output into colors in the output pane, instead of
+    /// leaving them out (toggled with n)
+    #[arg(long, conflicts_with = "quiet")]
+    pub ansi: bool,
+    /// color the grid by what kind of instruction each cell is (toggled with y)
+    #[arg(long, conflicts_with = "quiet")]
+    pub color_grid: bool,
 
     /// start interpretation paused
     #[arg(short, long, conflicts_with = "quiet")]
     pub paused: bool,
+
+    /// pause whenever an IP dies, by '@' or 'q', to look at its stacks (toggled with d)
+    #[arg(long, conflicts_with = "quiet")]
+    pub pause_on_death: bool,
     /// jump many ticks before starting tui
     #[arg(short, long, conflicts_with = "quiet")]
     pub jump: Option<u32>,
@@ -20,7 +198,326 @@ pub struct Arguments {
     /// log the stack(s) after ending
     #[arg(short, long, requires = "quiet")]
     pub log_stack: bool,
-    /// end interpreting early
-    #[arg(short, long, requires = "quiet")]
+    /// end interpreting after this many ticks (pauses instead in the tui)
+    #[arg(short, long)]
+    pub max_ticks: Option<u32>,
+    /// print the tick, live IPs, and where the first one is to stderr every
+    /// this many seconds
+    #[arg(long, requires = "quiet", value_parser = clap::value_parser!(u64).range(1..))]
+    pub progress: Option<u64>,
+    /// most IPs running at once (10000 by default), beyond which 't' reflects
+    #[arg(long)]
+    pub max_ips: Option<usize>,
+    /// most cells 'p' and 'i' can grow the grid to, beyond which they reflect
+    #[arg(long)]
+    pub max_grid_cells: Option<usize>,
+    /// what IPs do at the edges of the grid: loop each axis, go back along
+    /// their path (as the spec says), or reflect
+    #[arg(long, value_enum, default_value_t)]
+    pub wrap_mode: WrapMode,
+    /// how the grid keeps its cells: every one of them, only the ones that
+    /// aren't spaces, or every one until it gets too big
+    #[arg(long, value_enum, default_value_t)]
+    pub grid: GridMode,
+    /// keep the spaces at the end of lines, letting them widen the grid and
+    /// move where IPs wrap around (they're left out by default)
+    #[arg(long)]
+    pub preserve_trailing_space: bool,
+    /// what to do about tabs in the program: keep each as a cell of its own
+    /// (which reflects), expand them to spaces, or refuse to run it
+    #[arg(long, value_enum, default_value_t)]
+    pub tabs: TabMode,
+    /// columns between tab stops for --tabs expand (1 by default, so nothing
+    /// after a tab moves over)
+    #[arg(long)]
+    pub tab_width: Option<usize>,
+    /// keep the IPs and the cells written this many ticks back, for TRDS to jump into
+    #[arg(long)]
+    pub history: Option<usize>,
+}
+
+/// tools that work on source files instead of running them
+#[derive(clap::Subcommand)]
+pub enum Command {
+    /// normalize trailing whitespace and line endings in a source file
+    Fmt(FmtArguments),
+    /// report likely problems in a source file without running it
+    Check(CheckArguments),
+    /// time a program over several runs in quiet mode, with its output thrown away
+    Bench(BenchArguments),
+}
+
+/// arguments for `refunge fmt`
+#[derive(clap::Args)]
+pub struct FmtArguments {
+    /// file to format in place
+    pub file: String,
+    /// pad every line with spaces to the full grid width
+    #[arg(long)]
+    pub pad: bool,
+    /// print the formatted source instead, failing if the file would change
+    #[arg(long)]
+    pub check: bool,
+}
+
+/// arguments for `refunge check`
+#[derive(clap::Args)]
+pub struct CheckArguments {
+    /// file to check
+    pub file: String,
+    /// only print the summary line
+    #[arg(short, long)]
+    pub quiet: bool,
+    /// start on the first non-# line
+    #[arg(short, long)]
+    pub script: bool,
+    /// count refunge's own instructions (l) as valid, for programs run with --extensions
+    #[arg(long)]
+    pub extensions: bool,
+}
+
+/// arguments for `refunge bench`
+#[derive(clap::Args)]
+pub struct BenchArguments {
+    /// program to time
+    pub file: String,
+    /// timed runs, after one more to warm up
+    #[arg(short, long, default_value_t = 10)]
+    pub iterations: u32,
+    /// stop each run after this many ticks
+    #[arg(short, long)]
     pub max_ticks: Option<u32>,
+    /// answer & and ~ from this file, which programs reading input need
+    #[arg(long)]
+    pub input_file: Option<String>,
+    /// start on the first non-# line
+    #[arg(short, long)]
+    pub script: bool,
+    /// print the results as json instead of a table
+    #[arg(long)]
+    pub json: bool,
+}
+
+/// parse a log level name like warn or debug
+fn parse_log_level(text: &str) -> Result<LevelFilter, String> {
+    text.parse()
+        .map_err(|_| format!("unknown log level `{text}`"))
+}
+
+/// parse a duration like 500ms, 5s, 2m, or 1h (plain numbers are seconds)
+fn parse_duration(text: &str) -> Result<Duration, String> {
+    let split = text
+        .find(|c: char| !c.is_ascii_digit() && c != '.')
+        .unwrap_or(text.len());
+    let (num, unit) = text.split_at(split);
+    let num: f64 = num
+        .parse()
+        .map_err(|_| format!("invalid duration number `{num}`"))?;
+    let secs = match unit.trim() {
+        "ms" => num / 1000.0,
+        "" | "s" => num,
+        "m" => num * 60.0,
+        "h" => num * 3600.0,
+        unit => {
+            return Err(format!(
+                "unknown duration unit `{unit}` (use ms, s, m, or h)"
+            ))
+        }
+    };
+    Ok(Duration::from_secs_f64(secs))
+}
+
+/// values to start IP 0's stack with, from --stack-init
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StackInit(pub Vec<StackValue>);
+
+/// one value in --stack-init
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum StackValue {
+    /// a number, hex number, or 'c' character
+    Number(i32),
+    /// a "string", pushed as 0gnirts
+    Text(String),
+}
+
+impl StackInit {
+    /// parse a comma separated list of decimal or 0x hex numbers, 'c' characters,
+    /// and "strings" (which can hold commas of their own)
+    ///
+    /// ```
+    /// use refunge::arguments::{StackInit, StackValue};
+    /// use refunge::{Arguments, Befunge};
+    ///
+    /// let values = |text| StackInit::parse(text).map(|init| init.0);
+    /// assert_eq!(values("5, -10"), Ok(vec![StackValue::Number(5), StackValue::Number(-10)]));
+    /// assert_eq!(values("0x41,0xffffffff"), Ok(vec![StackValue::Number(65), StackValue::Number(-1)]));
+    /// assert_eq!(values("'c',','"), Ok(vec![StackValue::Number(99), StackValue::Number(44)]));
+    /// assert_eq!(values("\"a, b\""), Ok(vec![StackValue::Text("a, b".to_string())]));
+    /// assert_eq!(values("1,0x2g"), Err("invalid stack value `0x2g`".to_string()));
+    /// assert_eq!(values("1,\"open"), Err("unclosed string `\"open`".to_string()));
+    /// assert_eq!(values("'ab'"), Err("invalid stack value `'ab'`".to_string()));
+    ///
+    /// // pushed bottom to top, so the string ends up on top
+    /// let stack_init = StackInit::parse("5,0x41,'c',\"hi\"").ok();
+    /// let args = Arguments { quiet: true, stack_init, ..Default::default() };
+    /// let mut befunge = Befunge::from_text(">:#,_$...@", args).unwrap();
+    /// befunge.capture_output();
+    /// befunge.run(|| true);
+    /// assert_eq!(befunge.output(), "hi99 65 5 ");
+    /// befunge.restart();
+    /// befunge.run(|| true);
+    /// assert_eq!(befunge.output(), "hi99 65 5 ");
+    /// ```
+    pub fn parse(text: &str) -> Result<StackInit, String> {
+        let mut values = vec![];
+        let mut rest = text;
+        loop {
+            rest = rest.trim_start();
+            let end = match rest.chars().next() {
+                Some('"') => rest[1..]
+                    .find('"')
+                    .map(|end| end + 2)
+                    .ok_or(format!("unclosed string `{rest}`"))?,
+                // the character itself might be a comma, so look for one after it
+                Some('\'') => {
+                    let from = rest.char_indices().nth(2).map_or(rest.len(), |(at, _)| at);
+                    rest[from..].find(',').map_or(rest.len(), |end| from + end)
+                }
+                _ => rest.find(',').unwrap_or(rest.len()),
+            };
+            values.push(StackValue::parse(rest[..end].trim())?);
+            match rest[end..].trim_start().strip_prefix(',') {
+                Some(next) => rest = next,
+                None if rest[end..].trim().is_empty() => return Ok(StackInit(values)),
+                None => return Err(format!("expected a comma after `{}`", &rest[..end])),
+            }
+        }
+    }
+}
+
+impl StackValue {
+    /// parse a single value, see [StackInit::parse]
+    fn parse(token: &str) -> Result<StackValue, String> {
+        let invalid = || format!("invalid stack value `{token}`");
+        if let Some(text) = token.strip_prefix('"').and_then(|t| t.strip_suffix('"')) {
+            return Ok(StackValue::Text(text.to_string()));
+        }
+        if let Some(inner) = token.strip_prefix('\'').and_then(|t| t.strip_suffix('\'')) {
+            let mut chars = inner.chars();
+            return match (chars.next(), chars.next()) {
+                (Some(c), None) => Ok(StackValue::Number(c as i32)),
+                _ => Err(invalid()),
+            };
+        }
+        let (negative, digits) = match token.strip_prefix('-') {
+            Some(digits) => (true, digits),
+            None => (false, token),
+        };
+        let number = match digits.strip_prefix("0x") {
+            // hex past i32::MAX wraps around, like fingerprint ids
+            Some(hex) => u32::from_str_radix(hex, 16).map(|n| {
+                if negative {
+                    (n as i32).wrapping_neg()
+                } else {
+                    n as i32
+                }
+            }),
+            None => token.parse::<i32>(),
+        };
+        number.map(StackValue::Number).map_err(|_| invalid())
+    }
+}
+
+/// an extra file placed into the grid at a given offset
+///
+/// ```
+/// use refunge::arguments::Overlay;
+/// use refunge::{Arguments, Befunge, FungeVector};
+///
+/// // the program heads down into one overlay, which sends it east into the other
+/// let dir = std::env::temp_dir();
+/// let write = |name: &str, text: &str| {
+///     let path = dir.join(name);
+///     std::fs::write(&path, text).unwrap();
+///     path.to_string_lossy().into_owned()
+/// };
+/// let main = write("refunge-doctest-overlay-main.bf", "1.   v");
+/// let down = write("refunge-doctest-overlay-down.bf", "2\n.\n>");
+/// let east = write("refunge-doctest-overlay-east.bf", "3.@");
+/// let overlay = vec![
+///     Overlay { path: down.clone(), pos: FungeVector(5, 1) },
+///     Overlay { path: east.clone(), pos: FungeVector(6, 3) },
+/// ];
+/// let args = Arguments { quiet: true, file: Some(main.clone()), overlay, ..Default::default() };
+/// let mut befunge = Befunge::new(args).unwrap();
+/// let grid = befunge.grid();
+/// assert_eq!((grid.width(), grid.height()), (9, 4));
+/// let at = |x, y| grid.char_at(FungeVector(x, y));
+/// assert_eq!([at(5, 0), at(5, 1), at(5, 2), at(5, 3), at(6, 3), at(7, 3), at(8, 3)], ['v', '2', '.', '>', '3', '.', '@']);
+/// assert_eq!(grid.name(), format!("{main} + {down}@5,1 + {east}@6,3"));
+/// befunge.capture_output();
+/// befunge.run(|| true);
+/// assert_eq!(befunge.output(), "1 2 3 ");
+///
+/// // the second one landing on the first is only an error under --strict
+/// let overlay = vec![
+///     Overlay { path: down.clone(), pos: FungeVector(5, 1) },
+///     Overlay { path: east.clone(), pos: FungeVector(5, 3) },
+/// ];
+/// let args = Arguments { quiet: true, file: Some(main.clone()), overlay: overlay.clone(), ..Default::default() };
+/// assert_eq!(Befunge::new(args).unwrap().grid().char_at(FungeVector(5, 3)), '3');
+/// let args = Arguments { quiet: true, file: Some(main), overlay, strict: true, ..Default::default() };
+/// let error = Befunge::new(args).err().unwrap();
+/// assert_eq!(error.to_string(), format!("overlay {east} overwrote 1 cell(s), first at (5, 3)"));
+/// ```
+#[derive(Debug, Clone)]
+pub struct Overlay {
+    pub path: String,
+    pub pos: FungeVector,
+}
+
+/// parse an overlay like lib.bf@60,0
+fn parse_overlay(text: &str) -> Result<Overlay, String> {
+    let (path, pos) = text
+        .rsplit_once('@')
+        .ok_or("expected path@x,y".to_string())?;
+    Ok(Overlay {
+        path: path.to_string(),
+        pos: parse_vector(pos)?,
+    })
+}
+
+/// parse a handprint in hex like 0x52464e47, with or without the 0x
+fn parse_handprint(text: &str) -> Result<i32, String> {
+    let digits = text.trim_start_matches("0x").trim_start_matches("0X");
+    u32::from_str_radix(digits, 16)
+        .map(|n| n as i32)
+        .map_err(|_| format!("invalid handprint `{text}`, expected up to 8 hex digits"))
+}
+
+/// parse a direction like -1,0, which has to go somewhere
+fn parse_delta(text: &str) -> Result<FungeVector, String> {
+    let (x, y) = text.split_once(',').ok_or("expected dx,dy".to_string())?;
+    let coord = |n: &str| {
+        n.trim()
+            .parse::<i32>()
+            .map_err(|_| format!("invalid delta `{}`", n.trim()))
+    };
+    match FungeVector(coord(x)?, coord(y)?) {
+        FungeVector(0, 0) => Err("a delta of 0,0 never moves".to_string()),
+        delta => Ok(delta),
+    }
+}
+
+/// parse a grid position like 12,3
+pub(crate) fn parse_vector(text: &str) -> Result<FungeVector, String> {
+    let (x, y) = text.split_once(',').ok_or("expected x,y".to_string())?;
+    let coord = |n: &str| {
+        n.trim()
+            .parse::<i32>()
+            .ok()
+            .filter(|n| *n >= 0)
+            .ok_or(format!("invalid coordinate `{n}`"))
+    };
+    Ok(FungeVector(coord(x)?, coord(y)?))
 }
@@ -1,21 +1,52 @@
-use crate::arguments::Arguments;
-use crate::event::{Event, EventHandler, KeyHandler, TickHandler};
-use crate::grid::FungeGrid;
-use crate::key;
-use crate::pointer::InstructionPointer;
-use crate::vector::directions;
-use crossterm::event::{KeyCode, KeyEvent, KeyEventKind, KeyModifiers};
-use ratatui::layout::{Constraint, Direction::Horizontal, Layout};
-use ratatui::style::{Color, Style};
-use ratatui::widgets::{Block, Borders, Paragraph, Wrap};
-use ratatui::Frame;
+use crate::arguments::{Arguments, StackValue};
+use crate::error::RefungeError;
+use crate::event::{Event, EventHandler, InterpreterEvent};
+#[cfg(feature = "tui")]
+use crate::event::{KeyHandler, TickHandler};
+use crate::fingerprint::{Destination, Fingerprint, Tardis, FINGERPRINTS};
+use crate::golden::{self, RunResult};
+use crate::grid::{expand_tabs, first_tab, FungeGrid, TabMode};
+use crate::hook::{
+    AfterHook, BeforeHook, Breakpoint, DebugEntry, HookAction, HookContext, Tally, Watch,
+    WatchTarget,
+};
+use crate::input::{InputBuffer, InputEcho, InputReader, InputType};
+use crate::output::Output;
+use crate::platform::Platform;
+use crate::pointer::{InstructionPointer, Reflection};
+use crate::profile::Profiler;
+use crate::report::{IpStep, RunOutcome, StepEvent, Tick, TickOutcome, TickReport};
+use crate::report::{OUTPUT_LIMIT_EXIT_CODE, OUTPUT_MATCH_EXIT_CODE, TIMEOUT_EXIT_CODE};
+use crate::session::{Environment, RngState, Session};
+use crate::snapshot::{self, BefungeSnapshot, SNAPSHOT_VERSION};
+use crate::source::Source;
+use crate::stats::{ReflectionRecord, Stats};
+use crate::timeline::{EventLog, Happening};
+use crate::vector::{directions, FungeVector};
+use anyhow::{bail, Context, Result};
+use std::cell::RefCell;
+use std::collections::hash_map::RandomState;
+#[cfg(feature = "tui")]
+use std::collections::BTreeMap;
+use std::collections::HashMap;
 use std::collections::VecDeque;
-use std::fmt::Display;
-use std::fs::read_to_string;
-use std::io;
-use std::str::FromStr;
+use std::fs::{read_to_string, File};
+use std::hash::BuildHasher;
+use std::io::{BufRead, BufWriter, Write};
+use std::net::TcpStream;
+use std::path::Path;
+use std::rc::Rc;
+use std::sync::mpsc;
+use std::time::{Duration, Instant, SystemTime};
+#[cfg(feature = "tui")]
 use tui_textarea::TextArea;
 
+#[cfg(feature = "tui")]
+mod tui;
+
+/// how many ips can be running at once without --max-ips
+const DEFAULT_MAX_IPS: usize = 10_000;
+
 #[derive(Default)]
 pub struct Befunge<'a> {
     /// the grid that is being traversed
@@ -23,20 +54,135 @@ pub struct Befunge<'a> {
     /// ip running around executing commands
     ip_list: VecDeque<InstructionPointer>,
     /// output text produced by , and .
-    out: String,
+    out: Output,
+    /// rng, clock, and input record shared by all IPs
+    env: Environment,
+    /// counters for --stats and the status bar
+    stats: Stats,
+    /// per-instruction log written by --trace, shared with the hook writing it
+    trace: Option<Rc<RefCell<BufWriter<File>>>>,
+    /// called around every instruction, see on_before_instruction and on_after_instruction
+    before_hooks: Vec<BeforeHook<'a>>,
+    after_hooks: Vec<AfterHook<'a>>,
+    /// where IPs pause, from --break and add_breakpoint, shared with the hook pausing them
+    breakpoints: Rc<RefCell<Vec<(Breakpoint, Tally)>>>,
+    /// values kept an eye on, from add_watch or w in the tui
+    watches: Vec<Watch>,
+    /// the watch that paused the last tick, with its old and new values
+    watch_hit: Option<(WatchTarget, Option<i32>, Option<i32>)>,
+    /// text that pauses things once the output ends with it, from --break-on-output or /
+    output_triggers: Vec<(String, Tally)>,
+    /// the end of the output, as long as the longest trigger
+    output_tail: String,
+    /// the trigger that paused the last tick and the IP whose output matched it
+    output_hit: Option<(String, usize)>,
+    /// recent spawns, deaths, file I/O and reflections
+    event_log: EventLog,
+    /// cells IPs reflected off and when, flashed in the grid for a moment
+    #[cfg(feature = "tui")]
+    flashes: HashMap<FungeVector, Instant>,
+    /// execution counts written by --profile
+    profiler: Option<Profiler>,
+    /// every cell IPs reflected off, when first and how often
+    reflected_cells: HashMap<FungeVector, ReflectionRecord>,
 
     /// toggled by pressing p
     paused: bool,
+    /// when turbo mode started and the tick count then, while it's on (toggled with t)
+    #[cfg(feature = "tui")]
+    turbo: Option<(Instant, u64)>,
     /// how far down the grid we've scrolled
+    #[cfg(feature = "tui")]
     grid_scroll: (u16, u16),
     /// scrolling for output text
+    #[cfg(feature = "tui")]
     output_scroll: u16,
+    /// show output lines as they are instead of wrapping them, toggled with u
+    #[cfg(feature = "tui")]
+    unwrapped_output: bool,
+    /// columns scrolled past while output is unwrapped
+    #[cfg(feature = "tui")]
+    output_hscroll: u16,
+    /// the IP picked out with tab, whose output f filters down to
+    #[cfg(feature = "tui")]
+    focus: usize,
+    /// show breakpoints and watches in the debug pane, toggled with b
+    #[cfg(feature = "tui")]
+    show_debug: bool,
+    /// the debug entry picked out with tab while the debug pane is open
+    #[cfg(feature = "tui")]
+    debug_selected: usize,
+    /// show the event log pane, toggled with v
+    #[cfg(feature = "tui")]
+    show_events: bool,
+    /// entries scrolled back from the end of the event log
+    #[cfg(feature = "tui")]
+    events_scroll: u16,
+    /// grid positions saved with m and a letter, kept across restarts
+    #[cfg(feature = "tui")]
+    marks: BTreeMap<char, FungeVector>,
+    /// m or ` was pressed and the letter naming the mark comes next
+    #[cfg(feature = "tui")]
+    mark_key: Option<tui::MarkKey>,
+    /// show every stack of the focused IP instead of just its TOSS, toggled with a
+    #[cfg(feature = "tui")]
+    expand_stacks: bool,
+    /// lay the stacks out one per row instead of side by side, or pick by the
+    /// shape of the terminal when none, cycled with z
+    #[cfg(feature = "tui")]
+    horizontal_stacks: Option<bool>,
+    /// columns horizontally laid out stacks are scrolled back from their tops
+    #[cfg(feature = "tui")]
+    stack_hscroll: usize,
+    /// only show output written by the focused IP
+    #[cfg(feature = "tui")]
+    filter_output: bool,
     /// input for tui
+    #[cfg(feature = "tui")]
     textarea: TextArea<'a>,
-    inputting: bool,
-    valid_input: bool,
-    input_type: InputType,
-    input_target: usize,
+    /// what the input box is taking instead of & or ~ input, opened with w or /
+    #[cfg(feature = "tui")]
+    text_prompt: Option<tui::TextPrompt>,
+    /// IPs waiting on & or ~ and what they asked for, in the order they asked,
+    /// with the first one being prompted for
+    input_queue: VecDeque<(usize, InputType)>,
+    /// queued input from --input-file
+    input: InputBuffer,
+    /// every answered & and ~, oldest first, unless --no-echo-input
+    input_echoes: Vec<InputEcho>,
+    /// where & and ~ are answered once the input file runs out, the tui
+    /// prompts instead when there is none
+    reader: Option<InputReader>,
+    /// set once --time-limit runs out
+    timed_out: bool,
+    /// set once a 't' got reflected for going past --max-ips
+    ip_limit_reached: bool,
+    /// the IP that died and where, when --pause-on-death paused for it
+    death: Option<(usize, FungeVector)>,
+    /// the IP --strict stopped the run over for being stuck with a zero delta,
+    /// where it is and what it's running
+    stuck: Option<(usize, FungeVector, char)>,
+    /// the last --history ticks, oldest first, for TRDS to jump back into
+    history: VecDeque<Checkpoint>,
+    /// a tick that ran out of fuel partway through, to be carried on with
+    unfinished_tick: Option<TickState>,
+    /// IPs that jumped back in time as (tick, id), which vanish when their
+    /// past selves get to the same jump
+    departures: Vec<(u64, usize)>,
+    /// the IP that stopped time with TRDS, the only one running until it starts again
+    time_stopper: Option<usize>,
+    /// short message shown below the output pane
+    notice: Option<String>,
+    #[cfg(feature = "tui")]
+    show_log: bool,
+
+    /// where the program was loaded from
+    source: Source,
+    /// --watch has loaded a newer version of the source since starting
+    #[cfg(feature = "tui")]
+    reloaded: bool,
+    /// source file modification time and when it was last checked, for --watch
+    watched: Option<(Option<SystemTime>, Instant)>,
 
     /// exit code for q command
     pub exit_code: Option<i32>,
@@ -44,181 +190,2200 @@ pub struct Befunge<'a> {
     args: Arguments,
     /// global events
     events: EventHandler,
+    /// frontends listening in with subscribe, dropped once they hang up
+    subscribers: Vec<mpsc::Sender<InterpreterEvent>>,
     /// tickspeed handling
+    #[cfg(feature = "tui")]
     ticks: TickHandler,
     /// key input
+    #[cfg(feature = "tui")]
     key_events: KeyHandler,
 }
 impl<'a> Befunge<'a> {
-    /// create a new befunge simulation
-    pub fn new(args: Arguments) -> Befunge<'a> {
+    /// create a new befunge simulation, loading the program named by the arguments
+    ///
+    /// ```
+    /// use refunge::{Arguments, Befunge, RefungeError};
+    ///
+    /// let args = |file: &str| Arguments { quiet: true, file: Some(file.into()), ..Default::default() };
+    /// let missing = Befunge::new(args("no/such/program.bf")).err().unwrap();
+    /// assert!(matches!(&missing, RefungeError::Load { from, .. } if from == "no/such/program.bf"));
+    /// let dir = std::env::temp_dir().to_string_lossy().into_owned();
+    /// let directory = Befunge::new(args(&dir)).err().unwrap();
+    /// assert!(matches!(&directory, RefungeError::IsDirectory { path } if *path == dir));
+    /// ```
+    pub fn new(args: Arguments) -> Result<Befunge<'a>, RefungeError> {
+        Befunge::with_source(Source::new(&args), args)
+    }
+    /// create a new befunge simulation running the given program text
+    pub fn from_text(text: &str, args: Arguments) -> Result<Befunge<'a>, RefungeError> {
+        Befunge::with_source(Source::Eval(text.to_string()), args)
+    }
+    fn with_source(source: Source, mut args: Arguments) -> Result<Befunge<'a>, RefungeError> {
         let paused = args.paused;
-        let grid = FungeGrid::new(read_to_string(&args.file).expect("failed to read file"));
-        let ip_list = [InstructionPointer::new(
-            grid.start_pos(args.script),
-            directions::EAST,
-            0,
-        )]
-        .into();
-        let mut textarea = TextArea::default();
-        textarea.set_cursor_style(Style::default());
-        Befunge {
+        let mut env = match &args.replay {
+            Some(path) => {
+                let text = read_file("read session", path)?;
+                Environment::replay(serde_json::from_str::<Session>(&text).map_err(|e| {
+                    RefungeError::Invalid(format!("failed to read session from {path}: {e}"))
+                })?)
+            }
+            None => Environment::new(*args.seed.get_or_insert_with(random_seed))
+                .recording(args.record.is_some()),
+        };
+        let seed = env.session().seed;
+        args.seed = Some(seed);
+        env.set_identity(args.handprint, args.report_version);
+        env.set_history(args.history.unwrap_or(0));
+        env.set_args(args.program_args.clone());
+        let (grid, conflicts) = load_grid(&source, &args)?;
+        for conflict in conflicts {
+            log::warn!("{conflict}");
+        }
+        log::info!("loaded {} ({}x{})", source, grid.width(), grid.height());
+        if let Some(start) = args.start {
+            if start.0 as usize >= grid.width() || start.1 as usize >= grid.height() {
+                return Err(RefungeError::OutOfBounds {
+                    what: "--start".into(),
+                    pos: start,
+                    width: grid.width(),
+                    height: grid.height(),
+                });
+            }
+        }
+        let out = Output::new(&args).map_err(|error| RefungeError::Io {
+            action: "create",
+            path: args.output.clone().unwrap_or_default(),
+            error,
+        })?;
+        let trace = match &args.trace {
+            Some(path) => Some(Rc::new(RefCell::new(BufWriter::new(
+                File::create(path).map_err(|error| RefungeError::Io {
+                    action: "create",
+                    path: path.clone(),
+                    error,
+                })?,
+            )))),
+            None => None,
+        };
+        let input = match &args.input_file {
+            Some(path) => InputBuffer::new(read_file("read input from", path)?),
+            None => InputBuffer::default(),
+        };
+        let ip_list = [starting_ip(&grid, &args)].into();
+        let watched = args.watch.then(|| (source.modified(), Instant::now()));
+        let reader = args
+            .quiet
+            .then(|| InputReader::stdin().unbuffered(args.unbuffered_input));
+        // the tui answers ~ with a single key press
+        env.set_unbuffered_input(
+            reader
+                .as_ref()
+                .map_or(cfg!(feature = "tui"), InputReader::is_unbuffered),
+        );
+        let mut befunge = Befunge {
             grid,
             ip_list,
+            out,
+            env,
+            stats: Stats::new(seed),
+            trace: trace.clone(),
+            profiler: args.profile.as_ref().map(|_| Profiler::default()),
             paused,
-            textarea,
+            #[cfg(feature = "tui")]
+            textarea: tui::textarea(),
+            input,
+            reader,
+            source,
+            watched,
+            breakpoints: Rc::new(RefCell::new(
+                args.breakpoints
+                    .iter()
+                    .map(|pos| (Breakpoint::Position(*pos), Tally::default()))
+                    .collect(),
+            )),
+            output_triggers: args
+                .break_on_output
+                .iter()
+                .map(|text| (text.clone(), Tally::default()))
+                .collect(),
             args,
             ..Default::default()
+        };
+        // first in line, so a breakpoint pauses no matter what later hooks want
+        befunge.on_before_instruction(breakpoint_hook(befunge.breakpoints.clone()));
+        if let Some(trace) = trace {
+            befunge.on_after_instruction(move |hook| {
+                writeln!(
+                    trace.borrow_mut(),
+                    "{}\t{}\t{},{}\t{}\t{}",
+                    hook.tick,
+                    hook.ip.id,
+                    hook.pos.0,
+                    hook.pos.1,
+                    hook.instruction,
+                    hook.ip.stacks[0].len()
+                )
+                .unwrap_or(());
+            });
+        }
+        if let Some(path) = befunge.args.snapshot_in.clone() {
+            befunge
+                .read_snapshot(&path)
+                .map_err(|e| RefungeError::Invalid(format!("{e:#}")))?;
         }
+        let names: Vec<&str> = befunge
+            .available_fingerprints()
+            .iter()
+            .map(|fp| fp.name)
+            .collect();
+        log::info!("fingerprints available: {}", names.join(" "));
+        Ok(befunge)
     }
     /// step forward once and run whatever char we're standing on
+    ///
+    /// ```
+    /// use refunge::{Arguments, Befunge, FungeVector};
+    ///
+    /// let args = || Arguments { quiet: true, ..Default::default() };
+    /// // @ ends the program in the tick it runs, ticking after that does nothing
+    /// let mut befunge = Befunge::from_text("@", args()).unwrap();
+    /// befunge.tick();
+    /// assert!(befunge.ended());
+    /// befunge.tick();
+    /// assert_eq!(befunge.stats().ticks, 1);
+    ///
+    /// // the child t makes goes first, from the cell behind the t
+    /// let mut befunge = Befunge::from_text("1.t2.@", args()).unwrap();
+    /// befunge.capture_output();
+    /// let mut moves = vec![];
+    /// while !befunge.ended() {
+    ///     let steps = befunge.step().steps;
+    ///     moves.push(steps.iter().map(|step| (step.id, step.pos.0)).collect::<Vec<_>>());
+    /// }
+    /// let (child, parent) = (0, 1);
+    /// assert_eq!(moves[3..], [
+    ///     vec![(child, 1), (parent, 3)],
+    ///     vec![(child, 0), (parent, 4)],
+    ///     vec![(child, 5), (parent, 5)],
+    /// ]);
+    /// assert_eq!(befunge.output(), "1 0 2 ");
+    /// befunge.tick();
+    /// assert_eq!(befunge.stats().ticks, 6);
+    /// ```
     pub fn tick(&mut self) {
-        for ip in self.ip_list.iter_mut() {
-            if ip.dead {
-                continue;
+        self.step();
+    }
+    /// tick, reporting what each IP did
+    ///
+    /// ```
+    /// use refunge::report::StepEvent;
+    /// use refunge::{Arguments, Befunge, FungeVector};
+    ///
+    /// let args = Arguments { quiet: true, ..Default::default() };
+    /// let mut befunge = Befunge::from_text("7.t@", args).unwrap();
+    /// befunge.capture_output();
+    /// let first = befunge.step();
+    /// assert_eq!((first.tick, first.steps[0].instruction), (0, '7'));
+    /// assert_eq!(befunge.step().steps[0].output, "7 ");
+    /// assert_eq!(befunge.step().steps[0].events, vec![StepEvent::Spawned]);
+    /// let last = befunge.step();
+    /// assert_eq!(last.steps.len(), 2);
+    /// assert_eq!(last.steps[0].pos, FungeVector(1, 0));
+    /// assert_eq!(last.steps[1].instruction, '@');
+    /// ```
+    pub fn step(&mut self) -> TickReport {
+        match self.run_tick(None) {
+            TickOutcome::Completed(report) => report,
+            TickOutcome::OutOfFuel => unreachable!("ticks without a fuel limit always finish"),
+        }
+    }
+    /// tick, but stop once fuel operations have been used up, to carry on from
+    /// the same place in the same tick on the next call
+    ///
+    /// running an instruction (k counts each one it repeats) and jumping to the
+    /// next non-space cell while skipping spaces or a ;-region each take one
+    ///
+    /// ```
+    /// use refunge::report::TickOutcome;
+    /// use refunge::{Arguments, Befunge};
+    ///
+    /// // a long ;-region, a k pushing a thousand 1s, and two IPs sharing ticks
+    /// let programs = [
+    ///     format!(";{};2.@", "x".repeat(50)),
+    ///     "a:*:*k1a:*:*k+.@".to_string(),
+    ///     "t;xxxx; 3k1.@".to_string(),
+    /// ];
+    /// let args = || Arguments { quiet: true, ..Default::default() };
+    /// for program in programs {
+    ///     let mut whole = Befunge::from_text(&program, args()).unwrap();
+    ///     whole.capture_output();
+    ///     whole.run(|| true);
+    ///
+    ///     let mut bounded = Befunge::from_text(&program, args()).unwrap();
+    ///     bounded.capture_output();
+    ///     let mut ran_out = 0;
+    ///     while !bounded.ended() {
+    ///         if bounded.tick_bounded(2) == TickOutcome::OutOfFuel {
+    ///             ran_out += 1;
+    ///         }
+    ///     }
+    ///     assert!(ran_out > 0);
+    ///     assert_eq!(bounded.output(), whole.output());
+    ///     assert_eq!(bounded.stats().ticks, whole.stats().ticks);
+    ///     assert_eq!(bounded.stats().instructions, whole.stats().instructions);
+    /// }
+    ///
+    /// // 'p' a "d" out at (100000000, 100000000), then go diagonally from the
+    /// // 'x' at (14, 0): a hundred million spaces until the path wraps around,
+    /// // walked a step per unit of fuel
+    /// let mut befunge = Befunge::from_text("\"d\"aa*:*:*:p11x", args()).unwrap();
+    /// for _ in 0..15 {
+    ///     befunge.step();
+    /// }
+    /// assert!(befunge.grid().is_sparse());
+    /// // the 'x' ran in the last step, leaving the IP on the space at (15, 1)
+    /// for calls in 1..=3 {
+    ///     assert_eq!(befunge.tick_bounded(100), TickOutcome::OutOfFuel);
+    ///     let (_, pos) = befunge.live_positions().next().unwrap();
+    ///     assert_eq!((pos.0 - 14, pos.1), (pos.1, 100 * calls + 1));
+    /// }
+    /// ```
+    pub fn tick_bounded(&mut self, fuel: u32) -> TickOutcome {
+        self.run_tick(Some(fuel))
+    }
+    /// run a tick, or the rest of one that ran out of fuel, with or without a limit
+    fn run_tick(&mut self, mut fuel: Option<u32>) -> TickOutcome {
+        let mut tick = match self.unfinished_tick.take() {
+            Some(tick) => tick,
+            // the program ended in the tick its last IP died, there's nothing left to count
+            None if self.ended() => {
+                return TickOutcome::Completed(TickReport {
+                    tick: self.stats.ticks,
+                    steps: vec![],
+                })
             }
-            if !ip.first_tick {
-                ip.walk(&self.grid)
+            None => self.begin_tick(),
+        };
+        #[cfg(feature = "tracing")]
+        let _tick = tracing::trace_span!("tick", tick = tick.report.tick).entered();
+        while tick.next < self.ip_list.len() {
+            if fuel == Some(0) {
+                self.unfinished_tick = Some(tick);
+                return TickOutcome::OutOfFuel;
             }
-            let c = self.grid.char_at(ip.pos);
-            if ip.string_mode {
-                match c {
+            let turn = match tick.turn.take() {
+                Some(turn) => turn,
+                None => match self.start_turn(&tick) {
+                    Some(turn) => turn,
+                    None => {
+                        tick.next += 1;
+                        continue;
+                    }
+                },
+            };
+            let ip = &mut self.ip_list[tick.next];
+            let c = turn.instruction;
+            match turn.action {
+                HookAction::SkipInstruction => {}
+                _ if turn.string_mode => match c {
                     '"' => ip.string_mode = false,
                     ' ' => {
-                        while self.grid.char_at(ip.pos) == ' ' {
-                            ip.walk(&self.grid);
+                        ip.fuel = fuel;
+                        let done = ip.skip_string_spaces(&self.grid, &mut self.stats);
+                        fuel = ip.fuel.take();
+                        if !done {
+                            tick.turn = Some(turn);
+                            self.unfinished_tick = Some(tick);
+                            return TickOutcome::OutOfFuel;
                         }
-                        ip.walk_reverse(&self.grid);
-                        ip.push(32);
                     }
                     _ => ip.push(c as i32),
+                },
+                _ => {
+                    self.out.set_ip(ip.id);
+                    ip.fuel = fuel;
+                    let result = ip.command(
+                        c,
+                        &mut self.grid,
+                        self.events.sender.clone(),
+                        &mut self.out,
+                        &mut self.env,
+                        &mut self.stats,
+                    );
+                    fuel = ip.fuel.take();
+                    if let Err(err) = result {
+                        log::error!("ip {}: {err:#}", ip.id);
+                        #[cfg(feature = "tracing")]
+                        tracing::error!(ip = ip.id, error = %format_args!("{err:#}"), "ip stopped");
+                        ip.dead = true;
+                    } else if ip.unfinished.is_some() {
+                        tick.turn = Some(turn);
+                        self.unfinished_tick = Some(tick);
+                        return TickOutcome::OutOfFuel;
+                    }
                 }
-            } else {
-                ip.command(
-                    c,
-                    &mut self.grid,
-                    self.events.sender.clone(),
-                    &mut self.out,
-                    self.args.quiet,
-                );
             }
-            if ip.first_tick {
-                ip.first_tick = false
+            self.finish_turn(&mut tick, turn);
+            tick.next += 1;
+        }
+        TickOutcome::Completed(self.end_tick(tick))
+    }
+    /// get a tick going, before any IP has moved
+    fn begin_tick(&mut self) -> TickState {
+        self.death = None;
+        self.watch_hit = None;
+        self.output_hit = None;
+        if let Some(depth) = self.args.history.filter(|depth| *depth > 0) {
+            let undo = self.grid.take_undo();
+            if let Some(last) = self.history.back_mut() {
+                last.undo = undo;
             }
+            self.history.push_back(Checkpoint {
+                tick: self.stats.ticks,
+                size: (self.grid.width(), self.grid.height()),
+                undo: vec![],
+                ips: self.ip_list.clone(),
+                rng: self.env.rng_state(),
+            });
+            if self.history.len() > depth + 1 {
+                self.history.pop_front();
+            }
+        }
+        TickState {
+            report: TickReport {
+                tick: self.stats.ticks,
+                steps: vec![],
+            },
+            events: vec![],
+            died: None,
+            reflections: vec![],
+            logged: self.event_log.total(),
+            subscribed: !self.subscribers.is_empty(),
+            published: vec![],
+            was_running: !self.ended(),
+            next: 0,
+            turn: None,
+        }
+    }
+    /// move the next IP in the tick and see what it's about to run, or None when
+    /// it sits this tick out or a hook pauses on it
+    fn start_turn(&mut self, tick: &TickState) -> Option<Turn> {
+        let ip = &mut self.ip_list[tick.next];
+        if ip.dead
+            || ip.waiting.is_some()
+            || self.time_stopper.is_some_and(|id| id != ip.id)
+            || ip
+                .tardis
+                .arriving
+                .is_some_and(|arriving| arriving > tick.report.tick)
+        {
+            return None;
         }
-        if let Some(event) = self.events.next() {
+        ip.tardis.arriving = None;
+        ip.reflected = None;
+        if !ip.first_tick {
+            ip.walk(&self.grid, &mut self.stats)
+        }
+        // keep hold of a bounce off the edge, which command() would forget
+        let bounced = ip.reflected.take();
+        let (c, pos) = (self.grid.char_at(ip.pos), ip.pos);
+        let string_mode = ip.string_mode;
+        let hook = HookContext {
+            tick: tick.report.tick,
+            ip,
+            instruction: c,
+            pos,
+            grid: &self.grid,
+        };
+        // every hook sees the instruction, the first one to object decides
+        let mut action = HookAction::Continue;
+        for before in self.before_hooks.iter_mut() {
+            let wanted = before(&hook);
+            if action == HookAction::Continue {
+                action = wanted;
+            }
+        }
+        if action == HookAction::Pause && !ip.held {
+            ip.held = true;
+            ip.first_tick = true;
+            self.paused = true;
+            return None;
+        }
+        Some(Turn {
+            instruction: c,
+            pos,
+            string_mode,
+            bounced,
+            action,
+        })
+    }
+    /// note down what the IP whose turn it was did, once its instruction is done
+    fn finish_turn(&mut self, tick: &mut TickState, turn: Turn) {
+        let Turn {
+            instruction: c,
+            pos,
+            string_mode,
+            bounced,
+            ..
+        } = turn;
+        let ip = &mut self.ip_list[tick.next];
+        let TickState {
+            report,
+            events,
+            died,
+            reflections,
+            subscribed,
+            published,
+            ..
+        } = tick;
+        if let Some(why) = ip.reflected.or(bounced) {
+            reflections.push((ip.id, pos, why, ip.failure.take()));
+        }
+        #[cfg(feature = "tracing")]
+        tracing::trace!(ip = ip.id, x = pos.0, y = pos.1, instruction = %c, string_mode, "instruction");
+        ip.held = false;
+        if ip.first_tick {
+            ip.first_tick = false
+        }
+        if ip.delta == directions::ORIGIN && !ip.dead && !ip.zero_delta_logged {
+            ip.zero_delta_logged = true;
+            log::warn!(
+                "ip {}: zero delta at {}, it runs the same cell every tick",
+                ip.id,
+                ip.pos
+            );
+        }
+        if self.args.strict && self.stuck.is_none() && ip.is_stuck(&self.grid) {
+            self.stuck = Some((ip.id, ip.pos, self.grid.char_at(ip.pos)));
+        }
+        let depth = ip.stacks.iter().map(|s| s.len()).max().unwrap_or(0);
+        self.stats.max_stack_depth = self.stats.max_stack_depth.max(depth);
+        // count what actually ran rather than the spaces skipped to get there
+        let (ran_pos, ran) = ip.ran.take().unwrap_or((pos, c));
+        if let Some(profiler) = &mut self.profiler {
+            profiler.record(ran_pos, ran);
+        }
+        let hook = HookContext {
+            tick: report.tick,
+            ip,
+            instruction: c,
+            pos,
+            grid: &self.grid,
+        };
+        for after in self.after_hooks.iter_mut() {
+            after(&hook);
+        }
+        let writes = self.grid.take_writes();
+        let mut step = IpStep {
+            id: ip.id,
+            instruction: c,
+            pos,
+            delta: ip.delta,
+            string_mode,
+            writes: writes.iter().map(|&(pos, _, new)| (pos, new)).collect(),
+            output: self.out.take_recent(),
+            events: vec![],
+            died: ip.dead,
+        };
+        if *subscribed {
+            published.extend(
+                writes
+                    .into_iter()
+                    .map(|(pos, old, new)| InterpreterEvent::CellWritten { pos, old, new }),
+            );
+            if !step.output.is_empty() {
+                let text = step.output.clone();
+                published.push(InterpreterEvent::OutputChunk { ip: ip.id, text });
+            }
+            published.push(match ip.dead {
+                true => InterpreterEvent::IpDied { ip: ip.id, pos },
+                false => InterpreterEvent::IpMoved {
+                    ip: ip.id,
+                    pos: ip.pos,
+                    delta: ip.delta,
+                },
+            });
+        }
+        while let Some(event) = self.events.next() {
+            let what = match &event {
+                Event::Kill(code) => Some(Happening::Quit(*code)),
+                Event::ReadFile(name, cells) => Some(Happening::ReadFile(name.clone(), *cells)),
+                Event::WroteFile(name, cells) => Some(Happening::WroteFile(name.clone(), *cells)),
+                _ => None,
+            };
+            if let Some(what) = what {
+                self.event_log.push(report.tick, ip.id, what);
+            }
+            step.events.push(match &event {
+                Event::Spawn(_) => StepEvent::Spawned,
+                Event::Kill(code) => StepEvent::Quit(*code),
+                Event::Input(t, _) => StepEvent::InputRequested(*t),
+                Event::Jump(_) => StepEvent::Jumped,
+                Event::StopTime(_) => StepEvent::TimeStopped,
+                Event::ResumeTime => StepEvent::TimeResumed,
+                Event::ReadFile(name, cells) => StepEvent::ReadFile(name.clone(), *cells),
+                Event::WroteFile(name, cells) => StepEvent::WroteFile(name.clone(), *cells),
+            });
+            events.push(event);
+        }
+        if ip.dead {
+            self.event_log
+                .push(report.tick, ip.id, Happening::Died(pos));
+        }
+        let quit = step.events.iter().any(|e| matches!(e, StepEvent::Quit(_)));
+        if died.is_none() && (ip.dead || quit) {
+            *died = Some((ip.id, pos));
+        }
+        report.steps.push(step);
+    }
+    /// wrap up a tick once every IP has had its turn
+    fn end_tick(&mut self, tick: TickState) -> TickReport {
+        let TickState {
+            report,
+            events,
+            mut died,
+            reflections,
+            logged,
+            subscribed,
+            mut published,
+            was_running,
+            ..
+        } = tick;
+        for (id, pos, why, failure) in reflections {
+            self.note_reflection(report.tick, id, pos, why);
+            if let Some(error) = failure {
+                self.event_log.push(report.tick, id, Happening::Failed(error));
+            }
+        }
+        if !self.output_triggers.is_empty() {
+            for step in report.steps.iter().filter(|step| !step.output.is_empty()) {
+                self.check_output(&step.output, step.id);
+            }
+        }
+        self.stats.ticks += 1;
+        // reading the clock every tick adds up, so only look now and then
+        if self.stats.ticks.is_multiple_of(1024) {
+            self.out.flush_if_due();
+        }
+        if self
+            .args
+            .max_ticks
+            .is_some_and(|max| self.stats.ticks == max as u64)
+        {
+            self.paused = true;
+        }
+        self.check_time_limit();
+        // events come in IP order, so every spawn handled so far shifts later ids by one
+        let mut spawned = 0;
+        // the one jump back in time allowed each tick, made once the rest are handled
+        let mut rewind = None;
+        let mut quit = false;
+        for event in events {
             match event {
                 Event::Spawn(id) => {
+                    let id = id + spawned;
+                    let max_ips = self.args.max_ips.unwrap_or(DEFAULT_MAX_IPS);
+                    if self.live_ips() >= max_ips {
+                        log::debug!("ip {id} reflected, already {max_ips} ips running");
+                        self.ip_list[id].reflect(Reflection::Failed, &mut self.stats);
+                        let pos = self.ip_list[id].pos;
+                        self.note_reflection(self.stats.ticks - 1, id, pos, Reflection::Failed);
+                        self.ip_limit_reached = true;
+                        continue;
+                    }
+                    spawned += 1;
+                    self.stats.spawned += 1;
+                    let at = self.ip_list[id].pos;
+                    self.event_log
+                        .push(self.stats.ticks - 1, id + 1, Happening::Spawned(id, at));
+                    log::debug!("ip {id} split into a new ip");
+                    #[cfg(feature = "tracing")]
+                    tracing::debug!(ip = id, "spawn");
+                    // the child starts on the t going the other way and, like cfunge,
+                    // moves off it before its first instruction, next tick and before its parent
                     let mut new_ip = self.ip_list[id].clone();
                     new_ip.delta.invert();
+                    new_ip.first_tick = false;
                     self.ip_list.insert(id, new_ip);
                     for (idx, ip) in self.ip_list.iter_mut().enumerate() {
                         ip.id = idx
                     }
+                    if subscribed {
+                        let parent = id + 1;
+                        published.push(InterpreterEvent::IpSpawned { ip: id, parent });
+                    }
+                    for (target, _) in self
+                        .input_queue
+                        .iter_mut()
+                        .filter(|(target, _)| *target >= id)
+                    {
+                        *target += 1;
+                    }
+                    match &mut died {
+                        Some((target, _)) if *target >= id => *target += 1,
+                        _ => {}
+                    }
+                    match &mut self.time_stopper {
+                        Some(target) if *target >= id => *target += 1,
+                        _ => {}
+                    }
+                }
+                Event::Jump(id) => {
+                    if let Some(back) = self.time_travel(id + spawned, rewind.is_none()) {
+                        rewind = Some(back);
+                    }
                 }
+                Event::StopTime(id) => self.time_stopper = Some(id + spawned),
+                // only there for the event log and step reports
+                Event::ReadFile(..) | Event::WroteFile(..) => {}
+                Event::ResumeTime => self.time_stopper = None,
+                // the first IP to quit this tick picks the exit code
+                Event::Kill(_) if quit => {}
                 Event::Kill(code) => {
+                    quit = true;
+                    log::info!("quit with exit code {code}");
+                    #[cfg(feature = "tracing")]
+                    tracing::info!(code, "kill");
                     self.exit_code = Some(code);
+                    self.out.flush();
+                    self.input_queue.clear();
                     for ip in self.ip_list.iter_mut() {
                         ip.dead = true
                     }
                 }
                 Event::Input(t, id) => {
-                    if self.args.quiet {
-                        self.ip_list[id].push(t.parse_stdin());
+                    let id = id + spawned;
+                    if self.env.replaying() {
+                        if let Some(val) = self.env.replay_input() {
+                            self.give_input(id, t, val);
+                        }
+                    } else if let Some(val) = self.input.take(t) {
+                        self.give_input(id, t, Some(val));
+                        self.notice = Some(match t {
+                            InputType::Number => format!("Read {val} from input file"),
+                            InputType::Character => format!(
+                                "Read {:?} from input file",
+                                char::from_u32(val as u32).unwrap_or(' ')
+                            ),
+                        });
+                    } else if let Some(reader) = self.reader.as_mut().filter(|r| !r.interrupted()) {
+                        self.out.flush();
+                        let val = reader.read(t);
+                        // ctrl-c while reading a key leaves the IP waiting, and stops the run
+                        if reader.interrupted() {
+                            self.ip_list[id].waiting = Some(t);
+                            self.input_queue.push_back((id, t));
+                        } else {
+                            self.give_input(id, t, val);
+                        }
                     } else {
-                        self.inputting = true;
-                        self.input_type = t;
-                        self.input_target = id;
-                        let title = match t {
-                            InputType::Number => "Input Number",
-                            InputType::Character => "Input Character",
-                        };
-                        self.textarea
-                            .set_block(Block::default().borders(Borders::ALL).title(title));
+                        // so whatever the program printed first shows up before the prompt
+                        self.out.flush();
+                        self.ip_list[id].waiting = Some(t);
+                        self.input_queue.push_back((id, t));
+                        if subscribed {
+                            published.push(InterpreterEvent::InputRequested { ip: id, kind: t });
+                        }
+                        #[cfg(feature = "tui")]
+                        if self.input_queue.len() == 1 {
+                            self.prompt();
+                        }
                     }
                 }
             }
         }
+        if let Some((tick, traveler)) = rewind {
+            self.rewind(tick, traveler);
+        }
+        // time starts again once whoever stopped it is gone
+        if self
+            .time_stopper
+            .is_some_and(|id| self.ip_list.get(id).is_none_or(|ip| ip.dead))
+        {
+            self.time_stopper = None;
+        }
+        if let Some(msg) = self.env.diverged() {
+            self.paused = true;
+            self.notice = Some(format!("Replay diverged: {msg}"));
+        }
+        if self.stuck.is_some() {
+            self.paused = true;
+        }
+        if let Some((id, pos)) = died.filter(|_| self.args.pause_on_death) {
+            self.paused = true;
+            self.death = Some((id, pos));
+            #[cfg(feature = "tui")]
+            {
+                self.focus = id;
+            }
+        }
+        if !self.watches.is_empty() {
+            self.check_watches(true);
+        }
+        if let Some(trace) = &self.trace {
+            let new = (self.event_log.total() - logged) as usize;
+            for entry in self.event_log.iter().rev().take(new).rev() {
+                writeln!(
+                    trace.borrow_mut(),
+                    "{}\t{}\tevent\t{}",
+                    entry.tick,
+                    entry.ip,
+                    entry.what
+                )
+                .unwrap_or(());
+            }
+        }
+        if subscribed {
+            if was_running && self.ended() {
+                let code = self.exit_code.unwrap_or(0);
+                published.push(InterpreterEvent::Ended { code });
+            }
+            for event in published {
+                self.subscribers
+                    .retain(|subscriber| subscriber.send(event.clone()).is_ok());
+            }
+        }
+        report
+    }
+    /// keep track of an IP reflecting at pos, in the event log, the cell's
+    /// record, and the cells the tui flashes, logging it the first time
+    fn note_reflection(&mut self, tick: u64, id: usize, pos: FungeVector, why: Reflection) {
+        self.event_log.push(tick, id, Happening::Reflected(pos, why));
+        let instruction = self.grid.char_at(pos);
+        let record = self.reflected_cells.entry(pos).or_insert_with(|| {
+            let level = if self.args.warn_reflect {
+                log::Level::Warn
+            } else {
+                log::Level::Debug
+            };
+            log::log!(level, "ip {id}: reflected at {pos} on tick {tick}: {why}");
+            self.stats.reflecting_cells += 1;
+            ReflectionRecord {
+                first_tick: tick,
+                count: 0,
+                instruction,
+            }
+        });
+        record.count += 1;
+        #[cfg(feature = "tui")]
+        self.flashes.insert(pos, Instant::now());
+    }
+    /// send an IP where its TRDS destination points, reflecting it if that's
+    /// further back than the history goes, and handing back the tick and the
+    /// traveler to rewind to once the tick is over if it's in the past at all
+    fn time_travel(&mut self, id: usize, can_rewind: bool) -> Option<(u64, InstructionPointer)> {
+        let now = self.stats.ticks - 1;
+        if let Some(idx) = self.departures.iter().position(|d| *d == (now, id)) {
+            self.departures.remove(idx);
+            self.ip_list[id].dead = true;
+            return None;
+        }
+        let ip = &mut self.ip_list[id];
+        let pos = match ip.tardis.space {
+            None => ip.pos,
+            Some(Destination::Absolute(pos)) => pos,
+            Some(Destination::Relative(v)) => ip.pos + v,
+        };
+        let time = ip.tardis.time.unwrap_or(now as i64);
+        let kept = |tick| self.history.iter().any(|cp| cp.tick as i64 == tick);
+        if time < now as i64 && !(can_rewind && kept(time)) {
+            log::debug!("ip {id} reflected, tick {time} is out of reach");
+            ip.reflect(Reflection::Failed, &mut self.stats);
+            let pos = ip.pos;
+            self.note_reflection(now, id, pos, Reflection::Failed);
+            return None;
+        }
+        log::debug!("ip {id} jumped to {pos} on tick {time}");
+        ip.tardis.departed = Some((self.grid.wrap(ip.pos, ip.delta, 1), now as i64 + 1, ip.delta));
+        ip.pos = pos;
+        ip.delta = ip.tardis.delta.unwrap_or(ip.delta);
+        ip.first_tick = true;
+        if time > now as i64 {
+            ip.tardis.arriving = Some(time as u64);
+            None
+        } else {
+            (time < now as i64).then(|| (time as u64, ip.clone()))
+        }
+    }
+    /// go back to the start of a tick in the history, with a time traveler
+    /// arriving there
+    fn rewind(&mut self, tick: u64, mut traveler: InstructionPointer) {
+        let Some(idx) = self.history.iter().position(|cp| cp.tick == tick) else {
+            return;
+        };
+        let undo = self.grid.take_undo();
+        if let Some(last) = self.history.back_mut() {
+            last.undo.extend(undo);
+        }
+        // undo every tick since, newest first
+        let mut checkpoint = None;
+        for cp in self.history.drain(idx..).rev() {
+            self.grid.undo(&cp.undo, cp.size);
+            checkpoint = Some(cp);
+        }
+        let Some(checkpoint) = checkpoint else {
+            return;
+        };
+        self.departures.push((self.stats.ticks - 1, traveler.id));
+        self.ip_list = checkpoint.ips;
+        self.env.set_rng_state(&checkpoint.rng);
+        self.stats.ticks = tick;
+        self.time_stopper = None;
+        traveler.id = self.ip_list.len();
+        traveler.tardis = Tardis {
+            departed: traveler.tardis.departed,
+            ..Default::default()
+        };
+        self.ip_list.push_back(traveler);
+        self.input_queue = self
+            .ip_list
+            .iter()
+            .filter_map(|ip| Some((ip.id, ip.waiting?)))
+            .collect();
+        #[cfg(feature = "tui")]
+        self.prompt();
+    }
+    /// hand an IP its answer to & or ~, reflecting it if there is none
+    fn give_input(&mut self, id: usize, t: InputType, val: Option<i32>) {
+        self.env.record_input(val);
+        if !self.args.no_echo_input {
+            let echo = InputEcho {
+                tick: self.stats.ticks.saturating_sub(1),
+                ip: id,
+                kind: t,
+                value: val,
+                offset: self.out.written(),
+            };
+            if let Some(trace) = &self.trace {
+                let val = val.map_or("none".to_string(), |val| val.to_string());
+                writeln!(trace.borrow_mut(), "{}\t{id}\tinput\t{val}", echo.tick).unwrap_or(());
+            }
+            self.input_echoes.push(echo);
+        }
+        self.ip_list[id].waiting = None;
+        let ip = &mut self.ip_list[id];
+        match val {
+            Some(val) => ip.push(val),
+            None => {
+                ip.reflect(Reflection::EndOfInput, &mut self.stats);
+                let pos = ip.pos;
+                let tick = self.stats.ticks.saturating_sub(1);
+                self.note_reflection(tick, id, pos, Reflection::EndOfInput);
+            }
+        }
+    }
+    /// pause once --time-limit has run out, only looking at the clock
+    /// every so often in quiet mode to keep the overhead down
+    fn check_time_limit(&mut self) {
+        const CHECK_INTERVAL: u64 = 1024;
+        let Some(limit) = self.args.time_limit else {
+            return;
+        };
+        if self.timed_out || (self.args.quiet && !self.stats.ticks.is_multiple_of(CHECK_INTERVAL)) {
+            return;
+        }
+        if self.stats.elapsed() >= limit {
+            self.timed_out = true;
+            self.paused = true;
+        }
+    }
+    /// has --time-limit run out
+    pub fn timed_out(&self) -> bool {
+        self.timed_out
+    }
+    /// what stopped a run short, and the exit status a quiet run leaves
+    /// with for it: --time-limit running out, a --break-on-output trigger,
+    /// or going past --max-output
+    ///
+    /// ```
+    /// use refunge::report::TIMEOUT_EXIT_CODE;
+    /// use refunge::{Arguments, Befunge};
+    /// use std::time::{Duration, Instant};
+    ///
+    /// // an infinite loop, stopped by the clock rather than a tick count
+    /// let time_limit = Some(Duration::from_millis(50));
+    /// let args = Arguments { quiet: true, time_limit, ..Default::default() };
+    /// let mut befunge = Befunge::from_text(">", args).unwrap();
+    /// let start = Instant::now();
+    /// befunge.run(|| true);
+    /// assert!(start.elapsed() < Duration::from_secs(5));
+    /// assert!(befunge.timed_out());
+    /// assert!(!befunge.ended());
+    /// let (code, why) = befunge.stopped_by().unwrap();
+    /// assert_eq!((code, TIMEOUT_EXIT_CODE), (124, 124));
+    /// assert_eq!(why, format!("time limit reached after {} ticks", befunge.stats().ticks));
+    /// ```
+    pub fn stopped_by(&self) -> Option<(i32, String)> {
+        let ticks = self.stats.ticks;
+        if self.timed_out {
+            let why = format!("time limit reached after {ticks} ticks");
+            Some((TIMEOUT_EXIT_CODE, why))
+        } else if let Some((text, ip)) = self.output_hit() {
+            let why = format!("ip {ip} wrote {text:?} after {ticks} ticks");
+            Some((OUTPUT_MATCH_EXIT_CODE, why))
+        } else if self.output_limit_reached() {
+            let why = format!("output limit reached after {ticks} ticks");
+            Some((OUTPUT_LIMIT_EXIT_CODE, why))
+        } else {
+            None
+        }
+    }
+    /// run up to n ticks at once, stopping early if the program ends,
+    /// needs input, or hits --max-ticks, and returns how many ticks ran
+    pub fn run_ticks(&mut self, n: u32) -> u32 {
+        for done in 0..n {
+            if self.ended()
+                || !self.input_queue.is_empty()
+                || self.max_ticks_reached()
+                || self.timed_out
+            {
+                return done;
+            }
+            self.tick();
+        }
+        n
+    }
+    /// jump forward up to n ticks, noting how far it got
+    ///
+    /// ```
+    /// use refunge::input::InputType;
+    /// use refunge::{Arguments, Befunge};
+    ///
+    /// // jumping stops at the &, waiting on it instead of skipping past the read
+    /// let mut befunge = Befunge::from_text("12&+.@", Arguments::default()).unwrap();
+    /// befunge.capture_output();
+    /// befunge.jump(100);
+    /// assert_eq!(befunge.notice(), Some("Jumped 3 of 100 ticks"));
+    /// assert_eq!(befunge.waiting_for_input(), Some((0, InputType::Number)));
+    /// befunge.answer_input(Some(4));
+    /// befunge.run(|| true);
+    /// assert!(befunge.ended());
+    /// assert_eq!(befunge.output(), "6 ");
+    /// ```
+    pub fn jump(&mut self, n: u32) {
+        let done = self.run_ticks(n);
+        self.notice = Some(if done == n {
+            format!("Jumped {done} ticks")
+        } else {
+            format!("Jumped {done} of {n} ticks")
+        });
     }
     /// reset everything
     pub fn restart(&mut self) {
         self.grid.reset();
-        self.ip_list = [InstructionPointer::new(
-            self.grid.start_pos(self.args.script),
-            directions::EAST,
-            0,
-        )]
-        .into();
+        self.ip_list = [starting_ip(&self.grid, &self.args)].into();
+        self.unfinished_tick = None;
+        self.input_queue.clear();
         self.out.clear();
+        self.input.reset();
+        self.input_echoes.clear();
+        self.notice = None;
+        self.timed_out = false;
+        self.ip_limit_reached = false;
+        self.death = None;
+        self.stuck = None;
+        self.watch_hit = None;
+        self.output_tail.clear();
+        self.output_hit = None;
+        self.event_log.clear();
+        self.reflected_cells.clear();
+        self.history.clear();
+        self.departures.clear();
+        self.time_stopper = None;
+        self.env.restart();
+        self.stats = Stats::new(self.args.seed.unwrap_or_default());
         self.paused = self.args.paused;
-        self.textarea = TextArea::default();
-        self.textarea.set_cursor_style(Style::default());
+        self.check_watches(false);
+        #[cfg(feature = "tui")]
+        {
+            self.textarea = tui::textarea();
+            self.text_prompt = None;
+            self.mark_key = None;
+            self.flashes.clear();
+            self.focus = 0;
+            self.turbo = None;
+        }
     }
 
-    /// is there a tick available
-    pub fn has_tick(&self) -> bool {
-        self.ticks.has_tick()
-    }
-    /// handle key input for scrolling, pausing, etc
-    pub fn handle_key_events(&mut self) -> bool {
-        if let Some(event) = self.key_events.next() {
-            if matches!(event, key!(ctrl;'c')) {
-                return true;
-            } // give priority to input events
-            if self.inputting {
-                self.handle_tui_input(event);
-                return false;
-            }
-            match event {
-                key!('.') => self.ticks.speed_up(),
-                key!(',') => self.ticks.slow_down(),
-                key!(Right) if self.paused => self.tick(),
-                key!('p') => self.paused = !self.paused,
-                key!('h') => self.grid_scroll.1 = self.grid_scroll.1.saturating_sub(1),
-                key!('j') => self.grid_scroll.0 += 1,
-                key!('k') => self.grid_scroll.0 = self.grid_scroll.0.saturating_sub(1),
-                key!('l') => self.grid_scroll.1 += 1,
-                key!('i') => self.output_scroll = self.output_scroll.saturating_sub(1),
-                key!('o') => self.output_scroll += 1,
-                key!('r') => self.restart(),
-                key!('q') if self.ended() => return true,
-                _ => {}
-            }
-        }
-        false
-    }
-    fn handle_tui_input(&mut self, event: KeyEvent) {
-        if matches!(event, key!(Enter)) {
-            if self.valid_input {
-                let text = self.textarea.lines().last().unwrap();
-                self.ip_list[self.input_target].push(self.input_type.parse(text));
-                self.inputting = false;
-            }
-            self.textarea.move_cursor(tui_textarea::CursorMove::Head);
-            self.textarea.delete_line_by_end();
+    /// with --watch, reload and restart if the source file changed,
+    /// keeping the old grid (and saying why) if the new one can't be loaded
+    pub fn check_reload(&mut self) {
+        const POLL_INTERVAL: Duration = Duration::from_millis(250);
+        let Some((modified, last_check)) = &mut self.watched else {
+            return;
+        };
+        if last_check.elapsed() < POLL_INTERVAL {
             return;
         }
-        if self.textarea.input(event) {
-            if self.input_type.can_parse(&self.textarea.lines()[0]) {
-                self.textarea
-                    .set_style(Style::default().fg(Color::LightGreen));
-                self.valid_input = true;
-            } else {
-                self.textarea
-                    .set_style(Style::default().fg(Color::LightRed));
-                self.valid_input = false;
+        *last_check = Instant::now();
+        let new_modified = self.source.modified();
+        if new_modified == *modified {
+            return;
+        }
+        *modified = new_modified;
+        let text = match self.source.read() {
+            Ok(text) if text.lines().next().is_some() => text,
+            Ok(_) => {
+                self.notice = Some(format!("Not reloading {}: file is empty", self.source));
+                return;
+            }
+            Err(err) => {
+                self.notice = Some(format!("Failed to reload {}: {err}", self.source));
+                return;
+            }
+        };
+        match load_text(text, &self.source, &self.args) {
+            Ok(grid) => {
+                self.grid = grid.named(self.source.to_string());
+                self.grid.set_max_cells(self.args.max_grid_cells);
+                self.grid.set_wrap_mode(self.args.wrap_mode);
+                self.grid.set_grid_mode(self.args.grid);
+                let paused = self.paused;
+                self.restart();
+                self.paused = paused;
+                self.notice = Some(format!("Reloaded {}", self.source));
+                #[cfg(feature = "tui")]
+                {
+                    self.reloaded = true;
+                }
             }
+            Err(err) => self.notice = Some(format!("Not reloading: {err}")),
         }
     }
-    /// is the tui paused
+
+    /// the last message for the notice area below the output, like what a jump did
+    pub fn notice(&self) -> Option<&str> {
+        self.notice.as_deref()
+    }
+    /// is the tui paused, or is there nothing left to run until input comes in
     pub fn paused(&self) -> bool {
-        self.paused || self.inputting
+        self.paused || self.all_waiting()
+    }
+    /// is every IP that's still running waiting on input
+    fn all_waiting(&self) -> bool {
+        !self.input_queue.is_empty()
+            && self
+                .ip_list
+                .iter()
+                .all(|ip| ip.dead || ip.waiting.is_some())
+    }
+    /// the IP first in line for input and what it asked for, if any are waiting
+    ///
+    /// only that IP stops, the rest keep running while it waits:
+    ///
+    /// ```
+    /// use refunge::input::InputType;
+    /// use refunge::{Arguments, Befunge};
+    ///
+    /// // ip 1 waits on '&' while ip 0 loops around printing a's
+    /// let program = "#vt&.@\n >'a,v\n ^   <";
+    /// let mut befunge = Befunge::from_text(program, Arguments::default()).unwrap();
+    /// (0..5).for_each(|_| befunge.tick());
+    /// assert_eq!(befunge.waiting_for_input(), Some((1, InputType::Number)));
+    /// let before = befunge.output().len();
+    /// (0..50).for_each(|_| befunge.tick());
+    /// assert!(!befunge.paused());
+    /// assert!(befunge.output().len() > before);
+    /// assert_eq!(befunge.waiting_for_input(), Some((1, InputType::Number)));
+    ///
+    /// befunge.answer_input(Some(7));
+    /// assert_eq!(befunge.waiting_for_input(), None);
+    /// (0..2).for_each(|_| befunge.tick());
+    /// assert!(befunge.output().ends_with("7 "));
+    /// ```
+    pub fn waiting_for_input(&self) -> Option<(usize, InputType)> {
+        self.input_queue.front().copied()
+    }
+    /// can programs load the fingerprint with this id
+    pub fn fingerprint_allowed(&self, id: i32) -> bool {
+        self.args.fingerprints.as_ref().is_none_or(|f| f.allows(id))
+    }
+    /// the known fingerprints programs are allowed to load
+    pub fn available_fingerprints(&self) -> Vec<&'static Fingerprint> {
+        FINGERPRINTS
+            .iter()
+            .filter(|fp| self.fingerprint_allowed(fp.id()))
+            .collect()
+    }
+    /// has anything stopped a run without the tui: ending, --max-ticks, --time-limit,
+    /// a diverged --replay, a breakpoint, a watch, an output trigger, --max-output,
+    /// or ctrl-c while reading a key
+    pub fn stopped(&self) -> bool {
+        self.ended()
+            || self.max_ticks_reached()
+            || self.timed_out
+            || self.stuck.is_some()
+            || self.diverged().is_some()
+            || self.held()
+            || self.watch_hit().is_some()
+            || self.output_hit().is_some()
+            || (self.args.quiet && self.output_limit_reached())
+            || self.all_waiting()
+            || self.interrupted()
+    }
+    /// call this before every instruction, to look at it and maybe skip it or pause
+    ///
+    /// ```
+    /// use refunge::hook::HookAction;
+    /// use refunge::{Arguments, Befunge, FungeVector};
+    ///
+    /// let args = Arguments { quiet: true, ..Default::default() };
+    /// let mut puts = 0;
+    /// let mut befunge = Befunge::from_text("'z10p'b10p.@", args).unwrap();
+    /// befunge.capture_output();
+    /// befunge.on_after_instruction(|hook| puts += (hook.instruction == 'p') as i32);
+    /// befunge.on_before_instruction(|hook| match hook.pos {
+    ///     FungeVector(9, 0) => HookAction::Pause,
+    ///     _ => HookAction::Continue,
+    /// });
+    /// befunge.run(|| true);
+    /// assert!(!befunge.ended());
+    /// assert_eq!(befunge.grid().char_at(FungeVector(1, 0)), 'z');
+    /// befunge.resume();
+    /// befunge.run(|| true);
+    /// assert!(befunge.ended());
+    /// assert_eq!(befunge.grid().char_at(FungeVector(1, 0)), 'b');
+    /// drop(befunge);
+    /// assert_eq!(puts, 2);
+    /// ```
+    pub fn on_before_instruction(&mut self, hook: impl FnMut(&HookContext) -> HookAction + 'a) {
+        self.before_hooks.push(Box::new(hook));
+    }
+    /// call this after every instruction, to look at what it did
+    pub fn on_after_instruction(&mut self, hook: impl FnMut(&HookContext) + 'a) {
+        self.after_hooks.push(Box::new(hook));
+    }
+    /// hear about everything that happens from here on, tick by tick, for
+    /// frontends other than the tui, see [InterpreterEvent]
+    ///
+    /// ```
+    /// use refunge::event::InterpreterEvent::*;
+    /// use refunge::{Arguments, Befunge, FungeVector};
+    ///
+    /// let args = Arguments { quiet: true, ..Default::default() };
+    /// // 't' sends a new ip 0 west around to the '@', while ip 1 puts an 'x' past the end
+    /// let mut befunge = Befunge::from_text("t'x90p.@", args).unwrap();
+    /// befunge.capture_output();
+    /// let events = befunge.subscribe();
+    /// befunge.run(|| true);
+    /// let moves = |event: &_| matches!(event, IpMoved { .. });
+    /// assert_eq!(
+    ///     events.try_iter().filter(|event| !moves(event)).collect::<Vec<_>>(),
+    ///     [
+    ///         IpSpawned { ip: 0, parent: 1 },
+    ///         IpDied { ip: 0, pos: FungeVector(7, 0) },
+    ///         CellWritten { pos: FungeVector(9, 0), old: ' ', new: 'x' },
+    ///         OutputChunk { ip: 1, text: "0 ".into() },
+    ///         IpDied { ip: 1, pos: FungeVector(7, 0) },
+    ///         Ended { code: 0 },
+    ///     ]
+    /// );
+    /// ```
+    pub fn subscribe(&mut self) -> mpsc::Receiver<InterpreterEvent> {
+        let (sender, receiver) = mpsc::channel();
+        self.subscribers.push(sender);
+        receiver
+    }
+    /// the grid as it is now
+    pub fn grid(&self) -> &FungeGrid {
+        &self.grid
+    }
+    /// is an IP being held by a pause hook
+    fn held(&self) -> bool {
+        self.paused && self.ip_list.iter().any(|ip| ip.held)
+    }
+    /// carry on after pausing
+    pub fn resume(&mut self) {
+        self.paused = false;
+    }
+    /// pause whenever an IP reaches this cell
+    pub fn add_breakpoint(&mut self, pos: FungeVector) {
+        self.add(Breakpoint::Position(pos));
+    }
+    /// pause whenever an IP is about to run this instruction
+    pub fn add_instruction_breakpoint(&mut self, c: char) {
+        self.add(Breakpoint::Instruction(c));
+    }
+    /// stop pausing at this cell, returning false if there was no breakpoint
+    pub fn remove_breakpoint(&mut self, pos: FungeVector) -> bool {
+        self.remove(Breakpoint::Position(pos))
+    }
+    /// stop pausing on this instruction, returning false if there was no breakpoint
+    pub fn remove_instruction_breakpoint(&mut self, c: char) -> bool {
+        self.remove(Breakpoint::Instruction(c))
+    }
+    /// every breakpoint, in the order they were added
+    pub fn list_breakpoints(&self) -> Vec<Breakpoint> {
+        self.breakpoints.borrow().iter().map(|(b, _)| *b).collect()
+    }
+    fn add(&mut self, breakpoint: Breakpoint) {
+        let mut breakpoints = self.breakpoints.borrow_mut();
+        if !breakpoints.iter().any(|(b, _)| *b == breakpoint) {
+            breakpoints.push((breakpoint, Tally::default()));
+        }
+    }
+    fn remove(&mut self, breakpoint: Breakpoint) -> bool {
+        let mut breakpoints = self.breakpoints.borrow_mut();
+        let before = breakpoints.len();
+        breakpoints.retain(|(b, _)| *b != breakpoint);
+        breakpoints.len() != before
+    }
+    /// keep an eye on a grid cell or a place on an IP's stack, optionally
+    /// pausing at the end of any tick that changes it
+    ///
+    /// ```
+    /// use refunge::hook::WatchTarget;
+    /// use refunge::{Arguments, Befunge, FungeVector};
+    ///
+    /// // the 'p' on tick 3 writes an 'a' over the '2'
+    /// let args = Arguments { quiet: true, ..Default::default() };
+    /// let mut befunge = Befunge::from_text("'a20p@", args).unwrap();
+    /// let cell = WatchTarget::Cell(FungeVector(2, 0));
+    /// befunge.add_watch(cell, true);
+    /// befunge.add_watch(WatchTarget::Stack { ip: 0, depth: 1 }, false);
+    /// befunge.run(|| true);
+    /// assert_eq!(befunge.stats().ticks, 4);
+    /// assert_eq!(befunge.watch_hit(), Some((cell, Some('2' as i32), Some('a' as i32))));
+    /// let values: Vec<Option<i32>> = befunge.watches().iter().map(|w| w.value).collect();
+    /// assert_eq!(values, [Some('a' as i32), None]);
+    /// befunge.resume();
+    /// befunge.run(|| true);
+    /// assert!(befunge.ended());
+    /// assert!(befunge.remove_watch(cell));
+    /// assert_eq!(befunge.watches().len(), 1);
+    /// ```
+    pub fn add_watch(&mut self, target: WatchTarget, break_on_change: bool) {
+        let value = self.watch_value(target);
+        match self.watches.iter_mut().find(|w| w.target == target) {
+            Some(watch) => watch.break_on_change = break_on_change,
+            None => self.watches.push(Watch {
+                target,
+                break_on_change,
+                value,
+                tally: Tally::default(),
+            }),
+        }
+    }
+    /// stop watching something, returning false if it wasn't watched
+    pub fn remove_watch(&mut self, target: WatchTarget) -> bool {
+        let before = self.watches.len();
+        self.watches.retain(|w| w.target != target);
+        self.watches.len() != before
+    }
+    /// everything being watched, in the order it was added
+    pub fn watches(&self) -> &[Watch] {
+        &self.watches
+    }
+    /// the watch whose change paused things, with its old and new values, if any
+    pub fn watch_hit(&self) -> Option<(WatchTarget, Option<i32>, Option<i32>)> {
+        self.watch_hit.filter(|_| self.paused)
+    }
+    /// what a watched thing holds right now
+    fn watch_value(&self, target: WatchTarget) -> Option<i32> {
+        match target {
+            WatchTarget::Cell(pos) => Some(self.grid.cell_at(pos)),
+            WatchTarget::Stack { ip, depth } => {
+                self.ip_list.get(ip)?.stacks.front()?.get(depth).copied()
+            }
+        }
+    }
+    /// bring every watch up to date, counting the changes made by a tick and
+    /// pausing for the first one set to break on them
+    fn check_watches(&mut self, ticked: bool) {
+        for idx in 0..self.watches.len() {
+            let value = self.watch_value(self.watches[idx].target);
+            let watch = &mut self.watches[idx];
+            if ticked && value != watch.value {
+                watch.tally.hits += 1;
+                if watch.break_on_change && !watch.tally.disabled && self.watch_hit.is_none() {
+                    self.paused = true;
+                    self.watch_hit = Some((watch.target, watch.value, value));
+                }
+            }
+            watch.value = value;
+        }
+    }
+    /// pause the moment the output ends with text, checked against whatever
+    /// was written so far whenever an IP writes more
+    ///
+    /// ```
+    /// use refunge::{Arguments, Befunge};
+    ///
+    /// // counts up forever, printing each number
+    /// let args = Arguments { quiet: true, ..Default::default() };
+    /// let mut befunge = Befunge::from_text("0>1+:.v\n ^    <", args).unwrap();
+    /// befunge.capture_output();
+    /// befunge.add_output_trigger("7 ");
+    /// befunge.run(|| true);
+    /// assert_eq!(befunge.output(), "1 2 3 4 5 6 7 ");
+    /// assert_eq!(befunge.stats().ticks, 54);
+    /// assert_eq!(befunge.output_hit(), Some(("7 ", 0)));
+    /// befunge.resume();
+    /// befunge.add_output_trigger("1 ");
+    /// befunge.run(|| true);
+    /// assert!(befunge.output().ends_with("8 9 10 11 "));
+    /// assert!(befunge.remove_output_trigger("7 "));
+    /// assert!(!befunge.remove_output_trigger("7 "));
+    /// ```
+    pub fn add_output_trigger(&mut self, text: &str) {
+        if !text.is_empty() && !self.output_triggers.iter().any(|(t, _)| t == text) {
+            self.output_triggers
+                .push((text.to_string(), Tally::default()));
+        }
+    }
+    /// stop pausing on this text, returning false if there was no such trigger
+    pub fn remove_output_trigger(&mut self, text: &str) -> bool {
+        let before = self.output_triggers.len();
+        self.output_triggers.retain(|(t, _)| t != text);
+        self.output_triggers.len() != before
+    }
+    /// the output trigger that paused things and the IP whose output matched it, if any
+    pub fn output_hit(&self) -> Option<(&str, usize)> {
+        self.output_hit
+            .as_ref()
+            .filter(|_| self.paused)
+            .map(|(text, id)| (text.as_str(), *id))
+    }
+    /// add what an IP wrote to the end of the output kept around, pausing for the
+    /// first trigger it now ends with (only as much is kept as the longest one needs)
+    fn check_output(&mut self, text: &str, id: usize) {
+        let longest = self
+            .output_triggers
+            .iter()
+            .map(|(t, _)| t.len())
+            .max()
+            .unwrap_or(0);
+        self.output_tail.push_str(text);
+        if self.output_tail.len() > longest {
+            let mut cut = self.output_tail.len() - longest;
+            while !self.output_tail.is_char_boundary(cut) {
+                cut -= 1;
+            }
+            self.output_tail.drain(..cut);
+        }
+        for (trigger, tally) in self.output_triggers.iter_mut() {
+            if tally.disabled || !self.output_tail.ends_with(trigger.as_str()) {
+                continue;
+            }
+            tally.hits += 1;
+            if self.output_hit.is_none() {
+                self.paused = true;
+                self.output_hit = Some((trigger.clone(), id));
+                #[cfg(feature = "tui")]
+                {
+                    self.focus = id;
+                }
+            }
+        }
+    }
+    /// every breakpoint, then every watch, then every output trigger, in the order they were added,
+    /// with how often each went off and whether it's enabled
+    ///
+    /// ```
+    /// use refunge::hook::{Breakpoint, DebugEntry, WatchTarget};
+    /// use refunge::{Arguments, Befunge, FungeVector};
+    ///
+    /// let args = Arguments { quiet: true, ..Default::default() };
+    /// let mut befunge = Befunge::from_text("1.2.3.@", args).unwrap();
+    /// befunge.capture_output();
+    /// befunge.add_instruction_breakpoint('.');
+    /// befunge.add_breakpoint(FungeVector(5, 0));
+    /// befunge.add_watch(WatchTarget::Stack { ip: 0, depth: 0 }, false);
+    /// assert!(befunge.set_debug_entry_enabled(0, false));
+    /// befunge.run_until_break(100);
+    /// befunge.run_until_break(100);
+    /// let hits: Vec<u64> = befunge.debug_entries().iter().map(|(_, t)| t.hits).collect();
+    /// assert_eq!(hits, [0, 1, 6]);
+    /// assert!(befunge.remove_debug_entry(1));
+    /// let entries: Vec<DebugEntry> = befunge.debug_entries().iter().map(|(e, _)| e.clone()).collect();
+    /// assert_eq!(entries, [
+    ///     DebugEntry::Breakpoint(Breakpoint::Instruction('.')),
+    ///     DebugEntry::Watch(WatchTarget::Stack { ip: 0, depth: 0 }, false),
+    /// ]);
+    /// befunge.restart();
+    /// assert_eq!(befunge.debug_entries().len(), 2);
+    /// ```
+    pub fn debug_entries(&self) -> Vec<(DebugEntry, Tally)> {
+        let breakpoints = self.breakpoints.borrow();
+        let breakpoints = breakpoints
+            .iter()
+            .map(|(b, tally)| (DebugEntry::Breakpoint(*b), *tally));
+        let watches = self
+            .watches
+            .iter()
+            .map(|w| (DebugEntry::Watch(w.target, w.break_on_change), w.tally));
+        let outputs = self
+            .output_triggers
+            .iter()
+            .map(|(text, tally)| (DebugEntry::Output(text.clone()), *tally));
+        breakpoints.chain(watches).chain(outputs).collect()
+    }
+    /// turn the debug entry at idx on or off, returning false if there isn't one
+    pub fn set_debug_entry_enabled(&mut self, idx: usize, enabled: bool) -> bool {
+        let mut breakpoints = self.breakpoints.borrow_mut();
+        let tally = match idx.checked_sub(breakpoints.len()) {
+            None => &mut breakpoints[idx].1,
+            Some(idx) => match idx.checked_sub(self.watches.len()) {
+                None => &mut self.watches[idx].tally,
+                Some(idx) => match self.output_triggers.get_mut(idx) {
+                    Some((_, tally)) => tally,
+                    None => return false,
+                },
+            },
+        };
+        tally.disabled = !enabled;
+        true
+    }
+    /// remove the debug entry at idx, returning false if there isn't one
+    pub fn remove_debug_entry(&mut self, idx: usize) -> bool {
+        let mut breakpoints = self.breakpoints.borrow_mut();
+        match idx.checked_sub(breakpoints.len()) {
+            None => {
+                breakpoints.remove(idx);
+                true
+            }
+            Some(idx) if idx < self.watches.len() => {
+                self.watches.remove(idx);
+                true
+            }
+            Some(idx) if idx - self.watches.len() < self.output_triggers.len() => {
+                self.output_triggers.remove(idx - self.watches.len());
+                true
+            }
+            Some(_) => false,
+        }
+    }
+    /// the IP held at a breakpoint (or by a pause hook) and where, if any
+    pub fn break_hit(&self) -> Option<(usize, FungeVector)> {
+        if !self.paused {
+            return None;
+        }
+        self.ip_list
+            .iter()
+            .find(|ip| ip.held)
+            .map(|ip| (ip.id, ip.pos))
+    }
+    /// tick until a breakpoint, the end, a request for input, or max_ticks ticks,
+    /// resuming first if stopped at a breakpoint
+    ///
+    /// like [Befunge::ticks], this asks for input instead of reading stdin
+    ///
+    /// ```
+    /// use refunge::input::InputType;
+    /// use refunge::report::RunOutcome;
+    /// use refunge::{Arguments, Befunge, FungeVector};
+    ///
+    /// let args = Arguments { quiet: true, ..Default::default() };
+    /// let mut befunge = Befunge::from_text("12+.&.@", args).unwrap();
+    /// befunge.capture_output();
+    /// befunge.add_breakpoint(FungeVector(2, 0));
+    /// befunge.add_instruction_breakpoint('.');
+    ///
+    /// let hit = |x| RunOutcome::BreakpointHit { ip: 0, pos: FungeVector(x, 0) };
+    /// assert_eq!(befunge.run_until_break(100), hit(2));
+    /// assert_eq!(befunge.run_until_break(100), hit(3));
+    /// assert_eq!(befunge.output(), "");
+    /// assert_eq!(befunge.run_until_break(100), RunOutcome::InputRequested { ip: 0, input: InputType::Number });
+    /// befunge.answer_input(Some(7));
+    /// assert_eq!(befunge.run_until_break(100), hit(5));
+    /// assert!(befunge.remove_instruction_breakpoint('.'));
+    /// assert_eq!(befunge.list_breakpoints().len(), 1);
+    /// assert_eq!(befunge.run_until_break(1), RunOutcome::MaxTicks);
+    /// assert_eq!(befunge.run_until_break(100), RunOutcome::Ended);
+    /// assert_eq!(befunge.output(), "3 7 ");
+    /// ```
+    pub fn run_until_break(&mut self, max_ticks: u64) -> RunOutcome {
+        if self.break_hit().is_some() {
+            self.resume();
+        }
+        let mut ticks = self.ticks();
+        for _ in 0..max_ticks {
+            match ticks.next() {
+                Some(Tick::Ran(_)) => {}
+                Some(Tick::Input(input)) => {
+                    let (ip, _) = ticks.befunge.waiting_for_input().unwrap_or_default();
+                    return RunOutcome::InputRequested { ip, input };
+                }
+                None => break,
+            }
+            if let Some((ip, pos)) = ticks.befunge.break_hit() {
+                return RunOutcome::BreakpointHit { ip, pos };
+            }
+        }
+        drop(ticks);
+        match self.break_hit() {
+            _ if self.ended() => RunOutcome::Ended,
+            Some((ip, pos)) => RunOutcome::BreakpointHit { ip, pos },
+            None => RunOutcome::MaxTicks,
+        }
+    }
+    /// tick until stopped or keep_going says otherwise
+    ///
+    /// with --progress, [Befunge::headline] goes to stderr every so often too
+    pub fn run(&mut self, mut keep_going: impl FnMut() -> bool) {
+        let every = self.args.progress.map(Duration::from_secs);
+        let mut shown = Instant::now();
+        while !self.stopped() && keep_going() {
+            self.tick();
+            if every.is_some_and(|every| shown.elapsed() >= every) {
+                eprintln!("{}", self.headline(None));
+                shown = Instant::now();
+            }
+        }
+        self.flush();
+    }
+    /// tick until stopped, yielding what each tick did
+    ///
+    /// input that would come from stdin is asked for with [Tick::Input] instead,
+    /// after which the iterator ends until [Befunge::answer_input] is called
+    ///
+    /// ```
+    /// use refunge::report::Tick;
+    /// use refunge::input::InputType;
+    /// use refunge::{Arguments, Befunge};
+    ///
+    /// let args = || Arguments { quiet: true, ..Default::default() };
+    /// let mut befunge = Befunge::from_text("1230\"!iH\">:#,_@", args()).unwrap();
+    /// befunge.capture_output();
+    /// assert_eq!(befunge.ticks().count(), 32);
+    /// assert_eq!(befunge.output(), "Hi!");
+    ///
+    /// let mut befunge = Befunge::from_text("&.@", args()).unwrap();
+    /// befunge.capture_output();
+    /// let last = befunge.ticks().last().unwrap();
+    /// assert_eq!(last, Tick::Input(InputType::Number));
+    /// befunge.answer_input(Some(42));
+    /// assert_eq!(befunge.ticks().count(), 2);
+    /// assert_eq!(befunge.output(), "42 ");
+    /// ```
+    pub fn ticks(&mut self) -> Ticks<'_, 'a> {
+        let stdin = self.reader.take_if(|r| r.is_stdin());
+        Ticks {
+            befunge: self,
+            stdin,
+            asked: false,
+        }
+    }
+    /// answer the & or ~ the first IP in line is waiting on, None reflects it instead
+    ///
+    /// IPs asking on the same tick are answered in the order they ran:
+    ///
+    /// ```
+    /// use refunge::input::InputType;
+    /// use refunge::{Arguments, Befunge};
+    ///
+    /// // 't' sends a new ip 0 west around to one '&' as ip 1 reaches the other
+    /// let mut befunge = Befunge::from_text("t&.@.&", Arguments::default()).unwrap();
+    /// befunge.capture_output();
+    /// (0..2).for_each(|_| befunge.tick());
+    /// assert_eq!(befunge.waiting_for_input(), Some((0, InputType::Number)));
+    /// befunge.answer_input(Some(10));
+    /// assert_eq!(befunge.waiting_for_input(), Some((1, InputType::Number)));
+    /// befunge.answer_input(Some(20));
+    /// assert_eq!(befunge.waiting_for_input(), None);
+    /// befunge.run(|| true);
+    /// let segments: Vec<(usize, &str)> = befunge.output_segments().collect();
+    /// assert_eq!(segments, [(0, "10 "), (1, "20 ")]);
+    /// ```
+    pub fn answer_input(&mut self, val: Option<i32>) {
+        if let Some((id, t)) = self.input_queue.pop_front() {
+            self.give_input(id, t, val);
+            // an iterated & or ~ asks again right away
+            self.ip_list[id].waiting = self
+                .input_queue
+                .iter()
+                .find(|(target, _)| *target == id)
+                .map(|(_, t)| *t);
+            #[cfg(feature = "tui")]
+            self.prompt();
+        }
+    }
+    /// save the grid, IPs, output, tick count, and rng state
+    ///
+    /// ```
+    /// use refunge::snapshot::BefungeSnapshot;
+    /// use refunge::{Arguments, Befunge};
+    ///
+    /// let program = "1230\"!iH\">:#,_@";
+    /// let args = || Arguments { quiet: true, ..Default::default() };
+    /// let mut first = Befunge::from_text(program, args()).unwrap();
+    /// first.capture_output();
+    /// for _ in 0..12 {
+    ///     first.tick();
+    /// }
+    /// let saved = BefungeSnapshot::from_json(&first.snapshot().to_json().unwrap()).unwrap();
+    ///
+    /// let mut second = Befunge::from_text(program, args()).unwrap();
+    /// second.capture_output();
+    /// second.restore(saved).unwrap();
+    /// first.run(|| true);
+    /// second.run(|| true);
+    /// assert_eq!(first.output(), "Hi!");
+    /// assert_eq!(second.output(), first.output());
+    /// let stacks = |b: &Befunge| serde_json::to_string(&b.snapshot().ips).unwrap();
+    /// assert_eq!(stacks(&second), stacks(&first));
+    /// assert!(second.snapshot() == first.snapshot());
+    /// ```
+    pub fn snapshot(&self) -> BefungeSnapshot {
+        BefungeSnapshot {
+            version: SNAPSHOT_VERSION,
+            grid: self.grid.clone(),
+            ips: self.ip_list.clone(),
+            output: self.out.text().to_string(),
+            ticks: self.stats.ticks,
+            rng: self.env.rng_state(),
+            exit_code: self.exit_code,
+        }
+    }
+    /// how the run has gone so far, for comparing against a golden one
+    pub fn run_result(&self) -> RunResult {
+        RunResult {
+            output: self.out.text().to_string(),
+            exit_code: self.exit_code.unwrap_or(0),
+            ticks: self.stats.ticks,
+            grid_digest: golden::grid_digest(&self.grid),
+            stacks_digest: golden::stacks_digest(&self.ip_list),
+        }
+    }
+    /// pick up from a snapshot, keeping this run's settings
+    pub fn restore(&mut self, snapshot: BefungeSnapshot) -> Result<()> {
+        snapshot::check_version(snapshot.version)?;
+        let fingerprints = self.args.fingerprints.clone().unwrap_or_default();
+        self.grid = snapshot.grid;
+        self.history.clear();
+        self.departures.clear();
+        self.grid.set_max_cells(self.args.max_grid_cells);
+        self.grid.set_wrap_mode(self.args.wrap_mode);
+        self.grid.set_grid_mode(self.args.grid);
+        self.ip_list = snapshot.ips;
+        self.unfinished_tick = None;
+        for ip in self.ip_list.iter_mut() {
+            ip.fingerprints = fingerprints.clone();
+            ip.extensions = self.args.extensions;
+        }
+        // IPs saved while waiting on input get asked again
+        self.input_queue = self
+            .ip_list
+            .iter()
+            .filter_map(|ip| Some((ip.id, ip.waiting?)))
+            .collect();
+        #[cfg(feature = "tui")]
+        self.prompt();
+        self.out.restore(&snapshot.output);
+        self.stats.ticks = snapshot.ticks;
+        self.env.set_rng_state(&snapshot.rng);
+        self.exit_code = snapshot.exit_code;
+        self.check_watches(false);
+        Ok(())
+    }
+    /// save a snapshot to a file as json
+    pub fn write_snapshot(&self, path: &str) -> Result<()> {
+        std::fs::write(path, self.snapshot().to_json()?)
+            .with_context(|| format!("failed to write snapshot to {path}"))
+    }
+    /// pick up from a snapshot file
+    pub fn read_snapshot(&mut self, path: &str) -> Result<()> {
+        let text = read_to_string(path).with_context(|| format!("failed to read {path}"))?;
+        let snapshot = BefungeSnapshot::from_json(&text)
+            .with_context(|| format!("failed to read snapshot from {path}"))?;
+        self.restore(snapshot)
+    }
+    /// answer & and ~ from this reader instead of stdin or the tui prompt
+    ///
+    /// ```
+    /// use refunge::{Arguments, Befunge};
+    ///
+    /// let args = Arguments { quiet: true, ..Default::default() };
+    /// let mut befunge = Befunge::from_text("&&+.@", args).unwrap();
+    /// befunge.set_input(&b"4\n5\n"[..]);
+    /// befunge.capture_output();
+    /// befunge.run(|| true);
+    /// assert_eq!(befunge.output(), "9 ");
+    /// ```
+    pub fn set_input(&mut self, reader: impl BufRead + 'static) {
+        self.set_reader(InputReader::new(reader));
+    }
+    /// answer & and ~ with this reader, which 'y' says reads ~ a key at a time or not
+    pub fn set_reader(&mut self, reader: InputReader) {
+        self.env.set_unbuffered_input(reader.is_unbuffered());
+        self.reader = Some(reader);
+    }
+    /// was the run stopped by ctrl-c while reading a key for ~, see [InputReader::keys]
+    pub fn interrupted(&self) -> bool {
+        self.reader.as_ref().is_some_and(InputReader::interrupted)
+    }
+    /// write program output here instead of stdout or the output file
+    pub fn set_output(&mut self, writer: impl Write + 'static) {
+        self.out.redirect(writer);
+    }
+    /// decide what files, commands, environment and clock instructions can reach
+    pub fn set_platform(&mut self, platform: impl Platform + 'static) {
+        self.env.set_platform(platform);
+    }
+    /// hand over a socket for SCKE's 'H' to look at, returning the number
+    /// programs know it as (counting up from 0)
+    pub fn add_socket(&mut self, socket: TcpStream) -> i32 {
+        self.env.add_socket(socket)
+    }
+    /// keep program output in memory for output() instead of writing it anywhere
+    pub fn capture_output(&mut self) {
+        self.out = Output::captured().with_max_output(self.args.max_output);
+    }
+    /// throw output away instead of printing it, for timing runs
+    pub fn discard_output(&mut self) {
+        self.out.redirect(std::io::sink());
     }
     /// has the interpreter reached the end
     pub fn ended(&self) -> bool {
         self.ip_list.iter().all(|ip| ip.dead)
     }
+    /// how many IPs haven't stopped yet
+    pub fn live_ips(&self) -> usize {
+        self.ip_list.iter().filter(|ip| !ip.dead).count()
+    }
+    /// the ids and positions of the IPs that haven't stopped yet
+    ///
+    /// ```
+    /// use refunge::vector::FungeVector;
+    /// use refunge::{Arguments, Befunge};
+    ///
+    /// let mut befunge = Befunge::from_text("t12@", Arguments::default()).unwrap();
+    /// // the new IP 0 heads west straight into the '@'
+    /// (0..3).for_each(|_| befunge.tick());
+    /// let live: Vec<(usize, FungeVector)> = befunge.live_positions().collect();
+    /// assert_eq!(live, [(1, FungeVector(2, 0))]);
+    /// ```
+    pub fn live_positions(&self) -> impl Iterator<Item = (usize, FungeVector)> + '_ {
+        self.ip_list
+            .iter()
+            .filter(|ip| !ip.dead)
+            .map(|ip| (ip.id, ip.pos))
+    }
+    /// the IP that died and where it was, if --pause-on-death is holding the
+    /// run paused for it
+    ///
+    /// ```
+    /// use refunge::vector::FungeVector;
+    /// use refunge::{Arguments, Befunge};
+    ///
+    /// let args = || Arguments { pause_on_death: true, ..Default::default() };
+    /// // the new IP 0 heads west into the '@' while IP 1 carries on
+    /// let mut befunge = Befunge::from_text("t12@", args()).unwrap();
+    /// while !befunge.paused() {
+    ///     befunge.tick();
+    /// }
+    /// assert_eq!(befunge.stats().ticks, 2);
+    /// assert_eq!(befunge.died(), Some((0, FungeVector(3, 0))));
+    /// assert_eq!(befunge.live_ips(), 1);
+    ///
+    /// // a 'q' counts too, pausing on the IP that quit
+    /// let mut befunge = Befunge::from_text("t1q", args()).unwrap();
+    /// while !befunge.paused() {
+    ///     befunge.tick();
+    /// }
+    /// assert_eq!(befunge.stats().ticks, 2);
+    /// assert_eq!(befunge.died(), Some((0, FungeVector(2, 0))));
+    /// ```
+    pub fn died(&self) -> Option<(usize, FungeVector)> {
+        self.death.filter(|_| self.paused)
+    }
+    /// the IP that --strict stopped the run over, for getting stuck with a zero
+    /// delta on a cell that can't get it moving again, with where and what that is
+    ///
+    /// ```
+    /// use refunge::{Arguments, Befunge, FungeVector};
+    ///
+    /// let args = |strict| Arguments { quiet: true, strict, max_ticks: Some(100), ..Default::default() };
+    /// // x sets a zero delta and keeps doing so, running forever on the x
+    /// let mut befunge = Befunge::from_text("00x z", args(false)).unwrap();
+    /// befunge.run(|| true);
+    /// assert_eq!(befunge.stats().ticks, 100);
+    /// assert_eq!(befunge.zero_delta_ips(), [0]);
+    /// assert_eq!(befunge.stuck(), None);
+    /// let mut befunge = Befunge::from_text("00x z", args(true)).unwrap();
+    /// befunge.run(|| true);
+    /// assert_eq!(befunge.stats().ticks, 3);
+    /// assert_eq!(befunge.stuck(), Some((0, FungeVector(2, 0), 'x')));
+    ///
+    /// // the second x finds a 1 to head east with, on to the end
+    /// let mut befunge = Befunge::from_text("1000x.@", args(true)).unwrap();
+    /// befunge.capture_output();
+    /// befunge.run(|| true);
+    /// assert!(befunge.ended());
+    /// assert_eq!(befunge.output(), "0 ");
+    /// assert_eq!(befunge.stuck(), None);
+    /// ```
+    pub fn stuck(&self) -> Option<(usize, FungeVector, char)> {
+        self.stuck
+    }
+    /// the ids of the live IPs with a zero delta, which run the same cell every tick
+    /// (each one warned about once, and once more if it tries to 'j' anywhere)
+    ///
+    /// ```
+    /// use refunge::arguments::StackInit;
+    /// use refunge::{logger, Arguments, Befunge, FungeVector};
+    ///
+    /// // starting on a j with nowhere to go, and plenty of 1s to jump with
+    /// let args = Arguments {
+    ///     delta: Some(FungeVector(0, 0)),
+    ///     stack_init: Some(StackInit::parse(&["1"; 20].join(",")).unwrap()),
+    ///     max_ticks: Some(10),
+    ///     ..Default::default()
+    /// };
+    /// logger::init(&args).unwrap();
+    /// let mut befunge = Befunge::from_text("j@", args).unwrap();
+    /// befunge.run(|| true);
+    /// assert_eq!(befunge.zero_delta_ips(), [0]);
+    /// let warned = |about: &str| logger::recent(100).iter().filter(|line| line.contains(about)).count();
+    /// assert_eq!(warned("zero delta at"), 1);
+    /// assert_eq!(warned("cannot jump"), 1);
+    /// ```
+    pub fn zero_delta_ips(&self) -> Vec<usize> {
+        self.ip_list
+            .iter()
+            .filter(|ip| !ip.dead && ip.delta == directions::ORIGIN)
+            .map(|ip| ip.id)
+            .collect()
+    }
+    /// a one-line summary of where the run is: the tick, how many IPs are
+    /// live, and where primary (or else the first live IP) is and which way
+    /// it's going
+    ///
+    /// ```
+    /// use refunge::{Arguments, Befunge};
+    ///
+    /// let mut befunge = Befunge::from_text("1t  v\n   @<", Arguments::default()).unwrap();
+    /// assert_eq!(befunge.headline(None), "tick 0 · 1 IP · IP0 @ (0,0) Δ(1,0)");
+    /// (0..4).for_each(|_| befunge.tick());
+    /// assert_eq!(befunge.headline(None), "tick 4 · 2 IPs · IP0 @ (4,0) Δ(0,1)");
+    /// assert_eq!(befunge.headline(Some(1)), "tick 4 · 2 IPs · IP1 @ (4,1) Δ(-1,0)");
+    /// befunge.run(|| true);
+    /// assert_eq!(befunge.headline(Some(1)), "tick 6 · 0 IPs");
+    /// ```
+    pub fn headline(&self, primary: Option<usize>) -> String {
+        use std::fmt::Write as _;
+        let live = self.live_ips();
+        let mut line = String::with_capacity(64);
+        let plural = if live == 1 { "" } else { "s" };
+        let _ = write!(line, "tick {} · {live} IP{plural}", self.stats.ticks);
+        let ip = primary
+            .and_then(|id| self.ip_list.iter().find(|ip| ip.id == id && !ip.dead))
+            .or_else(|| self.ip_list.iter().find(|ip| !ip.dead));
+        if let Some(ip) = ip {
+            let _ = write!(line, " · IP{} @ {} Δ{}", ip.id, ip.pos, ip.delta);
+        }
+        line
+    }
+    /// has a 't' been turned away for going past --max-ips
+    ///
+    /// ```
+    /// use refunge::{Arguments, Befunge};
+    ///
+    /// // a fork bomb, every ip splits every other tick
+    /// let args = Arguments { quiet: true, max_ips: Some(64), ..Default::default() };
+    /// let mut befunge = Befunge::from_text("t<", args).unwrap();
+    /// assert_eq!(befunge.run_ticks(1000), 1000);
+    /// assert_eq!(befunge.live_ips(), 64);
+    /// assert!(befunge.ip_limit_reached());
+    /// assert!(!befunge.ended());
+    /// ```
+    pub fn ip_limit_reached(&self) -> bool {
+        self.ip_limit_reached
+    }
+    /// has the interpreter run for as many ticks as --max-ticks allows
+    ///
+    /// ```
+    /// use refunge::{Arguments, Befunge};
+    ///
+    /// for quiet in [true, false] {
+    ///     let args = Arguments { quiet, max_ticks: Some(10), ..Default::default() };
+    ///     let mut befunge = Befunge::from_text("1>:.1+", args).unwrap();
+    ///     befunge.capture_output();
+    ///     befunge.run(|| true);
+    ///     assert!(befunge.max_ticks_reached());
+    ///     assert_eq!(befunge.stats().ticks, 10);
+    ///     assert_eq!(befunge.output(), "1 1 ");
+    /// }
+    /// // the tui pauses there, but can still be stepped on by hand
+    /// let args = Arguments { max_ticks: Some(10), ..Default::default() };
+    /// let mut befunge = Befunge::from_text("1>:.1+", args).unwrap();
+    /// befunge.run(|| true);
+    /// assert!(befunge.paused());
+    /// befunge.tick();
+    /// assert_eq!(befunge.stats().ticks, 11);
+    /// ```
+    pub fn max_ticks_reached(&self) -> bool {
+        self.args
+            .max_ticks
+            .is_some_and(|max| self.stats.ticks >= max as u64)
+    }
 
+    /// push out any program output still sitting in a buffer, and the --trace
+    ///
+    /// ```
+    /// use refunge::{Arguments, Befunge};
+    ///
+    /// let path = std::env::temp_dir().join("refunge-doctest-trace.tsv");
+    /// let trace = Some(path.to_string_lossy().into_owned());
+    /// let args = Arguments { quiet: true, trace, ..Default::default() };
+    /// let mut befunge = Befunge::from_text("123+*4-:.@", args).unwrap();
+    /// befunge.capture_output();
+    /// befunge.run(|| true);
+    /// befunge.flush();
+    /// // tick, IP, position, instruction, and stack depth after, then the IP dying
+    /// let golden = [
+    ///     "0\t0\t0,0\t1\t1",
+    ///     "1\t0\t1,0\t2\t2",
+    ///     "2\t0\t2,0\t3\t3",
+    ///     "3\t0\t3,0\t+\t2",
+    ///     "4\t0\t4,0\t*\t1",
+    ///     "5\t0\t5,0\t4\t2",
+    ///     "6\t0\t6,0\t-\t1",
+    ///     "7\t0\t7,0\t:\t2",
+    ///     "8\t0\t8,0\t.\t1",
+    ///     "9\t0\t9,0\t@\t1",
+    ///     "9\t0\tevent\tdied at (9,0)",
+    /// ];
+    /// let trace = std::fs::read_to_string(&path).unwrap();
+    /// for (n, (line, expected)) in trace.lines().zip(golden).enumerate() {
+    ///     assert_eq!(line, expected, "line {}", n + 1);
+    /// }
+    /// assert_eq!(trace.lines().count(), golden.len());
+    /// assert_eq!(befunge.output(), "1 ");
+    /// ```
+    pub fn flush(&mut self) {
+        self.out.flush();
+        if let Some(trace) = &self.trace {
+            trace.borrow_mut().flush().unwrap_or(());
+        }
+    }
+    /// write the --profile report, if one was asked for
+    ///
+    /// ```
+    /// use refunge::{Arguments, Befunge};
+    ///
+    /// // count down from 5, going round the loop once per number
+    /// let path = std::env::temp_dir().join("refunge-doctest-profile.csv");
+    /// let profile = Some(path.to_string_lossy().into_owned());
+    /// let args = Arguments { quiet: true, profile, ..Default::default() };
+    /// let mut befunge = Befunge::from_text("5>1-:v\n ^   _@", args).unwrap();
+    /// befunge.run(|| true);
+    /// befunge.write_profile().unwrap();
+    /// let csv = std::fs::read_to_string(&path).unwrap();
+    /// let expected = [
+    ///     "x,y,instruction,count,reflections",
+    ///     "1,0,\">\",5,0",
+    ///     "2,0,\"1\",5,0",
+    ///     "3,0,\"-\",5,0",
+    ///     "4,0,\":\",5,0",
+    ///     "5,0,\"v\",5,0",
+    ///     "5,1,\"_\",5,0",
+    ///     // the spaces skipped on the way back aren't counted, the ^ they lead to is
+    ///     "1,1,\"^\",4,0",
+    ///     "0,0,\"5\",1,0",
+    ///     "6,1,\"@\",1,0",
+    ///     "",
+    ///     "instruction,count",
+    ///     "\"-\",5",
+    ///     "\"1\",5",
+    ///     "\":\",5",
+    ///     "\">\",5",
+    ///     "\"_\",5",
+    ///     "\"v\",5",
+    ///     "\"^\",4",
+    ///     "\"5\",1",
+    ///     "\"@\",1",
+    ///     "",
+    ///     "x,y,instruction,first_tick,reflections",
+    /// ];
+    /// assert_eq!(csv.lines().collect::<Vec<_>>(), expected);
+    /// ```
+    pub fn write_profile(&self) -> Result<()> {
+        if let (Some(profiler), Some(path)) = (&self.profiler, &self.args.profile) {
+            let file = File::create(path).with_context(|| format!("failed to create {path}"))?;
+            profiler
+                .write(&self.reflected_cells, BufWriter::new(file))
+                .with_context(|| format!("failed to write {path}"))?;
+        }
+        Ok(())
+    }
+    /// save the session for --record, or complain about anything a finished
+    /// --replay left unused
+    ///
+    /// ```
+    /// use refunge::session::Session;
+    /// use refunge::{Arguments, Befunge};
+    ///
+    /// // print the input, then the time from y, then wander off a ? to print 0 or 1
+    /// let program = "&.f1+y.v\n      .?1.@";
+    /// let path = std::env::temp_dir().join("refunge-doctest-session.json");
+    /// let session = Some(path.to_string_lossy().into_owned());
+    /// let args = Arguments { quiet: true, record: session.clone(), ..Default::default() };
+    /// let mut recorded = Befunge::from_text(program, args).unwrap();
+    /// recorded.set_input(&b"41\n"[..]);
+    /// recorded.capture_output();
+    /// recorded.run(|| true);
+    /// recorded.finish_session().unwrap();
+    /// let saved: Session = serde_json::from_str(&std::fs::read_to_string(&path).unwrap()).unwrap();
+    /// assert_eq!((saved.inputs, saved.clock.len()), (vec![Some(41)], 1));
+    ///
+    /// // no input this time, it all comes from the recording
+    /// let args = Arguments { quiet: true, replay: session, ..Default::default() };
+    /// let mut replayed = Befunge::from_text(program, args).unwrap();
+    /// replayed.capture_output();
+    /// replayed.run(|| true);
+    /// replayed.finish_session().unwrap();
+    /// assert!(recorded.output().starts_with("41 "));
+    /// assert_eq!(replayed.output(), recorded.output());
+    /// assert_eq!(replayed.stats().ticks, recorded.stats().ticks);
+    /// ```
+    pub fn finish_session(&self) -> Result<()> {
+        if let Some(path) = &self.args.record {
+            let json = serde_json::to_string_pretty(self.env.session())?;
+            std::fs::write(path, json + "\n")
+                .with_context(|| format!("failed to write session to {path}"))?;
+        }
+        if self.env.replaying() && self.ended() {
+            match self.env.unused() {
+                (0, 0) => {}
+                (inputs, clock) => bail!(
+                    "replay diverged: program ended with {inputs} inputs and {clock} clock readings unused"
+                ),
+            }
+        }
+        Ok(())
+    }
+    /// why --replay stopped matching the recording, if it has
+    pub fn diverged(&self) -> Option<&str> {
+        self.env.diverged()
+    }
+    /// has the program written more than --max-output allows, which
+    /// stops a quiet run
+    ///
+    /// ```
+    /// use refunge::{Arguments, Befunge};
+    ///
+    /// let args = Arguments { quiet: true, max_output: Some(1024), ..Default::default() };
+    /// let mut befunge = Befunge::from_text("\"a\">:#,_", args).unwrap();
+    /// befunge.capture_output();
+    /// befunge.run(|| true);
+    /// assert!(befunge.output_limit_reached());
+    /// assert_eq!(befunge.output(), "a".repeat(1024));
+    /// assert!(befunge.stats().ticks < 20_000);
+    /// ```
+    pub fn output_limit_reached(&self) -> bool {
+        self.out.full()
+    }
+    /// all program output kept so far (tui and --expect only)
+    pub fn output(&self) -> &str {
+        self.out.text()
+    }
+    /// output() split up by the IP that wrote it, as (ip id, text), oldest first
+    ///
+    /// ```
+    /// use refunge::{Arguments, Befunge};
+    ///
+    /// // 't' sends a new ip 0 west around to "2.", while ip 1 carries on east to "1."
+    /// let args = Arguments { quiet: true, ..Default::default() };
+    /// let mut befunge = Befunge::from_text("t1.@.2", args).unwrap();
+    /// befunge.capture_output();
+    /// befunge.run(|| true);
+    /// assert_eq!(befunge.output(), "2 1 ");
+    /// let segments: Vec<(usize, &str)> = befunge.output_segments().collect();
+    /// assert_eq!(segments, [(0, "2 "), (1, "1 ")]);
+    /// ```
+    pub fn output_segments(&self) -> impl Iterator<Item = (usize, &str)> {
+        self.out.segments()
+    }
+    /// every & and ~ answered so far, oldest first (none with --no-echo-input)
+    ///
+    /// ```
+    /// use refunge::input::InputType;
+    /// use refunge::{Arguments, Befunge};
+    ///
+    /// let path = std::env::temp_dir().join("refunge-doctest-echo.txt");
+    /// std::fs::write(&path, "42x").unwrap();
+    /// let args = Arguments {
+    ///     quiet: true,
+    ///     input_file: Some(path.to_string_lossy().into_owned()),
+    ///     ..Default::default()
+    /// };
+    /// let mut befunge = Befunge::from_text("&.~.@", args).unwrap();
+    /// befunge.capture_output();
+    /// befunge.run(|| true);
+    /// assert_eq!(befunge.output(), "42 120 ");
+    /// let echoes: Vec<String> = befunge.input_echoes().iter().map(|e| e.to_string()).collect();
+    /// assert_eq!(echoes, ["\u{27e8}input: 42\u{27e9}", "\u{27e8}input: 'x'\u{27e9}"]);
+    /// let echo = befunge.input_echoes()[1];
+    /// assert_eq!((echo.tick, echo.ip, echo.kind, echo.offset), (2, 0, InputType::Character, 3));
+    /// ```
+    pub fn input_echoes(&self) -> &[InputEcho] {
+        &self.input_echoes
+    }
+    /// the most recent spawns, deaths, quits, file I/O and reflections, oldest first
+    /// (also written to --trace)
+    ///
+    /// ```
+    /// use refunge::{Arguments, Befunge};
+    ///
+    /// // each 't' sends an IP west into an '@', and the last one standing quits
+    /// let args = Arguments { quiet: true, ..Default::default() };
+    /// let mut befunge = Befunge::from_text("#@t#@t5q", args).unwrap();
+    /// befunge.run(|| true);
+    /// let log: Vec<String> = befunge.event_log().iter().map(|e| e.to_string()).collect();
+    /// assert_eq!(log, [
+    ///     "tick 1: IP 1 spawned IP 0 at (2,0)",
+    ///     "tick 2: IP 0 died at (1,0)",
+    ///     "tick 3: IP 2 spawned IP 1 at (5,0)",
+    ///     "tick 4: IP 1 died at (4,0)",
+    ///     "tick 5: IP 2 quit with exit code 5",
+    /// ]);
+    /// ```
+    pub fn event_log(&self) -> &EventLog {
+        &self.event_log
+    }
+    /// counters collected so far
+    pub fn stats(&self) -> &Stats {
+        &self.stats
+    }
+    /// every cell IPs have reflected off since the run started
+    ///
+    /// a loop that keeps reflecting off the same cell only warns once, with
+    /// --warn-reflect, but counts every time:
+    ///
+    /// ```
+    /// use log::{Level, LevelFilter, Log, Metadata, Record};
+    /// use refunge::{Arguments, Befunge, FungeVector};
+    /// use std::sync::atomic::{AtomicUsize, Ordering};
+    ///
+    /// static WARNINGS: AtomicUsize = AtomicUsize::new(0);
+    /// struct Warnings;
+    /// impl Log for Warnings {
+    ///     fn enabled(&self, metadata: &Metadata) -> bool {
+    ///         metadata.level() <= Level::Warn
+    ///     }
+    ///     fn log(&self, record: &Record) {
+    ///         if self.enabled(record.metadata()) {
+    ///             WARNINGS.fetch_add(1, Ordering::Relaxed);
+    ///         }
+    ///     }
+    ///     fn flush(&self) {}
+    /// }
+    /// log::set_logger(&Warnings).unwrap();
+    /// log::set_max_level(LevelFilter::Warn);
+    ///
+    /// let args = Arguments { quiet: true, warn_reflect: true, max_ticks: Some(1000), ..Default::default() };
+    /// let mut befunge = Befunge::from_text("X", args).unwrap();
+    /// befunge.run(|| true);
+    /// assert_eq!(WARNINGS.load(Ordering::Relaxed), 1);
+    /// let record = befunge.reflected_cells()[&FungeVector(0, 0)];
+    /// assert_eq!((record.first_tick, record.count, record.instruction), (0, 1000, 'X'));
+    /// assert_eq!(befunge.stats().reflection_summary(), "1 cell caused 1000 reflections");
+    /// ```
+    pub fn reflected_cells(&self) -> &HashMap<FungeVector, ReflectionRecord> {
+        &self.reflected_cells
+    }
     /// log the contents of all IPs' stacks
     pub fn log_stacks(&self) {
         println!("Final stack contents:");
@@ -226,132 +2391,257 @@ impl<'a> Befunge<'a> {
             println!("IP {idx}: {:?}", ip.stacks);
         }
     }
-    fn stack_constraints(&self) -> Vec<Constraint> {
-        let mut arr = vec![];
-        for ip in &self.ip_list {
-            arr.push(Constraint::Length(1));
-            for _ in &ip.stacks {
-                arr.push(Constraint::Length(8));
+}
+
+/// iterator over ticks, made by [Befunge::ticks]
+pub struct Ticks<'b, 'a> {
+    befunge: &'b mut Befunge<'a>,
+    /// put back once done, so input goes to stdin again outside the iterator
+    stdin: Option<InputReader>,
+    /// has the pending input already been yielded
+    asked: bool,
+}
+impl Iterator for Ticks<'_, '_> {
+    type Item = Tick;
+    fn next(&mut self) -> Option<Tick> {
+        if let Some((_, input)) = self.befunge.waiting_for_input() {
+            if self.asked {
+                return None;
             }
+            self.asked = true;
+            return Some(Tick::Input(input));
+        }
+        if self.befunge.stopped() {
+            return None;
         }
-        arr.push(Constraint::Min(1));
-        arr
+        Some(Tick::Ran(self.befunge.step()))
     }
-    fn max_stack_len(&self) -> u16 {
-        self.ip_list
-            .iter()
-            .map(|ip| ip.stacks.iter().max_by_key(|s| s.len()).unwrap().len())
-            .max()
-            .unwrap() as u16
+}
+impl Drop for Ticks<'_, '_> {
+    fn drop(&mut self) {
+        if let Some(stdin) = self.stdin.take() {
+            self.befunge.reader = Some(stdin);
+        }
     }
+}
 
-    /// render the grid, stack, output, and message
-    pub fn render(&mut self, f: &mut Frame) {
-        let grid_width = (self.grid.width() as u16 + 2).clamp(20, 80);
-        let grid_height = (self.grid.height() as u16 + 2).clamp(9, 25);
-        let output_height = textwrap::wrap(&self.out, grid_width as usize - 2).len() as u16 + 2;
-        let stack_height = (grid_height + output_height).max(self.max_stack_len() + 2);
-        let chunks = Layout::new()
-            .constraints(vec![Constraint::Length(grid_width), Constraint::Min(0)])
-            .direction(Horizontal)
-            .split(f.size());
-        let column_a = Layout::new()
-            .constraints(vec![
-                Constraint::Length(grid_height),
-                Constraint::Length(output_height),
-                Constraint::Length(3),
-                Constraint::Min(0),
-            ])
-            .split(chunks[0]);
-        let column_b = Layout::new()
-            .constraints([Constraint::Length(stack_height), Constraint::Min(1)])
-            .split(chunks[1]);
-        let stack_zone = Layout::new()
-            .constraints(self.stack_constraints())
-            .direction(Horizontal)
-            .split(column_b[0]);
-        let output = Paragraph::new(self.out.clone())
-            .wrap(Wrap { trim: false })
-            .block(Block::default().borders(Borders::ALL).title("Output"))
-            .scroll((self.output_scroll, 0));
+/// how a program run to completion by [run] went
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Outcome {
+    /// the code given to q, or 0
+    pub exit_code: i32,
+    /// everything written by , and .
+    pub output: String,
+}
 
-        f.render_widget(
-            self.grid.clone().highlights(self.ip_list.clone()),
-            column_a[0],
-        );
-        f.render_widget(output, column_a[1]);
-        if self.inputting {
-            f.render_widget(self.textarea.widget(), column_a[2])
-        }
-        if self.ended() {
-            f.render_widget(
-                Paragraph::new("Funge ended.\nPress r to restart,\nor q to exit."),
-                column_a[2],
-            )
-        }
-        let mut index = 0;
-        for ip in &self.ip_list {
-            f.render_widget(
-                Paragraph::new(format!("IP {}", ip.id))
-                    .wrap(Wrap { trim: true })
-                    .block(Block::default().borders(Borders::TOP | Borders::BOTTOM)),
-                stack_zone[index],
-            );
-            index += 1;
-            for stack in &ip.stacks {
-                stack.render(f, stack_zone[index], stack_height, "Stack");
-                index += 1;
+/// run a program without the tui until it stops, capturing its output
+///
+/// ```
+/// let outcome = refunge::run("\"!dlrow ,olleH\">:#,_@", refunge::Arguments::default()).unwrap();
+/// assert_eq!(outcome.output, "Hello, world!");
+/// assert_eq!(outcome.exit_code, 0);
+/// ```
+pub fn run(text: &str, mut args: Arguments) -> Result<Outcome, RefungeError> {
+    args.quiet = true;
+    let mut befunge = Befunge::from_text(text, args)?;
+    befunge.capture_output();
+    befunge.run(|| true);
+    Ok(Outcome {
+        exit_code: befunge.exit_code.unwrap_or(0),
+        output: befunge.output().to_string(),
+    })
+}
+
+/// a before hook pausing IPs on the breakpoints that aren't disabled,
+/// counting a hit each time one gets held there
+fn breakpoint_hook(
+    breakpoints: Rc<RefCell<Vec<(Breakpoint, Tally)>>>,
+) -> impl FnMut(&HookContext) -> HookAction {
+    move |hook| {
+        let mut breakpoints = breakpoints.borrow_mut();
+        let hit = breakpoints.iter_mut().find(|(b, tally)| {
+            !tally.disabled && b.matches(hook.pos, hook.instruction, hook.ip.string_mode)
+        });
+        match hit {
+            // a held IP is carrying on from this very pause, not hitting it again
+            Some((_, tally)) => {
+                if !hook.ip.held {
+                    tally.hits += 1;
+                }
+                HookAction::Pause
             }
-        }
-        if self.paused {
-            f.render_widget(Paragraph::new("paused"), column_b[1])
+            None => HookAction::Continue,
         }
     }
 }
 
-#[derive(Default, Copy, Clone, Debug)]
-pub enum InputType {
-    #[default]
-    Number,
-    Character,
+/// the IPs at the start of a tick and the cells written during it, kept
+/// for TRDS to jump back to
+struct Checkpoint {
+    tick: u64,
+    /// the grid's width and height at the start of the tick
+    size: (usize, usize),
+    /// what the cells written during the tick held before, in the order
+    /// they were written
+    undo: Vec<(FungeVector, i32)>,
+    ips: VecDeque<InstructionPointer>,
+    rng: RngState,
+}
+
+/// a tick in progress, and everything put aside until every IP has had its turn
+struct TickState {
+    report: TickReport,
+    /// requests from the IPs, handled once the tick is over
+    events: Vec<Event>,
+    /// the first IP to die this tick, for --pause-on-death
+    died: Option<(usize, FungeVector)>,
+    /// IPs that reflected off something and where, noted down once they're all done
+    reflections: Vec<(usize, FungeVector, Reflection, Option<String>)>,
+    /// how long the event log was at the start, for --trace
+    logged: u64,
+    /// nothing gets put together for subscribers when there aren't any
+    subscribed: bool,
+    published: Vec<InterpreterEvent>,
+    was_running: bool,
+    /// the IP whose turn is next, or still going if it ran out of fuel
+    next: usize,
+    /// what that IP is running, once it's started
+    turn: Option<Turn>,
 }
-impl InputType {
-    /// parse some text into the desired type
-    fn parse(&self, text: &str) -> i32 {
-        match self {
-            InputType::Number => text.parse().unwrap_or_default(),
-            InputType::Character => text.parse::<char>().unwrap_or_default() as i32,
+
+/// the instruction an IP is running this tick, and where it was
+struct Turn {
+    instruction: char,
+    pos: FungeVector,
+    string_mode: bool,
+    /// a bounce off the edge getting there, which command() would forget
+    bounced: Option<Reflection>,
+    action: HookAction,
+}
+
+/// read a file other than the program, saying what it was for if that fails
+fn read_file(action: &'static str, path: &str) -> Result<String, RefungeError> {
+    read_to_string(path).map_err(|error| RefungeError::Io {
+        action,
+        path: path.to_string(),
+        error,
+    })
+}
+
+/// a seed for runs not given one, from the keys std picks for hash maps,
+/// since rand's own source of randomness doesn't build for wasm32
+fn random_seed() -> u64 {
+    RandomState::new().hash_one("refunge")
+}
+
+/// IP 0 as it is before the first tick, at the start of the first line (or the
+/// first non-# one with --script) heading east, unless --start or --delta say otherwise
+///
+/// ```
+/// use refunge::arguments::Arguments;
+/// use refunge::{Befunge, FungeVector};
+///
+/// let program = "1.@\n2.@\n@.3";
+/// let run = |start, delta| {
+///     let args = Arguments { quiet: true, start, delta, ..Default::default() };
+///     let mut befunge = Befunge::from_text(program, args).unwrap();
+///     befunge.capture_output();
+///     befunge.run(|| true);
+///     let first = befunge.output().to_string();
+///     befunge.restart();
+///     befunge.run(|| true);
+///     assert_eq!(befunge.output(), first);
+///     first
+/// };
+/// assert_eq!(run(None, None), "1 ");
+/// assert_eq!(run(Some(FungeVector(0, 1)), None), "2 ");
+/// assert_eq!(run(Some(FungeVector(2, 2)), Some(FungeVector(-1, 0))), "3 ");
+///
+/// let args = Arguments { start: Some(FungeVector(3, 0)), ..Default::default() };
+/// let err = Befunge::from_text(program, args).err().unwrap();
+/// assert_eq!(err.to_string(), "--start (3,0) is outside the 3x3 grid");
+/// ```
+fn starting_ip(grid: &FungeGrid, args: &Arguments) -> InstructionPointer {
+    let start = args.start.unwrap_or(grid.start_pos(args.script));
+    let delta = args.delta.unwrap_or(directions::EAST);
+    let mut ip = InstructionPointer::new(start, delta, 0);
+    ip.fingerprints = args.fingerprints.clone().unwrap_or_default();
+    ip.extensions = args.extensions;
+    for value in args.stack_init.iter().flat_map(|init| &init.0) {
+        match value {
+            StackValue::Number(n) => ip.push(*n),
+            StackValue::Text(text) => ip.push(text.clone()),
         }
     }
-    /// parse user input into the desired type
-    fn parse_stdin(&self) -> i32 {
-        match self {
-            InputType::Number => parse_input(),
-            InputType::Character => parse_input::<char>() as i32,
+    ip
+}
+
+/// the grid for a program's text, trailing spaces and all if asked for, and
+/// its tabs dealt with as --tabs says
+fn load_text(text: String, source: &Source, args: &Arguments) -> Result<FungeGrid, RefungeError> {
+    let text = match (args.tabs, first_tab(&text)) {
+        (_, None) => text,
+        (TabMode::Keep, Some((line, column))) => {
+            log::warn!("{source} has a tab at line {line}, column {column}, which takes up one cell and reflects (see --tabs)");
+            text
         }
-    }
-    /// check if a string would be valid if it was parsed as the desired type
-    fn can_parse(&self, text: &str) -> bool {
-        match self {
-            InputType::Number => text.parse::<i32>().is_ok(),
-            InputType::Character => text.parse::<char>().is_ok(),
+        (TabMode::Expand, Some(_)) => expand_tabs(&text, args.tab_width.unwrap_or(1)),
+        (TabMode::Reject, Some((line, column))) => {
+            return Err(RefungeError::Tab {
+                from: source.to_string(),
+                line,
+                column,
+            })
         }
-    }
+    };
+    Ok(match args.preserve_trailing_space {
+        true => FungeGrid::with_trailing_space(text),
+        false => FungeGrid::new(text),
+    })
 }
-
-/// loop getting inputs until the user enters one that can be parsed
-fn parse_input<T>() -> T
-where
-    T: FromStr,
-    <T as FromStr>::Err: Display,
-{
-    let mut buffer = String::new();
-    loop {
-        buffer.clear();
-        io::stdin().read_line(&mut buffer).unwrap();
-        match buffer.trim().parse() {
-            Ok(parsed) => return parsed,
-            Err(err) => eprintln!("\x1b[31m{err}\x1b[m"),
+/// read the program and place any overlays over it, returning the grid along
+/// with a message for each overlay that overwrote existing cells
+fn load_grid(source: &Source, args: &Arguments) -> Result<(FungeGrid, Vec<String>), RefungeError> {
+    if let Source::File(path) = source {
+        if Path::new(path).is_dir() {
+            return Err(RefungeError::IsDirectory { path: path.clone() });
+        }
+    }
+    let text = source.read().map_err(|error| RefungeError::Load {
+        from: source.to_string(),
+        error,
+    })?;
+    if text.lines().next().is_none() {
+        return Err(RefungeError::Empty {
+            from: source.to_string(),
+        });
+    }
+    let mut grid = load_text(text, source, args)?;
+    let mut name = source.to_string();
+    let mut conflicts = vec![];
+    for overlay in &args.overlay {
+        let text = read_file("read overlay", &overlay.path)?;
+        let overwritten = grid.overlay(text, overlay.pos);
+        if let Some(first) = overwritten.first() {
+            let msg = format!(
+                "overlay {} overwrote {} cell(s), first at ({}, {})",
+                overlay.path,
+                overwritten.len(),
+                first.0,
+                first.1
+            );
+            if args.strict {
+                return Err(RefungeError::Invalid(msg));
+            }
+            conflicts.push(msg);
         }
+        name.push_str(&format!(
+            " + {}@{},{}",
+            overlay.path, overlay.pos.0, overlay.pos.1
+        ));
     }
+    grid.set_max_cells(args.max_grid_cells);
+    grid.set_wrap_mode(args.wrap_mode);
+    grid.set_grid_mode(args.grid);
+    Ok((grid.named(name), conflicts))
 }
@@ -0,0 +1,1516 @@
+use super::Befunge;
+use crate::ansi;
+use crate::glyphs;
+use crate::grid::{ip_color, FungeGrid};
+use crate::hook::{DebugEntry, Watch, WatchTarget};
+use crate::input::{self, InputType};
+use crate::key;
+use crate::logger;
+use crate::pointer::InstructionPointer;
+use crate::stack::StackLayout;
+use crate::vector::directions::ORIGIN;
+use crate::vector::FungeVector;
+use crossterm::event::{KeyCode, KeyEvent, KeyEventKind, KeyModifiers};
+use ratatui::layout::{Constraint, Direction::Horizontal, Direction::Vertical, Layout};
+use ratatui::style::{Color, Modifier, Style};
+use ratatui::text::{Line, Span};
+use ratatui::widgets::{Borders, Paragraph, Wrap};
+use ratatui::Frame;
+use std::borrow::Cow;
+use std::collections::BTreeMap;
+use std::ops::Range;
+use std::time::{Duration, Instant};
+use tui_textarea::TextArea;
+
+/// how long a cell stays flashed after an IP reflects off it
+const REFLECTION_FLASH: Duration = Duration::from_millis(500);
+/// ticks run in turbo mode between looks at the clock
+const TURBO_CHECK_EVERY: u64 = 1024;
+
+/// what the letter after m or ` does with its mark
+#[derive(Clone, Copy)]
+pub(super) enum MarkKey {
+    Set,
+    Jump,
+}
+
+/// where the stack panes go: side by side in so many columns, or one under
+/// another in so many rows
+#[derive(Clone, Copy)]
+enum StackRoom {
+    Columns(u16),
+    Rows(u16),
+}
+
+/// what's being typed into the input box, when it isn't & or ~ input
+#[derive(Clone, Copy)]
+pub(super) enum TextPrompt {
+    /// a watch to add or remove, opened with w
+    Watch,
+    /// an output trigger to add or remove, opened with /
+    Output,
+}
+
+/// an empty input box for the & and ~ prompt
+pub(super) fn textarea<'a>() -> TextArea<'a> {
+    let mut textarea = TextArea::default();
+    textarea.set_cursor_style(Style::default());
+    textarea
+}
+
+impl Befunge<'_> {
+    /// is there a tick available
+    #[doc(hidden)]
+    pub fn has_tick(&self) -> bool {
+        self.ticks.has_tick()
+    }
+    /// stop listening for keys, before handing the terminal back
+    #[doc(hidden)]
+    pub fn stop_key_events(&mut self) {
+        self.key_events.stop();
+    }
+    /// is turbo mode running ticks as fast as they'll go
+    pub fn turbo(&self) -> bool {
+        self.turbo.is_some()
+    }
+    /// start or stop turbo mode, resuming first when paused
+    pub fn toggle_turbo(&mut self) {
+        if self.turbo.take().is_none() {
+            self.resume();
+            self.turbo = Some((Instant::now(), self.stats.ticks));
+        }
+    }
+    /// in turbo mode, tick for about as long as slice with nothing in between,
+    /// leaving turbo mode once a breakpoint, a watch, a request for input,
+    /// or the end stops the run
+    ///
+    /// ```
+    /// use refunge::{Arguments, Befunge};
+    /// use std::time::{Duration, Instant};
+    ///
+    /// // counting down from 125000 at 8 ticks a lap, a bit over a million ticks
+    /// let program = "'}:*8*>1-:zv\n      ^    _@";
+    /// let mut befunge = Befunge::from_text(program, Arguments::default()).unwrap();
+    /// befunge.toggle_turbo();
+    /// let start = Instant::now();
+    /// while befunge.turbo() {
+    ///     befunge.run_turbo(Duration::from_millis(50));
+    /// }
+    /// assert!(befunge.ended());
+    /// assert_eq!(befunge.stats().ticks, 1_000_005);
+    /// assert!(start.elapsed() < Duration::from_millis(500));
+    /// ```
+    pub fn run_turbo(&mut self, slice: Duration) {
+        let start = Instant::now();
+        let mut ticks = 0u64;
+        while self.turbo.is_some() {
+            self.tick();
+            ticks += 1;
+            if self.paused() || self.stopped() || self.waiting_for_input().is_some() {
+                self.turbo = None;
+            } else if ticks.is_multiple_of(TURBO_CHECK_EVERY) && start.elapsed() >= slice {
+                break;
+            }
+        }
+    }
+    /// ticks per second since turbo mode started, while it's on
+    pub fn turbo_rate(&self) -> Option<f64> {
+        let (since, ticks) = self.turbo?;
+        let secs = since.elapsed().as_secs_f64();
+        Some((self.stats.ticks - ticks) as f64 / secs.max(f64::EPSILON))
+    }
+    /// handle key input for scrolling, pausing, etc
+    #[doc(hidden)]
+    pub fn handle_key_events(&mut self) -> bool {
+        match self.key_events.next() {
+            Some(event) => self.handle_key(event),
+            None => false,
+        }
+    }
+    /// handle a single key press, returning true once it's time to quit
+    ///
+    /// esc or ctrl-c cancel an input prompt, reflecting the IP that asked:
+    ///
+    /// ```
+    /// use crossterm::event::{KeyCode, KeyEvent, KeyEventKind, KeyModifiers};
+    /// use refunge::{Arguments, Befunge, FungeVector};
+    ///
+    /// let key = |code| KeyEvent::new_with_kind(code, KeyModifiers::NONE, KeyEventKind::Release);
+    /// let mut befunge = Befunge::from_text("&.@", Arguments::default()).unwrap();
+    /// befunge.tick();
+    /// assert!(befunge.waiting_for_input().is_some());
+    /// befunge.handle_key(key(KeyCode::Char('4')));
+    /// assert!(!befunge.handle_key(key(KeyCode::Esc)));
+    /// assert_eq!(befunge.waiting_for_input(), None);
+    /// assert!(!befunge.paused());
+    /// assert_eq!(befunge.snapshot().ips[0].delta, FungeVector(-1, 0));
+    /// ```
+    #[doc(hidden)]
+    pub fn handle_key(&mut self, event: KeyEvent) -> bool {
+        // give priority to input events
+        if !self.input_queue.is_empty() {
+            if matches!(event, key!(Esc) | key!(ctrl;'c')) {
+                self.answer_input(None);
+                self.clear_prompt();
+            } else {
+                self.handle_tui_input(event);
+            }
+            return false;
+        }
+        if let Some(text_prompt) = self.text_prompt {
+            if matches!(event, key!(Esc) | key!(ctrl;'c')) {
+                self.text_prompt = None;
+                self.clear_prompt();
+            } else {
+                match text_prompt {
+                    TextPrompt::Watch => self.handle_watch_input(event),
+                    TextPrompt::Output => self.handle_output_input(event),
+                }
+            }
+            return false;
+        }
+        if let Some(mark_key) = self.mark_key {
+            if event.kind == KeyEventKind::Release {
+                self.mark_key = None;
+                match event.code {
+                    KeyCode::Char(c) if c.is_ascii_alphabetic() => self.use_mark(mark_key, c),
+                    KeyCode::Esc => {}
+                    _ => self.notice = Some("Marks are named with a letter".to_string()),
+                }
+            }
+            return false;
+        }
+        if matches!(event, key!(ctrl;'c')) {
+            return true;
+        }
+        match event {
+            key!('.') => self.ticks.speed_up(),
+            key!(',') => self.ticks.slow_down(),
+            key!(Right) if self.paused => self.tick(),
+            key!('p') => self.paused = !self.paused,
+            key!('t') => self.toggle_turbo(),
+            key!('d') => {
+                self.args.pause_on_death = !self.args.pause_on_death;
+                self.notice = Some(match self.args.pause_on_death {
+                    true => "Pausing when an IP dies".to_string(),
+                    false => "Not pausing when an IP dies".to_string(),
+                });
+            }
+            key!('n') => {
+                self.args.ansi = !self.args.ansi;
+                self.notice = Some(match self.args.ansi {
+                    true => "Coloring output by its escape sequences".to_string(),
+                    false => "Leaving escape sequences out of output".to_string(),
+                });
+            }
+            key!('y') => {
+                self.args.color_grid = !self.args.color_grid;
+                self.notice = Some(match self.args.color_grid {
+                    true => "Coloring the grid by instruction".to_string(),
+                    false => "Not coloring the grid".to_string(),
+                });
+            }
+            key!('h') => self.grid_scroll.1 = self.grid_scroll.1.saturating_sub(1),
+            key!('j') => self.grid_scroll.0 = self.grid_scroll.0.saturating_add(1),
+            key!('k') => self.grid_scroll.0 = self.grid_scroll.0.saturating_sub(1),
+            key!('l') => self.grid_scroll.1 = self.grid_scroll.1.saturating_add(1),
+            key!('i') => self.output_scroll = self.output_scroll.saturating_add(1),
+            key!('o') => self.output_scroll = self.output_scroll.saturating_sub(1),
+            key!('u') => {
+                self.unwrapped_output = !self.unwrapped_output;
+                self.output_hscroll = 0;
+            }
+            key!(any;'<') => self.output_hscroll = self.output_hscroll.saturating_sub(1),
+            key!(any;'>') => self.output_hscroll = self.output_hscroll.saturating_add(1),
+            key!('m') => self.mark_key = Some(MarkKey::Set),
+            key!('`') | key!('\'') => self.mark_key = Some(MarkKey::Jump),
+            key!('r') => self.restart(),
+            key!('g') => self.show_log = !self.show_log,
+            key!(Tab) if self.show_debug => {
+                self.debug_selected = (self.debug_selected + 1) % self.debug_entries().len().max(1)
+            }
+            key!(Tab) => self.focus = (self.focus + 1) % self.ip_list.len().max(1),
+            key!('b') => self.show_debug = !self.show_debug,
+            key!('v') => self.show_events = !self.show_events,
+            key!('[') => self.events_scroll = self.events_scroll.saturating_add(1),
+            key!(']') => self.events_scroll = self.events_scroll.saturating_sub(1),
+            key!('e') if self.show_debug => {
+                if let Some((entry, tally)) = self.debug_entries().get(self.debug_selected) {
+                    self.set_debug_entry_enabled(self.debug_selected, tally.disabled);
+                    self.notice = Some(match tally.disabled {
+                        true => format!("Enabled {} {entry}", entry.kind()),
+                        false => format!("Disabled {} {entry}", entry.kind()),
+                    });
+                }
+            }
+            key!('x') if self.show_debug => {
+                if let Some((entry, _)) = self.debug_entries().get(self.debug_selected) {
+                    self.remove_debug_entry(self.debug_selected);
+                    self.notice = Some(format!("Deleted {} {entry}", entry.kind()));
+                    self.debug_selected = self
+                        .debug_selected
+                        .min(self.debug_entries().len().saturating_sub(1));
+                }
+            }
+            key!('c') if self.show_debug => self.jump_to_debug_entry(),
+            key!('f') => self.filter_output = !self.filter_output,
+            key!('a') => self.expand_stacks = !self.expand_stacks,
+            key!('z') => {
+                self.horizontal_stacks = match self.horizontal_stacks {
+                    None => Some(true),
+                    Some(true) => Some(false),
+                    Some(false) => None,
+                };
+                self.notice = Some(match self.horizontal_stacks {
+                    None => "Laying out stacks to fit the terminal".to_string(),
+                    Some(true) => "Laying out stacks horizontally".to_string(),
+                    Some(false) => "Laying out stacks vertically".to_string(),
+                });
+            }
+            key!('{') => self.stack_hscroll = self.stack_hscroll.saturating_add(1),
+            key!('}') => self.stack_hscroll = self.stack_hscroll.saturating_sub(1),
+            key!('w') => {
+                self.text_prompt = Some(TextPrompt::Watch);
+                self.title_watch(None);
+            }
+            key!(any;'/') => {
+                self.text_prompt = Some(TextPrompt::Output);
+                self.title_output(None);
+            }
+            key!('s') => {
+                let path = self.args.snapshot_out.as_deref().unwrap_or("snapshot.json");
+                self.notice = Some(match self.write_snapshot(path) {
+                    Ok(()) => format!("Saved snapshot to {path}"),
+                    Err(err) => format!("{err:#}"),
+                });
+            }
+            key!('q') if self.ended() => return true,
+            _ => {}
+        }
+        false
+    }
+    fn handle_tui_input(&mut self, event: KeyEvent) {
+        let Some((_, input_type)) = self.waiting_for_input() else {
+            return;
+        };
+        if let InputType::Character = input_type {
+            return self.handle_raw_input(event);
+        }
+        if matches!(event, key!(Enter)) {
+            if let Ok(val) = input_type.parse(&self.textarea.lines()[0]) {
+                self.answer_input(Some(val));
+                self.clear_prompt();
+            }
+            return;
+        }
+        if self.textarea.input(event) {
+            let parsed = input_type.parse(&self.textarea.lines()[0]);
+            let color = if parsed.is_ok() {
+                Color::LightGreen
+            } else {
+                Color::LightRed
+            };
+            self.textarea.set_style(Style::default().fg(color));
+            self.title_prompt(parsed.err().as_deref());
+        }
+    }
+    /// take a watch to add (or remove, if it's already there) from the input box,
+    /// like 3,4 for a cell or 0:1 for IP 0's second value from the top,
+    /// with a ! on the end to pause when it changes
+    ///
+    /// ```
+    /// use crossterm::event::{KeyCode, KeyEvent, KeyEventKind, KeyModifiers};
+    /// use ratatui::{backend::TestBackend, Terminal};
+    /// use refunge::hook::WatchTarget;
+    /// use refunge::{Arguments, Befunge, FungeVector};
+    ///
+    /// let key = |code, kind| KeyEvent::new_with_kind(code, KeyModifiers::NONE, kind);
+    /// let mut befunge = Befunge::from_text("'a20p@", Arguments::default()).unwrap();
+    /// for code in "w2,0!".chars().map(KeyCode::Char).chain([KeyCode::Enter]) {
+    ///     befunge.handle_key(key(code, KeyEventKind::Press));
+    ///     befunge.handle_key(key(code, KeyEventKind::Release));
+    /// }
+    /// assert_eq!(befunge.watches()[0].target, WatchTarget::Cell(FungeVector(2, 0)));
+    /// (0..4).for_each(|_| befunge.tick());
+    /// assert!(befunge.paused());
+    /// let mut terminal = Terminal::new(TestBackend::new(80, 24)).unwrap();
+    /// terminal.draw(|f| befunge.render(f)).unwrap();
+    /// let buffer = terminal.backend().buffer();
+    /// let rows: Vec<String> = (0..24)
+    ///     .map(|y| (0..80).map(|x| buffer.get(x, y).symbol.as_str()).collect())
+    ///     .collect();
+    /// assert!(rows.iter().any(|row| row.contains("(2,0)! = 97 'a'")));
+    /// assert!(rows.iter().any(|row| row.contains("watch (2,0) changed from 50 to 97")));
+    /// ```
+    fn handle_watch_input(&mut self, event: KeyEvent) {
+        if event.code == KeyCode::Enter && event.kind != KeyEventKind::Release {
+            return;
+        }
+        if !matches!(event, key!(Enter)) {
+            self.textarea.input(event);
+            return;
+        }
+        let text = self.textarea.lines()[0].trim().to_string();
+        let (text, break_on_change) = match text.strip_suffix('!') {
+            Some(text) => (text, true),
+            None => (text.as_str(), false),
+        };
+        match WatchTarget::parse(text) {
+            Ok(target) if self.remove_watch(target) => {
+                self.notice = Some(format!("Stopped watching {target}"));
+            }
+            Ok(target) => {
+                self.add_watch(target, break_on_change);
+                self.notice = Some(format!("Watching {target}"));
+            }
+            Err(reason) => return self.title_watch(Some(&reason)),
+        }
+        self.text_prompt = None;
+        self.clear_prompt();
+    }
+    /// take an output trigger typed into the input box once enter is pressed,
+    /// removing it instead if it's already there
+    ///
+    /// ```
+    /// use crossterm::event::{KeyCode, KeyEvent, KeyEventKind, KeyModifiers};
+    /// use ratatui::{backend::TestBackend, Terminal};
+    /// use refunge::hook::DebugEntry;
+    /// use refunge::{Arguments, Befunge};
+    ///
+    /// let key = |code, kind| KeyEvent::new_with_kind(code, KeyModifiers::NONE, kind);
+    /// let mut befunge = Befunge::from_text("0>1+:.v\n ^    <", Arguments::default()).unwrap();
+    /// let type_in = |befunge: &mut Befunge| {
+    ///     for code in "/7\\n".chars().map(KeyCode::Char).chain([KeyCode::Enter]) {
+    ///         befunge.handle_key(key(code, KeyEventKind::Press));
+    ///         befunge.handle_key(key(code, KeyEventKind::Release));
+    ///     }
+    /// };
+    /// type_in(&mut befunge);
+    /// assert_eq!(befunge.debug_entries()[0].0, DebugEntry::Output("7\n".to_string()));
+    /// type_in(&mut befunge);
+    /// assert!(befunge.debug_entries().is_empty());
+    ///
+    /// befunge.add_output_trigger("7 ");
+    /// befunge.run(|| true);
+    /// let mut terminal = Terminal::new(TestBackend::new(100, 24)).unwrap();
+    /// terminal.draw(|f| befunge.render(f)).unwrap();
+    /// let buffer = terminal.backend().buffer();
+    /// let rows: Vec<String> = (0..24)
+    ///     .map(|y| (0..100).map(|x| buffer.get(x, y).symbol.as_str()).collect())
+    ///     .collect();
+    /// assert!(rows.iter().any(|row| row.contains("output matched \"7 \" from IP 0")));
+    /// ```
+    fn handle_output_input(&mut self, event: KeyEvent) {
+        if event.code == KeyCode::Enter && event.kind != KeyEventKind::Release {
+            return;
+        }
+        if !matches!(event, key!(Enter)) {
+            self.textarea.input(event);
+            return;
+        }
+        // spaces count, so only escapes get special treatment
+        let text = self.textarea.lines()[0]
+            .replace("\\n", "\n")
+            .replace("\\t", "\t");
+        if text.is_empty() {
+            return self.title_output(Some("nothing to match"));
+        }
+        self.notice = Some(match self.remove_output_trigger(&text) {
+            true => format!("Stopped pausing on output {text:?}"),
+            false => {
+                self.add_output_trigger(&text);
+                format!("Pausing once the output ends with {text:?}")
+            }
+        });
+        self.text_prompt = None;
+        self.clear_prompt();
+    }
+    /// title the input box for entering an output trigger, along with why the last one wouldn't do
+    fn title_output(&mut self, reason: Option<&str>) {
+        let mut title = "Pause when output ends with (\\n for a newline)".to_string();
+        if let Some(reason) = reason {
+            title.push_str(&format!(" - {reason}"));
+        }
+        self.textarea
+            .set_block(glyphs::block(self.args.ascii).title(title));
+    }
+    /// title the input box for entering a watch, along with why the last one wouldn't do
+    fn title_watch(&mut self, reason: Option<&str>) {
+        let mut title = "Watch x,y or ip:depth (! to pause on change)".to_string();
+        if let Some(reason) = reason {
+            title.push_str(&format!(" - {reason}"));
+        }
+        self.textarea
+            .set_block(glyphs::block(self.args.ascii).title(title));
+    }
+    /// empty the input box for the next answer
+    fn clear_prompt(&mut self) {
+        self.textarea.move_cursor(tui_textarea::CursorMove::Head);
+        self.textarea.delete_line_by_end();
+        self.textarea.set_style(Style::default());
+    }
+    /// take a single keypress as character input
+    fn handle_raw_input(&mut self, event: KeyEvent) {
+        if event.kind != KeyEventKind::Release {
+            return;
+        }
+        if let Some(val) = input::key_value(&event) {
+            self.answer_input(val);
+        }
+    }
+    /// title the input box for the IP first in line and the kind of input it wants
+    pub(super) fn prompt(&mut self) {
+        self.title_prompt(None);
+    }
+    /// title the input box, along with why what's typed so far won't do
+    fn title_prompt(&mut self, reason: Option<&str>) {
+        let Some((id, t)) = self.waiting_for_input() else {
+            return;
+        };
+        let mut title = match t {
+            InputType::Number => format!("Input Number \u{2192} IP {id}"),
+            InputType::Character => format!("Input Character \u{2192} IP {id} (press any key)"),
+        };
+        if let Some(reason) = reason {
+            title.push_str(&format!(" - {reason}"));
+        }
+        self.textarea
+            .set_block(glyphs::block(self.args.ascii).title(title));
+    }
+    /// scroll the grid over to the selected debug entry's cell, or focus the IP
+    /// whose stack it watches
+    fn jump_to_debug_entry(&mut self) {
+        let Some((entry, _)) = self.debug_entries().get(self.debug_selected).cloned() else {
+            return;
+        };
+        match (entry.cell(), &entry) {
+            (Some(pos), _) => {
+                // a couple of cells of context above and to the left, clamped when drawn
+                self.grid_scroll = (
+                    pos.1.saturating_sub(2).clamp(0, u16::MAX as i32) as u16,
+                    pos.0.saturating_sub(2).clamp(0, u16::MAX as i32) as u16,
+                );
+            }
+            (None, DebugEntry::Watch(WatchTarget::Stack { ip, .. }, _)) => self.focus = *ip,
+            (None, _) => self.notice = Some(format!("{} {entry} has no cell", entry.kind())),
+        }
+    }
+    /// save where the grid is scrolled to under a letter, or scroll back there
+    fn use_mark(&mut self, mark_key: MarkKey, c: char) {
+        let here = FungeVector(self.grid_scroll.1 as i32, self.grid_scroll.0 as i32);
+        self.notice = Some(match (mark_key, self.marks.get(&c).copied()) {
+            (MarkKey::Set, Some(old)) if old != here => {
+                self.marks.insert(c, here);
+                format!("Moved mark {c} from {old} to {here}")
+            }
+            (MarkKey::Set, _) => {
+                self.marks.insert(c, here);
+                format!("Marked {here} as {c}")
+            }
+            (MarkKey::Jump, Some(pos)) => {
+                self.grid_scroll = (pos.1 as u16, pos.0 as u16);
+                format!("Jumped to mark {c} at {pos}")
+            }
+            (MarkKey::Jump, None) => format!("Mark {c} isn't set"),
+        });
+    }
+    /// grid positions saved with m and a letter, as the top left corner of the grid pane
+    ///
+    /// ```
+    /// use crossterm::event::{KeyCode, KeyEvent, KeyEventKind, KeyModifiers};
+    /// use refunge::{Arguments, Befunge, FungeVector};
+    ///
+    /// let press = |befunge: &mut Befunge, keys: &str| {
+    ///     for c in keys.chars() {
+    ///         let key = KeyEvent::new_with_kind(KeyCode::Char(c), KeyModifiers::NONE, KeyEventKind::Release);
+    ///         befunge.handle_key(key);
+    ///     }
+    /// };
+    /// let mut befunge = Befunge::from_text(&"@\n".repeat(40), Arguments::default()).unwrap();
+    /// press(&mut befunge, "jjjma");
+    /// assert_eq!(befunge.marks()[&'a'], FungeVector(0, 3));
+    /// press(&mut befunge, "jjma");
+    /// assert_eq!(befunge.notice(), Some("Moved mark a from (0,3) to (0,5)"));
+    /// press(&mut befunge, "`b");
+    /// assert_eq!(befunge.notice(), Some("Mark b isn't set"));
+    /// press(&mut befunge, "kkkkk'a");
+    /// assert_eq!(befunge.notice(), Some("Jumped to mark a at (0,5)"));
+    ///
+    /// // marks outlive restarts
+    /// befunge.restart();
+    /// press(&mut befunge, "mb");
+    /// assert_eq!(befunge.marks().len(), 2);
+    /// assert_eq!(befunge.marks()[&'b'], FungeVector(0, 5));
+    /// ```
+    #[doc(hidden)]
+    pub fn marks(&self) -> &BTreeMap<char, FungeVector> {
+        &self.marks
+    }
+    /// pick out an IP, whose stacks are kept in view and whose output f filters down to
+    ///
+    /// ```
+    /// use ratatui::{backend::TestBackend, Terminal};
+    /// use refunge::{Arguments, Befunge};
+    ///
+    /// // more IPs than there is room for next to the grid, each counting its laps
+    /// let args = Arguments { max_ips: Some(10), ..Default::default() };
+    /// let mut befunge = Befunge::from_text("t+1<", args).unwrap();
+    /// befunge.run_ticks(100);
+    /// assert_eq!(befunge.live_ips(), 10);
+    /// befunge.focus_ip(9);
+    /// let mut terminal = Terminal::new(TestBackend::new(80, 30)).unwrap();
+    /// terminal.draw(|f| befunge.render(f)).unwrap();
+    /// // labels go down a single column
+    /// let buffer = terminal.backend().buffer();
+    /// let column = |x| (0..30).map(|y| buffer.get(x, y).symbol.as_str()).collect::<String>();
+    /// let label = (0..80).find(|x| column(*x).contains("IP9")).unwrap();
+    /// assert!(!(0..80).any(|x| column(x).contains("IP0")));
+    /// // with its stack right beside the label, and where it is up top
+    /// let row = |y, xs: std::ops::Range<u16>| xs.map(|x| buffer.get(x, y).symbol.as_str()).collect::<String>();
+    /// assert_eq!(befunge.snapshot().ips[9].stacks[0].len(), 1);
+    /// assert_eq!(row(1, label + 2..label + 12).trim(), "32");
+    /// assert!(row(0, 0..80).contains("IP9 @ (1,0)"));
+    /// ```
+    #[doc(hidden)]
+    pub fn focus_ip(&mut self, id: usize) {
+        self.focus = id;
+    }
+    /// the IPs to show stacks for in the room there is, paging through them in order
+    /// as many at a time as fit, and showing the page the focused IP is on
+    fn stack_page(&self, room: StackRoom) -> Range<usize> {
+        // an IP takes up a label and then a pane for each stack side by side,
+        // or just a row for each stack one under another
+        let (label, each, width) = match room {
+            StackRoom::Columns(width) => (LABEL_WIDTH, STACK_WIDTH, width),
+            StackRoom::Rows(height) => (0, STACK_ROW_HEIGHT, height),
+        };
+        let width_of = |ip: &InstructionPointer| {
+            label.saturating_add(each.saturating_mul(self.stacks_wanted(ip) as u16))
+        };
+        let focus = self.focus.min(self.ip_list.len().saturating_sub(1));
+        let mut start = 0;
+        while start < self.ip_list.len() {
+            let mut used = width_of(&self.ip_list[start]);
+            let mut end = start + 1;
+            while end < self.ip_list.len()
+                && used.saturating_add(width_of(&self.ip_list[end])) <= width
+            {
+                used += width_of(&self.ip_list[end]);
+                end += 1;
+            }
+            if focus < end {
+                return start..end;
+            }
+            start = end;
+        }
+        0..0
+    }
+    /// how many of an IP's stacks get a pane, every one for the focused IP
+    /// once expanded with a and just the TOSS otherwise
+    fn stacks_wanted(&self, ip: &InstructionPointer) -> usize {
+        match self.expand_stacks && ip.id == self.focus {
+            true => ip.stacks.len(),
+            false => ip.stacks.len().min(1),
+        }
+    }
+    /// how many of an IP's stack panes fit next to its label in so many columns,
+    /// or one under another in so many rows, TOSS first
+    fn stacks_shown(&self, ip: &InstructionPointer, room: StackRoom) -> usize {
+        let fit = match room {
+            StackRoom::Columns(width) => width.saturating_sub(LABEL_WIDTH) / STACK_WIDTH,
+            StackRoom::Rows(height) => height / STACK_ROW_HEIGHT,
+        };
+        (fit as usize).min(self.stacks_wanted(ip))
+    }
+    fn stack_constraints(&self, page: Range<usize>, room: StackRoom) -> Vec<Constraint> {
+        let mut arr = vec![];
+        for ip in self.ip_list.range(page) {
+            match room {
+                StackRoom::Columns(_) => arr.push(Constraint::Length(LABEL_WIDTH)),
+                StackRoom::Rows(_) => {}
+            }
+            for _ in 0..self.stacks_shown(ip, room) {
+                arr.push(Constraint::Length(match room {
+                    StackRoom::Columns(_) => STACK_WIDTH,
+                    StackRoom::Rows(_) => STACK_ROW_HEIGHT,
+                }));
+            }
+        }
+        arr.push(Constraint::Min(0));
+        arr
+    }
+    /// a line under the stack panes for each stack without one: a size summary
+    /// of the stacks below each TOSS, or the values of expanded stacks that didn't fit
+    fn stack_lines(&self, page: Range<usize>, room: StackRoom) -> Vec<Line<'static>> {
+        let mut lines = vec![];
+        for ip in self.ip_list.range(page) {
+            let shown = self.stacks_shown(ip, room);
+            let style = Style::default().fg(ip_color(ip.id));
+            if shown == self.stacks_wanted(ip) && shown < ip.stacks.len() {
+                let sizes: Vec<String> = ip
+                    .stacks
+                    .iter()
+                    .skip(shown)
+                    .map(|s| s.len().to_string())
+                    .collect();
+                let plural = if sizes.len() == 1 { "" } else { "s" };
+                lines.push(Line::styled(
+                    format!(
+                        "IP {} +{} stack{plural} below: {} elems",
+                        ip.id,
+                        sizes.len(),
+                        sizes.join(", ")
+                    ),
+                    style,
+                ));
+                continue;
+            }
+            for (depth, stack) in ip.stacks.iter().enumerate().skip(shown) {
+                let title = format!("IP {} {} ({})", ip.id, stack_name(depth), stack.len());
+                lines.push(stack.render_line(Span::styled(title, style)));
+            }
+        }
+        lines
+    }
+    fn max_stack_len(&self, page: Range<usize>) -> u16 {
+        self.ip_list
+            .range(page)
+            .flat_map(|ip| {
+                ip.stacks
+                    .iter()
+                    .take(self.stacks_wanted(ip))
+                    .map(|s| s.len())
+            })
+            .max()
+            .unwrap_or(0)
+            .min(u16::MAX as usize) as u16
+    }
+
+    /// list the breakpoints and watches, picking out the selected one like the focused IP
+    ///
+    /// ```
+    /// use crossterm::event::{KeyCode, KeyEvent, KeyEventKind, KeyModifiers};
+    /// use ratatui::{backend::TestBackend, Terminal};
+    /// use refunge::hook::WatchTarget;
+    /// use refunge::{Arguments, Befunge, FungeVector};
+    ///
+    /// let key = |c| KeyEvent::new_with_kind(KeyCode::Char(c), KeyModifiers::NONE, KeyEventKind::Release);
+    /// let mut befunge = Befunge::from_text("1.@", Arguments::default()).unwrap();
+    /// befunge.add_breakpoint(FungeVector(1, 0));
+    /// befunge.add_instruction_breakpoint('@');
+    /// befunge.add_watch(WatchTarget::Cell(FungeVector(2, 0)), true);
+    /// befunge.handle_key(key('b'));
+    /// befunge.handle_key(key('e'));
+    /// let mut terminal = Terminal::new(TestBackend::new(80, 30)).unwrap();
+    /// terminal.draw(|f| befunge.render(f)).unwrap();
+    /// let buffer = terminal.backend().buffer();
+    /// let rows: Vec<String> = (0..30)
+    ///     .map(|y| (0..80).map(|x| buffer.get(x, y).symbol.as_str()).collect())
+    ///     .collect();
+    /// let shown = |text: &str| rows.iter().any(|row| row.contains(text));
+    /// assert!(shown("0  cell    (1,0)  0 hits  off"));
+    /// assert!(shown("1  instr   '@'    0 hits  on"));
+    /// assert!(shown("2  watch!  (2,0)  0 hits  on"));
+    /// ```
+    fn render_debug(&self, f: &mut Frame, area: ratatui::layout::Rect) {
+        let entries = self.debug_entries();
+        let width = |text: fn(&DebugEntry) -> String| {
+            entries
+                .iter()
+                .map(|(e, _)| text(e).chars().count())
+                .max()
+                .unwrap_or(0)
+        };
+        let (kind_width, place_width) = (width(|e| e.kind().to_string()), width(|e| e.to_string()));
+        let lines: Vec<Line> = entries
+            .iter()
+            .enumerate()
+            .map(|(idx, (entry, tally))| {
+                let text = format!(
+                    "{idx}  {:kind_width$}  {:place_width$}  {} hits  {}",
+                    entry.kind(),
+                    entry.to_string(),
+                    tally.hits,
+                    if tally.disabled { "off" } else { "on" }
+                );
+                match idx == self.debug_selected {
+                    true => Line::styled(
+                        text,
+                        Style::default().add_modifier(Modifier::BOLD | Modifier::REVERSED),
+                    ),
+                    false => Line::raw(text),
+                }
+            })
+            .collect();
+        f.render_widget(
+            Paragraph::new(lines).block(glyphs::block(self.args.ascii).title("Debug")),
+            area,
+        );
+    }
+    /// show the end of the event log, or further back after scrolling with [
+    fn render_events(&mut self, f: &mut Frame, area: ratatui::layout::Rect) {
+        let rows = area.height.saturating_sub(2) as usize;
+        self.events_scroll = self
+            .events_scroll
+            .min(self.event_log.len().saturating_sub(rows) as u16);
+        let lines: Vec<Line> = self
+            .event_log
+            .iter()
+            .rev()
+            .skip(self.events_scroll as usize)
+            .take(rows)
+            .map(|entry| Line::raw(entry.to_string()))
+            .collect::<Vec<Line>>()
+            .into_iter()
+            .rev()
+            .collect();
+        let title = match self.events_scroll {
+            0 => "Events".to_string(),
+            n => format!("Events ({n} newer)"),
+        };
+        f.render_widget(
+            Paragraph::new(lines).block(glyphs::block(self.args.ascii).title(title)),
+            area,
+        );
+    }
+    /// render the grid, stack, output, and message
+    ///
+    /// only the visible end of the output gets wrapped, so a program that
+    /// printed a lot doesn't slow every frame down
+    ///
+    /// ```
+    /// use ratatui::{backend::TestBackend, Terminal};
+    /// use refunge::{Arguments, Befunge};
+    /// use std::time::{Duration, Instant};
+    ///
+    /// // prints 10^6 characters in a couple of ticks
+    /// let mut befunge = Befunge::from_text("'aa:*:*a:**k:a:*:*a:**k,@", Arguments::default()).unwrap();
+    /// befunge.run(|| true);
+    /// assert!(befunge.output().len() <= 128 * 1024);
+    /// let mut terminal = Terminal::new(TestBackend::new(120, 40)).unwrap();
+    /// terminal.draw(|f| befunge.render(f)).unwrap();
+    /// // the quickest of a few frames, so one slow one on a busy machine doesn't count
+    /// let frame = (0..5)
+    ///     .map(|_| {
+    ///         let start = Instant::now();
+    ///         terminal.draw(|f| befunge.render(f)).unwrap();
+    ///         start.elapsed()
+    ///     })
+    ///     .min()
+    ///     .unwrap();
+    /// assert!(frame < Duration::from_millis(10), "{frame:?}");
+    /// ```
+    ///
+    /// none of the layout math counts on there being anything to show:
+    ///
+    /// ```
+    /// use ratatui::{backend::TestBackend, Terminal};
+    /// use refunge::{Arguments, Befunge};
+    ///
+    /// let mut terminal = Terminal::new(TestBackend::new(80, 24)).unwrap();
+    /// // a 1x1 grid, before and after it ends
+    /// let mut befunge = Befunge::from_text("@", Arguments::default()).unwrap();
+    /// terminal.draw(|f| befunge.render(f)).unwrap();
+    /// befunge.tick();
+    /// assert!(befunge.ended());
+    /// terminal.draw(|f| befunge.render(f)).unwrap();
+    /// // no IPs at all
+    /// let mut snapshot = befunge.snapshot();
+    /// snapshot.ips.clear();
+    /// befunge.restore(snapshot).unwrap();
+    /// terminal.draw(|f| befunge.render(f)).unwrap();
+    /// // or no room
+    /// let mut terminal = Terminal::new(TestBackend::new(1, 1)).unwrap();
+    /// terminal.draw(|f| befunge.render(f)).unwrap();
+    /// ```
+    ///
+    /// output can be left unwrapped with u and scrolled sideways with < and >,
+    /// and each stack is titled with how many values it holds:
+    ///
+    /// ```
+    /// use crossterm::event::{KeyCode, KeyEvent, KeyEventKind, KeyModifiers};
+    /// use ratatui::style::Modifier;
+    /// use ratatui::{backend::TestBackend, Terminal};
+    /// use refunge::{Arguments, Befunge};
+    ///
+    /// let key = |c| KeyEvent::new_with_kind(KeyCode::Char(c), KeyModifiers::NONE, KeyEventKind::Release);
+    /// // a line of 35 zeros, then three values left on the stack
+    /// let mut befunge = Befunge::from_text("\"#\"k.123@", Arguments::default()).unwrap();
+    /// befunge.run(|| true);
+    /// let mut terminal = Terminal::new(TestBackend::new(80, 24)).unwrap();
+    /// let mut draw = |befunge: &mut Befunge| {
+    ///     terminal.draw(|f| befunge.render(f)).unwrap();
+    ///     let buffer = terminal.backend().buffer().clone();
+    ///     let rows: Vec<String> = (0..24)
+    ///         .map(|y| (0..80).map(|x| buffer.get(x, y).symbol.as_str()).collect())
+    ///         .collect();
+    ///     (buffer, rows)
+    /// };
+    /// let zero_rows = |rows: &[String]| rows.iter().filter(|row| row.contains("0 0 0 0")).count();
+    /// let (buffer, rows) = draw(&mut befunge);
+    /// assert!(zero_rows(&rows) > 1);
+    /// let (y, row) = rows.iter().enumerate().find(|(_, row)| row.contains("TOSS (3)")).unwrap();
+    /// let x = row.chars().position(|c| c == 'T').unwrap() as u16;
+    /// assert!(buffer.get(x, y as u16).modifier.contains(Modifier::REVERSED));
+    ///
+    /// befunge.handle_key(key('u'));
+    /// let (_, rows) = draw(&mut befunge);
+    /// assert_eq!(zero_rows(&rows), 1);
+    /// assert!(rows.iter().any(|row| row.contains("Output - unwrapped")));
+    /// assert!(rows.iter().any(|row| row.contains("\u{2502}0 0 0 0 0 0 0 0 0 \u{2502}")));
+    /// befunge.handle_key(key('>'));
+    /// let (_, rows) = draw(&mut befunge);
+    /// assert!(rows.iter().any(|row| row.contains("\u{2502} 0 0 0 0 0 0 0 0 0\u{2502}")));
+    /// ```
+    ///
+    /// only the TOSS gets a pane, with the stacks below it summed up underneath,
+    /// until a expands the focused IP's whole stack stack:
+    ///
+    /// ```
+    /// use crossterm::event::{KeyCode, KeyEvent, KeyEventKind, KeyModifiers};
+    /// use ratatui::{backend::TestBackend, Terminal};
+    /// use refunge::{Arguments, Befunge};
+    ///
+    /// let key = |c| KeyEvent::new_with_kind(KeyCode::Char(c), KeyModifiers::NONE, KeyEventKind::Release);
+    /// // four stacks, each { leaving the storage offset under what was there
+    /// let mut befunge = Befunge::from_text("9870{650{40{@", Arguments::default()).unwrap();
+    /// befunge.run(|| true);
+    /// let mut terminal = Terminal::new(TestBackend::new(80, 24)).unwrap();
+    /// let mut draw = |befunge: &mut Befunge| {
+    ///     terminal.draw(|f| befunge.render(f)).unwrap();
+    ///     let buffer = terminal.backend().buffer();
+    ///     (0..24)
+    ///         .map(|y| (0..80).map(|x| buffer.get(x, y).symbol.as_str()).collect())
+    ///         .collect::<Vec<String>>()
+    /// };
+    /// let rows = draw(&mut befunge);
+    /// assert!(rows.iter().any(|row| row.contains("TOSS (0)")));
+    /// assert!(!rows.iter().any(|row| row.contains("SOSS")));
+    /// assert!(rows.iter().any(|row| row.contains("IP 0 +3 stacks below: 3, 4, 5 elems")));
+    ///
+    /// befunge.handle_key(key('a'));
+    /// let rows = draw(&mut befunge);
+    /// let titles = rows.iter().find(|row| row.contains("TOSS (0)")).unwrap();
+    /// assert!(titles.contains("SOSS (3)") && titles.contains("S3 (4)") && titles.contains("S4 (5)"));
+    /// assert!(!rows.iter().any(|row| row.contains("stacks below")));
+    /// ```
+    ///
+    /// a cell an IP reflects off, like an unknown instruction, flashes red for a moment:
+    ///
+    /// ```
+    /// use ratatui::style::Color;
+    /// use ratatui::{backend::TestBackend, Terminal};
+    /// use refunge::{Arguments, Befunge};
+    ///
+    /// let mut befunge = Befunge::from_text(">#1Q", Arguments::default()).unwrap();
+    /// (0..3).for_each(|_| befunge.tick());
+    /// let mut terminal = Terminal::new(TestBackend::new(80, 24)).unwrap();
+    /// terminal.draw(|f| befunge.render(f)).unwrap();
+    /// // the grid starts inside its border, one cell in
+    /// let buffer = terminal.backend().buffer();
+    /// assert_eq!(buffer.get(4, 1).symbol, "Q");
+    /// assert_eq!(buffer.get(4, 1).bg, Color::Red);
+    /// assert_eq!(buffer.get(2, 1).symbol, "#");
+    /// assert_ne!(buffer.get(2, 1).bg, Color::Red);
+    /// ```
+    ///
+    /// the grid is titled with the program, and whether it has been written to:
+    ///
+    /// ```
+    /// use ratatui::{backend::TestBackend, Terminal};
+    /// use refunge::{Arguments, Befunge};
+    ///
+    /// let program = format!("'x70p@{}.", " ".repeat(40));
+    /// let mut befunge = Befunge::from_text(&program, Arguments::default()).unwrap();
+    /// let mut terminal = Terminal::new(TestBackend::new(80, 24)).unwrap();
+    /// let mut title = |befunge: &mut Befunge| {
+    ///     terminal.draw(|f| befunge.render(f)).unwrap();
+    ///     let buffer = terminal.backend().buffer();
+    ///     (0..80).map(|x| buffer.get(x, 0).symbol.as_str()).collect::<String>()
+    /// };
+    /// assert!(title(&mut befunge).starts_with("┌Grid - <eval> [98, 32-bit]─"));
+    /// befunge.run(|| true);
+    /// assert!(title(&mut befunge).starts_with("┌Grid - <eval> [98, 32-bit] (modified)─"));
+    /// befunge.restart();
+    /// assert!(title(&mut befunge).starts_with("┌Grid - <eval> [98, 32-bit]─"));
+    /// ```
+    ///
+    /// over the stacks is where the run is at, cut short when there isn't room:
+    ///
+    /// ```
+    /// use ratatui::{backend::TestBackend, Terminal};
+    /// use refunge::{Arguments, Befunge};
+    ///
+    /// let mut befunge = Befunge::from_text("12v\n  >@", Arguments::default()).unwrap();
+    /// (0..4).for_each(|_| befunge.tick());
+    /// let mut top = |width: u16| {
+    ///     let mut terminal = Terminal::new(TestBackend::new(width, 24)).unwrap();
+    ///     terminal.draw(|f| befunge.render(f)).unwrap();
+    ///     let buffer = terminal.backend().buffer();
+    ///     (0..width).map(|x| buffer.get(x, 0).symbol.as_str()).collect::<String>()
+    /// };
+    /// assert!(top(80).ends_with("  tick 4 · 1 IP · IP0 @ (2,1) Δ(1,0) "));
+    /// assert!(top(40).ends_with("┐─tick 4 · 1 IP · I… "));
+    /// ```
+    ///
+    /// escape sequences in output are left out of the output pane, or with --ansi (toggled
+    /// with n) the colors they pick are used:
+    ///
+    /// ```
+    /// use crossterm::event::{KeyCode, KeyEvent, KeyEventKind, KeyModifiers};
+    /// use ratatui::style::{Color, Modifier};
+    /// use ratatui::{backend::TestBackend, Terminal};
+    /// use refunge::{Arguments, Befunge};
+    ///
+    /// let key = |c| KeyEvent::new_with_kind(KeyCode::Char(c), KeyModifiers::NONE, KeyEventKind::Release);
+    /// // printed a character at a time, so every sequence is split across writes
+    /// let text = "\x1b[1;31mred\x1b[0m \x1b[2;5Hok";
+    /// let program = format!("\"{}\">:#,_@", text.chars().rev().collect::<String>());
+    /// let mut befunge = Befunge::from_text(&program, Arguments::default()).unwrap();
+    /// befunge.run(|| true);
+    /// assert_eq!(befunge.output(), text);
+    /// let mut terminal = Terminal::new(TestBackend::new(80, 24)).unwrap();
+    /// // the output pane's first line, under the grid
+    /// let mut cells = |befunge: &mut Befunge| {
+    ///     terminal.draw(|f| befunge.render(f)).unwrap();
+    ///     let buffer = terminal.backend().buffer();
+    ///     (1..7).map(|x| buffer.get(x, 10).clone()).collect::<Vec<_>>()
+    /// };
+    /// let stripped = cells(&mut befunge);
+    /// assert_eq!(stripped.iter().map(|cell| cell.symbol.as_str()).collect::<String>(), "red ok");
+    /// assert_eq!(stripped[0].fg, Color::Reset);
+    /// befunge.handle_key(key('n'));
+    /// let colored = cells(&mut befunge);
+    /// assert_eq!(colored.iter().map(|cell| cell.symbol.as_str()).collect::<String>(), "red ok");
+    /// assert_eq!(colored[0].fg, Color::Red);
+    /// assert!(colored[2].modifier.contains(Modifier::BOLD));
+    /// assert_eq!(colored[4].fg, Color::Reset);
+    /// ```
+    ///
+    /// wide, short terminals lay each stack out on a row of its own, top at the right,
+    /// and z cycles through horizontal, vertical, and picking by shape again:
+    ///
+    /// ```
+    /// use crossterm::event::{KeyCode, KeyEvent, KeyEventKind, KeyModifiers};
+    /// use ratatui::{backend::TestBackend, Terminal};
+    /// use refunge::arguments::{StackInit, StackValue};
+    /// use refunge::{Arguments, Befunge};
+    ///
+    /// let key = |c| KeyEvent::new_with_kind(KeyCode::Char(c), KeyModifiers::NONE, KeyEventKind::Release);
+    /// let stack_init = Some(StackInit((1..=50).map(StackValue::Number).collect()));
+    /// let mut befunge = Befunge::from_text("v\n@", Arguments { stack_init, ..Default::default() }).unwrap();
+    /// let mut rows = |befunge: &mut Befunge, width: u16, height: u16| {
+    ///     let mut terminal = Terminal::new(TestBackend::new(width, height)).unwrap();
+    ///     terminal.draw(|f| befunge.render(f)).unwrap();
+    ///     let buffer = terminal.backend().buffer();
+    ///     (0..height)
+    ///         .map(|y| (0..width).map(|x| buffer.get(x, y).symbol.as_str()).collect())
+    ///         .collect::<Vec<String>>()
+    /// };
+    /// let horizontal = |rows: &[String]| {
+    ///     rows.iter().any(|row| row.contains("┌IP 0 → TOSS (50)"))
+    ///         && rows.iter().any(|row| row.ends_with("│ 47 │ 48 │ 49 │ 50│"))
+    /// };
+    /// let vertical = |rows: &[String]| rows.iter().any(|row| row.contains("┌TOSS (50)"));
+    /// assert!(horizontal(&rows(&mut befunge, 150, 24)));
+    /// assert!(vertical(&rows(&mut befunge, 80, 24)));
+    /// befunge.handle_key(key('z'));
+    /// assert!(horizontal(&rows(&mut befunge, 80, 24)));
+    /// // { scrolls back toward the bottom of the stack
+    /// for _ in 0..5 {
+    ///     befunge.handle_key(key('{'));
+    /// }
+    /// assert!(rows(&mut befunge, 80, 24).iter().any(|row| row.ends_with("│ 46 │ 47 │ 48 │ 49│")));
+    /// befunge.handle_key(key('z'));
+    /// assert!(vertical(&rows(&mut befunge, 150, 24)));
+    /// befunge.handle_key(key('z'));
+    /// assert!(horizontal(&rows(&mut befunge, 150, 24)));
+    /// ```
+    #[doc(hidden)]
+    pub fn render(&mut self, f: &mut Frame) {
+        let grid_width = self.grid.width().saturating_add(2).clamp(20, 80) as u16;
+        let grid_height = self.grid.height().saturating_add(2).clamp(9, 25) as u16;
+        // no scrolling past the last row or column
+        self.grid_scroll = (
+            self.grid_scroll
+                .0
+                .min(self.grid.height().saturating_sub(grid_height as usize - 2) as u16),
+            self.grid_scroll
+                .1
+                .min(self.grid.width().saturating_sub(grid_width as usize - 2) as u16),
+        );
+        // only wrap as much output as can be shown, counting back from the end
+        let max_lines = f.size().height.saturating_sub(grid_height + 5).max(1) as usize;
+        let filtered = self.filter_output.then(|| {
+            self.out
+                .segments()
+                .filter(|(ip, _)| *ip == self.focus)
+                .map(|(_, text)| text)
+                .collect::<String>()
+        });
+        let text = filtered.as_deref().unwrap_or(self.out.text());
+        let lines = wrapped_tail(
+            text,
+            (!self.unwrapped_output).then_some(grid_width as usize - 2),
+            max_lines + self.output_scroll as usize,
+        );
+        self.output_scroll = self
+            .output_scroll
+            .min(lines.len().saturating_sub(max_lines) as u16);
+        let end = lines.len() - self.output_scroll as usize;
+        let shown = &lines[end.saturating_sub(max_lines)..end];
+        let output_height = shown.len() as u16 + 2;
+        let chunks = Layout::new()
+            .constraints(vec![Constraint::Length(grid_width), Constraint::Min(0)])
+            .direction(Horizontal)
+            .split(f.size());
+        let column_a = Layout::new()
+            .constraints(vec![
+                Constraint::Length(grid_height),
+                Constraint::Length(output_height),
+                Constraint::Length(3),
+                Constraint::Min(0),
+            ])
+            .split(chunks[0]);
+        let watches_height = match self.watches.len() {
+            0 => 0,
+            n => (n as u16).saturating_add(2),
+        };
+        let debug_height = match self.show_debug {
+            true => (self.debug_entries().len() as u16).saturating_add(2),
+            false => 0,
+        };
+        let events_height = match self.show_events {
+            true => (self.event_log.len().clamp(1, EVENTS_SHOWN) as u16).saturating_add(2),
+            false => 0,
+        };
+        // wide, short terminals get a row for each stack instead of a column,
+        // leaving a few rows for the status
+        let size = f.size();
+        let room = match self
+            .horizontal_stacks
+            .unwrap_or(size.width >= size.height.saturating_mul(HORIZONTAL_RATIO))
+        {
+            true => StackRoom::Rows(
+                chunks[1]
+                    .height
+                    .saturating_sub(watches_height + debug_height + events_height + 4),
+            ),
+            false => StackRoom::Columns(chunks[1].width),
+        };
+        let page = self.stack_page(room);
+        let stack_height = match room {
+            StackRoom::Columns(_) => grid_height
+                .saturating_add(output_height)
+                .max(self.max_stack_len(page.clone()).saturating_add(2)),
+            StackRoom::Rows(_) => self
+                .ip_list
+                .range(page.clone())
+                .map(|ip| self.stacks_shown(ip, room) as u16 * STACK_ROW_HEIGHT)
+                .sum(),
+        };
+        let stack_lines = self.stack_lines(page.clone(), room);
+        let column_b = Layout::new()
+            .constraints([
+                Constraint::Length(stack_height.saturating_add(stack_lines.len() as u16)),
+                Constraint::Length(watches_height),
+                Constraint::Length(debug_height),
+                Constraint::Length(events_height),
+                Constraint::Min(1),
+            ])
+            .split(chunks[1]);
+        let stack_rows = Layout::new()
+            .constraints([Constraint::Length(stack_height), Constraint::Min(0)])
+            .split(column_b[0]);
+        let stack_zone = Layout::new()
+            .constraints(self.stack_constraints(page.clone(), room))
+            .direction(match room {
+                StackRoom::Columns(_) => Horizontal,
+                StackRoom::Rows(_) => Vertical,
+            })
+            .split(stack_rows[0]);
+        let mut title = match self.out.dropped() {
+            0 => "Output".to_string(),
+            n => format!("Output ({n} older bytes dropped)"),
+        };
+        if self.filter_output {
+            title.push_str(&format!(" - IP {} only", self.focus));
+        }
+        // unwrapped lines scroll sideways, up to the end of the longest one shown
+        let longest = shown.iter().map(|(_, line)| ansi::strip(line).chars().count()).max();
+        self.output_hscroll = match self.unwrapped_output {
+            true => self
+                .output_hscroll
+                .min(longest.unwrap_or(0).saturating_sub(grid_width as usize - 2) as u16),
+            false => 0,
+        };
+        if self.unwrapped_output {
+            title.push_str(" - unwrapped");
+        }
+        // output is only colored by IP once more than one has written some
+        let colored = self.out.segments().nth(1).is_some();
+        // answered input is echoed dimly after the line it was typed at
+        let mut echoes: Vec<Vec<Span>> = vec![vec![]; shown.len()];
+        if !self.filter_output {
+            let dim = Style::default().add_modifier(Modifier::DIM);
+            for echo in &self.input_echoes {
+                let Some(at) = echo.offset.checked_sub(self.out.dropped()) else {
+                    continue;
+                };
+                if let Some(idx) = shown
+                    .iter()
+                    .position(|(start, line)| at <= start + line.len())
+                    .filter(|idx| *idx > 0 || shown[0].0 <= at)
+                {
+                    echoes[idx].push(Span::styled(format!(" {echo}"), dim));
+                }
+            }
+        }
+        // escape sequences pick up the style left by the ones before the first line shown
+        let mut sgr = self.args.ansi.then(|| {
+            let start = shown.first().map_or(text.len(), |(start, _)| *start);
+            ansi::style_after(text.get(..start).unwrap_or(text), Style::default())
+        });
+        let shown: Vec<Line> = shown
+            .iter()
+            .zip(echoes)
+            .map(|((start, line), echoes)| {
+                let mut line = match (colored, self.filter_output) {
+                    (true, true) => {
+                        Line::styled(line.to_string(), Style::default().fg(ip_color(self.focus)))
+                    }
+                    (true, false) => Line::from(
+                        self.out
+                            .runs(*start, start + line.len())
+                            .flat_map(|(ip, text)| {
+                                let style = Style::default().fg(ip_color(ip));
+                                // colors alone might not show, so say whose output it is too
+                                let tag = self.args.ascii.then(|| Span::styled(format!("[{ip}]"), style));
+                                tag.into_iter().chain([Span::styled(text, style)])
+                            })
+                            .collect::<Vec<Span>>(),
+                    ),
+                    (false, _) => Line::raw(line.to_string()),
+                };
+                line.spans = unescape(line.spans, sgr.as_mut());
+                line.spans.extend(echoes);
+                line
+            })
+            .collect();
+        let output = Paragraph::new(shown)
+            .block(glyphs::block(self.args.ascii).title(title))
+            .scroll((0, self.output_hscroll));
+
+        self.flashes.retain(|_, at| at.elapsed() < REFLECTION_FLASH);
+        let watched = self
+            .watches
+            .iter()
+            .filter_map(|watch| match watch.target {
+                WatchTarget::Cell(pos) => Some(pos),
+                WatchTarget::Stack { .. } => None,
+            })
+            .collect();
+        if self.args.color_grid {
+            let top = self.grid_scroll.0 as usize;
+            self.grid.categorize(top..top + grid_height as usize);
+        }
+        f.render_widget(
+            self.grid
+                .clone()
+                .highlights(self.ip_list.clone())
+                .watched(watched)
+                .flashing(self.flashes.keys().copied().collect())
+                .reloaded(self.reloaded)
+                .ascii(self.args.ascii)
+                .colored(self.args.color_grid)
+                .scrolled(self.grid_scroll),
+            column_a[0],
+        );
+        f.render_widget(output, column_a[1]);
+        if !self.input_queue.is_empty() || self.text_prompt.is_some() {
+            f.render_widget(self.textarea.widget(), column_a[2])
+        } else if let Some(msg) = &self.notice {
+            f.render_widget(Paragraph::new(msg.as_str()), column_a[2])
+        }
+        if self.ended() {
+            f.render_widget(
+                Paragraph::new("Funge ended.\nPress r to restart,\nor q to exit."),
+                column_a[2],
+            )
+        }
+        if self.show_log {
+            let lines = logger::recent(column_a[3].height.saturating_sub(2) as usize);
+            // each cell only gets logged the first time, so say how it's added up since
+            let title = match self.stats.reflections {
+                0 => "Log".to_string(),
+                _ => format!("Log · {}", self.stats.reflection_summary()),
+            };
+            f.render_widget(
+                Paragraph::new(lines.join("\n"))
+                    .block(glyphs::block(self.args.ascii).title(title)),
+                column_a[3],
+            )
+        }
+        if self.show_debug {
+            self.render_debug(f, column_b[2]);
+        }
+        if self.show_events {
+            self.render_events(f, column_b[3]);
+        }
+        // scrolling stops once the longest row shown is scrolled back to its bottom
+        let longest = self
+            .ip_list
+            .range(page.clone())
+            .flat_map(|ip| ip.stacks.iter().take(self.stacks_shown(ip, room)))
+            .map(|stack| stack.row_width())
+            .max()
+            .unwrap_or(0);
+        self.stack_hscroll = match room {
+            StackRoom::Rows(_) => self
+                .stack_hscroll
+                .min(longest.saturating_sub(chunks[1].width.saturating_sub(2) as usize)),
+            StackRoom::Columns(_) => 0,
+        };
+        let mut index = 0;
+        for ip in self.ip_list.range(page.clone()) {
+            let mut style = Style::default().fg(ip_color(ip.id));
+            if ip.id == self.focus {
+                style = style.add_modifier(Modifier::BOLD | Modifier::REVERSED);
+            }
+            if let StackRoom::Columns(_) = room {
+                f.render_widget(
+                    Paragraph::new(Span::styled(ip_label(ip, &self.grid, self.args.ascii), style))
+                        .wrap(Wrap { trim: true })
+                        .block(
+                            glyphs::block(self.args.ascii)
+                                .borders(Borders::TOP | Borders::BOTTOM),
+                        ),
+                    stack_zone[index],
+                );
+                index += 1;
+            }
+            for (depth, stack) in ip.stacks.iter().take(self.stacks_shown(ip, room)).enumerate() {
+                let name = format!("{} ({})", stack_name(depth), stack.len());
+                let (mut title, layout) = match room {
+                    StackRoom::Columns(_) => (
+                        Span::raw(name),
+                        StackLayout::Vertical { height: stack_height },
+                    ),
+                    // with no label pane, the title says whose stack it is
+                    StackRoom::Rows(_) => (
+                        Span::styled(
+                            format!("{} {name}", ip_label(ip, &self.grid, self.args.ascii)),
+                            style,
+                        ),
+                        StackLayout::Horizontal { scroll: self.stack_hscroll },
+                    ),
+                };
+                if ip.id == self.focus {
+                    title.style = title.style.add_modifier(Modifier::BOLD | Modifier::REVERSED);
+                }
+                let block = glyphs::block(self.args.ascii).title(title);
+                stack.render(f, stack_zone[index], layout, block);
+                index += 1;
+            }
+        }
+        f.render_widget(Paragraph::new(stack_lines), stack_rows[1]);
+        if !self.watches.is_empty() {
+            let lines: Vec<Line> = self.watches.iter().map(watch_line).collect();
+            f.render_widget(
+                Paragraph::new(lines)
+                    .block(glyphs::block(self.args.ascii).title("Watches")),
+                column_b[1],
+            );
+        }
+        let mut status = format!(
+            "ticks: {}  instructions: {}  spawned: {}  reflections: {}",
+            self.stats.ticks, self.stats.instructions, self.stats.spawned, self.stats.reflections
+        );
+        // say what didn't fit in the stack pane
+        if page.len() < self.ip_list.len() {
+            status.push_str(&format!(
+                "\nstacks of IPs {}-{} of {} shown, tab for the others",
+                page.start,
+                page.end - 1,
+                self.ip_list.len()
+            ));
+        }
+        if !self.marks.is_empty() {
+            let marks: Vec<String> = self
+                .marks
+                .iter()
+                .map(|(c, pos)| format!("{c} {pos}"))
+                .collect();
+            status.push_str(&format!("\nmarks: {}", marks.join("  ")));
+        }
+        if self.paused {
+            status.insert_str(0, "paused\n");
+        }
+        if let Some(rate) = self.turbo_rate() {
+            status.insert_str(0, &format!("turbo: {rate:.0} ticks/s\n"));
+        }
+        if let Some((target, old, new)) = self.watch_hit() {
+            let show = |value: Option<i32>| value.map_or("nothing".to_string(), |v| v.to_string());
+            status.insert_str(
+                0,
+                &format!(
+                    "watch {target} changed from {} to {}\n",
+                    show(old),
+                    show(new)
+                ),
+            );
+        }
+        if let Some((text, id)) = self.output_hit() {
+            status.insert_str(0, &format!("output matched {text:?} from IP {id}\n"));
+        }
+        if let Some((id, pos)) = self.died() {
+            status.insert_str(0, &format!("IP {id} died at {pos}\n"));
+        }
+        for id in self.zero_delta_ips().into_iter().rev() {
+            status.insert_str(0, &format!("IP {id} has zero delta\n"));
+        }
+        if let Some((id, pos, c)) = self.stuck() {
+            status.insert_str(0, &format!("IP {id} stuck running {c:?} at {pos}\n"));
+        }
+        if self.max_ticks_reached() {
+            status.insert_str(0, "max ticks reached\n");
+        }
+        if self.ip_limit_reached {
+            status.insert_str(0, "IP limit reached\n");
+        }
+        if self.timed_out {
+            status.insert_str(0, "time limit reached\n");
+        }
+        f.render_widget(Paragraph::new(status), column_b[4]);
+        // where things are at, over the top border of the stacks
+        let mut headline = self.headline(Some(self.focus));
+        let room = chunks[1].width.saturating_sub(2);
+        truncate(&mut headline, room as usize);
+        let width = headline.chars().count() as u16;
+        let area = ratatui::layout::Rect {
+            x: chunks[1].right().saturating_sub(width + 1),
+            width,
+            height: width.min(1),
+            ..chunks[1]
+        };
+        f.render_widget(Paragraph::new(headline), area);
+        if self.args.ascii {
+            glyphs::asciify(f.buffer_mut());
+        }
+    }
+}
+
+/// shorten text to at most width characters, ending in … if any were cut
+fn truncate(text: &mut String, width: usize) {
+    if text.chars().count() <= width {
+        return;
+    }
+    match text.char_indices().nth(width.saturating_sub(1)) {
+        Some((end, _)) if width > 0 => {
+            text.truncate(end);
+            text.push('…');
+        }
+        _ => text.clear(),
+    }
+}
+
+/// spans of output with their escape sequences turned into styles, carrying
+/// on from sgr, or left out when there isn't one
+fn unescape<'a>(spans: Vec<Span<'a>>, mut sgr: Option<&mut Style>) -> Vec<Span<'a>> {
+    let mut unescaped = Vec::with_capacity(spans.len());
+    for span in spans {
+        match sgr.as_deref_mut() {
+            Some(sgr) if span.content.contains('\x1b') => unescaped.extend(
+                ansi::styled(&span.content, sgr)
+                    .into_iter()
+                    .map(|(style, text)| Span::styled(text.to_string(), span.style.patch(style))),
+            ),
+            Some(sgr) => unescaped.push(Span::styled(span.content, span.style.patch(*sgr))),
+            None => match ansi::strip(&span.content) {
+                Cow::Borrowed(_) => unescaped.push(span),
+                Cow::Owned(text) => unescaped.push(Span::styled(text, span.style)),
+            },
+        }
+    }
+    unescaped
+}
+
+/// an IP's label in the stack pane, with an arrow for which way it's heading
+/// when the grid can't show one because it's on an instruction
+fn ip_label(ip: &InstructionPointer, grid: &FungeGrid, ascii: bool) -> String {
+    match (grid.char_at(ip.pos), ascii) {
+        _ if ip.delta == ORIGIN && !ip.dead => format!("IP {} zero delta", ip.id),
+        (' ', _) => format!("IP {}", ip.id),
+        (_, false) => format!("IP {} {}", ip.id, ip.delta.arrow()),
+        (_, true) => format!("IP {} {}", ip.id, ip.delta.ascii_arrow()),
+    }
+}
+
+/// a watch as a line in the watches pane, marked with a ! if it pauses on change,
+/// showing its value as a character too when it's a printable one
+fn watch_line(watch: &Watch) -> Line<'static> {
+    let marker = if watch.break_on_change { "!" } else { "" };
+    let value = match watch.value {
+        None => "-".to_string(),
+        Some(v) => match char::from_u32(v as u32).filter(|c| !c.is_control()) {
+            Some(c) => format!("{v} {c:?}"),
+            None => v.to_string(),
+        },
+    };
+    Line::raw(format!("{}{marker} = {value}", watch.target))
+}
+
+/// most entries the event log pane shows at once
+const EVENTS_SHOWN: usize = 8;
+/// columns taken up by an IP's label in the stack pane
+const LABEL_WIDTH: u16 = 1;
+/// columns taken up by each stack in the stack pane
+const STACK_WIDTH: u16 = 12;
+/// rows taken up by each stack when they're laid out horizontally
+const STACK_ROW_HEIGHT: u16 = 3;
+/// terminals at least this many times wider than they are tall lay out stacks horizontally
+const HORIZONTAL_RATIO: u16 = 5;
+
+/// what a stack is called in its pane title, by how deep it is in the stack stack
+fn stack_name(depth: usize) -> Cow<'static, str> {
+    match depth {
+        0 => Cow::Borrowed("TOSS"),
+        1 => Cow::Borrowed("SOSS"),
+        n => Cow::Owned(format!("S{}", n + 1)),
+    }
+}
+
+/// the last count lines of text once wrapped to width (if there is one),
+/// oldest first, wrapping only the lines at the end that are needed
+///
+/// each line comes with where it starts in text, so it can be colored by
+/// whichever IPs wrote it
+fn wrapped_tail(text: &str, width: Option<usize>, count: usize) -> Vec<(usize, Cow<'_, str>)> {
+    let mut lines = vec![];
+    for line in text.rsplit('\n') {
+        let line_start = line.as_ptr() as usize - text.as_ptr() as usize;
+        let Some(width) = width else {
+            lines.push((line_start, Cow::Borrowed(line)));
+            if lines.len() >= count {
+                break;
+            }
+            continue;
+        };
+        let wrapped = textwrap::wrap(line, width);
+        lines.extend(wrapped.into_iter().rev().map(|l| {
+            // wrapping hands back copies (or a static "" for empty lines)
+            // when it can't point into the line itself
+            let start = (l.as_ptr() as usize)
+                .checked_sub(text.as_ptr() as usize)
+                .filter(|start| matches!(l, Cow::Borrowed(_)) && *start <= text.len())
+                .unwrap_or(line_start);
+            (start, l)
+        }));
+        if lines.len() >= count {
+            break;
+        }
+    }
+    lines.truncate(count);
+    lines.reverse();
+    lines
+}
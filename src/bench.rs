@@ -0,0 +1,144 @@
+//! results of `refunge bench`, as a table or as json
+
+use serde::Serialize;
+use std::fmt::{Display, Formatter};
+use std::time::Duration;
+
+/// one timed run of a program
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct BenchRun {
+    pub ticks: u64,
+    pub time: Duration,
+    /// false if --max-ticks stopped it first
+    pub ended: bool,
+}
+impl BenchRun {
+    pub fn ticks_per_second(&self) -> f64 {
+        self.ticks as f64 / self.time.as_secs_f64().max(f64::EPSILON)
+    }
+}
+
+/// the smallest, largest and average of something across runs
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub struct Spread {
+    pub mean: f64,
+    pub min: f64,
+    pub max: f64,
+}
+impl Spread {
+    fn of(values: impl Iterator<Item = f64> + Clone) -> Spread {
+        let count = values.clone().count().max(1) as f64;
+        Spread {
+            mean: values.clone().sum::<f64>() / count,
+            min: values.clone().reduce(f64::min).unwrap_or(0.0),
+            max: values.reduce(f64::max).unwrap_or(0.0),
+        }
+    }
+}
+
+/// every timed run of a program, not counting the warm-up
+#[derive(Debug, Clone, PartialEq)]
+pub struct BenchReport {
+    pub program: String,
+    pub runs: Vec<BenchRun>,
+}
+impl BenchReport {
+    /// wall time of each run in seconds
+    pub fn seconds(&self) -> Spread {
+        Spread::of(self.runs.iter().map(|run| run.time.as_secs_f64()))
+    }
+    pub fn ticks(&self) -> Spread {
+        Spread::of(self.runs.iter().map(|run| run.ticks as f64))
+    }
+    pub fn ticks_per_second(&self) -> Spread {
+        Spread::of(self.runs.iter().map(BenchRun::ticks_per_second))
+    }
+    /// the report as one line of json, for scripts comparing runs
+    ///
+    /// ```
+    /// use refunge::bench::{BenchReport, BenchRun};
+    /// use std::time::Duration;
+    ///
+    /// let run = |ms, ended| BenchRun { ticks: 1000, time: Duration::from_millis(ms), ended };
+    /// let report = BenchReport { program: "loop.bf".into(), runs: vec![run(500, true), run(250, false)] };
+    /// assert_eq!(
+    ///     report.to_json(),
+    ///     concat!(
+    ///         r#"{"program":"loop.bf","iterations":["#,
+    ///         r#"{"ticks":1000,"seconds":0.5,"ticks_per_second":2000.0,"ended":true},"#,
+    ///         r#"{"ticks":1000,"seconds":0.25,"ticks_per_second":4000.0,"ended":false}],"#,
+    ///         r#""seconds":{"mean":0.375,"min":0.25,"max":0.5},"#,
+    ///         r#""ticks":{"mean":1000.0,"min":1000.0,"max":1000.0},"#,
+    ///         r#""ticks_per_second":{"mean":3000.0,"min":2000.0,"max":4000.0}}"#,
+    ///     )
+    /// );
+    /// ```
+    pub fn to_json(&self) -> String {
+        #[derive(Serialize)]
+        struct Iteration {
+            ticks: u64,
+            seconds: f64,
+            ticks_per_second: f64,
+            ended: bool,
+        }
+        #[derive(Serialize)]
+        struct Report<'r> {
+            program: &'r str,
+            iterations: Vec<Iteration>,
+            seconds: Spread,
+            ticks: Spread,
+            ticks_per_second: Spread,
+        }
+        let report = Report {
+            program: &self.program,
+            iterations: self
+                .runs
+                .iter()
+                .map(|run| Iteration {
+                    ticks: run.ticks,
+                    seconds: run.time.as_secs_f64(),
+                    ticks_per_second: run.ticks_per_second(),
+                    ended: run.ended,
+                })
+                .collect(),
+            seconds: self.seconds(),
+            ticks: self.ticks(),
+            ticks_per_second: self.ticks_per_second(),
+        };
+        serde_json::to_string(&report).expect("bench reports are plain numbers and strings")
+    }
+}
+
+/// a table with a row per run, then the mean, min and max
+impl Display for BenchReport {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "{}: {} runs", self.program, self.runs.len())?;
+        writeln!(f, "{:<6}{:>14}{:>14}{:>16}", "run", "time", "ticks", "ticks/s")?;
+        for (idx, run) in self.runs.iter().enumerate() {
+            let mark = if run.ended { "" } else { " (max ticks)" };
+            writeln!(
+                f,
+                "{:<6}{:>14}{:>14}{:>16.0}{mark}",
+                idx + 1,
+                format!("{:.3?}", run.time),
+                run.ticks,
+                run.ticks_per_second()
+            )?;
+        }
+        let (seconds, ticks, rate) = (self.seconds(), self.ticks(), self.ticks_per_second());
+        for (name, pick) in [
+            ("mean", (|s: Spread| s.mean) as fn(Spread) -> f64),
+            ("min", |s| s.min),
+            ("max", |s| s.max),
+        ] {
+            writeln!(
+                f,
+                "{name:<6}{:>14}{:>14.0}{:>16.0}",
+                format!("{:.3?}", Duration::from_secs_f64(pick(seconds))),
+                pick(ticks),
+                pick(rate)
+            )?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,323 @@
+use crate::arguments::CheckArguments;
+use crate::instructions::{self, Kind};
+use crate::{FungeGrid, FungeVector};
+use anyhow::{bail, Context, Result};
+use std::collections::HashSet;
+use std::fmt::{Display, Formatter};
+use std::fs::read_to_string;
+
+/// how bad a diagnostic is, only warnings make `refunge check` fail
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Level {
+    Warning,
+    Note,
+}
+impl Display for Level {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Level::Warning => write!(f, "warning"),
+            Level::Note => write!(f, "note"),
+        }
+    }
+}
+
+/// something worth pointing out about a cell, with a 1-based line and column
+#[derive(Debug)]
+pub struct Diagnostic {
+    pub line: usize,
+    pub col: usize,
+    pub level: Level,
+    pub message: String,
+}
+
+/// check a source file for `refunge check`, returning whether it had no warnings
+///
+/// ```
+/// use refunge::arguments::CheckArguments;
+///
+/// let path = std::env::temp_dir().join("refunge-doctest-check.bf");
+/// let file = path.to_string_lossy().into_owned();
+/// let args = CheckArguments { file, quiet: true, script: false, extensions: false };
+/// std::fs::write(&path, "55+l.@").unwrap();
+/// assert!(!refunge::check::run(&args).unwrap());
+/// assert!(refunge::check::run(&CheckArguments { extensions: true, ..args }).unwrap());
+/// ```
+pub fn run(args: &CheckArguments) -> Result<bool> {
+    let path = &args.file;
+    let text = read_to_string(path).with_context(|| format!("failed to read {path}"))?;
+    if text.trim().is_empty() {
+        bail!("{path} is empty");
+    }
+    let grid = FungeGrid::new(text);
+    let diagnostics = diagnose(&grid, grid.start_pos(args.script), args.extensions);
+    if !args.quiet {
+        for d in &diagnostics {
+            println!("{path}:{}:{}: {}: {}", d.line, d.col, d.level, d.message);
+        }
+    }
+    let warnings = diagnostics
+        .iter()
+        .filter(|d| d.level == Level::Warning)
+        .count();
+    let notes = diagnostics.len() - warnings;
+    println!("{path}: {warnings} warnings, {notes} notes");
+    Ok(warnings == 0)
+}
+
+/// look over the grid for likely mistakes, without simulating anything
+///
+/// the cells that run as instructions are found by following the IP from the
+/// start, in whichever direction it goes, and lines it never gets to are read
+/// left to right instead
+///
+/// ```
+/// use refunge::check::diagnose;
+/// use refunge::{FungeGrid, FungeVector};
+///
+/// let check = |text: &str, extensions: bool| {
+///     let grid = FungeGrid::new(text.to_string());
+///     diagnose(&grid, FungeVector(0, 0), extensions)
+///         .into_iter()
+///         .map(|d| format!("{}:{} {}: {}", d.line, d.col, d.level, d.message))
+///         .collect::<Vec<_>>()
+/// };
+/// assert!(check("\"!ih\">:#,_@", false).is_empty());
+///
+/// // a string read going down, and one read going west
+/// assert!(check("v\n\"\nZ\n\"\n>,@", false).is_empty());
+/// assert!(check("<@,,\"Hi\"", false).is_empty());
+///
+/// // l only runs with --extensions
+/// assert_eq!(check("1l@", false), ["1:2 warning: 'l' is not an instruction and will reflect"]);
+/// assert!(check("1l@", true).is_empty());
+///
+/// assert_eq!(
+///     check("v  \"open\n>m'Q@ B\ni", false),
+///     [
+///         "1:4 warning: string is never closed on this line",
+///         "1:4 warning: instruction can never be reached",
+///         "2:2 warning: 'm' is not an instruction and will reflect",
+///         "2:3 warning: 3 instructions on this line can never be reached",
+///         "2:7 warning: 'B' (Fingerprint Semantics) without any fingerprint loaded by '('",
+///         "3:1 note: 'i' (Input File) touches files or runs commands",
+///         "3:1 warning: instruction can never be reached",
+///     ]
+/// );
+/// assert_eq!(check("#@j@", false), ["1:3 note: not checking reachability past here, the jump depends on the stack"]);
+/// // a tab is flagged even inside a string
+/// assert_eq!(check("\"a\tb\"@", false), ["1:3 warning: tab takes up a single cell however wide it looks, and reflects (see --tabs)"]);
+/// ```
+pub fn diagnose(grid: &FungeGrid, start: FungeVector, extensions: bool) -> Vec<Diagnostic> {
+    let mut diagnostics = vec![];
+    let mut report = |(x, y): (i32, i32), level, message| {
+        diagnostics.push(Diagnostic {
+            line: y as usize + 1,
+            col: x as usize + 1,
+            level,
+            message,
+        })
+    };
+    let walk = walk(grid, start, extensions);
+    // anything the walk didn't get to gets read a line at a time instead
+    let unseen = |pos: &(i32, i32)| !walk.reached.contains(pos) && !walk.passed.contains(pos);
+    let (scanned, unterminated) = code_cells(grid, start.1);
+    let mut code: Vec<((i32, i32), char)> = walk
+        .reached
+        .iter()
+        .map(|&(x, y)| ((x, y), grid.char_at(FungeVector(x, y))))
+        .filter(|&(_, c)| c != ' ')
+        .chain(scanned.into_iter().filter(|(pos, _)| unseen(pos)))
+        .collect();
+    code.sort_by_key(|&((x, y), _)| (y, x));
+    for pos in walk
+        .unterminated
+        .iter()
+        .copied()
+        .chain(unterminated.into_iter().filter(unseen))
+    {
+        report(
+            pos,
+            Level::Warning,
+            "string is never closed on this line".into(),
+        );
+    }
+    for (pos, value) in grid.non_space_cells() {
+        if value == '\t' as i32 {
+            report(
+                (pos.0, pos.1),
+                Level::Warning,
+                "tab takes up a single cell however wide it looks, and reflects (see --tabs)"
+                    .into(),
+            );
+        }
+    }
+    let loads_fingerprints = code.iter().any(|&(_, c)| c == '(');
+    for &(pos, c) in &code {
+        match instructions::lookup(c, extensions) {
+            None if c == '\t' => {}
+            None => report(
+                pos,
+                Level::Warning,
+                format!("{c:?} is not an instruction and will reflect"),
+            ),
+            Some(i) if i.kind == Kind::System => report(
+                pos,
+                Level::Note,
+                format!("{c:?} ({}) touches files or runs commands", i.name),
+            ),
+            Some(i) if c.is_ascii_uppercase() && !loads_fingerprints => report(
+                pos,
+                Level::Warning,
+                format!("{c:?} ({}) without any fingerprint loaded by '('", i.name),
+            ),
+            _ => {}
+        }
+    }
+    match walk.stopped {
+        None => {
+            let unreached: Vec<(i32, i32)> = code
+                .iter()
+                .map(|&(pos, _)| pos)
+                .filter(|pos| !walk.reached.contains(pos))
+                .collect();
+            for line in unreached.chunk_by(|a, b| a.1 == b.1) {
+                let message = match line.len() {
+                    1 => "instruction can never be reached".to_string(),
+                    n => format!("{n} instructions on this line can never be reached"),
+                };
+                report(line[0], Level::Warning, message);
+            }
+        }
+        Some(pos) => report(
+            pos,
+            Level::Note,
+            "not checking reachability past here, the jump depends on the stack".into(),
+        ),
+    }
+    diagnostics.sort_by_key(|d| (d.line, d.col));
+    diagnostics
+}
+
+/// the cells that run as instructions when each line is read left to right,
+/// skipping strings, ; comments, and cells fetched by ' and s, along with
+/// the quotes that are never closed on their line
+#[allow(clippy::type_complexity)]
+fn code_cells(grid: &FungeGrid, first_line: i32) -> (Vec<((i32, i32), char)>, Vec<(i32, i32)>) {
+    let mut code = vec![];
+    let mut unterminated = vec![];
+    for y in first_line..grid.height() as i32 {
+        let mut open: Option<(char, i32)> = None;
+        let mut skip = false;
+        for x in 0..grid.width() as i32 {
+            let c = grid.char_at(FungeVector(x, y));
+            match open {
+                _ if skip => skip = false,
+                Some((close, _)) if c == close => open = None,
+                Some(_) => {}
+                None if c == ' ' => {}
+                None => {
+                    code.push(((x, y), c));
+                    match c {
+                        '"' | ';' => open = Some((c, x)),
+                        '\'' | 's' => skip = true,
+                        _ => {}
+                    }
+                }
+            }
+        }
+        if let Some(('"', x)) = open {
+            unterminated.push((x, y));
+        }
+    }
+    (code, unterminated)
+}
+
+/// where the IP can go following only fixed directions
+#[derive(Default)]
+struct Walk {
+    /// every cell the IP lands on outside a string or ;-region
+    reached: HashSet<(i32, i32)>,
+    /// cells passed over inside strings and ;-regions, or fetched by ' and s
+    passed: HashSet<(i32, i32)>,
+    /// quotes whose string never closes before coming back round to them
+    unterminated: Vec<(i32, i32)>,
+    /// the position of a j or x whose jump can't be known without running
+    stopped: Option<(i32, i32)>,
+}
+
+/// follow every way the IP could go from the start, in the direction it goes
+fn walk(grid: &FungeGrid, start: FungeVector, extensions: bool) -> Walk {
+    let (width, height) = (grid.width() as i32, grid.height() as i32);
+    let step = |(x, y): (i32, i32), (dx, dy): (i32, i32)| {
+        ((x + dx).rem_euclid(width), (y + dy).rem_euclid(height))
+    };
+    let char_at = |(x, y): (i32, i32)| grid.char_at(FungeVector(x, y));
+    let mut walk = Walk::default();
+    let mut seen = HashSet::new();
+    let mut todo = vec![((start.0, start.1), (1, 0))];
+    while let Some((pos, delta)) = todo.pop() {
+        if !seen.insert((pos, delta)) {
+            continue;
+        }
+        walk.reached.insert(pos);
+        let (left, right, back) = (
+            (delta.1, -delta.0),
+            (-delta.1, delta.0),
+            (-delta.0, -delta.1),
+        );
+        let c = char_at(pos);
+        let deltas = match c {
+            '>' => vec![(1, 0)],
+            '<' => vec![(-1, 0)],
+            '^' => vec![(0, -1)],
+            'v' => vec![(0, 1)],
+            '_' => vec![(1, 0), (-1, 0)],
+            '|' => vec![(0, 1), (0, -1)],
+            '?' => vec![(1, 0), (-1, 0), (0, 1), (0, -1)],
+            '[' => vec![left],
+            ']' => vec![right],
+            'w' => vec![left, right, delta],
+            'r' => vec![back],
+            't' => vec![delta, back],
+            '@' | 'q' => continue,
+            'j' | 'x' => {
+                walk.stopped = Some(pos);
+                return walk;
+            }
+            '#' => {
+                todo.push((step(step(pos, delta), delta), delta));
+                continue;
+            }
+            '\'' | 's' => {
+                walk.passed.insert(step(pos, delta));
+                todo.push((step(step(pos, delta), delta), delta));
+                continue;
+            }
+            '"' | ';' => {
+                // the line wraps back round to the opening one if nothing closes it
+                let mut end = step(pos, delta);
+                while end != pos && char_at(end) != c {
+                    walk.passed.insert(end);
+                    end = step(end, delta);
+                }
+                if end == pos {
+                    if c == '"' {
+                        walk.unterminated.push(pos);
+                    }
+                    continue;
+                }
+                walk.passed.insert(end);
+                todo.push((step(end, delta), delta));
+                continue;
+            }
+            // these reflect when they fail
+            '&' | '~' | 'i' | 'o' | '(' | ')' | 'A'..='Z' => vec![delta, back],
+            c if instructions::lookup(c, extensions).is_none() => vec![back],
+            _ => vec![delta],
+        };
+        for d in deltas {
+            todo.push((step(pos, d), d));
+        }
+    }
+    walk
+}
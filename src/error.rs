@@ -0,0 +1,61 @@
+//! what can go wrong loading a program, or for an instruction reaching outside of funge-space
+
+use crate::vector::FungeVector;
+use std::io;
+
+/// why a program couldn't start, or why an instruction failed and reflected
+///
+/// the io::Error behind one is its source, printed after it with `{:#}` in anyhow
+#[derive(Debug, thiserror::Error)]
+pub enum RefungeError {
+    /// the program couldn't be read from where it was said to be
+    #[error("failed to read program from {from}")]
+    Load {
+        from: String,
+        #[source]
+        error: io::Error,
+    },
+    /// the path given as the program is a directory
+    #[error("{path} is a directory, not a program")]
+    IsDirectory { path: String },
+    /// the program has a tab in it, under --tabs reject
+    #[error("{from} has a tab at line {line}, column {column}")]
+    Tab {
+        from: String,
+        line: usize,
+        column: usize,
+    },
+    /// the program has nothing in it
+    #[error("{from} is empty")]
+    Empty { from: String },
+    /// a position that has to be in the grid isn't, like --start
+    #[error("{what} {pos} is outside the {width}x{height} grid")]
+    OutOfBounds {
+        what: String,
+        pos: FungeVector,
+        width: usize,
+        height: usize,
+    },
+    /// something too big (or too far out) to fit in the grid
+    #[error("{what} doesn't fit in the grid")]
+    DoesNotFit { what: String },
+    /// reading or writing a file other than the program itself
+    #[error("failed to {action} {path}")]
+    Io {
+        action: &'static str,
+        path: String,
+        #[source]
+        error: io::Error,
+    },
+    /// = couldn't run a command
+    #[error("failed to execute {command:?}")]
+    Exec {
+        command: String,
+        #[source]
+        error: io::Error,
+    },
+    /// a file that was read but doesn't make sense, like a session or
+    /// snapshot, or overlays that clash with --strict
+    #[error("{0}")]
+    Invalid(String),
+}
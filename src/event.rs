@@ -1,7 +1,15 @@
-use crate::befunge::InputType;
+use crate::input::InputType;
+use crate::vector::FungeVector;
+#[cfg(feature = "tui")]
 use crossterm::event::{poll, read, Event as CrosstermEvent, KeyEvent};
-use std::sync::{mpsc, Arc, Mutex};
-use std::thread;
+#[cfg(feature = "tui")]
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc;
+#[cfg(feature = "tui")]
+use std::sync::{Arc, Mutex};
+#[cfg(feature = "tui")]
+use std::thread::{self, JoinHandle};
+#[cfg(feature = "tui")]
 use std::time::{Duration, Instant};
 
 /// global events used to communicate from IP to befunge
@@ -15,6 +23,47 @@ pub enum Event {
     /// called from an IP with a given index
     /// will pause tui to allow for input
     Input(InputType, usize),
+    /// send an IP with a given index where its TRDS destination points
+    Jump(usize),
+    /// freeze every IP but the one with a given index
+    StopTime(usize),
+    /// let frozen IPs carry on
+    ResumeTime,
+    /// a file was loaded into the grid with i, as (name, cells read)
+    ReadFile(String, usize),
+    /// part of the grid was saved with o, as (name, cells written)
+    WroteFile(String, usize),
+}
+
+/// what a frontend hears about once it subscribes with Befunge::subscribe,
+/// sent at the end of each tick in the order it happened
+///
+/// IP ids are the ones they had at the start of the tick, except for spawns,
+/// where the new IP takes its parent's id and every id from there shifts up one
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum InterpreterEvent {
+    /// a cell changed, from p, s, i or a fingerprint
+    CellWritten {
+        pos: FungeVector,
+        old: char,
+        new: char,
+    },
+    /// text written by , and .
+    OutputChunk { ip: usize, text: String },
+    /// where an IP is and which way it's heading after its instruction
+    IpMoved {
+        ip: usize,
+        pos: FungeVector,
+        delta: FungeVector,
+    },
+    /// t split off a new IP, and the parent it came from
+    IpSpawned { ip: usize, parent: usize },
+    /// an IP stopped at pos, with @ or an error
+    IpDied { ip: usize, pos: FungeVector },
+    /// an IP is waiting on & or ~, answer it with Befunge::answer_input
+    InputRequested { ip: usize, kind: InputType },
+    /// every IP has stopped, with the code given to q (or 0)
+    Ended { code: i32 },
 }
 
 /// multi-producer, single-receiver channel for global events
@@ -37,10 +86,25 @@ impl Default for EventHandler {
 }
 
 /// sends out a tick event based on the supplied tickrate
+///
+/// at most one tick waits to be picked up, so pausing doesn't pile them up
+///
+/// ```
+/// use refunge::event::TickHandler;
+/// use std::time::Duration;
+///
+/// let ticks = TickHandler::default();
+/// (0..3).for_each(|_| ticks.speed_up());
+/// std::thread::sleep(Duration::from_millis(200));
+/// assert!(ticks.has_tick());
+/// assert!(!ticks.has_tick());
+/// ```
+#[cfg(feature = "tui")]
 pub struct TickHandler {
     tickrate: Arc<Mutex<Duration>>,
     receiver: mpsc::Receiver<()>,
 }
+#[cfg(feature = "tui")]
 impl TickHandler {
     /// returns true if a tick has been produced since last called
     pub fn has_tick(&self) -> bool {
@@ -57,48 +121,98 @@ impl TickHandler {
         *tickrate = Duration::from_millis((tickrate.as_millis() * 2).min(1024) as u64)
     }
 }
+#[cfg(feature = "tui")]
 impl Default for TickHandler {
     fn default() -> TickHandler {
-        let (inner_sender, receiver) = mpsc::channel();
+        let (inner_sender, receiver) = mpsc::sync_channel(1);
         let inner_tickrate = Arc::new(Mutex::new(Duration::from_millis(128)));
         let tickrate = Arc::clone(&inner_tickrate);
         thread::spawn(move || {
             let mut last_tick = Instant::now();
             loop {
-                if last_tick.elapsed() >= *inner_tickrate.lock().unwrap() {
-                    inner_sender.send(()).unwrap();
-                    last_tick = Instant::now();
+                let rate = *inner_tickrate.lock().unwrap();
+                let elapsed = last_tick.elapsed();
+                if elapsed < rate {
+                    // wake up often enough that speeding up applies right away
+                    thread::sleep((rate - elapsed).min(Duration::from_millis(16)));
+                    continue;
                 }
+                // a full channel means the last tick hasn't been used yet, so drop this one
+                if let Err(mpsc::TrySendError::Disconnected(())) = inner_sender.try_send(()) {
+                    break;
+                }
+                last_tick = Instant::now();
             }
         });
         TickHandler { tickrate, receiver }
     }
 }
 
-/// wrapper around an infinitely looping thread waiting for key input
+/// wrapper around a thread waiting for key input until stopped or dropped
+#[cfg(feature = "tui")]
 pub struct KeyHandler {
     receiver: mpsc::Receiver<KeyEvent>,
+    running: Arc<AtomicBool>,
+    thread: Option<JoinHandle<()>>,
 }
+#[cfg(feature = "tui")]
 impl KeyHandler {
+    /// how long the thread waits for an event before checking if it should stop
+    const POLL_TIMEOUT: Duration = Duration::from_millis(50);
+
     /// returns the next key input if it exists
     pub fn next(&self) -> Option<KeyEvent> {
         self.receiver.try_recv().ok()
     }
+    /// stop reading terminal events, waiting for the thread to finish
+    /// so nothing typed afterwards gets swallowed
+    pub fn stop(&mut self) {
+        self.running.store(false, Ordering::Relaxed);
+        if let Some(thread) = self.thread.take() {
+            thread.join().unwrap_or(());
+        }
+    }
 }
+#[cfg(feature = "tui")]
 impl Default for KeyHandler {
     fn default() -> KeyHandler {
         let (sender, receiver) = mpsc::channel();
-        thread::spawn(move || loop {
-            if poll(Duration::ZERO).unwrap_or(false) {
-                if let CrosstermEvent::Key(key) = read().unwrap() {
-                    sender.send(key).unwrap_or(());
+        let running = Arc::new(AtomicBool::new(true));
+        let inner_running = Arc::clone(&running);
+        let thread = thread::spawn(move || {
+            while inner_running.load(Ordering::Relaxed) {
+                match poll(KeyHandler::POLL_TIMEOUT) {
+                    Ok(true) => {}
+                    Ok(false) => continue,
+                    // no terminal to poll, so no keys are ever coming
+                    Err(_) => break,
+                }
+                match read() {
+                    Ok(CrosstermEvent::Key(key)) => {
+                        if sender.send(key).is_err() {
+                            break;
+                        }
+                    }
+                    Ok(_) => {}
+                    Err(_) => break,
                 }
             }
         });
-        KeyHandler { receiver }
+        KeyHandler {
+            receiver,
+            running,
+            thread: Some(thread),
+        }
+    }
+}
+#[cfg(feature = "tui")]
+impl Drop for KeyHandler {
+    fn drop(&mut self) {
+        self.stop();
     }
 }
 
+#[cfg(feature = "tui")]
 #[macro_export]
 macro_rules! key {
     ($char:literal) => {
@@ -117,6 +231,13 @@ macro_rules! key {
             ..
         }
     };
+    (any;$char:literal) => {
+        ::crossterm::event::KeyEvent {
+            code: KeyCode::Char($char),
+            kind: KeyEventKind::Release,
+            ..
+        }
+    };
     (ctrl;$char:literal) => {
         ::crossterm::event::KeyEvent {
             code: KeyCode::Char($char),
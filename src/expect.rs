@@ -0,0 +1,141 @@
+use crate::Befunge;
+
+/// one line of a diff between expected and actual output
+enum DiffLine<'a> {
+    Same(&'a str),
+    Missing(&'a str),
+    Extra(&'a str),
+}
+
+/// compare program output against the expected text, returning a
+/// unified-style diff of the lines that differ, or none if they match
+pub fn compare(expected: &str, actual: &str, ignore_trailing_space: bool) -> Option<String> {
+    let normalize = |line: &str| {
+        if ignore_trailing_space {
+            line.trim_end().to_string()
+        } else {
+            line.to_string()
+        }
+    };
+    let (old, new): (Vec<&str>, Vec<&str>) = (expected.lines().collect(), actual.lines().collect());
+    let (old_cmp, new_cmp): (Vec<String>, Vec<String>) = (
+        old.iter().map(|l| normalize(l)).collect(),
+        new.iter().map(|l| normalize(l)).collect(),
+    );
+    if old_cmp == new_cmp {
+        return None;
+    }
+
+    // longest common subsequence table, filled from the end
+    let mut lcs = vec![vec![0usize; new.len() + 1]; old.len() + 1];
+    for i in (0..old.len()).rev() {
+        for j in (0..new.len()).rev() {
+            lcs[i][j] = if old_cmp[i] == new_cmp[j] {
+                lcs[i + 1][j + 1] + 1
+            } else {
+                lcs[i + 1][j].max(lcs[i][j + 1])
+            };
+        }
+    }
+    let mut lines = vec![];
+    let (mut i, mut j) = (0, 0);
+    while i < old.len() || j < new.len() {
+        if i < old.len() && j < new.len() && old_cmp[i] == new_cmp[j] {
+            lines.push(DiffLine::Same(old[i]));
+            (i, j) = (i + 1, j + 1);
+        } else if i < old.len() && (j == new.len() || lcs[i + 1][j] >= lcs[i][j + 1]) {
+            lines.push(DiffLine::Missing(old[i]));
+            i += 1;
+        } else {
+            lines.push(DiffLine::Extra(new[j]));
+            j += 1;
+        }
+    }
+
+    // only show unchanged lines close to a change
+    const CONTEXT: usize = 2;
+    let changed: Vec<usize> = (0..lines.len())
+        .filter(|&idx| !matches!(lines[idx], DiffLine::Same(_)))
+        .collect();
+    let mut diff = String::from("--- expected\n+++ actual\n");
+    let mut skipped = false;
+    for (idx, line) in lines.iter().enumerate() {
+        if !changed.iter().any(|&c| c.abs_diff(idx) <= CONTEXT) {
+            skipped = true;
+            continue;
+        }
+        if skipped {
+            diff.push_str("...\n");
+            skipped = false;
+        }
+        match line {
+            DiffLine::Same(l) => diff.push_str(&format!(" {l}\n")),
+            DiffLine::Missing(l) => diff.push_str(&format!("-{l}\n")),
+            DiffLine::Extra(l) => diff.push_str(&format!("+{l}\n")),
+        }
+    }
+    Some(diff)
+}
+
+/// check what a run printed against the expected text the way --expect
+/// does, handing back the exit status and what went wrong if it doesn't
+/// pass: the program stopping before it finished (with the status
+/// [Befunge::stopped_by] gives, or 1), or the diff when the output differs (1)
+///
+/// ```
+/// use refunge::{expect, Arguments, Befunge};
+/// use std::time::Duration;
+///
+/// let run = |program: &str, args: Arguments| {
+///     let mut befunge = Befunge::from_text(program, Arguments { quiet: true, ..args }).unwrap();
+///     befunge.capture_output();
+///     befunge.run(|| true);
+///     befunge
+/// };
+/// let hello = "\"!iH\">:#,_1.@";
+/// // a match, with trailing spaces only ignored when asked
+/// assert_eq!(expect::check(&run(hello, Arguments::default()), "Hi!1 ", false), Ok(()));
+/// assert_eq!(expect::check(&run(hello, Arguments::default()), "Hi!1", true), Ok(()));
+/// let (code, _) = expect::check(&run(hello, Arguments::default()), "Hi!1", false).unwrap_err();
+/// assert_eq!(code, 1);
+///
+/// // a mismatch
+/// let (code, why) = expect::check(&run(hello, Arguments::default()), "Hi?1 ", false).unwrap_err();
+/// assert_eq!(code, 1);
+/// assert_eq!(why, "output does not match\n--- expected\n+++ actual\n-Hi?1 \n+Hi!1 \n");
+///
+/// // timing out before it's done
+/// let time_limit = Some(Duration::from_millis(50));
+/// let (code, why) = expect::check(&run("\"!iH\",,,v\n        >", Arguments { time_limit, ..Default::default() }), "Hi!", false).unwrap_err();
+/// assert_eq!(code, 124);
+/// assert!(why.starts_with("program stopped before finishing (time limit reached after "));
+/// assert!(why.ends_with("output so far:\nHi!"));
+///
+/// // stopped by --break-on-output, or by running out of --max-ticks
+/// let break_on_output = vec!["H".to_string()];
+/// let (code, _) = expect::check(&run(hello, Arguments { break_on_output, ..Default::default() }), "Hi!1 ", false).unwrap_err();
+/// assert_eq!(code, 3);
+/// let (code, why) = expect::check(&run(hello, Arguments { max_ticks: Some(5), ..Default::default() }), "Hi!1 ", false).unwrap_err();
+/// assert_eq!((code, why.as_str()), (1, "program stopped before finishing, output so far:\n"));
+/// ```
+pub fn check(
+    befunge: &Befunge,
+    expected: &str,
+    ignore_trailing_space: bool,
+) -> Result<(), (i32, String)> {
+    if !befunge.ended() {
+        let (code, why) = match befunge.stopped_by() {
+            Some((code, why)) => (code, format!(" ({why})")),
+            None => (1, String::new()),
+        };
+        let output = befunge.output();
+        return Err((
+            code,
+            format!("program stopped before finishing{why}, output so far:\n{output}"),
+        ));
+    }
+    match compare(expected, befunge.output(), ignore_trailing_space) {
+        Some(diff) => Err((1, format!("output does not match\n{diff}"))),
+        None => Ok(()),
+    }
+}
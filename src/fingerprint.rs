@@ -0,0 +1,958 @@
+use crate::event::Event;
+use crate::grid::FungeGrid;
+use crate::output::Output;
+use crate::pointer::{InstructionPointer, Reflection};
+use crate::session::Environment;
+use crate::stats::Stats;
+use crate::vector::FungeVector;
+use serde::{Deserialize, Serialize};
+use std::fmt::Write as _;
+use std::net::IpAddr;
+use std::sync::mpsc;
+
+/// what a letter does while a fingerprint providing it is loaded
+pub type Semantic = fn(&mut InstructionPointer, &mut Context);
+
+/// everything besides its own IP that a semantic can reach
+pub struct Context<'c> {
+    pub grid: &'c mut FungeGrid,
+    pub out: &'c mut Output,
+    pub env: &'c mut Environment,
+    pub stats: &'c mut Stats,
+    pub sender: &'c mpsc::Sender<Event>,
+}
+impl Context<'_> {
+    /// hand an event to the interpreter (which only stops listening once it's gone)
+    pub fn send(&self, event: Event) {
+        self.sender.send(event).unwrap_or(());
+    }
+}
+
+/// a named set of semantics for some of the letters A-Z
+#[derive(Debug)]
+pub struct Fingerprint {
+    pub name: &'static str,
+    pub semantics: &'static [(char, Semantic)],
+}
+impl Fingerprint {
+    /// the numeric id '(' loads this fingerprint by
+    pub fn id(&self) -> i32 {
+        id_of(self.name)
+    }
+    /// what this fingerprint does for a letter, if anything
+    pub fn semantic(&self, letter: char) -> Option<Semantic> {
+        self.semantics
+            .iter()
+            .find(|(c, _)| *c == letter)
+            .map(|&(_, semantic)| semantic)
+    }
+}
+
+/// every fingerprint refunge knows how to load
+pub const FINGERPRINTS: &[Fingerprint] = &[NULL, ROMA, MODU, TRDS, SCKE, THREE_D_SP, FPRT];
+
+/// pack a fingerprint name into its numeric id, one byte per letter
+pub fn id_of(name: &str) -> i32 {
+    name.bytes()
+        .fold(0, |id: i32, b| id.wrapping_mul(256).wrapping_add(b as i32))
+}
+/// look up a known fingerprint by its numeric id
+pub fn find(id: i32) -> Option<&'static Fingerprint> {
+    FINGERPRINTS.iter().find(|fp| fp.id() == id)
+}
+
+/// serde for an IP's loaded fingerprints, storing each one by its id
+pub mod loaded {
+    use super::{find, Fingerprint};
+    use serde::de::Error;
+    use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+    type Loaded = [Vec<&'static Fingerprint>; 26];
+
+    pub fn serialize<S: Serializer>(loaded: &Loaded, serializer: S) -> Result<S::Ok, S::Error> {
+        let ids: Vec<Vec<i32>> = loaded
+            .iter()
+            .map(|fps| fps.iter().map(|fp| fp.id()).collect())
+            .collect();
+        ids.serialize(serializer)
+    }
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Loaded, D::Error> {
+        let ids = Vec::<Vec<i32>>::deserialize(deserializer)?;
+        let loaded = ids
+            .into_iter()
+            .map(|ids| {
+                ids.into_iter()
+                    .map(|id| {
+                        find(id).ok_or(D::Error::custom(format!("unknown fingerprint {id:#x}")))
+                    })
+                    .collect::<Result<Vec<_>, _>>()
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        loaded
+            .try_into()
+            .map_err(|_| D::Error::custom("expected semantics for 26 letters"))
+    }
+}
+
+/// which fingerprints programs are allowed to load, set by --fingerprints
+///
+/// '(' reflects for one that isn't allowed, and the ones that are still work:
+///
+/// ```
+/// use refunge::fingerprint::{id_of, FingerprintFilter};
+/// use refunge::{Arguments, Befunge};
+///
+/// // load ROMA and print V, or reflect back past the start and wrap round to @
+/// let roman_five = |filter: &str| {
+///     let fingerprints = Some(FingerprintFilter::parse(filter).unwrap());
+///     let args = Arguments { quiet: true, fingerprints, ..Default::default() };
+///     let mut befunge = Befunge::from_text("0\"AMOR\"4(V.@", args).unwrap();
+///     befunge.capture_output();
+///     befunge.run(|| true);
+///     let names: Vec<_> = befunge.available_fingerprints().iter().map(|fp| fp.name).collect();
+///     (befunge.output().to_string(), befunge.stats().reflections, names.contains(&"ROMA"))
+/// };
+/// assert_eq!(roman_five("allow=NULL,ROMA"), ("5 ".to_string(), 0, true));
+/// assert_eq!(roman_five("deny=MODU"), ("5 ".to_string(), 0, true));
+/// assert_eq!(roman_five("deny=ROMA"), (String::new(), 1, false));
+/// assert_eq!(roman_five("allow=0x4d4f4455"), (String::new(), 1, false));
+///
+/// assert!(FingerprintFilter::parse("deny=0x524f4d41").unwrap().allows(id_of("MODU")));
+/// assert!(!FingerprintFilter::parse("deny=0x524f4d41").unwrap().allows(id_of("ROMA")));
+/// assert_eq!(FingerprintFilter::parse("allow=ROMA,SOCK").unwrap_err(), "unknown fingerprint `SOCK`");
+/// assert_eq!(FingerprintFilter::parse("only=ROMA").unwrap_err(), "expected allow or deny, found `only`");
+/// ```
+#[derive(Debug, Clone, Default)]
+pub enum FingerprintFilter {
+    #[default]
+    All,
+    Allow(Vec<i32>),
+    Deny(Vec<i32>),
+}
+impl FingerprintFilter {
+    /// may the fingerprint with this id be loaded
+    pub fn allows(&self, id: i32) -> bool {
+        match self {
+            FingerprintFilter::All => true,
+            FingerprintFilter::Allow(ids) => ids.contains(&id),
+            FingerprintFilter::Deny(ids) => !ids.contains(&id),
+        }
+    }
+    /// parse allow=NAME,... or deny=NAME,... where each entry is a known
+    /// fingerprint name or a hex id like 0x524f4d41
+    pub fn parse(text: &str) -> Result<FingerprintFilter, String> {
+        let (kind, list) = text
+            .split_once('=')
+            .ok_or("expected allow=<ids> or deny=<ids>")?;
+        let ids = list
+            .split(',')
+            .filter(|s| !s.is_empty())
+            .map(parse_id)
+            .collect::<Result<Vec<i32>, String>>()?;
+        match kind {
+            "allow" => Ok(FingerprintFilter::Allow(ids)),
+            "deny" => Ok(FingerprintFilter::Deny(ids)),
+            _ => Err(format!("expected allow or deny, found `{kind}`")),
+        }
+    }
+}
+
+/// a hex id, or the name of a fingerprint in the registry
+fn parse_id(text: &str) -> Result<i32, String> {
+    if let Some(hex) = text.strip_prefix("0x") {
+        return u32::from_str_radix(hex, 16)
+            .map(|id| id as i32)
+            .map_err(|_| format!("invalid fingerprint id `{text}`"));
+    }
+    FINGERPRINTS
+        .iter()
+        .find(|fp| fp.name == text)
+        .map(Fingerprint::id)
+        .ok_or(format!("unknown fingerprint `{text}`"))
+}
+
+const REFLECT: Semantic = |ip, ctx| ip.reflect(Reflection::Fingerprint, ctx.stats);
+
+/// reflects on every letter, hiding whatever was loaded underneath
+const NULL: Fingerprint = Fingerprint {
+    name: "NULL",
+    semantics: &[
+        ('A', REFLECT),
+        ('B', REFLECT),
+        ('C', REFLECT),
+        ('D', REFLECT),
+        ('E', REFLECT),
+        ('F', REFLECT),
+        ('G', REFLECT),
+        ('H', REFLECT),
+        ('I', REFLECT),
+        ('J', REFLECT),
+        ('K', REFLECT),
+        ('L', REFLECT),
+        ('M', REFLECT),
+        ('N', REFLECT),
+        ('O', REFLECT),
+        ('P', REFLECT),
+        ('Q', REFLECT),
+        ('R', REFLECT),
+        ('S', REFLECT),
+        ('T', REFLECT),
+        ('U', REFLECT),
+        ('V', REFLECT),
+        ('W', REFLECT),
+        ('X', REFLECT),
+        ('Y', REFLECT),
+        ('Z', REFLECT),
+    ],
+};
+
+/// roman numerals push their value
+const ROMA: Fingerprint = Fingerprint {
+    name: "ROMA",
+    semantics: &[
+        ('C', |ip, _| ip.push(100)),
+        ('D', |ip, _| ip.push(500)),
+        ('I', |ip, _| ip.push(1)),
+        ('L', |ip, _| ip.push(50)),
+        ('M', |ip, _| ip.push(1000)),
+        ('V', |ip, _| ip.push(5)),
+        ('X', |ip, _| ip.push(10)),
+    ],
+};
+
+/// the other kinds of modulo, all giving 0 when dividing by 0
+const MODU: Fingerprint = Fingerprint {
+    name: "MODU",
+    semantics: &[
+        // signed-result modulo, takes the sign of the divisor
+        ('M', |ip, _| {
+            let (x, y) = (ip.pop(), ip.pop());
+            ip.push(
+                y.checked_rem(x)
+                    .map(|r| {
+                        if r != 0 && (r < 0) != (x < 0) {
+                            r + x
+                        } else {
+                            r
+                        }
+                    })
+                    .unwrap_or_default(),
+            );
+        }),
+        // unsigned-result modulo
+        ('U', |ip, _| {
+            let (x, y) = (ip.pop(), ip.pop());
+            ip.push(y.checked_rem_euclid(x).unwrap_or_default());
+        }),
+        // c-language remainder, takes the sign of the dividend
+        ('R', |ip, _| {
+            let (x, y) = (ip.pop(), ip.pop());
+            ip.push(y.checked_rem(x).unwrap_or_default());
+        }),
+    ],
+};
+
+/// where and when TRDS's 'J' sends an IP, set up one piece at a time
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Tardis {
+    /// the cell to land on, where the IP already is if none
+    pub space: Option<Destination>,
+    /// the tick to land on, the one it jumps on if none
+    pub time: Option<i64>,
+    /// the delta to land with, the one it has if none
+    pub delta: Option<FungeVector>,
+    /// sent forward in time, skipped by every tick until this one
+    pub arriving: Option<u64>,
+    /// the cell after the last jump, the tick after it and the delta it had,
+    /// for 'I' to head back to
+    pub departed: Option<(FungeVector, i64, FungeVector)>,
+}
+
+/// a cell for TRDS to land on
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Destination {
+    Absolute(FungeVector),
+    /// counted from wherever the IP is when it jumps
+    Relative(FungeVector),
+}
+
+/// time travel: set up a destination in space and time, then jump there with 'J'
+///
+/// jumps into the past go back to the grid and IPs as they were then (output
+/// already written stays written), but only as far as --history keeps them,
+/// reflecting any further; the IP that left vanishes once it gets back to
+/// jumping, its future self having already arrived; 'P' says how far back
+/// that is, and 'I' aims just past the last jump, to head back there
+///
+/// ```
+/// use refunge::report::Tick;
+/// use refunge::{Arguments, Befunge, GridMode};
+///
+/// // 'U' aims 2 ticks before itself, 3 before the 'J', landing on the '.' below
+/// // while the original IP is still on its way to the jump (and reflecting
+/// // when the history doesn't go back that far)
+/// let program = "\"SDRT\"4(01D7#@02-UJ\n.@";
+/// for (history, output, ticks) in [(3, "7 ", 18), (2, "", 23)] {
+///     let args = Arguments { quiet: true, history: Some(history), ..Default::default() };
+///     let mut befunge = Befunge::from_text(program, args).unwrap();
+///     befunge.capture_output();
+///     let landed: Vec<u64> = befunge
+///         .ticks()
+///         .filter_map(|tick| match tick {
+///             Tick::Ran(report) => Some(report),
+///             Tick::Input(_) => None,
+///         })
+///         .filter(|report| report.steps.iter().any(|step| step.pos.1 == 1))
+///         .map(|report| report.tick)
+///         .collect();
+///     assert_eq!(befunge.output(), output);
+///     assert_eq!(landed.first().copied(), (history == 3).then_some(14));
+///     assert_eq!(befunge.stats().ticks, ticks);
+/// }
+///
+/// // 'p' an '@' over the '.' it lands on and an 'x' out at (0, 5), then jump
+/// // back to tick 12, before both: the '.' is back and the grid shrinks again
+/// let program = "\"SDRT\"4(01D7\"@\"01p\"x\"05pcTJ\n.@";
+/// for grid in [GridMode::Dense, GridMode::Sparse] {
+///     let args = Arguments { quiet: true, history: Some(20), grid, ..Default::default() };
+///     let mut befunge = Befunge::from_text(program, args).unwrap();
+///     befunge.capture_output();
+///     let mut heights = vec![];
+///     while !befunge.ended() {
+///         befunge.tick();
+///         heights.push((befunge.stats().ticks, befunge.grid().height()));
+///     }
+///     assert_eq!(befunge.output(), "7 ");
+///     assert_eq!(heights[23..27], [(24, 6), (25, 6), (26, 6), (12, 2)]);
+/// }
+///
+/// // IP 1 lands on the second row, where 'I' sends it forward again to just
+/// // after the 'J' it left from, arriving once IP 0 has left to print the 7
+/// let program = "\"SDRT\"4(01D7#@03-UJ.@\nIJ";
+/// let args = Arguments { quiet: true, history: Some(5), ..Default::default() };
+/// let mut befunge = Befunge::from_text(program, args).unwrap();
+/// befunge.capture_output();
+/// let steps: Vec<(u64, char)> = befunge
+///     .ticks()
+///     .filter_map(|tick| match tick {
+///         Tick::Ran(report) => Some(report),
+///         Tick::Input(_) => None,
+///     })
+///     .flat_map(|report| {
+///         let tick = report.tick;
+///         report.steps.into_iter().filter(|step| step.id == 1).map(move |step| (tick, step.instruction))
+///     })
+///     .collect();
+/// assert_eq!(steps, [(13, 'I'), (14, 'J'), (18, '.'), (19, '@')]);
+/// assert_eq!(befunge.output(), "7 ");
+///
+/// // 'P' is --history, or the ticks so far if that's less, and 'I' with no
+/// // jump to head back to reflects into the '@'
+/// for (program, history, output) in [("P.@", Some(5), "5 "), ("P.@", None, "0 "), ("#@I1.@", Some(5), "")] {
+///     let args = Arguments { quiet: true, history, ..Default::default() };
+///     let mut befunge = Befunge::from_text(&format!("\"SDRT\"4({program}"), args).unwrap();
+///     befunge.capture_output();
+///     befunge.run(|| true);
+///     assert_eq!(befunge.output(), output);
+/// }
+/// ```
+const TRDS: Fingerprint = Fingerprint {
+    name: "TRDS",
+    semantics: &[
+        // let the other IPs carry on again
+        ('C', |_, ctx| ctx.send(Event::ResumeTime)),
+        // land on an absolute cell
+        ('D', |ip, _| {
+            let v = ip.pop_t();
+            ip.tardis.space = Some(Destination::Absolute(v));
+        }),
+        // land on a cell relative to the jump
+        ('E', |ip, _| {
+            let v = ip.pop_t();
+            ip.tardis.space = Some(Destination::Relative(v));
+        }),
+        // get the current tick
+        ('G', |ip, ctx| ip.push(ctx.stats.ticks as i32)),
+        // head back to just after the last jump, reflecting if there hasn't been one
+        ('I', |ip, ctx| match ip.tardis.departed {
+            Some((pos, time, delta)) => {
+                ip.tardis.space = Some(Destination::Absolute(pos));
+                ip.tardis.time = Some(time);
+                ip.tardis.delta = Some(delta);
+            }
+            None => ip.reflect(Reflection::Failed, ctx.stats),
+        }),
+        // jump
+        ('J', |ip, ctx| ctx.send(Event::Jump(ip.id))),
+        // how many ticks back a jump can go from here
+        ('P', |ip, ctx| {
+            let back = (ctx.env.history() as u64).min(ctx.stats.ticks);
+            ip.push(i32::try_from(back).unwrap_or(i32::MAX));
+        }),
+        // forget the destination
+        ('R', |ip, _| {
+            ip.tardis = Tardis {
+                arriving: ip.tardis.arriving,
+                departed: ip.tardis.departed,
+                ..Default::default()
+            }
+        }),
+        // stop time, freezing every other IP until 'C' (or this one dies)
+        ('S', |ip, ctx| ctx.send(Event::StopTime(ip.id))),
+        // land on an absolute tick
+        ('T', |ip, _| {
+            let n = ip.pop();
+            ip.tardis.time = Some(n as i64);
+        }),
+        // land some ticks from now
+        ('U', |ip, ctx| {
+            let n = ip.pop();
+            ip.tardis.time = Some(ctx.stats.ticks as i64 + n as i64);
+        }),
+        // land with a new delta
+        ('V', |ip, _| {
+            let v = ip.pop_t();
+            ip.tardis.delta = Some(v);
+        }),
+    ],
+};
+
+/// socket extensions: 'H' checks whether a socket has data waiting, for
+/// sockets handed over with add_socket since there's no SOCK to open them,
+/// and 'P' looks up a host name
+///
+/// ```
+/// use refunge::platform::Sandboxed;
+/// use refunge::{Arguments, Befunge};
+/// use std::io::Write;
+/// use std::net::{TcpListener, TcpStream};
+///
+/// // 'P' packs 127.0.0.1 into 0x7f000001, or reflects into the '@' when sandboxed
+/// let program = "\"EKCS\"4(0\"1.0.0.721\"#@P.@";
+/// for (sandboxed, output) in [(false, "2130706433 "), (true, "")] {
+///     let args = Arguments { quiet: true, ..Default::default() };
+///     let mut befunge = Befunge::from_text(program, args).unwrap();
+///     if sandboxed {
+///         befunge.set_platform(Sandboxed);
+///     }
+///     befunge.capture_output();
+///     befunge.run(|| true);
+///     assert_eq!(befunge.output(), output);
+/// }
+///
+/// // 'H' says there's nothing on socket 0 until the other end writes to it
+/// let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+/// let mut peer = TcpStream::connect(listener.local_addr().unwrap()).unwrap();
+/// let (socket, _) = listener.accept().unwrap();
+/// let waiting = socket.try_clone().unwrap();
+/// let args = Arguments { quiet: true, ..Default::default() };
+/// let mut befunge = Befunge::from_text("\"EKCS\"4(0H.0H.@", args).unwrap();
+/// assert_eq!(befunge.add_socket(socket), 0);
+/// befunge.capture_output();
+/// while befunge.output().is_empty() {
+///     befunge.tick();
+/// }
+/// peer.write_all(b"x").unwrap();
+/// waiting.peek(&mut [0]).unwrap();
+/// befunge.run(|| true);
+/// assert_eq!(befunge.output(), "0 1 ");
+///
+/// // but reflects into the '@' when sandboxed, or for a socket it wasn't given
+/// for (sandboxed, socket) in [(true, '0'), (false, '1')] {
+///     let args = Arguments { quiet: true, ..Default::default() };
+///     let program = format!("\"EKCS\"4({socket}#@H1.@");
+///     let mut befunge = Befunge::from_text(&program, args).unwrap();
+///     befunge.add_socket(TcpStream::connect(listener.local_addr().unwrap()).unwrap());
+///     if sandboxed {
+///         befunge.set_platform(Sandboxed);
+///     }
+///     befunge.capture_output();
+///     befunge.run(|| true);
+///     assert_eq!(befunge.output(), "");
+/// }
+/// ```
+const SCKE: Fingerprint = Fingerprint {
+    name: "SCKE",
+    semantics: &[
+        // whether a socket has data waiting to be read (1) or not (0)
+        ('H', |ip, ctx| {
+            let socket = ip.pop();
+            match ctx.env.socket_ready(socket) {
+                Ok(ready) => ip.push(ready as i32),
+                Err(error) => {
+                    log::debug!("ip {}: can't check socket {socket}: {error}", ip.id);
+                    ip.reflect(Reflection::Failed, ctx.stats);
+                }
+            }
+        }),
+        // resolve a 0gnirts host name to its first IPv4 address, big-endian
+        ('P', |ip, ctx| {
+            let host: String = ip.pop_t();
+            let ipv4 = ctx.env.platform().resolve(&host).ok().and_then(|addrs| {
+                addrs.into_iter().find_map(|addr| match addr {
+                    IpAddr::V4(v4) => Some(u32::from(v4)),
+                    IpAddr::V6(_) => None,
+                })
+            });
+            match ipv4 {
+                Some(addr) => ip.push(addr as i32),
+                None => {
+                    log::debug!(
+                        "ip {}: could not resolve {host:?} to an IPv4 address",
+                        ip.id
+                    );
+                    ip.reflect(Reflection::Failed, ctx.stats);
+                }
+            }
+        }),
+    ],
+};
+
+/// a 4x4 matrix of floats, by row
+type Matrix = [[f32; 4]; 4];
+
+/// pop a float stored as its bits
+fn pop_f32(ip: &mut InstructionPointer) -> f32 {
+    f32::from_bits(ip.pop() as u32)
+}
+fn push_f32(ip: &mut InstructionPointer, f: f32) {
+    ip.push(f.to_bits() as i32);
+}
+/// pop a vector of 3 floats, z being on top
+fn pop_v3(ip: &mut InstructionPointer) -> [f32; 3] {
+    let z = pop_f32(ip);
+    let y = pop_f32(ip);
+    [pop_f32(ip), y, z]
+}
+fn push_v3(ip: &mut InstructionPointer, v: [f32; 3]) {
+    v.into_iter().for_each(|f| push_f32(ip, f));
+}
+/// the matrix with its top left corner at pos (relative to the storage offset),
+/// each row going along x
+fn read_matrix(ip: &InstructionPointer, grid: &FungeGrid, pos: FungeVector) -> Matrix {
+    let mut m = Matrix::default();
+    for (row, col) in (0..4).flat_map(|row| (0..4).map(move |col| (row, col))) {
+        let cell = grid.cell_at(ip.offset + pos + FungeVector(col as i32, row as i32));
+        m[row][col] = f32::from_bits(cell as u32);
+    }
+    m
+}
+/// write a matrix out the same way read_matrix reads it, reflecting without
+/// writing any of it if it won't all fit
+fn write_matrix(ip: &mut InstructionPointer, ctx: &mut Context, pos: FungeVector, m: Matrix) {
+    let pos = ip.offset + pos;
+    if !ctx.grid.has_room(pos, pos + FungeVector(3, 3)) {
+        log::warn!("ip {}: no room for a matrix at {pos}", ip.id);
+        return ip.reflect(Reflection::Failed, ctx.stats);
+    }
+    for (row, col) in (0..4).flat_map(|row| (0..4).map(move |col| (row, col))) {
+        let cell = pos + FungeVector(col as i32, row as i32);
+        ctx.grid.set_cell(cell, m[row][col].to_bits() as i32);
+    }
+}
+fn identity() -> Matrix {
+    let mut m = Matrix::default();
+    (0..4).for_each(|idx| m[idx][idx] = 1.0);
+    m
+}
+fn length(v: [f32; 3]) -> f32 {
+    v.iter().map(|f| f * f).sum::<f32>().sqrt()
+}
+/// apply a function to each pair of components
+fn zip_v3(a: [f32; 3], b: [f32; 3], f: impl Fn(f32, f32) -> f32) -> [f32; 3] {
+    [f(a[0], b[0]), f(a[1], b[1]), f(a[2], b[2])]
+}
+
+/// 3d vectors and 4x4 transformation matrices, with floats stored as their bits
+/// and matrices kept in funge-space (one row of 4 cells per line)
+///
+/// ```
+/// use refunge::vector::FungeVector;
+/// use refunge::{Arguments, Befunge};
+///
+/// // 'T' writes a translation by (10, 20, 30) to (0, 4), which 'X' moves
+/// // (1, 2, 3) by before 'p'utting the result on the last line
+/// let program = "\"PSD3\"4(04 33g43g53gT 03g13g23g04X 28p18p08p@";
+/// let args = Arguments { quiet: true, ..Default::default() };
+/// let mut befunge = Befunge::from_text(program, args).unwrap();
+/// let mut snapshot = befunge.snapshot();
+/// for (x, f) in [1.0f32, 2.0, 3.0, 10.0, 20.0, 30.0].into_iter().enumerate() {
+///     snapshot.grid.set_cell(FungeVector(x as i32, 3), f.to_bits() as i32);
+/// }
+/// befunge.restore(snapshot).unwrap();
+/// befunge.run(|| true);
+/// let cell = |x, y| f32::from_bits(befunge.grid().cell_at(FungeVector(x, y)) as u32);
+/// assert_eq!([cell(0, 4), cell(3, 4), cell(3, 6), cell(3, 7)], [1.0, 10.0, 30.0, 1.0]);
+/// assert_eq!([cell(0, 8), cell(1, 8), cell(2, 8)], [11.0, 22.0, 33.0]);
+///
+/// // a matrix at (14, 0) needs 18x4 cells when only 16x4 are allowed, so
+/// // 'T' reflects back to the '@' without writing any of it
+/// let program = "\"PSD3\"4(#@e0111T";
+/// let args = Arguments { quiet: true, max_grid_cells: Some(64), ..Default::default() };
+/// let mut befunge = Befunge::from_text(program, args).unwrap();
+/// befunge.run(|| true);
+/// assert_eq!(befunge.grid().read_from(FungeVector(0, 0), FungeVector(15, 0)).trim_end(), program);
+/// ```
+const THREE_D_SP: Fingerprint = Fingerprint {
+    name: "3DSP",
+    semantics: &[
+        // add, subtract
+        ('A', |ip, _| {
+            let (b, a) = (pop_v3(ip), pop_v3(ip));
+            push_v3(ip, zip_v3(a, b, |a, b| a + b));
+        }),
+        ('B', |ip, _| {
+            let (b, a) = (pop_v3(ip), pop_v3(ip));
+            push_v3(ip, zip_v3(a, b, |a, b| a - b));
+        }),
+        // cross product
+        ('C', |ip, _| {
+            let (b, a) = (pop_v3(ip), pop_v3(ip));
+            push_v3(
+                ip,
+                [
+                    a[1] * b[2] - a[2] * b[1],
+                    a[2] * b[0] - a[0] * b[2],
+                    a[0] * b[1] - a[1] * b[0],
+                ],
+            );
+        }),
+        // dot product
+        ('D', |ip, _| {
+            let (b, a) = (pop_v3(ip), pop_v3(ip));
+            push_f32(ip, zip_v3(a, b, |a, b| a * b).iter().sum());
+        }),
+        ('L', |ip, _| {
+            let v = pop_v3(ip);
+            push_f32(ip, length(v));
+        }),
+        // multiply matrices a and b into d (Vdm Vam Vbm)
+        ('M', |ip, ctx| {
+            let (b, a, dest): (FungeVector, FungeVector, FungeVector) =
+                (ip.pop_t(), ip.pop_t(), ip.pop_t());
+            let (a, b) = (read_matrix(ip, ctx.grid, a), read_matrix(ip, ctx.grid, b));
+            let mut m = Matrix::default();
+            for (row, col) in (0..4).flat_map(|row| (0..4).map(move |col| (row, col))) {
+                m[row][col] = (0..4).map(|idx| a[row][idx] * b[idx][col]).sum();
+            }
+            write_matrix(ip, ctx, dest, m);
+        }),
+        // normalize, leaving a zero vector alone
+        ('N', |ip, _| {
+            let v = pop_v3(ip);
+            let len = length(v);
+            push_v3(ip, if len == 0.0 { v } else { v.map(|f| f / len) });
+        }),
+        // copy matrix s to d (Vdm Vsm)
+        ('P', |ip, ctx| {
+            let (src, dest): (FungeVector, FungeVector) = (ip.pop_t(), ip.pop_t());
+            let m = read_matrix(ip, ctx.grid, src);
+            write_matrix(ip, ctx, dest, m);
+        }),
+        // rotation by some degrees around axis 1 (x), 2 (y) or 3 (z) into d
+        ('R', |ip, ctx| {
+            let angle = pop_f32(ip).to_radians();
+            let axis = ip.pop();
+            let dest: FungeVector = ip.pop_t();
+            let (sin, cos) = angle.sin_cos();
+            let mut m = identity();
+            let (a, b) = match axis {
+                1 => (1, 2),
+                2 => (2, 0),
+                3 => (0, 1),
+                _ => return ip.reflect(Reflection::Failed, ctx.stats),
+            };
+            (m[a][a], m[a][b], m[b][a], m[b][b]) = (cos, -sin, sin, cos);
+            write_matrix(ip, ctx, dest, m);
+        }),
+        // scale into d (Vdm V3)
+        ('S', |ip, ctx| {
+            let v = pop_v3(ip);
+            let dest: FungeVector = ip.pop_t();
+            let mut m = identity();
+            (0..3).for_each(|idx| m[idx][idx] = v[idx]);
+            write_matrix(ip, ctx, dest, m);
+        }),
+        // translation into d (Vdm V3)
+        ('T', |ip, ctx| {
+            let v = pop_v3(ip);
+            let dest: FungeVector = ip.pop_t();
+            let mut m = identity();
+            (0..3).for_each(|idx| m[idx][3] = v[idx]);
+            write_matrix(ip, ctx, dest, m);
+        }),
+        ('U', |ip, _| {
+            let v = pop_v3(ip);
+            push_v3(ip, v);
+            push_v3(ip, v);
+        }),
+        // map onto 2d by dividing by z, unless it's 0
+        ('V', |ip, _| {
+            let [x, y, z] = pop_v3(ip);
+            let z = if z == 0.0 { 1.0 } else { z };
+            push_f32(ip, x / z);
+            push_f32(ip, y / z);
+        }),
+        // transform a point by matrix a (V3 Vam)
+        ('X', |ip, ctx| {
+            let pos: FungeVector = ip.pop_t();
+            let v = pop_v3(ip);
+            let m = read_matrix(ip, ctx.grid, pos);
+            let point = [v[0], v[1], v[2], 1.0];
+            let out = [0, 1, 2].map(|row| (0..4).map(|col| m[row][col] * point[col]).sum());
+            push_v3(ip, out);
+        }),
+        // multiply component by component
+        ('Y', |ip, _| {
+            let (b, a) = (pop_v3(ip), pop_v3(ip));
+            push_v3(ip, zip_v3(a, b, |a, b| a * b));
+        }),
+        // scale by a float (n V3)
+        ('Z', |ip, _| {
+            let v = pop_v3(ip);
+            let n = pop_f32(ip);
+            push_v3(ip, v.map(|f| f * n));
+        }),
+    ],
+};
+
+/// widest field and most decimal places a format may ask for, past which
+/// it's treated as invalid rather than padded out to gigabytes
+pub const MAX_FORMAT_WIDTH: usize = 1024;
+
+/// one piece of a printf format string
+enum Piece {
+    Text(String),
+    Conversion {
+        left: bool,
+        zero: bool,
+        width: usize,
+        precision: Option<usize>,
+        kind: char,
+    },
+}
+
+/// a number of at most MAX_FORMAT_WIDTH, none if it's more
+fn format_number(chars: &mut std::iter::Peekable<std::str::Chars>) -> Option<usize> {
+    let mut n = 0usize;
+    while let Some(digit) = chars.next_if(char::is_ascii_digit) {
+        n = n * 10 + (digit as usize - '0' as usize);
+        if n > MAX_FORMAT_WIDTH {
+            return None;
+        }
+    }
+    Some(n)
+}
+
+/// split a format string into text and conversions, none if it's invalid
+fn parse_format(format: &str) -> Option<Vec<Piece>> {
+    let mut pieces = vec![];
+    let mut text = String::new();
+    let mut chars = format.chars().peekable();
+    while let Some(c) = chars.next() {
+        if c != '%' {
+            text.push(c);
+            continue;
+        }
+        let (mut left, mut zero) = (false, false);
+        while let Some(flag) = chars.next_if(|c| matches!(c, '-' | '0')) {
+            (left, zero) = (left || flag == '-', zero || flag == '0');
+        }
+        let width = format_number(&mut chars)?;
+        let mut precision = None;
+        if chars.next_if_eq(&'.').is_some() {
+            precision = Some(format_number(&mut chars)?);
+        }
+        match chars.next()? {
+            '%' => text.push('%'),
+            kind @ ('d' | 'i' | 'x' | 'X' | 'o' | 'c' | 's' | 'f') => {
+                pieces.push(Piece::Text(std::mem::take(&mut text)));
+                pieces.push(Piece::Conversion {
+                    left,
+                    zero,
+                    width,
+                    precision,
+                    kind,
+                });
+            }
+            _ => return None,
+        }
+    }
+    pieces.push(Piece::Text(text));
+    Some(pieces)
+}
+
+/// a value for printf to fill a conversion with
+#[derive(Debug, Clone, PartialEq)]
+pub enum FormatArg {
+    Int(i32),
+    Long(i64),
+    Float(f64),
+    Text(String),
+}
+
+/// fill in a printf format string with d i x X o c s f conversions, flags `-`
+/// and `0`, widths and precisions up to [MAX_FORMAT_WIDTH] and `%%`, taking
+/// each argument in turn (0 or empty once they run out), none if the format
+/// is invalid or an argument is the wrong kind for its conversion
+///
+/// ```
+/// use refunge::fingerprint::{printf, FormatArg::*};
+///
+/// let args = [Int(-42), Int(-42), Int(255), Int(-1), Int(8), Int('x' as i32), Text("hi".into()), Float(2.5)];
+/// let formatted = printf("[%5d|%-05d|%X|%x|%o|%c|%s|%.2f|%%|%d]", &args);
+/// assert_eq!(formatted.as_deref(), Some("[  -42|-42  |FF|ffffffff|10|x|hi|2.50|%|0]"));
+/// assert_eq!(printf("%05d", &[Int(-7)]).as_deref(), Some("-0007"));
+/// assert_eq!(printf("%x", &[Long(-1)]).as_deref(), Some("ffffffffffffffff"));
+/// assert_eq!(printf("%q", &[]), None);
+/// assert_eq!(printf("50%", &[]), None);
+/// assert_eq!(printf("%d", &[Text("hi".into())]), None);
+/// // nothing wider than MAX_FORMAT_WIDTH, so a format can't ask for gigabytes
+/// assert_eq!(printf("%1024d", &[Int(1)]).map(|s| s.len()), Some(1024));
+/// assert_eq!(printf("%99999999999d", &[Int(1)]), None);
+/// assert_eq!(printf("%.999999999f", &[Float(1.0)]), None);
+/// ```
+pub fn printf(format: &str, args: &[FormatArg]) -> Option<String> {
+    let mut args = args.iter();
+    let mut out = String::new();
+    for piece in parse_format(format)? {
+        let (left, zero, width, precision, kind) = match piece {
+            Piece::Text(text) => {
+                out.push_str(&text);
+                continue;
+            }
+            Piece::Conversion {
+                left,
+                zero,
+                width,
+                precision,
+                kind,
+            } => (left, zero, width, precision, kind),
+        };
+        let arg = args.next();
+        let value = match (kind, arg) {
+            ('s', None) => String::new(),
+            ('s', Some(FormatArg::Text(text))) => text.clone(),
+            ('f', None) => format!("{:.*}", precision.unwrap_or(6), 0.0),
+            ('f', Some(FormatArg::Float(f))) => format!("{:.*}", precision.unwrap_or(6), f),
+            (_, None) => integer(kind, 0)?,
+            (_, Some(FormatArg::Int(n))) => integer(kind, *n)?,
+            (_, Some(FormatArg::Long(n))) => integer(kind, *n)?,
+            _ => return None,
+        };
+        let pad = width.saturating_sub(value.chars().count());
+        if left {
+            write!(out, "{value}{:pad$}", "").unwrap_or(());
+        } else if zero && !matches!(kind, 'c' | 's') {
+            let (sign, digits) = match value.strip_prefix('-') {
+                Some(digits) => ("-", digits),
+                None => ("", value.as_str()),
+            };
+            write!(out, "{sign}{:0>pad$}{digits}", "").unwrap_or(());
+        } else {
+            write!(out, "{:pad$}{value}", "").unwrap_or(());
+        }
+    }
+    Some(out)
+}
+
+/// an integer as a d i x X o or c conversion shows it, none for s or f
+fn integer<N>(kind: char, n: N) -> Option<String>
+where
+    N: std::fmt::Display + std::fmt::LowerHex + std::fmt::UpperHex + std::fmt::Octal + TryInto<u32>,
+{
+    Some(match kind {
+        'd' | 'i' => n.to_string(),
+        'x' => format!("{n:x}"),
+        'X' => format!("{n:X}"),
+        'o' => format!("{n:o}"),
+        'c' => n
+            .try_into()
+            .ok()
+            .and_then(char::from_u32)
+            .unwrap_or(char::REPLACEMENT_CHARACTER)
+            .to_string(),
+        _ => return None,
+    })
+}
+
+/// pop a 64-bit value kept in two cells, the low half on top, the way FPDP
+/// and LONG keep them
+fn pop_u64(ip: &mut InstructionPointer) -> u64 {
+    let low = ip.pop() as u32 as u64;
+    let high = ip.pop() as u32 as u64;
+    high << 32 | low
+}
+
+/// format the argument on top of the stack with the 0gnirts format under it,
+/// pushing what comes out as a 0gnirts or reflecting if the format is invalid
+fn format_top(ip: &mut InstructionPointer, ctx: &mut Context, arg: FormatArg) {
+    let format: String = ip.pop_t();
+    match printf(&format, &[arg]) {
+        Some(text) => ip.push(text),
+        None => {
+            log::warn!("ip {}: invalid format {format:?}, reflecting", ip.id);
+            ip.reflect(Reflection::Failed, ctx.stats);
+        }
+    }
+}
+
+/// formatted printing: each instruction pops a value and a 0gnirts printf
+/// format under it, and pushes the formatted text back as a 0gnirts
+///
+/// ```
+/// use refunge::{Arguments, Befunge};
+///
+/// // push the format (backwards, so it pops in order) and the value, format
+/// // it and print what comes out, or reflect into the '@'
+/// let run = |format: &str, value: &str, instruction: char| {
+///     let format: String = format.chars().rev().collect();
+///     let program = format!("\"TRPF\"4(0\"{format}\"{value}#@{instruction}>:#,_@");
+///     let args = Arguments { quiet: true, ..Default::default() };
+///     let mut befunge = Befunge::from_text(&program, args).unwrap();
+///     befunge.capture_output();
+///     befunge.run(|| true);
+///     befunge.output().to_string()
+/// };
+/// assert_eq!(run("%5d|", "07-", 'I'), "   -7|");
+/// assert_eq!(run("%x %d", "07-", 'I'), "fffffff9 0");
+/// assert_eq!(run("%d", "10", 'L'), "4294967296");
+/// // 2.5 as an FPSP float (0x40200000) and an FPDP double (0x40040000, 0)
+/// assert_eq!(run("%.2f", "\"@\"f1+:**\" \"+f1+:*:**", 'F'), "2.50");
+/// assert_eq!(run("%.1f", "\"@\"f1+:**4+f1+:*:**0", 'D'), "2.5");
+/// assert_eq!(run("<%-4s>", "0\"ih\"", 'S'), "<hi  >");
+/// // a format it doesn't know, the wrong kind of value, or a width that's too big
+/// assert_eq!(run("%q", "1", 'I'), "");
+/// assert_eq!(run("%s", "1", 'I'), "");
+/// assert_eq!(run("%99999999999d", "1", 'I'), "");
+/// ```
+const FPRT: Fingerprint = Fingerprint {
+    name: "FPRT",
+    semantics: &[
+        // a double, FPDP style
+        ('D', |ip, ctx| {
+            let f = f64::from_bits(pop_u64(ip));
+            format_top(ip, ctx, FormatArg::Float(f));
+        }),
+        // a float, FPSP style
+        ('F', |ip, ctx| {
+            let f = pop_f32(ip) as f64;
+            format_top(ip, ctx, FormatArg::Float(f));
+        }),
+        // an integer
+        ('I', |ip, ctx| {
+            let n = ip.pop();
+            format_top(ip, ctx, FormatArg::Int(n));
+        }),
+        // a long, the low cell on top
+        ('L', |ip, ctx| {
+            let n = pop_u64(ip) as i64;
+            format_top(ip, ctx, FormatArg::Long(n));
+        }),
+        // a 0gnirts
+        ('S', |ip, ctx| {
+            let text: String = ip.pop_t();
+            format_top(ip, ctx, FormatArg::Text(text));
+        }),
+    ],
+};
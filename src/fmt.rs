@@ -0,0 +1,97 @@
+use crate::arguments::FmtArguments;
+use crate::FungeGrid;
+use anyhow::{bail, Context, Result};
+use std::fs::{read_to_string, write};
+
+/// normalize a source file for `refunge fmt`, returning whether it was already formatted
+///
+/// ```
+/// use refunge::arguments::FmtArguments;
+///
+/// let path = std::env::temp_dir().join("refunge-doctest-fmt.bf");
+/// std::fs::write(&path, "v  \r\n>\"\u{e9}\",@ \r\n").unwrap();
+/// let args = FmtArguments { file: path.to_string_lossy().into_owned(), pad: false, check: false };
+/// assert!(!refunge::fmt::run(&args).unwrap());
+/// assert_eq!(std::fs::read_to_string(&path).unwrap(), "v\n>\"\u{e9}\",@\n");
+/// assert!(refunge::fmt::run(&args).unwrap());
+/// ```
+pub fn run(args: &FmtArguments) -> Result<bool> {
+    let path = &args.file;
+    let text = read_to_string(path).with_context(|| format!("failed to read {path}"))?;
+    if text.trim().is_empty() {
+        bail!("{path} is empty");
+    }
+    let formatted = format(&text, args.pad);
+    if let Some((old_width, new_width, lines)) = width_change(&text, &formatted) {
+        let lines: Vec<String> = lines.iter().map(usize::to_string).collect();
+        eprintln!(
+            "warning: trimming changed the grid width of {path} from {old_width} to {new_width} (lines {})",
+            lines.join(", ")
+        );
+    }
+    let unchanged = formatted == text;
+    if args.check {
+        print!("{formatted}");
+    } else if !unchanged {
+        write(path, formatted).with_context(|| format!("failed to write {path}"))?;
+    }
+    Ok(unchanged)
+}
+
+/// the grid width before and after formatting and the (1-based) lines that
+/// were wider than it ends up, if formatting changed it
+///
+/// ```
+/// use refunge::fmt::{format, width_change};
+///
+/// // only the first line was trimmed, the second is narrower in characters than in bytes
+/// let text = "abc   \n\u{3bb}\u{3bb}\n";
+/// assert_eq!(width_change(text, &format(text, false)), Some((6, 3, vec![1])));
+/// assert_eq!(width_change(text, &format(text, true)), Some((6, 3, vec![1])));
+/// assert_eq!(width_change("abc\n\u{3bb} \n", "abc\n\u{3bb}\n"), None);
+/// ```
+pub fn width_change(text: &str, formatted: &str) -> Option<(usize, usize, Vec<usize>)> {
+    let (old_width, new_width) = (grid_width(text), grid_width(formatted));
+    if old_width == new_width {
+        return None;
+    }
+    let lines = text
+        .lines()
+        .enumerate()
+        .filter(|(_, line)| line.chars().count() > new_width)
+        .map(|(i, _)| i + 1)
+        .collect();
+    Some((old_width, new_width, lines))
+}
+
+/// strip trailing whitespace and end every line with \n, optionally padding
+/// lines with spaces up to the width of the widest one
+///
+/// ```
+/// use refunge::fmt::format;
+///
+/// let crlf = "\"!\u{3bb}\",, \t\r\n>:#,_@\r\n\r\n";
+/// assert_eq!(format(crlf, false), "\"!\u{3bb}\",,\n>:#,_@\n\n");
+/// assert_eq!(format(crlf, true), "\"!\u{3bb}\",,\n>:#,_@\n      \n");
+/// // formatting again changes nothing
+/// for pad in [false, true] {
+///     assert_eq!(format(&format(crlf, pad), pad), format(crlf, pad));
+/// }
+/// ```
+pub fn format(text: &str, pad: bool) -> String {
+    let lines: Vec<&str> = text.lines().map(str::trim_end).collect();
+    let width = lines.iter().map(|l| l.chars().count()).max().unwrap_or(0);
+    lines
+        .iter()
+        .map(|line| match pad {
+            true => format!("{line:width$}\n"),
+            false => format!("{line}\n"),
+        })
+        .collect()
+}
+
+/// the width funge-space will have once the text is loaded as it is, the way
+/// --preserve-trailing-space (and plenty of other interpreters) load it
+fn grid_width(text: &str) -> usize {
+    FungeGrid::with_trailing_space(text.to_string()).width()
+}
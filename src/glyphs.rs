@@ -0,0 +1,108 @@
+//! what the tui draws with, which is plain ASCII under --ascii for terminals
+//! that turn box drawing and arrows into garbage
+use ratatui::buffer::Buffer;
+use ratatui::symbols::border;
+use ratatui::widgets::{Block, Borders};
+
+/// +, - and | borders for --ascii
+pub const ASCII_BORDERS: border::Set = border::Set {
+    top_left: "+",
+    top_right: "+",
+    bottom_left: "+",
+    bottom_right: "+",
+    vertical_left: "|",
+    vertical_right: "|",
+    horizontal_top: "-",
+    horizontal_bottom: "-",
+};
+
+/// what a tab in the grid is drawn as, since it's a single cell however
+/// wide the terminal would show it
+///
+/// ```
+/// use ratatui::{backend::TestBackend, Terminal};
+/// use refunge::{Arguments, Befunge};
+///
+/// let mut befunge = Befunge::from_text("1\t.@\n>\t\t@", Arguments::default()).unwrap();
+/// let mut terminal = Terminal::new(TestBackend::new(80, 24)).unwrap();
+/// terminal.draw(|f| befunge.render(f)).unwrap();
+/// let buffer = terminal.backend().buffer();
+/// let row = |y| (0..6).map(|x| buffer.get(x, y).symbol.as_str()).collect::<String>();
+/// assert_eq!(row(1), "│1⇥.@ ");
+/// assert_eq!(row(2), "│>⇥⇥@ ");
+/// ```
+pub const TAB: char = '⇥';
+
+/// a block with borders all around, drawn in plain ASCII if asked
+pub fn block<'a>(ascii: bool) -> Block<'a> {
+    let block = Block::default().borders(Borders::ALL);
+    match ascii {
+        true => block.border_set(ASCII_BORDERS),
+        false => block,
+    }
+}
+
+/// the closest plain ASCII stand-in for a symbol, or ? if there's nothing close
+///
+/// ```
+/// use refunge::glyphs::ascii_symbol;
+///
+/// let symbols = ["┌", "─", "│", "→", "↑", "↗", "·", "⟨", "x", "λ"];
+/// let ascii: Vec<&str> = symbols.iter().map(|s| ascii_symbol(s)).collect();
+/// assert_eq!(ascii, ["+", "-", "|", ">", "^", "/", ".", "<", "x", "?"]);
+/// ```
+pub fn ascii_symbol(symbol: &str) -> &str {
+    if symbol.is_ascii() {
+        return symbol;
+    }
+    match symbol {
+        "─" | "━" | "═" => "-",
+        "│" | "┃" | "║" => "|",
+        "┌" | "┐" | "└" | "┘" | "├" | "┤" | "┬" | "┴" | "┼" | "╭" | "╮" | "╯" | "╰" => {
+            "+"
+        }
+        "→" => ">",
+        "←" => "<",
+        "↑" => "^",
+        "↓" => "v",
+        "↗" | "↙" => "/",
+        "↖" | "↘" => "\\",
+        "·" => ".",
+        "…" => ".",
+        "Δ" => "d",
+        "⟨" => "<",
+        "⟩" => ">",
+        _ => "?",
+    }
+}
+
+/// swap every symbol drawn so far for its ASCII stand-in, catching whatever
+/// the program itself put in the grid or the output
+///
+/// ```
+/// use ratatui::{backend::TestBackend, Terminal};
+/// use refunge::{Arguments, Befunge};
+///
+/// // prints a λ, then bounces off the Q and heads back west over blank cells
+/// let args = Arguments { ascii: true, ..Default::default() };
+/// let mut befunge = Befunge::from_text("'λ,Q\n  1t", args).unwrap();
+/// (0..4).for_each(|_| befunge.tick());
+/// let mut terminal = Terminal::new(TestBackend::new(80, 24)).unwrap();
+/// terminal.draw(|f| befunge.render(f)).unwrap();
+/// let buffer = terminal.backend().buffer();
+/// let rows: Vec<String> = (0..24)
+///     .map(|y| (0..80).map(|x| buffer.get(x, y).symbol.as_str()).collect())
+///     .collect();
+/// assert!(rows.iter().all(|row| row.bytes().all(|b| b <= 0x7f)));
+/// assert!(rows[0].starts_with("+Grid - <eval>"));
+/// assert!(rows[4].contains("|<|"));
+/// assert!(rows[1].starts_with("|'?,!"));
+/// ```
+pub fn asciify(buffer: &mut Buffer) {
+    for cell in buffer.content.iter_mut() {
+        if !cell.symbol.is_ascii() {
+            let symbol = ascii_symbol(&cell.symbol).to_string();
+            cell.set_symbol(&symbol);
+        }
+    }
+}
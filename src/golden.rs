@@ -0,0 +1,254 @@
+//! golden tests: run a program to the end and compare what it left behind
+//! against a run that's known to be right
+//!
+//! ```
+//! use refunge::golden::{self, RunResult};
+//!
+//! let factorial = "&>:1-:v v *_$.@\n ^    _$>\\:^";
+//! let expected = RunResult {
+//!     output: "120 ".to_string(),
+//!     exit_code: 0,
+//!     ticks: 77,
+//!     grid_digest: 0xcdb0_2cc0_bb13_cab0,
+//!     stacks_digest: 0x3922_09f1_4dea_4c24,
+//! };
+//! let actual = golden::run_to_completion(factorial, "5", 0, 10_000).unwrap();
+//! if let Some(diff) = golden::diff(&expected, &actual) {
+//!     panic!("{diff}");
+//! }
+//! ```
+
+use crate::grid::FungeGrid;
+use crate::input::InputBuffer;
+use crate::pointer::InstructionPointer;
+use crate::{Arguments, Befunge, RefungeError};
+
+/// how a run ended up, compared field by field against a known good one
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct RunResult {
+    /// everything written by , and .
+    pub output: String,
+    /// the code given to q, or 0
+    pub exit_code: i32,
+    /// how many ticks it ran for
+    pub ticks: u64,
+    /// the final grid, see [grid_digest]
+    pub grid_digest: u64,
+    /// the stacks of every IP at the end, see [stacks_digest]
+    pub stacks_digest: u64,
+}
+
+/// run a program until it ends or max_ticks runs out, with the rng seeded and
+/// & and ~ answered from inputs (the same way as --input-file), reflecting
+/// once that runs out
+///
+/// ```
+/// use refunge::golden::{self, RunResult};
+///
+/// // hello world, writing to the grid, and picking a way to go at random
+/// let programs = [
+///     ("\"!dlrow ,olleH\">:#,_@", "Hello, world!", 98, 0x4c24_0fe8_e765_fa9b, 0x019c_c2bb_67e1_c915),
+///     ("\"@\"90p 7.", "7 ", 9, 0x29ea_a639_bc3a_feb9, 0x3922_09f1_4dea_4c24),
+///     ("v>2.@\n>?3.@\n 1\n ^<", "3 ", 16, 0x18e1_cffa_eea1_b00e, 0x7826_dd2c_1db5_f6b6),
+/// ];
+/// for (program, output, ticks, grid_digest, stacks_digest) in programs {
+///     let expected = RunResult { output: output.to_string(), exit_code: 0, ticks, grid_digest, stacks_digest };
+///     let actual = golden::run_to_completion(program, "", 1, 10_000).unwrap();
+///     assert_eq!(golden::diff(&expected, &actual), None, "{program:?}");
+/// }
+/// ```
+pub fn run_to_completion(
+    source: &str,
+    inputs: &str,
+    seed: u64,
+    max_ticks: u32,
+) -> Result<RunResult, RefungeError> {
+    let args = Arguments {
+        quiet: true,
+        seed: Some(seed),
+        max_ticks: Some(max_ticks),
+        ..Default::default()
+    };
+    let mut befunge = Befunge::from_text(source, args)?;
+    befunge.capture_output();
+    let mut inputs = InputBuffer::new(inputs.to_string());
+    loop {
+        befunge.ticks().for_each(drop);
+        let Some((_, kind)) = befunge.waiting_for_input() else {
+            break;
+        };
+        befunge.answer_input(inputs.take(kind));
+    }
+    Ok(befunge.run_result())
+}
+
+/// what's different about actual compared to expected, starting with the
+/// first byte of output that doesn't match, or none if they're the same
+///
+/// ```
+/// use refunge::golden;
+///
+/// let expected = golden::run_to_completion("\"!dlroW ,olleH\">:#,_@", "", 0, 1000).unwrap();
+/// let actual = golden::run_to_completion("\"!dlrow ,olleH\">:#,_@", "", 0, 1000).unwrap();
+/// let diff = golden::diff(&expected, &actual).unwrap();
+/// assert!(diff.starts_with(r#"output differs at byte 7 (line 1, column 8): expected "World!", got "world!""#));
+/// assert!(diff.contains("grid differs"));
+/// assert_eq!(golden::diff(&actual, &actual), None);
+/// ```
+pub fn diff(expected: &RunResult, actual: &RunResult) -> Option<String> {
+    let mut lines = vec![];
+    if let Some(at) = first_difference(expected.output.as_bytes(), actual.output.as_bytes()) {
+        let before = &expected.output.as_bytes()[..at];
+        let line = before.iter().filter(|b| **b == b'\n').count() + 1;
+        let column = at
+            - before
+                .iter()
+                .rposition(|b| *b == b'\n')
+                .map_or(0, |i| i + 1)
+            + 1;
+        lines.push(format!(
+            "output differs at byte {at} (line {line}, column {column}): expected {:?}, got {:?}",
+            excerpt(&expected.output, at),
+            excerpt(&actual.output, at),
+        ));
+    }
+    let mut compare = |what: &str, expected: String, actual: String| {
+        if expected != actual {
+            lines.push(format!("{what} differs: expected {expected}, got {actual}"));
+        }
+    };
+    compare(
+        "exit code",
+        expected.exit_code.to_string(),
+        actual.exit_code.to_string(),
+    );
+    compare(
+        "tick count",
+        expected.ticks.to_string(),
+        actual.ticks.to_string(),
+    );
+    let hex = |digest: u64| format!("{digest:#018x}");
+    compare("grid", hex(expected.grid_digest), hex(actual.grid_digest));
+    compare(
+        "stacks",
+        hex(expected.stacks_digest),
+        hex(actual.stacks_digest),
+    );
+    (!lines.is_empty()).then(|| lines.join("\n"))
+}
+
+/// the first cell of actual that isn't what it is in expected, or the sizes if
+/// those are all the same but one grew further, or none if they're equal
+///
+/// ```
+/// use refunge::golden;
+/// use refunge::{FungeGrid, FungeVector};
+///
+/// let expected = FungeGrid::new("v\n>@".to_string());
+/// let mut actual = expected.clone();
+/// actual.set_char(FungeVector(1, 1), '.');
+/// let diff = golden::diff_grids(&expected, &actual);
+/// assert_eq!(diff.as_deref(), Some("cell (1,1) differs: expected '@', got '.'"));
+/// actual.set_char(FungeVector(1, 1), '@');
+/// assert_eq!(golden::diff_grids(&expected, &actual), None);
+/// ```
+pub fn diff_grids(expected: &FungeGrid, actual: &FungeGrid) -> Option<String> {
+    let (mut expected_cells, mut actual_cells) = (
+        expected.non_space_cells().into_iter().peekable(),
+        actual.non_space_cells().into_iter().peekable(),
+    );
+    let row_major = |(pos, _): &(crate::FungeVector, i32)| (pos.1, pos.0);
+    loop {
+        let (pos, want, got) = match (expected_cells.peek(), actual_cells.peek()) {
+            (None, None) => break,
+            (Some(e), Some(a)) if e == a => {
+                expected_cells.next();
+                actual_cells.next();
+                continue;
+            }
+            (Some(e), Some(a)) if e.0 == a.0 => (e.0, e.1, a.1),
+            (Some(e), Some(a)) if row_major(e) < row_major(a) => (e.0, e.1, ' ' as i32),
+            (_, Some(a)) => (a.0, ' ' as i32, a.1),
+            (Some(e), None) => (e.0, e.1, ' ' as i32),
+        };
+        return Some(format!(
+            "cell {pos} differs: expected {}, got {}",
+            cell(want),
+            cell(got)
+        ));
+    }
+    let size = |grid: &FungeGrid| format!("{}x{}", grid.width(), grid.height());
+    (size(expected) != size(actual)).then(|| {
+        format!(
+            "grid size differs: expected {}, got {}",
+            size(expected),
+            size(actual)
+        )
+    })
+}
+
+/// a 64-bit FNV-1a digest of a grid's size and every cell in it that isn't a
+/// space, the same however the grid is kept
+pub fn grid_digest(grid: &FungeGrid) -> u64 {
+    let mut digest = Fnv::default();
+    digest.write(&(grid.width() as u64).to_le_bytes());
+    digest.write(&(grid.height() as u64).to_le_bytes());
+    for (pos, value) in grid.non_space_cells() {
+        digest.write(&pos.0.to_le_bytes());
+        digest.write(&pos.1.to_le_bytes());
+        digest.write(&value.to_le_bytes());
+    }
+    digest.0
+}
+
+/// a 64-bit FNV-1a digest of every stack of every IP, dead ones included,
+/// TOSS first and bottom to top
+pub fn stacks_digest<'a>(ips: impl IntoIterator<Item = &'a InstructionPointer>) -> u64 {
+    let mut digest = Fnv::default();
+    for ip in ips {
+        digest.write(&(ip.stacks.len() as u64).to_le_bytes());
+        for stack in &ip.stacks {
+            digest.write(&(stack.len() as u64).to_le_bytes());
+            for value in stack {
+                digest.write(&value.to_le_bytes());
+            }
+        }
+    }
+    digest.0
+}
+
+/// 64-bit FNV-1a, which comes out the same on every platform and version
+struct Fnv(u64);
+impl Default for Fnv {
+    fn default() -> Fnv {
+        Fnv(0xcbf2_9ce4_8422_2325)
+    }
+}
+impl Fnv {
+    fn write(&mut self, bytes: &[u8]) {
+        for byte in bytes {
+            self.0 = (self.0 ^ *byte as u64).wrapping_mul(0x0000_0100_0000_01b3);
+        }
+    }
+}
+
+/// where two byte strings first differ, if they do
+fn first_difference(a: &[u8], b: &[u8]) -> Option<usize> {
+    match a.iter().zip(b).position(|(a, b)| a != b) {
+        Some(at) => Some(at),
+        None if a.len() != b.len() => Some(a.len().min(b.len())),
+        None => None,
+    }
+}
+/// a few characters of text from byte at onwards
+fn excerpt(text: &str, at: usize) -> String {
+    let rest = text.as_bytes().get(at..).unwrap_or_default();
+    String::from_utf8_lossy(&rest[..rest.len().min(16)]).into_owned()
+}
+/// a cell's value as a character if it is one
+fn cell(value: i32) -> String {
+    match u32::try_from(value).ok().and_then(char::from_u32) {
+        Some(c) => format!("{c:?}"),
+        None => value.to_string(),
+    }
+}
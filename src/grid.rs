@@ -1,59 +1,473 @@
+#[cfg(feature = "tui")]
+use crate::glyphs;
+#[cfg(feature = "tui")]
+use crate::instructions::{categorize_row, Category};
 use crate::pointer::InstructionPointer;
+use crate::space::{DenseGrid, FungeSpace, SparseGrid};
 use crate::vector::FungeVector;
+#[cfg(feature = "tui")]
 use ratatui::buffer::Buffer;
+#[cfg(feature = "tui")]
 use ratatui::layout::Rect;
-use ratatui::prelude::{Line, Modifier, Span, Style};
-use ratatui::widgets::{Block, Borders, Paragraph, Widget};
+#[cfg(feature = "tui")]
+use ratatui::prelude::{Color, Line, Modifier, Span, Style};
+#[cfg(feature = "tui")]
+use ratatui::widgets::{Paragraph, Widget};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+#[cfg(feature = "tui")]
 use std::collections::VecDeque;
+#[cfg(feature = "tui")]
+use std::ops::Range;
+use std::sync::Arc;
+
+/// how IPs get back onto the grid after stepping off of an edge
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum WrapMode {
+    /// each axis loops on its own, like a torus
+    #[default]
+    Torus,
+    /// go back along the delta to the far edge, as the Funge-98 spec describes
+    Lahey,
+    /// don't wrap at all, reflecting off the edges instead
+    None,
+}
+
+/// what loading a program does about tabs, which take up a single cell
+/// (that reflects) however wide an editor shows them
+///
+/// ```
+/// use refunge::grid::TabMode;
+/// use refunge::{Arguments, Befunge};
+///
+/// let args = |tabs| Arguments { quiet: true, tabs, tab_width: Some(4), ..Default::default() };
+/// let program = "1\t.@";
+/// let mut kept = Befunge::from_text(program, args(TabMode::Keep)).unwrap();
+/// assert_eq!(kept.grid().width(), 4);
+/// let mut expanded = Befunge::from_text(program, args(TabMode::Expand)).unwrap();
+/// assert_eq!(expanded.grid().width(), 6);
+/// for (befunge, output) in [(&mut kept, ""), (&mut expanded, "1 ")] {
+///     befunge.capture_output();
+///     befunge.run(|| true);
+///     assert_eq!(befunge.output(), output);
+/// }
+/// let rejected = Befunge::from_text(program, args(TabMode::Reject)).err().unwrap();
+/// assert_eq!(rejected.to_string(), "<eval> has a tab at line 1, column 2");
+/// ```
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum TabMode {
+    /// keep each one as a cell, with a warning
+    #[default]
+    Keep,
+    /// turn each one into spaces up to the next tab stop
+    Expand,
+    /// refuse to load the program
+    Reject,
+}
+
+/// the 1-based line and column of the first tab in some text, if it has one
+///
+/// ```
+/// use refunge::grid::first_tab;
+///
+/// assert_eq!(first_tab("v\n>\t@"), Some((2, 2)));
+/// assert_eq!(first_tab("v\n> @"), None);
+/// ```
+pub fn first_tab(text: &str) -> Option<(usize, usize)> {
+    text.lines().enumerate().find_map(|(y, line)| {
+        let x = line.chars().position(|c| c == '\t')?;
+        Some((y + 1, x + 1))
+    })
+}
+/// swap every tab for spaces up to the next column that's a multiple of width
+///
+/// ```
+/// use refunge::grid::expand_tabs;
+///
+/// assert_eq!(expand_tabs("1\t.@", 1), "1 .@");
+/// assert_eq!(expand_tabs("1\t.\t@\n\t2", 4), "1   .   @\n    2");
+/// ```
+pub fn expand_tabs(text: &str, width: usize) -> String {
+    let width = width.max(1);
+    let lines = text.split('\n').map(|line| {
+        let mut expanded = String::with_capacity(line.len());
+        let mut column = 0;
+        for c in line.chars() {
+            match c {
+                '\t' => {
+                    let spaces = width - column % width;
+                    expanded.extend(std::iter::repeat_n(' ', spaces));
+                    column += spaces;
+                }
+                c => {
+                    expanded.push(c);
+                    column += 1;
+                }
+            }
+        }
+        expanded
+    });
+    lines.collect::<Vec<String>>().join("\n")
+}
+
+/// how a grid keeps its cells, see [FungeSpace]
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum GridMode {
+    /// every cell of the area the program has grown to, row by row
+    Dense,
+    /// only the cells that aren't spaces, for programs writing far and wide
+    Sparse,
+    /// dense until the grid would grow past [AUTO_SPARSE_CELLS], then sparse
+    #[default]
+    Auto,
+}
+
+/// how far [FungeGrid::seek_non_space] got
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Seek {
+    /// the next non-space cell, and the steps it took to get there
+    Found(FungeVector, u64),
+    /// still on a space when the steps ran out
+    Stopped(FungeVector),
+    /// nothing but spaces up to this cell, at the edge of a grid that doesn't wrap
+    Edge(FungeVector),
+    /// nothing but spaces all the way around
+    Nothing,
+}
 
 /// a 2-dimensional array of Funge cells with toroidal looping
-#[derive(Debug, Default, Clone)]
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
 pub struct FungeGrid {
-    chars: Vec<Vec<char>>,
-    og_chars: Vec<Vec<char>>,
-    width: usize,
-    height: usize,
-    highlights: Vec<FungeVector>,
+    cells: Cells,
+    /// the text the grid was loaded from (overlays included) for reset,
+    /// shared between clones rather than copied
+    original: Arc<String>,
+    /// the size the grid was loaded at, before anything grew it
+    #[serde(default)]
+    original_size: (usize, usize),
+    /// set once anything writes to the grid, cleared by reset
+    #[serde(default)]
+    modified: bool,
+    #[cfg(feature = "tui")]
+    #[serde(skip)]
+    /// IPs to draw by where they are, as (id, delta)
+    highlights: HashMap<FungeVector, (usize, FungeVector)>,
+    /// watched cells, drawn on a different background
+    #[cfg(feature = "tui")]
+    #[serde(skip)]
+    watched: Vec<FungeVector>,
+    /// cells an IP just reflected off, drawn as a warning
+    #[cfg(feature = "tui")]
+    #[serde(skip)]
+    flashing: Vec<FungeVector>,
+    /// rows and columns scrolled past when drawn
+    #[cfg(feature = "tui")]
+    #[serde(skip)]
+    scroll: (u16, u16),
+    /// the program was reloaded since starting, marked with a * in the title
+    #[cfg(feature = "tui")]
+    #[serde(skip)]
+    reloaded: bool,
+    /// draw in plain ASCII, for --ascii
+    #[cfg(feature = "tui")]
+    #[serde(skip)]
+    ascii: bool,
+    /// color cells by what kind of instruction they are, for --color-grid
+    #[cfg(feature = "tui")]
+    #[serde(skip)]
+    colored: bool,
+    /// the category of every cell of the rows classified so far, dropped
+    /// for a row whenever something in it changes
+    #[cfg(feature = "tui")]
+    #[serde(skip)]
+    categories: HashMap<usize, Vec<Option<Category>>>,
+    name: String,
+    /// values of cells that aren't characters (negative numbers, or the bits
+    /// of a float), which hold WIDE_CELL in chars
+    #[serde(default, with = "wide_cells")]
+    wide: HashMap<FungeVector, i32>,
+    /// cells changed since the last take_writes, as (pos, old, new)
+    #[serde(skip)]
+    writes: Vec<(FungeVector, char, char)>,
+    /// what the cells changed since the last take_undo held before, kept
+    /// once something asks for them
+    #[serde(skip)]
+    undo: Option<Vec<(FungeVector, i32)>>,
+    /// which storage to use, and whether to move to sparse storage when it gets big
+    #[serde(skip)]
+    grid_mode: GridMode,
+    /// most cells the grid may grow to, DEFAULT_MAX_CELLS if unset
+    #[serde(skip)]
+    max_cells: Option<usize>,
+    /// what happens to IPs stepping past an edge
+    #[serde(skip)]
+    wrap_mode: WrapMode,
+}
+
+/// how many cells a grid may grow to unless told otherwise, about 64MB of chars
+pub const DEFAULT_MAX_CELLS: usize = 1 << 24;
+/// how big a dense grid can get with [GridMode::Auto] before it moves to sparse storage
+pub const AUTO_SPARSE_CELLS: usize = 1 << 22;
+/// what a cell holding a value that isn't a character shows (and runs) as
+pub const WIDE_CELL: char = char::REPLACEMENT_CHARACTER;
+
+/// serde for the wide cells as a list of (position, value), since json
+/// objects can't have positions as keys
+mod wide_cells {
+    use super::FungeVector;
+    use serde::{Deserialize, Deserializer, Serialize, Serializer};
+    use std::collections::HashMap;
+
+    pub fn serialize<S: Serializer>(
+        wide: &HashMap<FungeVector, i32>,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        let mut cells: Vec<(&FungeVector, &i32)> = wide.iter().collect();
+        cells.sort_by_key(|(pos, _)| (pos.1, pos.0));
+        cells.serialize(serializer)
+    }
+    pub fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<HashMap<FungeVector, i32>, D::Error> {
+        Ok(Vec::<(FungeVector, i32)>::deserialize(deserializer)?
+            .into_iter()
+            .collect())
+    }
+}
+
+/// the storage a grid is using, saved in snapshots along with which it is
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "storage", rename_all = "snake_case")]
+enum Cells {
+    Dense(DenseGrid),
+    Sparse(SparseGrid),
+}
+impl Default for Cells {
+    fn default() -> Cells {
+        Cells::Dense(DenseGrid::default())
+    }
+}
+
+/// run the same thing on whichever storage the grid is using
+macro_rules! each_space {
+    ($cells:expr, $space:ident => $body:expr) => {
+        match $cells {
+            Cells::Dense($space) => $body,
+            Cells::Sparse($space) => $body,
+        }
+    };
+}
+
+/// copy every non-space cell from one storage into another, and its bounds
+fn copy_cells(from: &impl FungeSpace, to: &mut impl FungeSpace) {
+    let (width, height) = from.bounds();
+    to.reset("");
+    for (x, y) in from.non_spaces() {
+        to.set(x, y, from.get(x, y));
+    }
+    if width > 0 && height > 0 {
+        // setting the far corner to what it already is stretches the bounds out to it
+        to.set(width - 1, height - 1, from.get(width - 1, height - 1));
+    }
+}
+/// drop the spaces at the end of each line, along with how wide the widest
+/// line was before if that made it any narrower, leaving text that's nothing
+/// but spaces as it is since there's no other width to give it
+fn trim_trailing_spaces(text: String) -> (String, Option<usize>) {
+    let width = |text: &str| text.lines().map(|line| line.chars().count()).max();
+    let mut trimmed = String::with_capacity(text.len());
+    for line in text.lines() {
+        trimmed.push_str(line.trim_end_matches(' '));
+        trimmed.push('\n');
+    }
+    match (width(&text), width(&trimmed)) {
+        (_, None | Some(0)) => (text, None),
+        (before, after) => (trimmed, before.filter(|_| before != after)),
+    }
+}
+/// the lowest and highest k where p + k*d is within 0..size, given p is
+fn steps_inside(p: i64, d: i64, size: i64) -> (i64, i64) {
+    match d {
+        0 => (i64::MIN, i64::MAX),
+        d if d > 0 => (-p.div_euclid(d), (size - 1 - p).div_euclid(d)),
+        // the same thing mirrored, counting from the far edge
+        d => steps_inside(size - 1 - p, -d, size),
+    }
 }
 impl FungeGrid {
-    /// parse some text into the 2d grid of characters
+    /// parse some text into the 2d grid of characters, leaving out the spaces
+    /// at the end of each line so they can't make the grid any wider
+    ///
+    /// ```
+    /// use refunge::{Arguments, Befunge, FungeGrid};
+    ///
+    /// assert_eq!(FungeGrid::new("1.@  \n2".to_string()).width(), 3);
+    /// assert_eq!(FungeGrid::with_trailing_space("1.@  \n2".to_string()).width(), 5);
+    ///
+    /// // the # jumps over whatever is in the last column when it wraps around,
+    /// // which is a space only when the trailing spaces are kept
+    /// let run = |program: &str, preserve_trailing_space| {
+    ///     let args = Arguments { quiet: true, preserve_trailing_space, ..Default::default() };
+    ///     let mut befunge = Befunge::from_text(program, args).unwrap();
+    ///     befunge.capture_output();
+    ///     befunge.run(|| true);
+    ///     befunge.output().to_string()
+    /// };
+    /// assert_eq!(run(">v\n#<@.7", false), "0 ");
+    /// assert_eq!(run(">v\n#<@.7 ", false), "0 ");
+    /// assert_eq!(run(">v   \n#<@.7", false), "0 ");
+    /// assert_eq!(run(">v\n#<@.7 ", true), "7 ");
+    /// ```
     pub fn new(text: String) -> FungeGrid {
-        let width = text
-            .lines()
-            .max_by_key(|l| l.len())
-            .expect("empty text")
-            .len();
-        let height = text.lines().count();
-        let chars = text
-            .lines()
-            .map(|line| {
-                [
-                    line.chars().collect::<Vec<char>>(),
-                    vec![' '; width - line.len()],
-                ]
-                .concat()
-            })
-            .collect::<Vec<_>>();
+        let (text, untrimmed) = trim_trailing_spaces(text);
+        let grid = FungeGrid::with_trailing_space(text);
+        if let Some(width) = untrimmed {
+            log::warn!(
+                "ignored trailing spaces that would have made the grid {width} wide instead of {} (--preserve-trailing-space keeps them)",
+                grid.width()
+            );
+        }
+        grid
+    }
+    /// parse some text into the 2d grid of characters as it is, so the grid is
+    /// as wide as the longest line even if that's down to spaces at its end
+    pub fn with_trailing_space(text: String) -> FungeGrid {
+        let cells = DenseGrid::new(&text);
         FungeGrid {
-            og_chars: chars.clone(),
-            chars,
-            width,
-            height,
+            original_size: cells.bounds(),
+            cells: Cells::Dense(cells),
+            original: Arc::new(text),
             ..Default::default()
         }
     }
-    /// reset back to the unmodified grid
+    /// attach the name of the program this grid was loaded from
+    pub fn named(mut self, name: String) -> Self {
+        self.name = name;
+        self
+    }
+    /// place some text over the loaded program, making it part of the unmodified grid
+    /// returns the positions where a different non-space character got overwritten
+    pub fn overlay(&mut self, text: String, pos: FungeVector) -> Vec<FungeVector> {
+        let mut conflicts = vec![];
+        for (y, line) in text.lines().enumerate() {
+            for (x, c) in line.chars().enumerate() {
+                let cell = pos + FungeVector(x as i32, y as i32);
+                let old = self.char_at(cell);
+                if old != ' ' && old != c {
+                    conflicts.push(cell);
+                }
+            }
+        }
+        self.place(text, pos, false);
+        let end = FungeVector(self.width() as i32 - 1, self.height() as i32 - 1);
+        self.original = Arc::new(self.read_from(FungeVector(0, 0), end));
+        self.writes.clear();
+        self.original_size = (self.width(), self.height());
+        self.modified = false;
+        conflicts
+    }
+    /// reset back to the unmodified grid, at the size it was loaded at with
+    /// every row padded out to the same width, however far it grew since
+    ///
+    /// ```
+    /// use refunge::{Arguments, Befunge, FungeVector, GridMode};
+    ///
+    /// // 'p' an X out at (30, 5), past the end of the short second row, then 'g' it back
+    /// let program = "\"X\"65*5p65*5g,@\nv";
+    /// for grid in [GridMode::Dense, GridMode::Sparse, GridMode::Auto] {
+    ///     let args = Arguments { quiet: true, grid, ..Default::default() };
+    ///     let mut befunge = Befunge::from_text(program, args).unwrap();
+    ///     befunge.capture_output();
+    ///     befunge.run(|| true);
+    ///     assert_eq!((befunge.grid().width(), befunge.grid().height()), (31, 6));
+    ///     befunge.restart();
+    ///     assert_eq!((befunge.grid().width(), befunge.grid().height()), (15, 2));
+    ///     assert_eq!(befunge.grid().char_at(FungeVector(30, 5)), ' ');
+    ///     assert_eq!(befunge.grid().char_at(FungeVector(14, 1)), ' ');
+    ///     befunge.run(|| true);
+    ///     assert!(befunge.ended());
+    ///     assert_eq!(befunge.output(), "X");
+    /// }
+    /// ```
     pub fn reset(&mut self) {
-        self.chars = self.og_chars.clone();
-        self.width = self.og_chars.iter().max_by_key(|l| l.len()).unwrap().len();
-        self.height = self.og_chars.len();
+        self.cells = match self.grid_mode {
+            GridMode::Sparse => Cells::Sparse(SparseGrid::new(&self.original)),
+            _ => Cells::Dense(DenseGrid::new(&self.original)),
+        };
+        self.wide.clear();
+        self.writes.clear();
+        self.modified = false;
+        // nothing drawn over the grid can be trusted once it's been rebuilt
+        #[cfg(feature = "tui")]
+        {
+            self.categories.clear();
+            self.highlights.clear();
+            self.flashing.clear();
+        }
+    }
+    /// change how the grid keeps its cells, moving them over to the other
+    /// storage if need be, see [GridMode]
+    ///
+    /// ```
+    /// use refunge::{Arguments, Befunge, GridMode};
+    ///
+    /// // 'p' a "d" out at (1000000, 1000000), 'g' it back and print it, or
+    /// // reflect back through the string and wrap around to the '@'
+    /// let program = "\"d\"aa*:*aa**:p aa*:*aa**:g,@";
+    /// for (grid, output) in [(GridMode::Dense, ""), (GridMode::Sparse, "d"), (GridMode::Auto, "d")] {
+    ///     let args = Arguments { quiet: true, grid, ..Default::default() };
+    ///     let mut befunge = Befunge::from_text(program, args).unwrap();
+    ///     befunge.capture_output();
+    ///     befunge.run(|| true);
+    ///     assert_eq!(befunge.output(), output);
+    ///     assert_eq!(befunge.grid().is_sparse(), grid != GridMode::Dense);
+    /// }
+    /// ```
+    pub fn set_grid_mode(&mut self, grid_mode: GridMode) {
+        self.grid_mode = grid_mode;
+        self.store(grid_mode);
+    }
+    /// move the cells over to dense or sparse storage, if they aren't already there
+    fn store(&mut self, grid_mode: GridMode) {
+        match (&self.cells, grid_mode) {
+            (Cells::Dense(dense), GridMode::Sparse) => {
+                let mut sparse = SparseGrid::default();
+                copy_cells(dense, &mut sparse);
+                self.cells = Cells::Sparse(sparse);
+            }
+            (Cells::Sparse(sparse), GridMode::Dense) => {
+                let (width, height) = sparse.bounds();
+                let max_cells = self.max_cells.unwrap_or(DEFAULT_MAX_CELLS);
+                if width
+                    .checked_mul(height)
+                    .is_none_or(|cells| cells > max_cells)
+                {
+                    log::warn!(
+                        "a {width}x{height} grid is too big to keep dense, keeping it sparse"
+                    );
+                    return;
+                }
+                let mut dense = DenseGrid::default();
+                copy_cells(sparse, &mut dense);
+                self.cells = Cells::Dense(dense);
+            }
+            _ => {}
+        }
+    }
+    /// how the grid keeps its cells
+    pub fn grid_mode(&self) -> GridMode {
+        self.grid_mode
+    }
+    /// whether the cells are being kept sparse right now, which they can
+    /// be with [GridMode::Auto] once the grid got too big
+    pub fn is_sparse(&self) -> bool {
+        matches!(self.cells, Cells::Sparse(_))
     }
     /// find the top left corner, possibly lower if script mode + hashtag-started lines
     pub fn start_pos(&self, script_mode: bool) -> FungeVector {
         let y = if script_mode {
-            self.chars
-                .iter()
-                .position(|line| line.get(0) != Some(&'#'))
+            (0..self.height())
+                .position(|y| each_space!(&self.cells, space => space.get(0, y)) != '#')
                 .unwrap_or(0) as i32
         } else {
             0
@@ -66,12 +480,104 @@ impl FungeGrid {
         if pos.is_negative() {
             return ' ';
         }
-        self.chars[pos.1 as usize][pos.0 as usize]
+        each_space!(&self.cells, space => space.get(pos.0 as usize, pos.1 as usize))
+    }
+    /// the value of the cell at (x, y), which can be any number,
+    /// unlike char_at
+    ///
+    /// ```
+    /// use refunge::{Arguments, Befunge};
+    ///
+    /// // 'p' a -1 into the corner and 'g' it straight back out
+    /// let args = Arguments { quiet: true, ..Default::default() };
+    /// let mut befunge = Befunge::from_text("01-00p00g.@", args).unwrap();
+    /// befunge.capture_output();
+    /// befunge.run(|| true);
+    /// assert_eq!(befunge.output(), "-1 ");
+    /// ```
+    pub fn cell_at(&self, pos: FungeVector) -> i32 {
+        match self.wide.get(&pos) {
+            Some(value) => *value,
+            None => self.char_at(pos) as i32,
+        }
+    }
+    /// set the value of the cell at (x, y), growing the grid like set_char
+    pub fn set_cell(&mut self, pos: FungeVector, value: i32) -> bool {
+        match u32::try_from(value).ok().and_then(char::from_u32) {
+            Some(c) => self.set_char(pos, c),
+            None if self.set_char(pos, WIDE_CELL) => {
+                self.wide.insert(pos, value);
+                true
+            }
+            None => false,
+        }
+    }
+    /// whether every cell from start to end could be set without the grid
+    /// growing past its limit, to check a block before writing any of it
+    pub fn has_room(&self, start: FungeVector, end: FungeVector) -> bool {
+        if start.is_negative() || end.is_negative() {
+            return false;
+        }
+        let (x, y) = (end.0 as usize, end.1 as usize);
+        if x < self.width() && y < self.height() {
+            return true;
+        }
+        let max_cells = self.max_cells.unwrap_or(DEFAULT_MAX_CELLS);
+        let footprint = each_space!(&self.cells, space => space.footprint_with(x, y));
+        if !self.is_sparse()
+            && (self.grid_mode != GridMode::Auto
+                || footprint.is_some_and(|cells| cells <= AUTO_SPARSE_CELLS))
+        {
+            return footprint.is_some_and(|cells| cells <= max_cells);
+        }
+        // kept sparse (or about to be), every cell that's a space now may add one
+        let stored = each_space!(&self.cells, space => space.non_spaces().len());
+        let added = (start.1..=end.1)
+            .flat_map(|y| (start.0..=end.0).map(move |x| FungeVector(x, y)))
+            .filter(|pos| self.char_at(*pos) == ' ')
+            .count();
+        stored + added <= max_cells
     }
     /// copy an area of the grid into a string with line breaks
     pub fn read_from(&self, start: FungeVector, end: FungeVector) -> String {
-        if start.is_negative() || end.is_negative() {
+        let Some((left, right, top, bottom)) = self.area(start, end) else {
             return String::new();
+        };
+        let mut output = String::with_capacity((right - left + 2) * (bottom - top + 1));
+        for line in self.region(left, right, top, bottom) {
+            output.extend(line);
+            output.push('\n');
+        }
+        output
+    }
+    /// copy an area of the grid as linear text, like read_from but without
+    /// the spaces at the end of each line or the line breaks at the end
+    ///
+    /// ```
+    /// use refunge::{FungeGrid, FungeVector};
+    ///
+    /// let grid = FungeGrid::with_trailing_space("ab  \n    \n c  ".to_string());
+    /// assert_eq!(grid.read_from(FungeVector(0, 0), FungeVector(3, 2)), "ab  \n    \n c  \n");
+    /// assert_eq!(grid.read_linear(FungeVector(0, 0), FungeVector(3, 2)), "ab\n\n c");
+    /// assert_eq!(grid.read_linear(FungeVector(0, 0), FungeVector(3, 1)), "ab");
+    /// ```
+    pub fn read_linear(&self, start: FungeVector, end: FungeVector) -> String {
+        let Some((left, right, top, bottom)) = self.area(start, end) else {
+            return String::new();
+        };
+        let mut output = String::with_capacity((right - left + 2) * (bottom - top + 1));
+        for line in self.region(left, right, top, bottom) {
+            let len = line.iter().rposition(|c| *c != ' ').map_or(0, |i| i + 1);
+            output.extend(&line[..len]);
+            output.push('\n');
+        }
+        output.truncate(output.trim_end_matches('\n').len());
+        output
+    }
+    /// left, right, top and bottom of an area inside the grid, if it is
+    fn area(&self, start: FungeVector, end: FungeVector) -> Option<(usize, usize, usize, usize)> {
+        if start.is_negative() || end.is_negative() {
+            return None;
         }
         let (left, right, top, bottom) = (
             start.0 as usize,
@@ -79,124 +585,669 @@ impl FungeGrid {
             start.1 as usize,
             end.1 as usize,
         );
-        if right >= self.width || bottom >= self.height {
-            return String::new();
+        if right >= self.width() || bottom >= self.height() || left > right || top > bottom {
+            return None;
+        }
+        Some((left, right, top, bottom))
+    }
+    /// the rows top..=bottom of the cells left..=right
+    fn region(&self, left: usize, right: usize, top: usize, bottom: usize) -> Vec<Vec<char>> {
+        each_space!(&self.cells, space => space.read_region(left, right, top, bottom))
+    }
+    /// find the position ahead of an ip in the current direction, which is off
+    /// the grid if it's at the edge of one that doesn't wrap
+    pub fn cell_ahead_ip(&self, ip: &InstructionPointer) -> FungeVector {
+        self.step(ip.pos, ip.delta, 1).unwrap_or(ip.pos + ip.delta)
+    }
+    /// the position n steps of delta away, wrapping around the edges,
+    /// or pos itself if the grid doesn't wrap and that's off of it
+    pub fn wrap(&self, pos: FungeVector, delta: FungeVector, n: i64) -> FungeVector {
+        self.step(pos, delta, n).unwrap_or(pos)
+    }
+    /// the position n steps of delta away, wrapped the grid's [WrapMode],
+    /// or none if it doesn't wrap and that's off the grid
+    ///
+    /// ```
+    /// use refunge::{FungeGrid, FungeVector, WrapMode};
+    ///
+    /// let mut grid = FungeGrid::new("12345".to_string());
+    /// let (start, delta) = (FungeVector(0, 0), FungeVector(2, 0));
+    /// assert_eq!(grid.step(start, delta, 3), Some(FungeVector(1, 0)));
+    /// grid.set_wrap_mode(WrapMode::Lahey);
+    /// assert_eq!(grid.step(start, delta, 3), Some(FungeVector(0, 0)));
+    /// assert_eq!(grid.step(start, delta, -1), Some(FungeVector(4, 0)));
+    /// grid.set_wrap_mode(WrapMode::None);
+    /// assert_eq!(grid.step(start, delta, 2), Some(FungeVector(4, 0)));
+    /// assert_eq!(grid.step(start, delta, 3), None);
+    /// ```
+    pub fn step(&self, pos: FungeVector, delta: FungeVector, n: i64) -> Option<FungeVector> {
+        let (width, height) = (self.width() as i64, self.height() as i64);
+        let along = |k: i64| {
+            FungeVector(
+                (pos.0 as i64 + delta.0 as i64 * k) as i32,
+                (pos.1 as i64 + delta.1 as i64 * k) as i32,
+            )
+        };
+        let inside = |p: FungeVector| {
+            (0..width).contains(&(p.0 as i64)) && (0..height).contains(&(p.1 as i64))
+        };
+        match self.wrap_mode {
+            WrapMode::None => Some(along(n)).filter(|p| inside(*p)),
+            WrapMode::Lahey if inside(pos) && delta != FungeVector(0, 0) => {
+                // the steps k where pos + k*delta is still on the grid, which
+                // the ip goes back and forth across as if the ends were joined
+                let (low, high) = [
+                    steps_inside(pos.0 as i64, delta.0 as i64, width),
+                    steps_inside(pos.1 as i64, delta.1 as i64, height),
+                ]
+                .into_iter()
+                .fold((i64::MIN, i64::MAX), |(low, high), (l, h)| {
+                    (low.max(l), high.min(h))
+                });
+                Some(along(low + (n - low).rem_euclid(high - low + 1)))
+            }
+            _ => {
+                let step =
+                    |p: i32, d: i32, size: i64| (p as i64 + d as i64 * n).rem_euclid(size) as i32;
+                Some(FungeVector(
+                    step(pos.0, delta.0, width),
+                    step(pos.1, delta.1, height),
+                ))
+            }
+        }
+    }
+    /// the next non-space cell after pos going along delta, which is pos itself
+    /// when it's the only one, or none at all if the path is nothing but spaces
+    ///
+    /// ```
+    /// use refunge::{FungeGrid, FungeVector};
+    ///
+    /// let mut grid = FungeGrid::new(format!("a{}b", " ".repeat(10_000)));
+    /// let east = FungeVector(1, 0);
+    /// assert_eq!(grid.next_non_space(FungeVector(0, 0), east), Some(FungeVector(10_001, 0)));
+    /// assert_eq!(grid.next_non_space(FungeVector(10_001, 0), east), Some(FungeVector(0, 0)));
+    /// assert_eq!(grid.next_non_space(FungeVector(10_001, 0), -east), Some(FungeVector(0, 0)));
+    /// grid.set_char(FungeVector(500, 0), 'c');
+    /// assert_eq!(grid.next_non_space(FungeVector(0, 0), east), Some(FungeVector(500, 0)));
+    /// grid.set_char(FungeVector(500, 0), ' ');
+    /// assert_eq!(grid.next_non_space(FungeVector(0, 0), east), Some(FungeVector(10_001, 0)));
+    /// // growing the grid keeps the lookups right for new rows and columns
+    /// grid.set_char(FungeVector(20_000, 3), 'd');
+    /// assert_eq!(grid.next_non_space(FungeVector(20_000, 0), FungeVector(0, 1)), Some(FungeVector(20_000, 3)));
+    /// assert_eq!(grid.next_non_space(FungeVector(0, 1), east), None);
+    /// ```
+    pub fn next_non_space(&self, pos: FungeVector, delta: FungeVector) -> Option<FungeVector> {
+        match self.seek_non_space(pos, delta, u64::MAX) {
+            Seek::Found(pos, _) => Some(pos),
+            _ => None,
         }
-        let mut output = String::new();
-        for line in &self.chars[top..=bottom] {
-            for c in &line[left..=right] {
-                output.push(*c);
+    }
+    /// like next_non_space, but stopping after max_steps steps, where a
+    /// delta that isn't cardinal walks a step at a time and cardinal ones
+    /// jump straight there in one
+    ///
+    /// ```
+    /// use refunge::grid::Seek;
+    /// use refunge::{FungeGrid, FungeVector, WrapMode};
+    ///
+    /// let mut grid = FungeGrid::new("a   \n    \n    \n   b".to_string());
+    /// let diagonal = FungeVector(1, 1);
+    /// assert_eq!(grid.seek_non_space(FungeVector(0, 0), diagonal, 2), Seek::Stopped(FungeVector(2, 2)));
+    /// assert_eq!(grid.seek_non_space(FungeVector(0, 0), diagonal, 3), Seek::Found(FungeVector(3, 3), 3));
+    /// assert_eq!(grid.seek_non_space(FungeVector(3, 0), FungeVector(0, 1), 1), Seek::Found(FungeVector(3, 3), 1));
+    /// grid.set_wrap_mode(WrapMode::None);
+    /// assert_eq!(grid.seek_non_space(FungeVector(1, 0), diagonal, 10), Seek::Edge(FungeVector(3, 2)));
+    /// assert_eq!(grid.seek_non_space(FungeVector(1, 0), FungeVector(1, 0), 10), Seek::Edge(FungeVector(3, 0)));
+    /// ```
+    pub fn seek_non_space(&self, pos: FungeVector, delta: FungeVector, max_steps: u64) -> Seek {
+        // a zero delta never gets anywhere, not even back to pos
+        if delta == FungeVector(0, 0) {
+            return Seek::Nothing;
+        }
+        if delta.is_cardinal() && !pos.is_negative() {
+            if max_steps == 0 {
+                return Seek::Stopped(pos);
             }
-            output.push('\n');
+            let (x, y) = (pos.0 as usize, pos.1 as usize);
+            let found = each_space!(&self.cells, space => if delta.1 == 0 {
+                space.next_in_row(x, y, delta.0 > 0).map(|x| FungeVector(x as i32, pos.1))
+            } else {
+                space.next_in_col(x, y, delta.1 > 0).map(|y| FungeVector(pos.0, y as i32))
+            })
+            // the index wraps around, so anything not ahead is past the edge
+            .filter(|found| {
+                self.wrap_mode != WrapMode::None
+                    || (found.0 - pos.0) * delta.0 + (found.1 - pos.1) * delta.1 > 0
+            });
+            return match found {
+                Some(found) => Seek::Found(found, 1),
+                None if self.wrap_mode == WrapMode::None => Seek::Edge(self.last_inside(pos, delta)),
+                None => Seek::Nothing,
+            };
+        }
+        // anything else walks, at most as far as the path can go before repeating
+        let path = (self.width() as u64).saturating_mul(self.height() as u64);
+        let mut next = pos;
+        for step in 1..=path.min(max_steps) {
+            next = match self.step(next, delta, 1) {
+                Some(next) => next,
+                None => return Seek::Edge(next),
+            };
+            if self.char_at(next) != ' ' {
+                return Seek::Found(next, step);
+            }
+        }
+        if max_steps < path {
+            Seek::Stopped(next)
+        } else {
+            Seek::Nothing
         }
-        output
     }
-    /// find the position ahead of an ip in the current direction
-    pub fn cell_ahead_ip(&self, ip: &InstructionPointer) -> FungeVector {
-        FungeVector(
-            (ip.pos.0 + ip.delta.0).rem_euclid(self.width as i32),
-            (ip.pos.1 + ip.delta.1).rem_euclid(self.height as i32),
-        )
-    }
-    /// find the next runnable character ahead of a location
-    pub fn runnable_char_ahead(&self, pos: FungeVector, delta: FungeVector) -> char {
-        let pos2 = FungeVector(
-            (pos.0 + delta.0).rem_euclid(self.width as i32),
-            (pos.1 + delta.1).rem_euclid(self.height as i32),
-        );
-        match self.char_at(pos2) {
-            ' ' => self.runnable_char_ahead(pos2, delta),
-            ';' => {
-                let mut next_pos = pos2 + delta;
-                while self.char_at(next_pos) != ';' {
-                    next_pos += delta;
-                }
-                self.runnable_char_ahead(next_pos + delta, delta)
+    /// the last cell on the grid going from pos along delta
+    fn last_inside(&self, pos: FungeVector, delta: FungeVector) -> FungeVector {
+        let high = [
+            steps_inside(pos.0 as i64, delta.0 as i64, self.width() as i64).1,
+            steps_inside(pos.1 as i64, delta.1 as i64, self.height() as i64).1,
+        ]
+        .into_iter()
+        .min()
+        .unwrap_or(0);
+        pos + delta * high as i32
+    }
+    /// find the next runnable character ahead of a location,
+    /// or a space if there's nothing but spaces and ;-regions in the way
+    ///
+    /// ```
+    /// use refunge::{FungeGrid, FungeVector};
+    ///
+    /// let grid = FungeGrid::new("k ;x; 1".to_string());
+    /// assert_eq!(grid.runnable_char_ahead(FungeVector(0, 0), FungeVector(1, 0)), '1');
+    /// let grid = FungeGrid::new(" ".repeat(1000));
+    /// assert_eq!(grid.runnable_char_ahead(FungeVector(0, 0), FungeVector(1, 0)), ' ');
+    /// ```
+    pub fn runnable_char_ahead(&self, mut pos: FungeVector, delta: FungeVector) -> char {
+        let mut in_region = false;
+        // every non-space cell once outside a ;-region and once inside before it repeats
+        for _ in 0..(2 * self.width()).saturating_mul(self.height()) + 2 {
+            let Some(next) = self.next_non_space(pos, delta) else {
+                return ' ';
+            };
+            pos = next;
+            match self.char_at(pos) {
+                ';' => in_region = !in_region,
+                _ if in_region => {}
+                c => return c,
             }
-            c => c,
         }
+        ' '
     }
 
-    /// set a character in the grid, expanding if outside the grid area
-    pub fn set_char(&mut self, pos: FungeVector, c: char) {
+    /// limit how many cells writes can grow the grid to, or None for [DEFAULT_MAX_CELLS]
+    ///
+    /// ```
+    /// use refunge::{Arguments, Befunge};
+    ///
+    /// // 'p' out at (10000, 0) goes on to "2.", or reflects and wraps around to "1."
+    /// for (max_grid_cells, output) in [(None, "2 "), (Some(1000), "1 ")] {
+    ///     let args = Arguments { quiet: true, max_grid_cells, ..Default::default() };
+    ///     let mut befunge = Befunge::from_text("9a:*:*0p2.@.1", args).unwrap();
+    ///     befunge.capture_output();
+    ///     befunge.run(|| true);
+    ///     assert_eq!(befunge.output(), output);
+    /// }
+    /// ```
+    pub fn set_max_cells(&mut self, max_cells: Option<usize>) {
+        self.max_cells = max_cells;
+    }
+    /// change what IPs do at the edges, see [WrapMode]
+    ///
+    /// ```
+    /// use refunge::{Arguments, Befunge, FungeVector, WrapMode};
+    ///
+    /// // starting on the first '.' going two cells at a time, an IP wraps to the
+    /// // '2', comes back around to the '@', or turns back to the '.' at the edge
+    /// for (wrap_mode, output) in [(WrapMode::Torus, "0 2 "), (WrapMode::Lahey, "0 "), (WrapMode::None, "0 1 ")] {
+    ///     let (start, delta) = (Some(FungeVector(2, 0)), Some(FungeVector(2, 0)));
+    ///     let args = Arguments { quiet: true, wrap_mode, start, delta, ..Default::default() };
+    ///     let mut befunge = Befunge::from_text("@2..1", args).unwrap();
+    ///     befunge.capture_output();
+    ///     befunge.run(|| true);
+    ///     assert_eq!(befunge.output(), output);
+    /// }
+    /// ```
+    pub fn set_wrap_mode(&mut self, wrap_mode: WrapMode) {
+        self.wrap_mode = wrap_mode;
+    }
+    pub fn wrap_mode(&self) -> WrapMode {
+        self.wrap_mode
+    }
+    /// set a character in the grid, expanding if outside the grid area,
+    /// returning false (and leaving the grid alone) for negative positions or
+    /// if that would grow it past its maximum size
+    ///
+    /// ```
+    /// use refunge::{FungeGrid, FungeVector, GridMode};
+    /// use std::time::{Duration, Instant};
+    ///
+    /// let mut grid = FungeGrid::new(vec!["@"; 1000].join("\n"));
+    /// grid.set_grid_mode(GridMode::Dense);
+    /// let start = Instant::now();
+    /// assert!(grid.set_char(FungeVector(10_000, 0), 'x'));
+    /// assert!(start.elapsed() < Duration::from_secs(1));
+    /// assert_eq!((grid.width(), grid.height()), (10_001, 1000));
+    /// assert_eq!(grid.char_at(FungeVector(10_000, 0)), 'x');
+    ///
+    /// grid.set_max_cells(Some(20_000_000));
+    /// assert!(!grid.set_char(FungeVector(10_000, 5_000), 'y'));
+    /// assert_eq!((grid.width(), grid.height()), (10_001, 1000));
+    ///
+    /// // sparse grids only count the cells that aren't spaces
+    /// grid.set_grid_mode(GridMode::Sparse);
+    /// assert!(grid.set_char(FungeVector(10_000, 5_000), 'y'));
+    /// assert_eq!((grid.width(), grid.height()), (10_001, 5_001));
+    /// ```
+    pub fn set_char(&mut self, pos: FungeVector, c: char) -> bool {
         if pos.is_negative() {
-            return;
+            return false;
         }
         let (x, y) = (pos.0 as usize, pos.1 as usize);
-        if x < self.width && y < self.height {
-            self.chars[y][x] = c;
-        } else {
-            while x >= self.width {
-                for row in &mut self.chars {
-                    (*row).push(' ');
-                }
-                self.width += 1;
+        if x >= self.width() || y >= self.height() {
+            let footprint = |cells: &Cells| each_space!(cells, space => space.footprint_with(x, y));
+            if self.grid_mode == GridMode::Auto
+                && !self.is_sparse()
+                && footprint(&self.cells).is_none_or(|cells| cells > AUTO_SPARSE_CELLS)
+            {
+                log::info!("grid grew past {AUTO_SPARSE_CELLS} cells, switching to sparse storage");
+                self.store(GridMode::Sparse);
             }
-            while y >= self.height {
-                self.chars.push(vec![' '; self.width]);
-                self.height += 1;
+            let max_cells = self.max_cells.unwrap_or(DEFAULT_MAX_CELLS);
+            if footprint(&self.cells).is_none_or(|cells| cells > max_cells) {
+                return false;
             }
-            self.chars[y][x] = c;
         }
+        let old = each_space!(&mut self.cells, space => space.set(x, y, c));
+        #[cfg(feature = "tui")]
+        self.categories.remove(&y);
+        if let Some(undo) = &mut self.undo {
+            undo.push((pos, self.wide.get(&pos).copied().unwrap_or(old as i32)));
+        }
+        self.writes.push((pos, old, c));
+        self.modified = true;
+        if !self.wide.is_empty() {
+            self.wide.remove(&pos);
+        }
+        true
     }
-    /// place some text within the grid
+    /// place some text within the grid, returning false if some of it didn't fit
     /// uses line breaks to make area two-dimensional if not in binary mode
-    pub fn place(&mut self, text: String, pos: FungeVector, binary: bool) {
-        if binary {
-            for (n, c) in text.chars().enumerate() {
-                self.set_char(pos + FungeVector(n as i32, 0), c);
-            }
+    pub fn place(&mut self, text: String, pos: FungeVector, binary: bool) -> bool {
+        let lines: Vec<&str> = if binary {
+            vec![&text]
         } else {
-            for (y, line) in text.lines().enumerate() {
-                for (x, c) in line.chars().enumerate() {
-                    self.set_char(pos + FungeVector(x as i32, y as i32), c);
-                }
+            text.lines().collect()
+        };
+        self.writes.reserve(text.len());
+        let mut fit = true;
+        for (dy, line) in lines.into_iter().enumerate() {
+            let row = pos + FungeVector(0, dy as i32);
+            let len = line.chars().count();
+            // rows that are already big enough get copied straight in
+            if !row.is_negative()
+                && (row.1 as usize) < self.height()
+                && row.0 as usize + len <= self.width()
+            {
+                self.copy_row(row, line);
+                continue;
+            }
+            for (dx, c) in line.chars().enumerate() {
+                fit &= self.set_char(row + FungeVector(dx as i32, 0), c);
+            }
+        }
+        fit
+    }
+    /// write a line of text over cells that already exist, starting at pos
+    fn copy_row(&mut self, pos: FungeVector, line: &str) {
+        let (x, y) = (pos.0 as usize, pos.1 as usize);
+        self.modified = true;
+        let old = each_space!(&mut self.cells, space => space.place(x, y, line));
+        #[cfg(feature = "tui")]
+        self.categories.remove(&y);
+        for ((dx, old), c) in old.into_iter().enumerate().zip(line.chars()) {
+            let at = pos + FungeVector(dx as i32, 0);
+            if let Some(undo) = &mut self.undo {
+                undo.push((at, self.wide.get(&at).copied().unwrap_or(old as i32)));
+            }
+            self.writes.push((at, old, c));
+            if !self.wide.is_empty() {
+                self.wide.remove(&at);
             }
         }
     }
 
+    /// every cell changed since the last call, in order, as (pos, old, new)
+    pub fn take_writes(&mut self) -> Vec<(FungeVector, char, char)> {
+        std::mem::take(&mut self.writes)
+    }
+    /// what every cell changed since the last call held before, in order,
+    /// keeping track from the first call on
+    pub(crate) fn take_undo(&mut self) -> Vec<(FungeVector, i32)> {
+        self.undo.replace(vec![]).unwrap_or_default()
+    }
+    /// put back cells taken from take_undo, newest first, then shrink back
+    /// to the size the grid was before they were written
+    pub(crate) fn undo(&mut self, cells: &[(FungeVector, i32)], size: (usize, usize)) {
+        let undo = self.undo.take();
+        for &(pos, value) in cells.iter().rev() {
+            self.set_cell(pos, value);
+        }
+        if size != (self.width(), self.height()) {
+            each_space!(&mut self.cells, space => space.truncate(size.0, size.1));
+            #[cfg(feature = "tui")]
+            self.categories.clear();
+        }
+        self.writes.clear();
+        self.undo = undo;
+    }
+
+    /// every cell that isn't a space and its value, row by row
+    ///
+    /// ```
+    /// use refunge::{FungeGrid, FungeVector};
+    ///
+    /// let mut grid = FungeGrid::new("a b\n  c".to_string());
+    /// grid.set_cell(FungeVector(0, 1), -5);
+    /// let cells = [(FungeVector(0, 0), 'a' as i32), (FungeVector(2, 0), 'b' as i32),
+    ///     (FungeVector(0, 1), -5), (FungeVector(2, 1), 'c' as i32)];
+    /// assert_eq!(grid.non_space_cells(), cells);
+    /// ```
+    pub fn non_space_cells(&self) -> Vec<(FungeVector, i32)> {
+        each_space!(&self.cells, space => space.non_spaces())
+            .into_iter()
+            .map(|(x, y)| {
+                let pos = FungeVector(x as i32, y as i32);
+                (pos, self.cell_at(pos))
+            })
+            .collect()
+    }
+
     /// the current width of the grid
     pub fn width(&self) -> usize {
-        self.width
+        each_space!(&self.cells, space => space.bounds().0)
     }
     /// the current height of the grid
     pub fn height(&self) -> usize {
-        self.height
+        each_space!(&self.cells, space => space.bounds().1)
+    }
+    /// the name of the loaded program
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+    /// has anything written to the grid since it was loaded (or reset)
+    pub fn modified(&self) -> bool {
+        self.modified
+    }
+    /// what the grid pane is titled: the program, the standard and cell size,
+    /// the size if it grew past the loaded program, and whether it was written to
+    ///
+    /// ```
+    /// use refunge::{FungeGrid, FungeVector};
+    ///
+    /// let mut grid = FungeGrid::new("v\n@".to_string()).named("life.bf".to_string());
+    /// assert_eq!(grid.title(), "Grid - life.bf [98, 32-bit]");
+    /// grid.set_char(FungeVector(0, 1), '<');
+    /// assert_eq!(grid.title(), "Grid - life.bf [98, 32-bit] (modified)");
+    /// grid.set_char(FungeVector(4, 2), 'x');
+    /// assert_eq!(grid.title(), "Grid - life.bf [98, 32-bit] 5x3 (modified)");
+    /// grid.reset();
+    /// assert_eq!(grid.title(), "Grid - life.bf [98, 32-bit]");
+    /// ```
+    pub fn title(&self) -> String {
+        let mut title = format!("Grid - {}", self.name);
+        #[cfg(feature = "tui")]
+        if self.reloaded {
+            title.push('*');
+        }
+        title.push_str(" [98, 32-bit]");
+        if (self.width(), self.height()) != self.original_size {
+            title.push_str(&format!(" {}x{}", self.width(), self.height()));
+        }
+        if self.modified {
+            title.push_str(" (modified)");
+        }
+        title
     }
 
+    #[cfg(feature = "tui")]
     pub fn highlights(mut self, selections: VecDeque<InstructionPointer>) -> Self {
-        self.highlights = selections.iter().map(|ip| ip.pos).collect();
+        self.highlights = selections
+            .iter()
+            .map(|ip| (ip.pos, (ip.id, ip.delta)))
+            .collect();
+        self
+    }
+    /// set the cells to draw as watched
+    #[cfg(feature = "tui")]
+    pub fn watched(mut self, cells: Vec<FungeVector>) -> Self {
+        self.watched = cells;
+        self
+    }
+    /// set the cells to draw as just reflected off
+    #[cfg(feature = "tui")]
+    pub fn flashing(mut self, cells: Vec<FungeVector>) -> Self {
+        self.flashing = cells;
+        self
+    }
+    /// mark the title as coming from a newer version of the file
+    #[cfg(feature = "tui")]
+    pub fn reloaded(mut self, reloaded: bool) -> Self {
+        self.reloaded = reloaded;
+        self
+    }
+    /// draw with plain ASCII borders and arrows, and reflections as a ! over the cell
+    /// instead of only in red
+    #[cfg(feature = "tui")]
+    pub fn ascii(mut self, ascii: bool) -> Self {
+        self.ascii = ascii;
         self
     }
+    /// draw the grid scrolled down and right by (rows, columns)
+    #[cfg(feature = "tui")]
+    pub fn scrolled(mut self, scroll: (u16, u16)) -> Self {
+        self.scroll = scroll;
+        self
+    }
+    /// color cells by their [Category], under any other highlighting
+    #[cfg(feature = "tui")]
+    pub fn colored(mut self, colored: bool) -> Self {
+        self.colored = colored;
+        self
+    }
+    /// classify the cells of the rows about to be drawn, keeping them until
+    /// something in the row changes
+    #[cfg(feature = "tui")]
+    pub fn categorize(&mut self, rows: Range<usize>) {
+        let width = self.width();
+        for y in rows.start..rows.end.min(self.height()) {
+            if width > 0 && !self.categories.contains_key(&y) {
+                let row = self.region(0, width - 1, y, y).concat();
+                self.categories.insert(y, categorize_row(&row));
+            }
+        }
+    }
 }
+
+/// grids are equal when they're the same size with the same cells, however
+/// they're kept and wherever they were loaded from
+impl PartialEq for FungeGrid {
+    fn eq(&self, other: &FungeGrid) -> bool {
+        (self.width(), self.height()) == (other.width(), other.height())
+            && self.non_space_cells() == other.non_space_cells()
+    }
+}
+impl Eq for FungeGrid {}
+
+/// the color an IP is drawn in, in the grid and the output pane
+#[cfg(feature = "tui")]
+pub fn ip_color(id: usize) -> Color {
+    const COLORS: [Color; 6] = [
+        Color::LightYellow,
+        Color::LightCyan,
+        Color::LightMagenta,
+        Color::LightGreen,
+        Color::LightBlue,
+        Color::LightRed,
+    ];
+    COLORS[id % COLORS.len()]
+}
+/// how cells of each [Category] are drawn with --color-grid, leaving the
+/// light colors to IPs
+///
+/// ```
+/// use ratatui::style::{Color, Modifier};
+/// use ratatui::{backend::TestBackend, Terminal};
+/// use refunge::{Arguments, Befunge, FungeGrid, FungeVector};
+///
+/// let args = Arguments { color_grid: true, ..Default::default() };
+/// let mut befunge = Befunge::from_text(">12+\"hi\".@m", args).unwrap();
+/// let mut terminal = Terminal::new(TestBackend::new(80, 24)).unwrap();
+/// terminal.draw(|f| befunge.render(f)).unwrap();
+/// // the grid starts inside its border, one cell in
+/// let buffer = terminal.backend().buffer();
+/// let fg = |x: u16| buffer.get(x + 1, 1).fg;
+/// // the IP is drawn in its own color over the > it's on
+/// assert_eq!(fg(0), Color::LightYellow);
+/// assert_eq!([fg(1), fg(2), fg(3)], [Color::Magenta, Color::Magenta, Color::Yellow]);
+/// assert_eq!([fg(4), fg(5), fg(6), fg(7)], [Color::Gray; 4]);
+/// assert_eq!([fg(8), fg(9)], [Color::Green, Color::Red]);
+/// assert!(buffer.get(11, 1).modifier.contains(Modifier::DIM));
+///
+/// // changing a cell reclassifies its row
+/// let mut grid = FungeGrid::new("1 2\n3 4".to_string());
+/// grid.categorize(0..2);
+/// grid.set_char(FungeVector(1, 0), '"');
+/// terminal.draw(|f| f.render_widget(grid.clone().colored(true), f.size())).unwrap();
+/// let buffer = terminal.backend().buffer();
+/// assert_eq!(buffer.get(3, 1).fg, Color::Gray);
+/// assert_eq!(buffer.get(3, 2).fg, Color::Magenta);
+/// ```
+#[cfg(feature = "tui")]
+pub fn category_style(category: Category) -> Style {
+    let color = match category {
+        Category::Movement => Color::Cyan,
+        Category::Literal => Color::Magenta,
+        Category::Arithmetic => Color::Yellow,
+        Category::Stack => Color::Blue,
+        Category::Io => Color::Green,
+        Category::Control => Color::Red,
+        Category::Text => Color::Gray,
+        Category::Unknown => return Style::default().add_modifier(Modifier::DIM),
+    };
+    Style::default().fg(color)
+}
+#[cfg(feature = "tui")]
 impl Widget for FungeGrid {
     fn render(self, area: Rect, buf: &mut Buffer) {
-        Paragraph::new(
-            self.chars
+        let highlight = Style::default()
+            .add_modifier(Modifier::BOLD)
+            .add_modifier(Modifier::UNDERLINED);
+        let watched = Style::default().bg(Color::DarkGray);
+        let flashing = Style::default().bg(Color::Red);
+        let placeholder = Style::default().fg(Color::DarkGray);
+        // only the cells that fit in the pane get read, so sparse grids of
+        // any size draw as quickly as small ones
+        let (top, left) = (self.scroll.0 as usize, self.scroll.1 as usize);
+        let (width, height) = (self.width(), self.height());
+        let rows = match (width.checked_sub(1), height.checked_sub(1)) {
+            (Some(right), Some(bottom)) if left <= right && top <= bottom => self.region(
+                left,
+                right.min(left + area.width as usize),
+                top,
+                bottom.min(top + area.height as usize),
+            ),
+            _ => vec![],
+        };
+        let on_row = |pos: &FungeVector, y: usize, len: usize| {
+            pos.1 == y as i32 && pos.0 >= left as i32 && ((pos.0 as usize) - left) < len
+        };
+        let lines = rows.iter().enumerate().map(|(y, row)| {
+            let y = y + top;
+            // with --color-grid every cell is colored by category, under everything else
+            let computed;
+            let categories: &[Option<Category>] = match self.categories.get(&y) {
+                _ if !self.colored => &[],
+                Some(categories) => categories,
+                None => {
+                    computed = categorize_row(&self.region(0, width - 1, y, y).concat());
+                    &computed
+                }
+            };
+            let base = |x: usize| match categories.get(x + left) {
+                Some(Some(category)) => category_style(*category),
+                _ => Style::default(),
+            };
+            // cells from one to another share a span for each run of the same style
+            let plain = |spans: &mut Vec<Span>, from: usize, to: usize| {
+                let mut start = from;
+                for at in from + 1..=to {
+                    if at == to || base(at) != base(start) {
+                        spans.push(Span::styled(
+                            row[start..at].iter().collect::<String>(),
+                            base(start),
+                        ));
+                        start = at;
+                    }
+                }
+            };
+            // cells between highlights share one span, only ips, watches, flashes and tabs get their own
+            let mut xs: Vec<(usize, Style)> = self
+                .watched
+                .iter()
+                .filter(|pos| on_row(pos, y, row.len()))
+                .map(|pos| (pos.0 as usize - left, watched))
+                .collect();
+            let tabs = (0..row.len())
+                .filter(|x| row[*x] == '\t')
+                .map(|x| (FungeVector((x + left) as i32, y as i32), placeholder));
+            let flashes = self.flashing.iter().map(|pos| (*pos, flashing));
+            let ips = self
+                .highlights
                 .iter()
-                .enumerate()
-                .map(|(y, r)| {
-                    Line::from(
-                        r.iter()
-                            .enumerate()
-                            .map(|(x, c)| {
-                                if self.highlights.contains(&FungeVector(x as i32, y as i32)) {
-                                    Span::styled(
-                                        c.to_string(),
-                                        Style::default()
-                                            .add_modifier(Modifier::BOLD)
-                                            .add_modifier(Modifier::UNDERLINED),
-                                    )
-                                } else {
-                                    Span::raw(c.to_string())
-                                }
-                            })
-                            .collect::<Vec<Span>>(),
-                    )
-                })
-                .collect::<Vec<Line>>(),
-        )
-        .block(Block::default().borders(Borders::ALL).title("Grid"))
-        .render(area, buf)
+                .map(|(pos, (id, _))| (*pos, highlight.fg(ip_color(*id))));
+            for (pos, style) in tabs
+                .chain(flashes)
+                .chain(ips)
+                .filter(|(pos, _)| on_row(pos, y, row.len()))
+            {
+                let x = pos.0 as usize - left;
+                match xs.iter_mut().find(|(at, _)| *at == x) {
+                    Some((_, cell)) => *cell = cell.patch(style),
+                    None => xs.push((x, style)),
+                }
+            }
+            xs.sort_unstable_by_key(|(x, _)| *x);
+            xs.dedup_by_key(|(x, _)| *x);
+            let mut spans = Vec::with_capacity(xs.len() * 2 + 1);
+            let mut from = 0;
+            for (x, style) in xs {
+                plain(&mut spans, from, x);
+                // an IP on a blank cell shows which way it's heading instead
+                let pos = FungeVector((x + left) as i32, y as i32);
+                let c = match self.highlights.get(&pos) {
+                    _ if self.ascii && self.flashing.contains(&pos) => '!',
+                    // a tab drawn as is would push the rest of the row over
+                    _ if row[x] == '\t' => glyphs::TAB,
+                    Some((_, delta)) if row[x] == ' ' && self.ascii => delta.ascii_arrow(),
+                    Some((_, delta)) if row[x] == ' ' => delta.arrow(),
+                    _ => row[x],
+                };
+                spans.push(Span::styled(c.to_string(), base(x).patch(style)));
+                from = x + 1;
+            }
+            plain(&mut spans, from, row.len());
+            Line::from(spans)
+        });
+        Paragraph::new(lines.collect::<Vec<Line>>())
+            .block(glyphs::block(self.ascii).title(self.title()))
+            .render(area, buf)
     }
 }
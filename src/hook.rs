@@ -0,0 +1,149 @@
+use crate::arguments::parse_vector;
+use crate::grid::FungeGrid;
+use crate::pointer::InstructionPointer;
+use crate::vector::FungeVector;
+use std::fmt::{Display, Formatter};
+
+/// what a before hook wants done with the instruction about to run
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum HookAction {
+    /// run it as usual
+    #[default]
+    Continue,
+    /// move on without running it
+    SkipInstruction,
+    /// hold the IP on this cell and pause once the tick is over,
+    /// running the instruction without asking the hooks again after resuming
+    Pause,
+}
+
+/// what hooks get to look at, the IP as it is before running (or after, for after hooks)
+pub struct HookContext<'h> {
+    /// which tick this is, counting from 0
+    pub tick: u64,
+    pub ip: &'h InstructionPointer,
+    /// the character being run (or pushed, in string mode)
+    pub instruction: char,
+    /// where the character is
+    pub pos: FungeVector,
+    pub grid: &'h FungeGrid,
+}
+
+/// somewhere for IPs to stop, pausing like a [HookAction::Pause] would
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Breakpoint {
+    /// any IP reaching this cell
+    Position(FungeVector),
+    /// any IP about to run this instruction (not counting strings)
+    Instruction(char),
+}
+impl Breakpoint {
+    /// does an IP about to run c at pos stop here
+    pub fn matches(&self, pos: FungeVector, c: char, string_mode: bool) -> bool {
+        match *self {
+            Breakpoint::Position(at) => at == pos,
+            Breakpoint::Instruction(i) => i == c && !string_mode,
+        }
+    }
+}
+
+/// how a breakpoint, watch, or output trigger has been doing, for the debug pane
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct Tally {
+    /// times it went off, or for a watch, times its value changed
+    pub hits: u64,
+    /// kept in the list without pausing anything
+    pub disabled: bool,
+}
+
+/// a value to keep an eye on while the program runs
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WatchTarget {
+    /// a grid cell
+    Cell(FungeVector),
+    /// the value depth below the top of an IP's TOSS
+    Stack { ip: usize, depth: usize },
+}
+impl WatchTarget {
+    /// parse x,y for a cell, or ip:depth for a place on an IP's stack
+    pub fn parse(text: &str) -> Result<WatchTarget, String> {
+        match text.split_once(':') {
+            Some((ip, depth)) => {
+                let number = |n: &str| {
+                    n.trim()
+                        .parse::<usize>()
+                        .map_err(|_| format!("invalid number `{}`", n.trim()))
+                };
+                Ok(WatchTarget::Stack {
+                    ip: number(ip)?,
+                    depth: number(depth)?,
+                })
+            }
+            None => parse_vector(text).map(WatchTarget::Cell),
+        }
+    }
+}
+impl Display for WatchTarget {
+    fn fmt(&self, f: &mut Formatter) -> std::fmt::Result {
+        match self {
+            WatchTarget::Cell(pos) => write!(f, "{pos}"),
+            WatchTarget::Stack { ip, depth } => write!(f, "IP {ip} depth {depth}"),
+        }
+    }
+}
+
+/// a watched value, as of the end of the last tick
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Watch {
+    pub target: WatchTarget,
+    /// pause once the value changes
+    pub break_on_change: bool,
+    /// none when there's nothing there, like a stack that isn't that deep
+    pub value: Option<i32>,
+    pub tally: Tally,
+}
+
+/// a breakpoint, a watch, or an output trigger, as listed together in the debug pane
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DebugEntry {
+    Breakpoint(Breakpoint),
+    /// what's watched and whether it pauses on change
+    Watch(WatchTarget, bool),
+    /// text that pauses things once the output ends with it
+    Output(String),
+}
+impl DebugEntry {
+    /// a short name for the kind of entry
+    pub fn kind(&self) -> &'static str {
+        match self {
+            DebugEntry::Breakpoint(Breakpoint::Position(_)) => "cell",
+            DebugEntry::Breakpoint(Breakpoint::Instruction(_)) => "instr",
+            DebugEntry::Watch(_, true) => "watch!",
+            DebugEntry::Watch(_, false) => "watch",
+            DebugEntry::Output(_) => "output",
+        }
+    }
+    /// the grid cell it's about, if it's about one
+    pub fn cell(&self) -> Option<FungeVector> {
+        match *self {
+            DebugEntry::Breakpoint(Breakpoint::Position(pos)) => Some(pos),
+            DebugEntry::Watch(WatchTarget::Cell(pos), _) => Some(pos),
+            _ => None,
+        }
+    }
+}
+impl Display for DebugEntry {
+    fn fmt(&self, f: &mut Formatter) -> std::fmt::Result {
+        match self {
+            DebugEntry::Breakpoint(Breakpoint::Position(pos)) => write!(f, "{pos}"),
+            DebugEntry::Breakpoint(Breakpoint::Instruction(c)) => write!(f, "{c:?}"),
+            DebugEntry::Watch(target, _) => write!(f, "{target}"),
+            DebugEntry::Output(text) => write!(f, "{text:?}"),
+        }
+    }
+}
+
+/// called before each instruction, able to skip it or pause
+pub type BeforeHook<'a> = Box<dyn FnMut(&HookContext) -> HookAction + 'a>;
+/// called after each instruction, only to observe
+pub type AfterHook<'a> = Box<dyn FnMut(&HookContext) + 'a>;
@@ -0,0 +1,437 @@
+#[cfg(feature = "tui")]
+use crossterm::event::{KeyCode, KeyEvent, KeyEventKind, KeyModifiers};
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+use std::fmt::Display;
+use std::io::{self, BufRead, IsTerminal};
+use std::num::{IntErrorKind, ParseIntError};
+
+/// the kind of value an input instruction asks for
+#[derive(Default, Copy, Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum InputType {
+    #[default]
+    Number,
+    Character,
+}
+impl InputType {
+    /// parse some text (ignoring surrounding whitespace) into the desired type,
+    /// characters being the code point of the first one in the text,
+    /// or say why it can't be
+    ///
+    /// ```
+    /// use refunge::input::InputType;
+    ///
+    /// let number = |text: &str| InputType::Number.parse(text);
+    /// assert_eq!(number(" 42 "), Ok(42));
+    /// assert_eq!(number("-2147483648"), Ok(i32::MIN));
+    /// assert_eq!(number("2147483647"), Ok(i32::MAX));
+    /// let out_of_range = Err("out of range for 32-bit cell".to_string());
+    /// assert_eq!(number("-2147483649"), out_of_range);
+    /// assert_eq!(number("2147483648"), out_of_range);
+    /// assert_eq!(number("999999999999"), out_of_range);
+    /// assert_eq!(number("4x"), Err("not a number".to_string()));
+    /// assert_eq!(number(""), Err("expected a number".to_string()));
+    /// assert_eq!(InputType::Character.parse("\u{3bb}"), Ok(0x3bb));
+    /// ```
+    pub fn parse(&self, text: &str) -> Result<i32, String> {
+        let text = text.trim();
+        match self {
+            InputType::Number if text.is_empty() => Err("expected a number".to_string()),
+            InputType::Number => text.parse().map_err(|err: ParseIntError| match err.kind() {
+                IntErrorKind::PosOverflow | IntErrorKind::NegOverflow => {
+                    format!("out of range for {}-bit cell", i32::BITS)
+                }
+                _ => "not a number".to_string(),
+            }),
+            InputType::Character => text
+                .chars()
+                .next()
+                .map(|c| c as i32)
+                .ok_or_else(|| "expected a character".to_string()),
+        }
+    }
+}
+
+/// the value a key answers ~ with when it's read the moment it's pressed,
+/// Some(None) for esc (which reflects), or None for keys that don't answer
+/// (ctrl-c among them, which is left to interrupt)
+///
+/// ```
+/// use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+/// use refunge::input::key_value;
+///
+/// let key = |code| KeyEvent::new(code, KeyModifiers::NONE);
+/// assert_eq!(key_value(&key(KeyCode::Char('\u{3bb}'))), Some(Some(0x3bb)));
+/// assert_eq!(key_value(&key(KeyCode::Enter)), Some(Some(10)));
+/// assert_eq!(key_value(&key(KeyCode::Esc)), Some(None));
+/// assert_eq!(key_value(&KeyEvent::new(KeyCode::Char('c'), KeyModifiers::CONTROL)), None);
+/// assert_eq!(key_value(&key(KeyCode::Left)), None);
+/// ```
+#[cfg(feature = "tui")]
+pub fn key_value(event: &KeyEvent) -> Option<Option<i32>> {
+    match event.code {
+        KeyCode::Esc => Some(None),
+        KeyCode::Char('c') if event.modifiers.contains(KeyModifiers::CONTROL) => None,
+        KeyCode::Enter => Some(Some(10)),
+        KeyCode::Tab => Some(Some(9)),
+        KeyCode::Backspace => Some(Some(8)),
+        KeyCode::Char(c) => Some(Some(c as i32)),
+        _ => None,
+    }
+}
+
+/// where & and ~ get answered from outside the tui, a line at a time
+/// stdin by default, showing a prompt on stderr if someone is typing at a
+/// terminal (so piped input and redirected output stay clean)
+pub struct InputReader {
+    /// none for stdin, which is only locked while reading a line
+    reader: Option<Box<dyn BufRead>>,
+    prompt: bool,
+    /// answer ~ with a single key as soon as it's pressed (--unbuffered-input)
+    unbuffered: bool,
+    /// key presses to read instead of the terminal's, see [InputReader::keys]
+    #[cfg(feature = "tui")]
+    keys: Option<Box<dyn Iterator<Item = KeyEvent>>>,
+    /// set once ctrl-c was pressed while reading a key, where raw mode
+    /// keeps it from interrupting the way it usually does
+    interrupted: bool,
+}
+impl InputReader {
+    /// read from stdin
+    pub fn stdin() -> InputReader {
+        InputReader {
+            reader: None,
+            prompt: io::stdin().is_terminal(),
+            unbuffered: false,
+            #[cfg(feature = "tui")]
+            keys: None,
+            interrupted: false,
+        }
+    }
+    /// read from anything else, without prompting
+    pub fn new(reader: impl BufRead + 'static) -> InputReader {
+        InputReader {
+            reader: Some(Box::new(reader)),
+            prompt: false,
+            unbuffered: false,
+            #[cfg(feature = "tui")]
+            keys: None,
+            interrupted: false,
+        }
+    }
+    /// answer ~ with these key presses, read the way --unbuffered-input reads
+    /// them from a terminal, with nothing left for & or ~ after them
+    ///
+    /// ctrl-c doesn't answer, it interrupts the run instead:
+    ///
+    /// ```
+    /// use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+    /// use refunge::input::InputReader;
+    /// use refunge::{Arguments, Befunge, FungeVector};
+    ///
+    /// let key = |c, modifiers| KeyEvent::new(KeyCode::Char(c), modifiers);
+    /// let keys = vec![key('h', KeyModifiers::NONE), key('c', KeyModifiers::CONTROL), key('i', KeyModifiers::NONE)];
+    /// let args = Arguments { quiet: true, ..Default::default() };
+    /// let mut befunge = Befunge::from_text("~,~,@", args).unwrap();
+    /// befunge.set_reader(InputReader::keys(keys));
+    /// befunge.capture_output();
+    /// befunge.run(|| true);
+    /// assert!(befunge.interrupted());
+    /// assert_eq!(befunge.output(), "h");
+    /// // still waiting on the second ~, rather than reflected off it
+    /// assert_eq!(befunge.waiting_for_input().map(|(id, _)| id), Some(0));
+    /// assert_eq!(befunge.snapshot().ips[0].delta, FungeVector(1, 0));
+    /// ```
+    #[cfg(feature = "tui")]
+    pub fn keys(keys: Vec<KeyEvent>) -> InputReader {
+        InputReader {
+            reader: Some(Box::new(io::empty())),
+            prompt: false,
+            unbuffered: true,
+            keys: Some(Box::new(keys.into_iter())),
+            interrupted: false,
+        }
+    }
+    /// read ~ a key at a time instead of a line at a time, where that's
+    /// possible: reading from stdin when it's a terminal
+    ///
+    /// 'y' says which it is, the same way the tui reading a key at a time does:
+    ///
+    /// ```
+    /// use refunge::input::InputReader;
+    /// use refunge::{Arguments, Befunge};
+    ///
+    /// let flags = |args: Arguments, reader: Option<InputReader>| {
+    ///     let mut befunge = Befunge::from_text("1y.@", args).unwrap();
+    ///     if let Some(reader) = reader {
+    ///         befunge.set_reader(reader);
+    ///     }
+    ///     befunge.capture_output();
+    ///     befunge.run(|| true);
+    ///     befunge.output().to_string()
+    /// };
+    /// let tui = if cfg!(feature = "tui") { "31 " } else { "15 " };
+    /// assert_eq!(flags(Arguments::default(), None), tui);
+    /// let quiet = || Arguments { quiet: true, ..Default::default() };
+    /// assert_eq!(flags(quiet(), None), "15 ");
+    /// // keys can only be read one at a time from a terminal, not from a pipe
+    /// let piped = InputReader::new(&b""[..]).unbuffered(true);
+    /// assert!(!piped.is_unbuffered());
+    /// let unbuffered = Arguments { unbuffered_input: true, ..quiet() };
+    /// assert_eq!(flags(unbuffered, Some(piped)), "15 ");
+    /// #[cfg(feature = "tui")]
+    /// assert_eq!(flags(quiet(), Some(InputReader::keys(vec![]))), "31 ");
+    /// ```
+    pub fn unbuffered(mut self, unbuffered: bool) -> InputReader {
+        self.unbuffered = unbuffered && cfg!(feature = "tui") && self.prompt;
+        self
+    }
+    /// does ~ get a key as soon as it's pressed, see [InputReader::unbuffered]
+    pub fn is_unbuffered(&self) -> bool {
+        self.unbuffered
+    }
+    /// was ctrl-c pressed while reading a key, which should stop the run
+    pub fn interrupted(&self) -> bool {
+        self.interrupted
+    }
+    /// is this reading from stdin
+    pub fn is_stdin(&self) -> bool {
+        self.reader.is_none()
+    }
+    /// does this show "input num:"/"input char:" on stderr before reading,
+    /// only done for stdin when it's a terminal
+    ///
+    /// piped answers get read silently, skipping over lines that don't parse
+    /// (which say why on stderr), and nothing but the program's own output
+    /// ends up on stdout:
+    ///
+    /// ```
+    /// use refunge::input::{InputReader, InputType};
+    /// use refunge::{Arguments, Befunge};
+    /// use std::io::{stdin, IsTerminal};
+    ///
+    /// assert_eq!(InputReader::stdin().is_prompting(), stdin().is_terminal());
+    /// let mut reader = InputReader::new(&b"x\n 7 \n\n\nq\n"[..]);
+    /// assert!(!reader.is_prompting());
+    /// assert_eq!(reader.read(InputType::Number), Some(7));
+    /// assert_eq!(reader.read(InputType::Character), Some('q' as i32));
+    /// assert_eq!(reader.read(InputType::Number), None);
+    ///
+    /// let args = Arguments { quiet: true, ..Default::default() };
+    /// let mut befunge = Befunge::from_text("&.~,@", args).unwrap();
+    /// befunge.set_input(&b"oops\n12\nz\n"[..]);
+    /// befunge.capture_output();
+    /// befunge.run(|| true);
+    /// assert_eq!(befunge.output(), "12 z");
+    /// ```
+    pub fn is_prompting(&self) -> bool {
+        self.prompt
+    }
+    /// read a value of the desired type, none at EOF
+    /// (e.g. when the program itself was piped in)
+    ///
+    /// characters are the first one on a line, decoded as UTF-8, or the value
+    /// of the first byte if that isn't valid UTF-8
+    ///
+    /// ```
+    /// use refunge::input::{InputReader, InputType};
+    ///
+    /// let mut reader = InputReader::new(&b"a\n\xc3\xa9\n\xf0\x9f\xa6\x80\n\xff\n"[..]);
+    /// let chars: Vec<Option<i32>> = (0..5).map(|_| reader.read(InputType::Character)).collect();
+    /// assert_eq!(chars, [Some('a' as i32), Some('\u{e9}' as i32), Some('\u{1f980}' as i32), Some(0xff), None]);
+    /// ```
+    pub fn read(&mut self, t: InputType) -> Option<i32> {
+        match t {
+            InputType::Number => loop {
+                let line = self.read_line("input num:")?;
+                match t.parse(&String::from_utf8_lossy(&line)) {
+                    Ok(val) => return Some(val),
+                    Err(reason) => eprintln!("\x1b[31m{reason}\x1b[m"),
+                }
+            },
+            #[cfg(feature = "tui")]
+            InputType::Character if self.unbuffered => self.read_key(),
+            InputType::Character => loop {
+                let line = self.read_line("input char:")?;
+                match first_char(line.trim_ascii()) {
+                    Some(c) => return Some(c),
+                    None => eprintln!("\x1b[31mexpected a character\x1b[m"),
+                }
+            },
+        }
+    }
+    /// read a single key from the terminal without waiting for enter,
+    /// putting the terminal back how it was afterwards
+    #[cfg(feature = "tui")]
+    fn read_key(&mut self) -> Option<i32> {
+        use crossterm::event::{read, Event};
+        use crossterm::terminal::{disable_raw_mode, enable_raw_mode};
+        if let Some(mut keys) = self.keys.take() {
+            let val = self.answer_key(|| keys.next());
+            self.keys = Some(keys);
+            return val;
+        }
+        eprint!("\x1b[36minput char:\x1b[m ");
+        if enable_raw_mode().is_err() {
+            return self
+                .read_line("")
+                .and_then(|line| first_char(line.trim_ascii()));
+        }
+        let val = self.answer_key(|| loop {
+            match read() {
+                Ok(Event::Key(event)) => break Some(event),
+                Ok(_) => {}
+                Err(_) => break None,
+            }
+        });
+        disable_raw_mode().unwrap_or(());
+        // nothing gets echoed in raw mode, so show what was pressed
+        match val.and_then(|val| char::from_u32(val as u32)) {
+            Some(c) if !c.is_control() => eprintln!("{c}"),
+            _ => eprintln!(),
+        }
+        val
+    }
+    /// the value of the first key press that answers ~, none once they run
+    /// out or ctrl-c gets pressed (which interrupts)
+    #[cfg(feature = "tui")]
+    fn answer_key(&mut self, mut next: impl FnMut() -> Option<KeyEvent>) -> Option<i32> {
+        while let Some(event) = next() {
+            if event.kind == KeyEventKind::Release {
+                continue;
+            }
+            if event.code == KeyCode::Char('c') && event.modifiers.contains(KeyModifiers::CONTROL) {
+                self.interrupted = true;
+                return None;
+            }
+            if let Some(val) = key_value(&event) {
+                return val;
+            }
+        }
+        None
+    }
+    /// read one line as bytes, none at EOF
+    fn read_line(&mut self, prompt: &str) -> Option<Vec<u8>> {
+        if self.prompt {
+            eprint!("\x1b[36m{prompt}\x1b[m ");
+        }
+        let mut buffer = vec![];
+        let read = match &mut self.reader {
+            Some(reader) => reader.read_until(b'\n', &mut buffer),
+            None => io::stdin().lock().read_until(b'\n', &mut buffer),
+        };
+        match read {
+            Ok(0) | Err(_) => None,
+            Ok(_) => Some(buffer),
+        }
+    }
+}
+
+/// an answered & or ~, kept so what was typed can be shown next to the output
+/// (see --no-echo-input)
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct InputEcho {
+    /// the last tick run when it was answered
+    pub tick: u64,
+    /// the IP it went to
+    pub ip: usize,
+    pub kind: InputType,
+    /// what got pushed, none if the IP was reflected instead
+    pub value: Option<i32>,
+    /// how many bytes of output had been written by then, counting dropped ones
+    pub offset: usize,
+}
+impl Display for InputEcho {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match (self.kind, self.value) {
+            (_, None) => write!(f, "\u{27e8}input: none\u{27e9}"),
+            (InputType::Number, Some(val)) => write!(f, "\u{27e8}input: {val}\u{27e9}"),
+            (InputType::Character, Some(val)) => write!(
+                f,
+                "\u{27e8}input: {:?}\u{27e9}",
+                char::from_u32(val as u32).unwrap_or(char::REPLACEMENT_CHARACTER)
+            ),
+        }
+    }
+}
+
+/// the code point of the first UTF-8 character in some bytes,
+/// or the first byte itself when it doesn't start one
+fn first_char(bytes: &[u8]) -> Option<i32> {
+    let valid = match std::str::from_utf8(bytes) {
+        Ok(text) => text,
+        Err(err) => std::str::from_utf8(&bytes[..err.valid_up_to()]).unwrap_or_default(),
+    };
+    match valid.chars().next() {
+        Some(c) => Some(c as i32),
+        None => bytes.first().map(|b| *b as i32),
+    }
+}
+
+/// program input queued up ahead of time (from --input-file),
+/// consumed the same way for & and ~ in both quiet and tui mode
+///
+/// ```
+/// use refunge::{Arguments, Befunge};
+///
+/// let run = |program: &str, input: &str| {
+///     let path = std::env::temp_dir().join(format!("refunge-doctest-input-{}.txt", program.len()));
+///     std::fs::write(&path, input).unwrap();
+///     let input_file = Some(path.to_string_lossy().into_owned());
+///     let args = Arguments { quiet: true, input_file, ..Default::default() };
+///     let mut befunge = Befunge::from_text(program, args).unwrap();
+///     befunge.set_input(&b""[..]);
+///     befunge.capture_output();
+///     befunge.run(|| true);
+///     befunge.output().to_string()
+/// };
+/// // a hundred &s in a loop and three ~s, all answered from the file
+/// let numbers: Vec<String> = (1..=100).map(|n| n.to_string()).collect();
+/// let input = numbers.join("\n") + "\nhi";
+/// let program = "0aa*>\\&+\\1-:v\n    ^       _$.~$~,~,@";
+/// assert_eq!(run(program, &input), "5050 hi");
+/// // once the file runs out (and stdin too), the second & reflects back into the v
+/// assert_eq!(run("&.#v&@\n   .\n   @", "4"), "4 0 ");
+/// ```
+#[derive(Default, Debug)]
+pub struct InputBuffer {
+    text: String,
+    chars: VecDeque<char>,
+}
+impl InputBuffer {
+    /// queue up the given text as input
+    pub fn new(text: String) -> InputBuffer {
+        InputBuffer {
+            chars: text.chars().collect(),
+            text,
+        }
+    }
+    /// refill the buffer with everything it started with
+    pub fn reset(&mut self) {
+        self.chars = self.text.chars().collect();
+    }
+    /// take the next value of the desired type, none once the buffer runs out
+    /// numbers skip anything before the first digit and stop after the last one
+    pub fn take(&mut self, t: InputType) -> Option<i32> {
+        match t {
+            InputType::Character => self.chars.pop_front().map(|c| c as i32),
+            InputType::Number => {
+                let start = self.chars.iter().enumerate().position(|(idx, c)| {
+                    c.is_ascii_digit()
+                        || (*c == '-' && self.chars.get(idx + 1).is_some_and(char::is_ascii_digit))
+                })?;
+                self.chars.drain(..start);
+                let negative = self.chars.front() == Some(&'-');
+                if negative {
+                    self.chars.pop_front();
+                }
+                let mut n = 0i32;
+                while let Some(digit) = self.chars.front().and_then(|c| c.to_digit(10)) {
+                    n = n.saturating_mul(10).saturating_add(digit as i32);
+                    self.chars.pop_front();
+                }
+                Some(if negative { -n } else { n })
+            }
+        }
+    }
+}
@@ -0,0 +1,192 @@
+/// broad groups of instructions, for describing what a program does
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Kind {
+    /// changes where the IP goes next
+    Flow,
+    /// pushes, pops, or rearranges stack values
+    Stack,
+    /// does math or comparisons on the stack
+    Arithmetic,
+    /// reads from or writes to funge-space
+    Space,
+    /// talks to the user through & ~ , and .
+    Io,
+    /// touches files or runs commands, worth knowing about when sandboxing
+    System,
+    /// loads fingerprints or runs their semantics
+    Fingerprint,
+}
+
+/// one or more characters sharing a meaning
+#[derive(Debug)]
+pub struct Instruction {
+    pub chars: &'static str,
+    pub name: &'static str,
+    pub kind: Kind,
+}
+
+/// every character refunge knows how to run, anything else reflects
+pub const INSTRUCTIONS: &[Instruction] = &[
+    instruction(" ", "Space", Kind::Flow),
+    instruction("!", "Logical Not", Kind::Arithmetic),
+    instruction("\"", "Toggle String Mode", Kind::Stack),
+    instruction("#", "Trampoline", Kind::Flow),
+    instruction("$", "Pop", Kind::Stack),
+    instruction("%", "Remainder", Kind::Arithmetic),
+    instruction("&", "Input Integer", Kind::Io),
+    instruction("'", "Fetch Character", Kind::Stack),
+    instruction("(", "Load Semantics", Kind::Fingerprint),
+    instruction(")", "Unload Semantics", Kind::Fingerprint),
+    instruction("*", "Multiply", Kind::Arithmetic),
+    instruction("+", "Add", Kind::Arithmetic),
+    instruction(",", "Output Character", Kind::Io),
+    instruction("-", "Subtract", Kind::Arithmetic),
+    instruction(".", "Output Integer", Kind::Io),
+    instruction("/", "Divide", Kind::Arithmetic),
+    instruction("0123456789", "Decimal Literals", Kind::Stack),
+    instruction(":", "Duplicate", Kind::Stack),
+    instruction(";", "Jump Over", Kind::Flow),
+    instruction("<", "Go West", Kind::Flow),
+    instruction("=", "Execute", Kind::System),
+    instruction(">", "Go East", Kind::Flow),
+    instruction("?", "Go Away", Kind::Flow),
+    instruction("@", "Stop", Kind::Flow),
+    instruction(
+        "ABCDEFGHIJKLMNOPQRSTUVWXYZ",
+        "Fingerprint Semantics",
+        Kind::Fingerprint,
+    ),
+    instruction("[", "Turn Left", Kind::Flow),
+    instruction("\\", "Swap", Kind::Stack),
+    instruction("]", "Turn Right", Kind::Flow),
+    instruction("^", "Go North", Kind::Flow),
+    instruction("_", "East-West If", Kind::Flow),
+    instruction("`", "Greater Than", Kind::Arithmetic),
+    instruction("abcdef", "Hexadecimal Literals", Kind::Stack),
+    instruction("g", "Get", Kind::Space),
+    instruction("i", "Input File", Kind::System),
+    instruction("j", "Jump Forward", Kind::Flow),
+    instruction("k", "Iterate", Kind::Flow),
+    instruction("l", "Lehmer Code Permutation (--extensions)", Kind::Stack),
+    instruction("n", "Clear Stack", Kind::Stack),
+    instruction("o", "Output File", Kind::System),
+    instruction("p", "Put", Kind::Space),
+    instruction("q", "Quit", Kind::Flow),
+    instruction("r", "Reflect", Kind::Flow),
+    instruction("s", "Store Character", Kind::Space),
+    instruction("t", "Split", Kind::Flow),
+    instruction("u", "Stack under Stack", Kind::Stack),
+    instruction("v", "Go South", Kind::Flow),
+    instruction("w", "Compare", Kind::Flow),
+    instruction("x", "Absolute Delta", Kind::Flow),
+    instruction("y", "Get SysInfo", Kind::Stack),
+    instruction("z", "No-Op", Kind::Flow),
+    instruction("{", "Begin Block", Kind::Stack),
+    instruction("|", "North-South If", Kind::Flow),
+    instruction("}", "End Block", Kind::Stack),
+    instruction("~", "Input Character", Kind::Io),
+];
+
+const fn instruction(chars: &'static str, name: &'static str, kind: Kind) -> Instruction {
+    Instruction { chars, name, kind }
+}
+
+/// the instructions outside the spec, which only run with --extensions
+pub const EXTENSIONS: &str = "l";
+
+/// what a character does when run, None if it just reflects, as the
+/// instructions in [EXTENSIONS] do unless they're enabled
+///
+/// ```
+/// use refunge::instructions::lookup;
+///
+/// assert_eq!(lookup('p', false).map(|i| i.name), Some("Put"));
+/// assert_eq!(lookup('l', false).map(|i| i.name), None);
+/// assert_eq!(lookup('l', true).map(|i| i.name), Some("Lehmer Code Permutation (--extensions)"));
+/// assert_eq!(lookup('m', true).map(|i| i.name), None);
+/// ```
+pub fn lookup(c: char, extensions: bool) -> Option<&'static Instruction> {
+    if !extensions && EXTENSIONS.contains(c) {
+        return None;
+    }
+    INSTRUCTIONS.iter().find(|i| i.chars.contains(c))
+}
+
+/// what a cell looks like it's for, finer than [Kind], for coloring the grid
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Category {
+    /// sends the IP a new way: arrows, turns, ifs, and the like
+    Movement,
+    /// pushes the number it stands for
+    Literal,
+    /// does math or comparisons on the stack
+    Arithmetic,
+    /// pushes, pops, or rearranges stack values
+    Stack,
+    /// reads or writes something outside the stacks: the user, the grid, or files
+    Io,
+    /// skips, repeats, splits, stops, or runs fingerprint semantics
+    Control,
+    /// inside a string, quotes included
+    Text,
+    /// reflects when run
+    Unknown,
+}
+
+/// the category of a cell outside a string, None for a space
+///
+/// ```
+/// use refunge::instructions::{category, Category};
+///
+/// assert_eq!(category('>'), Some(Category::Movement));
+/// assert_eq!(category('7'), Some(Category::Literal));
+/// assert_eq!(category('e'), Some(Category::Literal));
+/// assert_eq!(category('g'), Some(Category::Io));
+/// assert_eq!(category('@'), Some(Category::Control));
+/// assert_eq!(category('"'), Some(Category::Text));
+/// assert_eq!(category('m'), Some(Category::Unknown));
+/// assert_eq!(category(' '), None);
+/// ```
+pub fn category(c: char) -> Option<Category> {
+    // colored for what it does with --extensions, which the grid doesn't know about
+    let Some(instruction) = lookup(c, true) else {
+        return Some(Category::Unknown);
+    };
+    Some(match instruction.kind {
+        _ if c == ' ' => return None,
+        _ if c == '"' => Category::Text,
+        _ if c.is_ascii_hexdigit() && !c.is_ascii_uppercase() => Category::Literal,
+        Kind::Flow if "<>^v?[]rx_|w".contains(c) => Category::Movement,
+        Kind::Flow | Kind::Fingerprint => Category::Control,
+        Kind::Stack => Category::Stack,
+        Kind::Arithmetic => Category::Arithmetic,
+        Kind::Space | Kind::Io | Kind::System => Category::Io,
+    })
+}
+
+/// the category of every cell in a row, with everything from a " to the
+/// next one on the row counted as text
+///
+/// ```
+/// use refunge::instructions::{categorize_row, Category};
+///
+/// let row: Vec<char> = "\"a b\",@".chars().collect();
+/// let categories = categorize_row(&row);
+/// assert_eq!(categories[..5], [Some(Category::Text); 5]);
+/// assert_eq!(categories[5..], [Some(Category::Io), Some(Category::Control)]);
+/// ```
+pub fn categorize_row(row: &[char]) -> Vec<Option<Category>> {
+    let mut in_string = false;
+    row.iter()
+        .map(|&c| {
+            let text = in_string || c == '"';
+            if c == '"' {
+                in_string = !in_string;
+            }
+            match text {
+                true => Some(Category::Text),
+                false => category(c),
+            }
+        })
+        .collect()
+}
@@ -0,0 +1,62 @@
+//! what refunge reports through `tracing` when built with the `tracing` feature
+//!
+//! without the feature none of this is compiled in, and with it the fields
+//! are only formatted when a subscriber actually wants the event
+//!
+//! - a `tick` span (trace level) around every tick, with the `tick` number
+//! - an `instruction` event (trace) for each IP that ran, with `ip`, `x`, `y`,
+//!   `instruction` and `string_mode`
+//! - a `spawn` event (debug) with the `ip` that split, and a `kill` event (info)
+//!   with the exit `code`
+//! - `input file` / `output file` events (debug) from 'i' and 'o' with the `ip`
+//!   and `file`, plus a warning with the `error` whenever 'i', 'o' or '=' reflect
+//! - an `ip stopped` error when an IP can't carry on at all
+//!
+//! ```
+//! use std::sync::{Arc, Mutex};
+//! use tracing::field::{Field, Visit};
+//! use tracing::span::{Attributes, Id, Record};
+//! use tracing::{Event, Metadata, Subscriber};
+//! use refunge::{Arguments, Befunge};
+//!
+//! /// keeps every event as "message field=value ..."
+//! #[derive(Clone, Default)]
+//! struct Capture(Arc<Mutex<Vec<String>>>);
+//! struct Fields(String, String);
+//! impl Visit for Fields {
+//!     fn record_debug(&mut self, field: &Field, value: &dyn std::fmt::Debug) {
+//!         match field.name() {
+//!             "message" => self.0 = format!("{value:?}"),
+//!             name => self.1 += &format!(" {name}={value:?}"),
+//!         }
+//!     }
+//! }
+//! impl Subscriber for Capture {
+//!     fn enabled(&self, _: &Metadata<'_>) -> bool { true }
+//!     fn new_span(&self, _: &Attributes<'_>) -> Id { Id::from_u64(1) }
+//!     fn record(&self, _: &Id, _: &Record<'_>) {}
+//!     fn record_follows_from(&self, _: &Id, _: &Id) {}
+//!     fn event(&self, event: &Event<'_>) {
+//!         let mut fields = Fields(String::new(), String::new());
+//!         event.record(&mut fields);
+//!         self.0.lock().unwrap().push(fields.0 + &fields.1);
+//!     }
+//!     fn enter(&self, _: &Id) {}
+//!     fn exit(&self, _: &Id) {}
+//! }
+//!
+//! let capture = Capture::default();
+//! tracing::subscriber::with_default(capture.clone(), || {
+//!     let args = Arguments { quiet: true, ..Default::default() };
+//!     let mut befunge = Befunge::from_text("t3q", args).unwrap();
+//!     befunge.capture_output();
+//!     befunge.run(|| true);
+//! });
+//! assert_eq!(*capture.0.lock().unwrap(), [
+//!     "instruction ip=0 x=0 y=0 instruction=t string_mode=false",
+//!     "spawn ip=0",
+//!     "instruction ip=0 x=2 y=0 instruction=q string_mode=false",
+//!     "instruction ip=1 x=1 y=0 instruction=3 string_mode=false",
+//!     "kill code=0",
+//! ]);
+//! ```
@@ -0,0 +1,61 @@
+//! Refunge, a Befunge-98 interpreter
+//!
+//! [run] takes care of the common case of running a program to completion,
+//! while [Befunge] can be ticked by hand to watch a program as it goes:
+//!
+//! ```
+//! use refunge::{Arguments, Befunge};
+//!
+//! let args = Arguments { quiet: true, ..Default::default() };
+//! let mut befunge = Befunge::from_text("25*:.q", args).unwrap();
+//! befunge.capture_output();
+//! befunge.tick();
+//! assert_eq!(befunge.stats().ticks, 1);
+//! befunge.run(|| true);
+//! assert_eq!(befunge.output(), "10 ");
+//! assert_eq!(befunge.exit_code, Some(10));
+//! ```
+mod befunge;
+pub mod vector;
+pub mod grid;
+pub mod space;
+pub mod event;
+pub mod arguments;
+pub mod stack;
+pub mod pointer;
+pub mod stackable;
+pub mod source;
+pub mod input;
+pub mod output;
+pub mod stats;
+pub mod check;
+pub mod expect;
+pub mod fmt;
+mod profile;
+pub mod logger;
+pub mod fingerprint;
+pub mod session;
+pub mod instructions;
+pub mod report;
+pub mod hook;
+pub mod snapshot;
+pub mod platform;
+pub mod timeline;
+pub mod bench;
+pub mod stream;
+pub mod error;
+pub mod golden;
+#[cfg(feature = "tui")]
+pub mod glyphs;
+#[cfg(feature = "tui")]
+pub mod ansi;
+#[cfg(feature = "tracing")]
+pub mod instrument;
+
+pub use arguments::Arguments;
+pub use befunge::{run, Befunge, Outcome, Ticks};
+pub use error::RefungeError;
+pub use grid::{FungeGrid, GridMode, WrapMode};
+pub use pointer::InstructionPointer;
+pub use stack::FungeStack;
+pub use vector::FungeVector;
@@ -0,0 +1,75 @@
+use crate::arguments::Arguments;
+use log::{LevelFilter, Log, Metadata, Record};
+use std::collections::VecDeque;
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::sync::{Mutex, OnceLock};
+
+/// how many messages the tui log pane holds on to
+const KEPT_MESSAGES: usize = 256;
+
+static LOGGER: OnceLock<Logger> = OnceLock::new();
+
+/// sends interpreter diagnostics to stderr in quiet mode or the log pane
+/// in the tui, plus the --log-file if there is one
+struct Logger {
+    stderr: bool,
+    file: Option<Mutex<BufWriter<File>>>,
+    recent: Mutex<VecDeque<String>>,
+}
+impl Log for Logger {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        metadata.level() <= log::max_level()
+    }
+    fn log(&self, record: &Record) {
+        if !self.enabled(record.metadata()) {
+            return;
+        }
+        let line = format!("[{}] {}", record.level(), record.args());
+        if let Some(file) = &self.file {
+            writeln!(file.lock().unwrap(), "{line}").unwrap_or(());
+        }
+        if self.stderr {
+            eprintln!("{line}");
+        } else {
+            let mut recent = self.recent.lock().unwrap();
+            if recent.len() == KEPT_MESSAGES {
+                recent.pop_front();
+            }
+            recent.push_back(line);
+        }
+    }
+    fn flush(&self) {
+        if let Some(file) = &self.file {
+            file.lock().unwrap().flush().unwrap_or(());
+        }
+    }
+}
+
+/// install the logger for this run, at --log-level (warn by default)
+pub fn init(args: &Arguments) -> io::Result<()> {
+    let file = match &args.log_file {
+        Some(path) => Some(Mutex::new(BufWriter::new(File::create(path)?))),
+        None => None,
+    };
+    let logger = LOGGER.get_or_init(|| Logger {
+        stderr: args.quiet,
+        file,
+        recent: Mutex::new(VecDeque::new()),
+    });
+    log::set_logger(logger).unwrap_or(());
+    log::set_max_level(args.log_level.unwrap_or(LevelFilter::Warn));
+    Ok(())
+}
+
+/// the last n messages for the log pane, oldest first
+pub fn recent(n: usize) -> Vec<String> {
+    LOGGER.get().map_or(vec![], |logger| {
+        let recent = logger.recent.lock().unwrap();
+        recent
+            .iter()
+            .skip(recent.len().saturating_sub(n))
+            .cloned()
+            .collect()
+    })
+}
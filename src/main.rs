@@ -1,67 +1,233 @@
-mod befunge;
-mod vector;
-mod grid;
-mod event;
-mod arguments;
-mod stack;
-mod pointer;
-mod stackable;
-
-use std::io;
+use std::fs::read_to_string;
+use std::io::Write;
 use clap::Parser;
-use std::io::{stdout, Stdout};
-use anyhow::{bail, Result};
+#[cfg(feature = "tui")]
+use std::io::{self, stdout, Stdout};
+use anyhow::{bail, Context, Result};
+#[cfg(feature = "tui")]
 use crossterm::execute;
+#[cfg(feature = "tui")]
 use crossterm::terminal::{disable_raw_mode, enable_raw_mode, LeaveAlternateScreen, EnterAlternateScreen};
+#[cfg(feature = "ctrlc")]
 use ctrlc_handler::CtrlCHandler;
+#[cfg(feature = "ctrlc")]
+use std::sync::{Arc, atomic::{AtomicBool, Ordering}};
+#[cfg(feature = "ctrlc")]
+use std::thread;
+#[cfg(any(feature = "tui", feature = "ctrlc"))]
+use std::time::Duration;
+#[cfg(feature = "tui")]
 use ratatui::{backend::CrosstermBackend, Terminal};
-use crate::arguments::Arguments;
-use crate::befunge::Befunge;
+use refunge::arguments::{Arguments, BenchArguments, Command};
+use refunge::bench::{BenchReport, BenchRun};
+use refunge::report::Tick;
+use refunge::{check, expect, fmt, logger, stream, Befunge};
+
+/// exit status used when ctrl-c stops a quiet run
+const INTERRUPT_EXIT_CODE: i32 = 130;
+/// how long turbo mode runs between repaints
+#[cfg(feature = "tui")]
+const TURBO_SLICE: Duration = Duration::from_millis(50);
+/// how many live IPs an interrupted run lists before leaving the rest off
+const SUMMARY_IPS: usize = 8;
 
-fn main() -> Result<()> {
+fn main() {
+    if let Err(err) = run() {
+        eprintln!("refunge: {err:#}");
+        std::process::exit(1);
+    }
+}
+/// everything main does, with errors left for it to print on one line
+fn run() -> Result<()> {
     let args = Arguments::parse();
+    match &args.command {
+        Some(Command::Fmt(fmt)) => {
+            let unchanged = fmt::run(fmt)?;
+            if fmt.check && !unchanged {bail!("{} is not formatted", fmt.file)}
+            return Ok(());
+        }
+        Some(Command::Check(check)) => {
+            if !check::run(check)? {std::process::exit(1)}
+            return Ok(());
+        }
+        Some(Command::Bench(bench)) => return run_bench(bench),
+        None => {}
+    }
+    logger::init(&args).context("failed to create log file")?;
 
-    if args.quiet {
-        let (max_ticks, log_stack) = (args.max_ticks, args.log_stack);
-        let mut befunge = Befunge::new(args);
-        let c = CtrlCHandler::new();
-        let mut ticks = 0u32;
-        while !befunge.ended() && c.should_continue() {
-            befunge.tick();
-            if let Some(max) = max_ticks {
-                if ticks > max {break} else {ticks += 1}
-            }
+    if args.json {run_json(args)} else if args.quiet {run_quiet(args)} else {run_tui(args)}
+}
+fn run_quiet(args: Arguments) -> Result<()> {
+    let (log_stack, stats) = (args.log_stack, args.stats);
+    let snapshot_out = args.snapshot_out.clone();
+    let (expect, ignore_trailing_space) = (args.expect.clone(), args.ignore_trailing_space);
+    let mut befunge = Befunge::new(args)?;
+    let interrupted = run_until_interrupted(&mut befunge) || befunge.interrupted();
+    if interrupted {eprintln!("{}", interrupt_summary(&befunge))}
+    if let Some((ip, pos)) = befunge.break_hit() {
+        eprintln!("ip {ip} stopped at breakpoint {pos} after {} ticks", befunge.stats().ticks);
+    }
+    if log_stack {befunge.log_stacks()}
+    finish(&befunge, snapshot_out.as_deref(), stats)?;
+    if interrupted {std::process::exit(INTERRUPT_EXIT_CODE)}
+    if let Some(msg) = befunge.diverged() {bail!("replay diverged: {msg}")}
+    if let Some((ip, pos, c)) = befunge.stuck() {
+        bail!("ip {ip} is stuck at {pos} with a zero delta, running {c:?} every tick (--strict)");
+    }
+    if let Some(path) = expect {
+        let expected = read_to_string(&path).with_context(|| format!("failed to read {path}"))?;
+        if let Err((code, why)) = expect::check(&befunge, &expected, ignore_trailing_space) {
+            eprintln!("refunge: {path}: {why}");
+            std::process::exit(code);
         }
-        if log_stack {befunge.log_stacks()}
-        if let Some(code) = befunge.exit_code {bail!("process created code {}", code)}
-        Ok(())
-    } else {
-        let mut terminal = create_tui()?;
-        let jump_ticks = args.jump;
-        let mut befunge = Befunge::new(args);
-        if let Some(n) = jump_ticks {
-            for _ in 0..n {
-                befunge.tick();
-                if befunge.paused() {break}
+        return Ok(());
+    }
+    if let Some((code, why)) = befunge.stopped_by() {
+        eprintln!("{why}");
+        std::process::exit(code);
+    }
+    if let Some(code) = befunge.exit_code {bail!("process created code {}", code)}
+    Ok(())
+}
+/// run quietly, printing json events instead of output, with the exit code in the last one
+fn run_json(args: Arguments) -> Result<()> {
+    let (stats, snapshot_out) = (args.stats, args.snapshot_out.clone());
+    let mut befunge = Befunge::new(args)?;
+    let mut out = std::io::stdout().lock();
+    stream::run(&mut befunge, std::io::stdin().lock(), |event| {
+        // a reader that went away can't be told anything more, so stop there
+        if writeln!(out, "{}", event.to_json()).is_err() {std::process::exit(1)}
+    })?;
+    finish(&befunge, snapshot_out.as_deref(), stats)
+}
+/// run a program once to warm up and then --iterations more times, timing each
+fn run_bench(bench: &BenchArguments) -> Result<()> {
+    let args = Arguments {
+        quiet: true,
+        file: Some(bench.file.clone()),
+        script: bench.script,
+        input_file: bench.input_file.clone(),
+        max_ticks: bench.max_ticks,
+        ..Default::default()
+    };
+    let mut befunge = Befunge::new(args)?;
+    befunge.discard_output();
+    let mut runs = vec![];
+    for iteration in 0..=bench.iterations {
+        befunge.restart();
+        // the ticks iterator asks for stdin input instead of waiting on it, which ends the benchmark
+        let asked = befunge.ticks().any(|tick| matches!(tick, Tick::Input(_)));
+        if asked {
+            match &bench.input_file {
+                Some(path) => bail!("{} ran out of input from {path} on tick {}", bench.file, befunge.stats().ticks),
+                None => bail!("{} asks for input on tick {}, give it an --input-file to benchmark it", bench.file, befunge.stats().ticks),
             }
         }
-        loop {
-            terminal.draw(|f| befunge.render(f))?;
-            if befunge.has_tick() && !befunge.paused() {befunge.tick()}
-            if befunge.handle_key_events() {break}
+        if iteration > 0 {
+            let stats = befunge.stats();
+            runs.push(BenchRun {ticks: stats.ticks, time: stats.elapsed(), ended: befunge.ended()});
         }
-        exit_tui(terminal)?;
-        if let Some(code) = befunge.exit_code {bail!("process created code {}", code)}
-        Ok(())
     }
+    let report = BenchReport {program: bench.file.clone(), runs};
+    if bench.json {println!("{}", report.to_json())} else {print!("{report}")}
+    Ok(())
+}
+#[cfg(feature = "tui")]
+fn run_tui(mut args: Arguments) -> Result<()> {
+    args.ascii |= !unicode_locale();
+    let (jump_ticks, stats) = (args.jump, args.stats);
+    let snapshot_out = args.snapshot_out.clone();
+    let mut befunge = Befunge::new(args)?;
+    let mut terminal = create_tui()?;
+    if let Some(n) = jump_ticks {befunge.jump(n)}
+    loop {
+        befunge.check_reload();
+        terminal.draw(|f| befunge.render(f))?;
+        if befunge.turbo() {befunge.run_turbo(TURBO_SLICE)}
+        else if befunge.has_tick() && !befunge.paused() {befunge.tick()}
+        if befunge.handle_key_events() {break}
+    }
+    befunge.stop_key_events();
+    exit_tui(terminal)?;
+    befunge.flush();
+    finish(&befunge, snapshot_out.as_deref(), stats)?;
+    if let Some(code) = befunge.exit_code {bail!("process created code {}", code)}
+    Ok(())
+}
+/// write out whatever was asked for once a run stops, however it stopped
+fn finish(befunge: &Befunge, snapshot_out: Option<&str>, stats: bool) -> Result<()> {
+    befunge.write_profile()?;
+    befunge.finish_session()?;
+    if let Some(path) = snapshot_out {befunge.write_snapshot(path)?}
+    log::logger().flush();
+    if stats {eprintln!("{}", befunge.stats())}
+    Ok(())
+}
+/// how far a run got before ctrl-c, and where its IPs were
+fn interrupt_summary(befunge: &Befunge) -> String {
+    let live: Vec<String> = befunge
+        .live_positions()
+        .take(SUMMARY_IPS)
+        .map(|(id, pos)| format!("ip {id} at {pos}"))
+        .collect();
+    let mut summary = format!("interrupted after {} ticks", befunge.stats().ticks);
+    if !live.is_empty() {summary.push_str(&format!(", {}", live.join(", ")))}
+    match befunge.live_ips().saturating_sub(SUMMARY_IPS) {
+        0 => {}
+        more => summary.push_str(&format!(" and {more} more")),
+    }
+    summary
+}
+/// does the locale say the terminal can show UTF-8, going by the first of
+/// LC_ALL, LC_CTYPE and LANG that's set (no locale at all counts as yes)
+#[cfg(feature = "tui")]
+fn unicode_locale() -> bool {
+    let locale = ["LC_ALL", "LC_CTYPE", "LANG"]
+        .iter()
+        .filter_map(|var| std::env::var(var).ok())
+        .find(|value| !value.is_empty());
+    locale.is_none_or(|locale| {
+        let locale = locale.to_ascii_lowercase();
+        locale.contains("utf-8") || locale.contains("utf8")
+    })
+}
+#[cfg(not(feature = "tui"))]
+fn run_tui(_args: Arguments) -> Result<()> {
+    bail!("refunge was built without the tui, run it with -q")
+}
+/// run until the program stops or ctrl-c is pressed, saying which it was
+///
+/// a second ctrl-c exits right away instead of waiting for the run to wrap up
+/// (or for a line of input it's stuck reading)
+#[cfg(feature = "ctrlc")]
+fn run_until_interrupted(befunge: &mut Befunge) -> bool {
+    let c = CtrlCHandler::new();
+    let interrupted = Arc::new(AtomicBool::new(false));
+    let watched = interrupted.clone();
+    thread::spawn(move || loop {
+        if !c.should_continue() {
+            if watched.swap(true, Ordering::Relaxed) {std::process::exit(INTERRUPT_EXIT_CODE)}
+            eprintln!("\ninterrupted, press ctrl-c again to quit right away");
+        }
+        thread::sleep(Duration::from_millis(50));
+    });
+    befunge.run(|| !interrupted.load(Ordering::Relaxed));
+    interrupted.load(Ordering::Relaxed)
+}
+#[cfg(not(feature = "ctrlc"))]
+fn run_until_interrupted(befunge: &mut Befunge) -> bool {
+    befunge.run(|| true);
+    false
 }
+#[cfg(feature = "tui")]
 fn create_tui() -> io::Result<Terminal<CrosstermBackend<Stdout>>> {
     enable_raw_mode()?;
     execute!(stdout(), EnterAlternateScreen)?;
-    Ok(Terminal::new(CrosstermBackend::new(stdout()))?)
+    Terminal::new(CrosstermBackend::new(stdout()))
 }
+#[cfg(feature = "tui")]
 fn exit_tui(mut terminal: Terminal<CrosstermBackend<Stdout>>) -> io::Result<()> {
     disable_raw_mode()?;
     execute!(terminal.backend_mut(), LeaveAlternateScreen)?;
-    Ok(terminal.show_cursor()?)
+    terminal.show_cursor()
 }
@@ -0,0 +1,355 @@
+use crate::arguments::Arguments;
+use std::collections::VecDeque;
+use std::fmt;
+use std::fs::File;
+use std::io::{self, stdout, BufWriter, Write};
+use std::time::{Duration, Instant};
+
+/// how long output can sit in the buffer before it gets written out anyway
+const FLUSH_INTERVAL: Duration = Duration::from_millis(100);
+/// a bigger buffer than usual for stdout, since quiet runs can print a lot
+const STDOUT_BUFFER: usize = 64 * 1024;
+/// how much output the tui holds on to, older output gets dropped past this
+const TUI_TEXT_LIMIT: usize = 64 * 1024;
+/// written once in place of everything past --max-output, in the tui
+pub const OUTPUT_LIMIT_MARKER: &str = "[output limit reached]";
+
+/// where program output from , and . ends up
+/// quiet mode prints to stdout, the tui (and --expect) keeps the text around,
+/// and either one can be redirected into a file with --output
+#[derive(Default)]
+pub struct Output {
+    /// text shown in the tui output pane or compared by --expect
+    text: String,
+    /// keep text around instead of only writing it out
+    keep_text: bool,
+    /// most bytes of text to keep, if it's capped
+    limit: Option<usize>,
+    /// bytes dropped off the front of text to stay under the limit
+    dropped: usize,
+    /// which IP wrote each run of text, as (where the run starts counting
+    /// dropped bytes, ip id), a new run starting whenever another IP writes
+    segments: VecDeque<(usize, usize)>,
+    /// the IP whose output is being written now
+    ip: usize,
+    /// prefix every line written out with the IP that wrote it (--tag-output)
+    tag: bool,
+    /// the IP that started the line being written out, none at the start of one
+    line_ip: Option<usize>,
+    /// buffered destination outside of the tui
+    writer: Option<BufWriter<Box<dyn Write>>>,
+    /// file being written to, if any
+    path: Option<String>,
+    /// output since the last take_recent
+    recent: String,
+    /// when the writer was last flushed, None where there's no clock (wasm32)
+    last_flush: Option<Instant>,
+    /// most bytes the program may write before the rest is thrown away (--max-output)
+    max_output: Option<usize>,
+    /// bytes written so far, counted against max_output
+    accepted: usize,
+    /// set once something went past max_output
+    full: bool,
+    /// write OUTPUT_LIMIT_MARKER when that happens
+    marker: bool,
+}
+impl Output {
+    /// create the output sink, creating/truncating the output file if given
+    ///
+    /// ```
+    /// use refunge::{expect, Arguments, Befunge};
+    ///
+    /// let path = std::env::temp_dir().join("refunge-doctest-output.txt");
+    /// std::fs::write(&path, "left over from before").unwrap();
+    /// // quietly, and alongside the tui
+    /// for quiet in [true, false] {
+    ///     let output = Some(path.to_string_lossy().into_owned());
+    ///     let args = Arguments { quiet, output, ..Default::default() };
+    ///     let mut befunge = Befunge::from_text("\"!olleH\">:#,_a,9.@", args).unwrap();
+    ///     befunge.run(|| true);
+    ///     befunge.flush();
+    ///     let written = std::fs::read_to_string(&path).unwrap();
+    ///     assert_eq!(expect::compare("Hello!\n9 ", &written, false), None);
+    /// }
+    /// ```
+    pub fn new(args: &Arguments) -> io::Result<Output> {
+        let path = args.output.clone();
+        let writer: Option<BufWriter<Box<dyn Write>>> = match &path {
+            Some(path) => Some(BufWriter::new(Box::new(File::create(path)?))),
+            // --json puts output inside its events instead
+            None if args.quiet && args.expect.is_none() && !args.json => {
+                Some(BufWriter::with_capacity(STDOUT_BUFFER, Box::new(stdout())))
+            }
+            None => None,
+        };
+        Ok(Output {
+            text: String::new(),
+            keep_text: !args.quiet || args.expect.is_some(),
+            // --expect needs every byte, the tui only ever shows the end
+            limit: (!args.quiet && args.expect.is_none()).then_some(TUI_TEXT_LIMIT),
+            dropped: 0,
+            segments: VecDeque::new(),
+            ip: 0,
+            tag: args.tag_output,
+            line_ip: None,
+            writer,
+            path,
+            recent: String::new(),
+            last_flush: clock(),
+            max_output: args.max_output,
+            accepted: 0,
+            full: false,
+            marker: !args.quiet,
+        })
+    }
+    /// keep output in memory only
+    pub fn captured() -> Output {
+        Output {
+            text: String::new(),
+            keep_text: true,
+            limit: None,
+            dropped: 0,
+            segments: VecDeque::new(),
+            ip: 0,
+            tag: false,
+            line_ip: None,
+            writer: None,
+            path: None,
+            recent: String::new(),
+            last_flush: None,
+            max_output: None,
+            accepted: 0,
+            full: false,
+            marker: false,
+        }
+    }
+    /// throw away output past this many bytes, see [Output::full]
+    pub fn with_max_output(mut self, max_output: Option<usize>) -> Output {
+        self.max_output = max_output;
+        self
+    }
+    /// send output to some other writer instead of stdout or the output file
+    pub fn redirect(&mut self, writer: impl Write + 'static) {
+        self.flush();
+        self.writer = Some(BufWriter::new(Box::new(writer)));
+        self.path = None;
+        self.last_flush = clock();
+    }
+    /// start over from output produced earlier, without writing it out again
+    pub fn restore(&mut self, text: &str) {
+        self.clear();
+        if self.keep_text {
+            self.text = text.to_string();
+            if !text.is_empty() {
+                self.segments.push_back((0, 0));
+            }
+        }
+    }
+    /// attribute whatever gets written next to this IP
+    pub fn set_ip(&mut self, id: usize) {
+        self.ip = id;
+    }
+    /// all output produced so far (tui and --expect only),
+    /// minus what the tui dropped to stay under its limit
+    ///
+    /// ```
+    /// use refunge::output::Output;
+    /// use refunge::Arguments;
+    /// use std::fmt::Write;
+    ///
+    /// let mut out = Output::new(&Arguments::default()).unwrap();
+    /// for _ in 0..100_000 {
+    ///     write!(out, "0123456789").unwrap();
+    /// }
+    /// assert!(out.text().len() <= 128 * 1024);
+    /// assert!(out.text().ends_with("789"));
+    /// assert_eq!(out.dropped() + out.text().len(), 1_000_000);
+    /// ```
+    pub fn text(&self) -> &str {
+        &self.text
+    }
+    /// how many bytes of output were dropped from the front of text()
+    pub fn dropped(&self) -> usize {
+        self.dropped
+    }
+    /// how many bytes of output were written in total, counting dropped ones
+    pub fn written(&self) -> usize {
+        self.dropped + self.text.len()
+    }
+    /// text() split up by the IP that wrote it, as (ip id, text), oldest first
+    pub fn segments(&self) -> impl Iterator<Item = (usize, &str)> {
+        (0..self.segments.len()).map(|idx| {
+            let (start, end) = self.segment_range(idx);
+            (self.segments[idx].1, &self.text[start..end])
+        })
+    }
+    /// the bytes start..end of text() split up by the IP that wrote them
+    pub fn runs(&self, start: usize, end: usize) -> impl Iterator<Item = (usize, &str)> {
+        let end = end.min(self.text.len());
+        let first = self
+            .segments
+            .partition_point(|&(from, _)| from <= start + self.dropped)
+            .saturating_sub(1);
+        (first..self.segments.len())
+            .map(move |idx| (idx, self.segment_range(idx)))
+            .take_while(move |(_, (from, _))| *from < end)
+            .map(move |(idx, (from, to))| {
+                let from = from.max(start);
+                let to = to.min(end).max(from);
+                (self.segments[idx].1, &self.text[from..to])
+            })
+    }
+    /// where a segment starts and ends in text()
+    fn segment_range(&self, idx: usize) -> (usize, usize) {
+        let start = self.segments[idx].0.max(self.dropped) - self.dropped;
+        let end = self
+            .segments
+            .get(idx + 1)
+            .map_or(self.text.len(), |next| next.0 - self.dropped);
+        (start, end)
+    }
+    /// has the program tried to write more than --max-output allows
+    ///
+    /// ```
+    /// use refunge::output::Output;
+    /// use std::fmt::Write;
+    ///
+    /// let mut out = Output::captured().with_max_output(Some(8));
+    /// write!(out, "1234").unwrap();
+    /// write!(out, "5678").unwrap();
+    /// assert!(!out.full());
+    /// write!(out, "9").unwrap();
+    /// assert!(out.full());
+    /// assert_eq!(out.text(), "12345678");
+    /// ```
+    pub fn full(&self) -> bool {
+        self.full
+    }
+    /// output written since the last call
+    pub fn take_recent(&mut self) -> String {
+        std::mem::take(&mut self.recent)
+    }
+    /// push any buffered output to its destination
+    pub fn flush(&mut self) {
+        if let Some(writer) = &mut self.writer {
+            writer.flush().unwrap_or(());
+        }
+        if self.last_flush.is_some() {
+            self.last_flush = clock();
+        }
+    }
+    /// flush if output has been sitting in the buffer for a while,
+    /// so a program printing slowly still shows up as it runs
+    pub fn flush_if_due(&mut self) {
+        if self
+            .last_flush
+            .is_some_and(|last| last.elapsed() >= FLUSH_INTERVAL)
+        {
+            self.flush();
+        }
+    }
+    /// forget all output, starting the output file over
+    pub fn clear(&mut self) {
+        self.text.clear();
+        self.dropped = 0;
+        self.segments.clear();
+        self.line_ip = None;
+        self.recent.clear();
+        self.accepted = 0;
+        self.full = false;
+        if let Some(path) = &self.path {
+            self.writer = File::create(path)
+                .ok()
+                .map(|f| BufWriter::new(Box::new(f) as Box<dyn Write>));
+        }
+    }
+    /// take in output that fits under the limit
+    fn push(&mut self, s: &str) {
+        self.recent.push_str(s);
+        if self.keep_text {
+            if !s.is_empty() && self.segments.back().is_none_or(|&(_, ip)| ip != self.ip) {
+                self.segments
+                    .push_back((self.dropped + self.text.len(), self.ip));
+            }
+            self.text.push_str(s);
+            // trim once twice over the limit, so trimming doesn't happen on every write
+            if let Some(limit) = self.limit.filter(|limit| self.text.len() > limit * 2) {
+                let mut cut = self.text.len() - limit;
+                while !self.text.is_char_boundary(cut) {
+                    cut += 1;
+                }
+                self.text.drain(..cut);
+                self.dropped += cut;
+                // only the last segment starting before the cut keeps any text
+                while self
+                    .segments
+                    .get(1)
+                    .is_some_and(|&(start, _)| start <= self.dropped)
+                {
+                    self.segments.pop_front();
+                }
+            }
+        }
+        if self.tag {
+            self.write_tagged(s);
+        } else if let Some(writer) = &mut self.writer {
+            writer.write_all(s.as_bytes()).unwrap_or(());
+        }
+    }
+    /// write text out with each line starting with the IP that wrote it,
+    /// breaking the line whenever another IP joins in partway through
+    fn write_tagged(&mut self, s: &str) {
+        let Some(writer) = &mut self.writer else {
+            return;
+        };
+        for line in s.split_inclusive('\n') {
+            if self.line_ip.is_some_and(|ip| ip != self.ip) {
+                writer.write_all(b"\n").unwrap_or(());
+                self.line_ip = None;
+            }
+            if self.line_ip.is_none() {
+                write!(writer, "[{}] ", self.ip).unwrap_or(());
+                self.line_ip = Some(self.ip);
+            }
+            writer.write_all(line.as_bytes()).unwrap_or(());
+            if line.ends_with('\n') {
+                self.line_ip = None;
+            }
+        }
+    }
+}
+impl fmt::Write for Output {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        let Some(max) = self.max_output else {
+            self.push(s);
+            return Ok(());
+        };
+        if self.full {
+            return Ok(());
+        }
+        let mut end = s.len().min(max - self.accepted);
+        while !s.is_char_boundary(end) {
+            end -= 1;
+        }
+        self.accepted += end;
+        self.push(&s[..end]);
+        if end < s.len() {
+            self.full = true;
+            if self.marker {
+                let gap = if self.text.ends_with('\n') { "" } else { "\n" };
+                self.push(&format!("{gap}{OUTPUT_LIMIT_MARKER}\n"));
+            }
+        }
+        Ok(())
+    }
+}
+impl Drop for Output {
+    fn drop(&mut self) {
+        self.flush();
+    }
+}
+
+/// now, where there is a clock to read
+fn clock() -> Option<Instant> {
+    (!cfg!(target_arch = "wasm32")).then(Instant::now)
+}
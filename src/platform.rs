@@ -0,0 +1,131 @@
+use chrono::{DateTime, Utc};
+use std::fmt::Debug;
+use std::io;
+use std::net::{IpAddr, TcpStream, ToSocketAddrs};
+use std::path::Path;
+use std::process::Command;
+
+/// everything instructions can reach outside of funge-space,
+/// so an embedder (or a browser) decides what a program gets to touch
+///
+/// failures come back as errors, which the instruction turns into a reflect
+pub trait Platform: Debug {
+    /// run a shell command for '=' and return its exit code
+    fn execute(&mut self, cmd: &str) -> io::Result<i32>;
+    /// read a whole file for 'i'
+    fn read_file(&mut self, path: &str) -> io::Result<String>;
+    /// write a whole file for 'o', replacing what was there
+    fn write_file(&mut self, path: &str, text: &str) -> io::Result<()>;
+    /// environment variables reported by 'y'
+    fn env(&self) -> Vec<(String, String)>;
+    /// more arguments reported by 'y', after the program's name and the
+    /// ones it was given on the command line
+    fn args(&self) -> Vec<String> {
+        vec![]
+    }
+    /// the current time, read by 'y'
+    fn clock(&self) -> DateTime<Utc>;
+    /// look up the addresses of a host name for SCKE's 'P'
+    fn resolve(&mut self, _host: &str) -> io::Result<Vec<IpAddr>> {
+        Err(denied())
+    }
+    /// whether a socket has data waiting, without reading it or waiting for
+    /// any, for SCKE's 'H'
+    fn peek(&mut self, _socket: &TcpStream) -> io::Result<bool> {
+        Err(denied())
+    }
+}
+
+/// the real filesystem, shell, environment and clock
+#[derive(Debug, Default, Clone, Copy)]
+pub struct Native;
+impl Platform for Native {
+    fn execute(&mut self, cmd: &str) -> io::Result<i32> {
+        let status = Command::new("cmd.exe").args(["/c", cmd]).status()?;
+        Ok(status.code().unwrap_or_default())
+    }
+    fn read_file(&mut self, path: &str) -> io::Result<String> {
+        std::fs::read_to_string(path)
+    }
+    fn write_file(&mut self, path: &str, text: &str) -> io::Result<()> {
+        let path = Path::new(path);
+        if path
+            .metadata()
+            .is_ok_and(|meta| meta.permissions().readonly())
+        {
+            return Err(io::Error::new(
+                io::ErrorKind::PermissionDenied,
+                "file is read-only",
+            ));
+        }
+        std::fs::write(path, text)
+    }
+    fn env(&self) -> Vec<(String, String)> {
+        std::env::vars().collect()
+    }
+    fn clock(&self) -> DateTime<Utc> {
+        Utc::now()
+    }
+    fn resolve(&mut self, host: &str) -> io::Result<Vec<IpAddr>> {
+        Ok((host, 0).to_socket_addrs()?.map(|addr| addr.ip()).collect())
+    }
+    fn peek(&mut self, socket: &TcpStream) -> io::Result<bool> {
+        socket.set_nonblocking(true)?;
+        // a closed socket has nothing to wait for either, reading it just ends
+        let ready = match socket.peek(&mut [0]) {
+            Ok(_) => Ok(true),
+            Err(error) if error.kind() == io::ErrorKind::WouldBlock => Ok(false),
+            Err(error) => Err(error),
+        };
+        socket.set_nonblocking(false)?;
+        ready
+    }
+}
+
+/// no files, no shell, no environment and a clock stuck at the unix epoch,
+/// for running untrusted programs or targets without an OS like wasm32
+///
+/// ```
+/// use refunge::platform::Sandboxed;
+/// use refunge::{Arguments, Befunge};
+///
+/// let args = Arguments { quiet: true, ..Default::default() };
+/// // 'i' reflects and wraps around to "1." instead of reading /etc/passwd and going on to "2."
+/// let mut befunge = Befunge::from_text("0000\"dwssap/cte/\"i2.@.1", args).unwrap();
+/// befunge.set_platform(Sandboxed);
+/// befunge.capture_output();
+/// befunge.run(|| true);
+/// assert_eq!(befunge.output(), "1 ");
+/// ```
+#[derive(Debug, Default, Clone, Copy)]
+pub struct Sandboxed;
+impl Platform for Sandboxed {
+    fn execute(&mut self, _cmd: &str) -> io::Result<i32> {
+        Err(denied())
+    }
+    fn read_file(&mut self, _path: &str) -> io::Result<String> {
+        Err(denied())
+    }
+    fn write_file(&mut self, _path: &str, _text: &str) -> io::Result<()> {
+        Err(denied())
+    }
+    fn env(&self) -> Vec<(String, String)> {
+        vec![]
+    }
+    fn clock(&self) -> DateTime<Utc> {
+        DateTime::UNIX_EPOCH
+    }
+}
+
+fn denied() -> io::Error {
+    io::Error::new(io::ErrorKind::Unsupported, "sandboxed")
+}
+
+/// what a new interpreter gets: the real world, unless there isn't one
+pub fn default_platform() -> Box<dyn Platform> {
+    if cfg!(target_arch = "wasm32") {
+        Box::new(Sandboxed)
+    } else {
+        Box::new(Native)
+    }
+}
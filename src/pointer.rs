@@ -1,16 +1,21 @@
-use crate::befunge::InputType;
+use crate::error::RefungeError;
 use crate::event::Event;
-use crate::grid::FungeGrid;
+use crate::fingerprint::{self, Fingerprint, FingerprintFilter, Tardis};
+use crate::grid::{FungeGrid, Seek};
+use crate::input::InputType;
+use crate::output::Output;
+use crate::session::Environment;
 use crate::stack::FungeStack;
 use crate::stackable::Stackable;
+use crate::stats::Stats;
 use crate::vector::{directions, FungeVector};
-use chrono::{Datelike, Timelike};
+use anyhow::{Context, Result};
+use chrono::{DateTime, Datelike, Timelike};
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+use std::cmp::Ordering;
 use std::collections::VecDeque;
-use std::env::{args, vars};
-use std::fs::{read_to_string, File};
-use std::io::Write;
-use std::path::Path;
-use std::process::Command;
+use std::fmt::{Display, Formatter, Write as _};
 use std::sync::mpsc;
 
 macro_rules! stack_op {
@@ -20,8 +25,54 @@ macro_rules! stack_op {
     }};
 }
 
+/// why an IP turned around without being asked to, see [InstructionPointer::reflect]
+///
+/// ```
+/// use refunge::{Arguments, Befunge};
+///
+/// // ~ with nothing to read, and NULL's A, both count as reflections
+/// for program in ["~.@", "\"LLUN\"4(# A@"] {
+///     let args = Arguments { quiet: true, ..Default::default() };
+///     let mut befunge = Befunge::from_text(program, args).unwrap();
+///     befunge.set_input(&b""[..]);
+///     befunge.run(|| true);
+///     assert_eq!(befunge.stats().reflections, 1);
+///     assert_eq!(befunge.reflected_cells().len(), 1);
+/// }
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Reflection {
+    /// an instruction it doesn't know, or a letter no loaded fingerprint provides
+    Unknown(char),
+    /// an instruction that couldn't do what it was asked, like i with a missing file
+    Failed,
+    /// k or ; went on for more than [MAX_WORK] instructions in one tick
+    TooMuchWork,
+    /// moved past an edge with --wrap-mode none
+    OffEdge,
+    /// & or ~ with no input left to answer it
+    EndOfInput,
+    /// a fingerprint letter that reflects on purpose, like all of NULL's
+    Fingerprint,
+}
+impl Display for Reflection {
+    fn fmt(&self, f: &mut Formatter) -> std::fmt::Result {
+        match self {
+            Reflection::Unknown(c) => write!(f, "unknown instruction {c:?}"),
+            Reflection::Failed => write!(f, "instruction failed"),
+            Reflection::TooMuchWork => write!(f, "too many instructions in one tick"),
+            Reflection::OffEdge => write!(f, "stepped off the edge of the grid"),
+            Reflection::EndOfInput => write!(f, "no input left"),
+            Reflection::Fingerprint => write!(f, "fingerprint reflected"),
+        }
+    }
+}
+
+/// pushes one entry of the 'y' sysinfo report
+type SysInfoCell = Box<dyn Fn(&FungeGrid, &mut InstructionPointer)>;
+
 /// an IP that reads from funge-space and performs instructions to its stack
-#[derive(Debug, Default, Clone)]
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
 pub struct InstructionPointer {
     pub pos: FungeVector,
     pub delta: FungeVector,
@@ -31,6 +82,78 @@ pub struct InstructionPointer {
     pub string_mode: bool,
     pub dead: bool,
     pub first_tick: bool,
+    /// stopped by a pause hook, and not to be stopped again on the same cell
+    #[serde(default)]
+    pub held: bool,
+    /// asked for input with & or ~ and waiting for the answer,
+    /// skipped by every tick until then while the other IPs carry on
+    #[serde(default)]
+    pub waiting: Option<InputType>,
+    /// fingerprints providing each letter A-Z, newest last
+    #[serde(with = "fingerprint::loaded")]
+    pub semantics: [Vec<&'static Fingerprint>; 26],
+    /// which fingerprints '(' may load
+    #[serde(skip)]
+    pub fingerprints: FingerprintFilter,
+    /// where TRDS is set to send this IP
+    #[serde(default)]
+    pub tardis: Tardis,
+    /// whether nonstandard instructions like 'l' run instead of reflecting
+    ///
+    /// ```
+    /// use refunge::{Arguments, Befunge};
+    ///
+    /// for (extensions, output) in [(true, "0 "), (false, "")] {
+    ///     let args = Arguments { quiet: true, extensions, ..Default::default() };
+    ///     let mut befunge = Befunge::from_text("0l.@", args).unwrap();
+    ///     befunge.capture_output();
+    ///     befunge.run(|| true);
+    ///     assert_eq!(befunge.output(), output);
+    /// }
+    /// ```
+    #[serde(skip)]
+    pub extensions: bool,
+    /// why the last instruction reflected, if it did
+    #[serde(skip)]
+    pub reflected: Option<Reflection>,
+    /// what went wrong, when the last instruction reflected because of an error
+    #[serde(skip)]
+    pub failure: Option<String>,
+    /// operations left before stopping partway through an instruction, when
+    /// the tick is bounded, see [Befunge::tick_bounded](crate::Befunge::tick_bounded)
+    #[serde(skip)]
+    pub(crate) fuel: Option<u32>,
+    /// what's left of an instruction that ran out of fuel, for the next call to carry on with
+    #[serde(skip)]
+    pub(crate) unfinished: Option<Unfinished>,
+    /// whether having a zero delta has been logged yet, which only happens once
+    #[serde(skip)]
+    pub(crate) zero_delta_logged: bool,
+    /// whether a 'j' going nowhere for having a zero delta has been logged yet, likewise
+    #[serde(skip)]
+    pub(crate) zero_jump_logged: bool,
+    /// where the last command ran its first instruction and what it was,
+    /// once past any spaces and ;-regions, for --profile
+    #[serde(skip)]
+    pub(crate) ran: Option<(FungeVector, char)>,
+}
+
+/// the instructions still to run as (instruction, times), and how many have
+/// run so far, kept while an IP is stopped partway through a k or a ;-region
+#[derive(Debug, Clone)]
+pub(crate) struct Unfinished {
+    todo: Vec<(char, u32)>,
+    work: u32,
+}
+
+/// how far skipping spaces and ;-regions got
+enum Skipped {
+    /// the instruction that actually runs
+    Runnable(char),
+    /// nothing but spaces and ;-regions in the ip's path
+    Nothing,
+    /// stopped on the way, with the char to start skipping from again
+    OutOfFuel(char),
 }
 impl InstructionPointer {
     /// create a new instruction pointer with specified pos, direction, and id
@@ -45,15 +168,41 @@ impl InstructionPointer {
         }
     }
 
-    /// move one space forwards, wrapping around if needed
-    pub fn walk(&mut self, grid: &FungeGrid) {
-        self.pos.0 = (self.pos.0 + self.delta.0).rem_euclid(grid.width() as i32);
-        self.pos.1 = (self.pos.1 + self.delta.1).rem_euclid(grid.height() as i32);
+    /// move one space forwards, wrapping around if needed, or
+    /// turning back at the edge of a grid that doesn't wrap
+    pub fn walk(&mut self, grid: &FungeGrid, stats: &mut Stats) {
+        match grid.step(self.pos, self.delta, 1) {
+            Some(pos) => self.pos = pos,
+            None => self.bounce(grid, stats),
+        }
+    }
+    /// whether the IP has a zero delta with nothing in the cell under it that
+    /// could get it moving again or end it, so it runs that cell every tick forever
+    pub fn is_stuck(&self, grid: &FungeGrid) -> bool {
+        if self.dead || self.delta != directions::ORIGIN {
+            return false;
+        }
+        if self.string_mode {
+            return true;
+        }
+        match grid.char_at(self.pos) {
+            '@' | 'q' | '<' | '>' | '^' | 'v' | '_' | '|' | '?' => false,
+            // x pops the new delta, which is zero again when there's nothing but zeros
+            'x' => (0..2).all(|n| self.stacks[0].get(n).is_none_or(|v| *v == 0)),
+            // fingerprints can do anything
+            'A'..='Z' => false,
+            _ => true,
+        }
     }
     /// move one space backwards, wrapping around if needed
     pub fn walk_reverse(&mut self, grid: &FungeGrid) {
-        self.pos.0 = (self.pos.0 - self.delta.0).rem_euclid(grid.width() as i32);
-        self.pos.1 = (self.pos.1 - self.delta.1).rem_euclid(grid.height() as i32);
+        self.pos = grid.wrap(self.pos, -self.delta, 1);
+    }
+    /// reflect off the edge of the grid, onto the cell before it if there is one
+    fn bounce(&mut self, grid: &FungeGrid, stats: &mut Stats) {
+        log::warn!("ip {}: stepped off the edge at {}", self.id, self.pos);
+        self.reflect(Reflection::OffEdge, stats);
+        self.pos = grid.wrap(self.pos, self.delta, 1);
     }
 
     /// get the top value from the stack
@@ -68,46 +217,334 @@ impl InstructionPointer {
     pub fn push<T: Stackable>(&mut self, val: T) {
         T::push(&mut self.stacks[0], val);
     }
+    /// the size of every stack on the stack-stack, TOSS first, as 'y' reports them
+    ///
+    /// ```
+    /// use refunge::{Arguments, Befunge};
+    ///
+    /// // stacks of 5 (1 2 3 and an offset), 4 (4 5 and an offset) and 1 (6) values,
+    /// // asked for on their own and as part of the full report, after the 22 cells before them
+    /// let programs = [
+    ///     ("1230{450{629*y....@".to_string(), "1 4 5 6 "),
+    ///     (format!("1230{{450{{60y{}...@", "$".repeat(22)), "1 4 5 "),
+    /// ];
+    /// for (program, output) in programs {
+    ///     let args = Arguments { quiet: true, ..Default::default() };
+    ///     let mut befunge = Befunge::from_text(&program, args).unwrap();
+    ///     befunge.capture_output();
+    ///     befunge.run(|| true);
+    ///     assert_eq!(befunge.output(), output);
+    /// }
+    /// ```
+    pub fn stack_sizes(&self) -> Vec<i32> {
+        self.stacks.iter().map(|s| s.len() as i32).collect()
+    }
+    /// turn around because something went wrong, counting it and keeping why in reflected
+    pub fn reflect(&mut self, why: Reflection, stats: &mut Stats) {
+        stats.reflections += 1;
+        self.reflected = Some(why);
+        self.delta.invert();
+    }
+    /// reflect because of an error, keeping what it was for the event log
+    pub fn fail(&mut self, error: RefungeError, stats: &mut Stats) {
+        log::warn!("ip {}: {error:#}", self.id);
+        self.failure = Some(format!("{:#}", anyhow::Error::new(error)));
+        self.reflect(Reflection::Failed, stats);
+    }
+    /// use up one operation's worth of fuel, or say there's none left
+    fn burn_fuel(&mut self) -> bool {
+        match &mut self.fuel {
+            Some(0) => false,
+            Some(fuel) => {
+                *fuel -= 1;
+                true
+            }
+            None => true,
+        }
+    }
+    /// pop a count and then that many cells, packed into a fingerprint id
+    fn pop_fingerprint_id(&mut self) -> i32 {
+        let count = self.pop();
+        (0..count).fold(0, |id: i32, _| {
+            id.wrapping_mul(256).wrapping_add(self.pop())
+        })
+    }
 
     /// execute a Funge-98 instruction based on a given character,
     /// requires access to a grid and external outputs
+    ///
+    /// instructions that fail reflect instead of panicking, so an error only
+    /// comes back when the interpreter can't go on (its event channel is gone)
+    ///
+    /// ```
+    /// use rand::{Rng, SeedableRng};
+    /// use rand_chacha::ChaCha8Rng;
+    /// use refunge::platform::Sandboxed;
+    /// use refunge::{Arguments, Befunge};
+    ///
+    /// // everything printable, with files, = and the rest of the outside world sandboxed
+    /// let chars: Vec<char> = (' '..='~').collect();
+    /// for seed in 0..20 {
+    ///     let mut rng = ChaCha8Rng::seed_from_u64(seed);
+    ///     let program: Vec<String> = (0..8)
+    ///         .map(|_| (0..16).map(|_| chars[rng.gen_range(0..chars.len())]).collect())
+    ///         .collect();
+    ///     let args = Arguments { quiet: true, max_ticks: Some(10_000), max_ips: Some(100), seed: Some(seed), ..Default::default() };
+    ///     let mut befunge = Befunge::from_text(&program.join("\n"), args).unwrap();
+    ///     befunge.set_platform(Sandboxed);
+    ///     befunge.set_input(&b""[..]);
+    ///     befunge.capture_output();
+    ///     befunge.run(|| true);
+    /// }
+    ///
+    /// // k iterating k, and ;-regions running into more ;-regions, go around a
+    /// // loop instead of recursing, so they can't run out of native stack
+    /// let programs = [
+    ///     format!("\"{}\"{}@", "~".repeat(100), "k".repeat(10_000)),
+    ///     ";;".repeat(10_000),
+    ///     " ".repeat(10_000),
+    /// ];
+    /// for program in programs {
+    ///     let args = Arguments { quiet: true, max_ticks: Some(100), ..Default::default() };
+    ///     let mut befunge = Befunge::from_text(&program, args).unwrap();
+    ///     befunge.capture_output();
+    ///     befunge.run(|| true);
+    /// }
+    ///
+    /// // @ and q repeated by k stop the IP the first time, with the rest left undone
+    /// let args = || Arguments { quiet: true, ..Default::default() };
+    /// let mut befunge = Befunge::from_text("3k@1.@", args()).unwrap();
+    /// befunge.capture_output();
+    /// befunge.run(|| true);
+    /// assert_eq!(befunge.output(), "");
+    /// let log: Vec<String> = befunge.event_log().iter().map(|e| e.to_string()).collect();
+    /// assert_eq!(log, ["tick 1: IP 0 died at (1,0)"]);
+    /// let mut befunge = Befunge::from_text("753kq", args()).unwrap();
+    /// befunge.run(|| true);
+    /// assert_eq!(befunge.exit_code, Some(5));
+    /// ```
     pub fn command(
         &mut self,
         c: char,
         grid: &mut FungeGrid,
         sender: mpsc::Sender<Event>,
-        out: &mut String,
-        quiet: bool,
-    ) {
-        match c {
-            // Space
-            ' ' => {
-                while grid.char_at(self.pos) == ' ' {
-                    self.walk(grid)
+        out: &mut Output,
+        env: &mut Environment,
+        stats: &mut Stats,
+    ) -> Result<()> {
+        // instructions still to run as (instruction, times), so nested k's and
+        // chains of ; and spaces never recurse
+        let (mut todo, mut work) = match self.unfinished.take() {
+            Some(Unfinished { todo, work }) => (todo, work),
+            None => {
+                self.reflected = None;
+                self.failure = None;
+                (vec![(c, 1)], 0)
+            }
+        };
+        while let Some((c, times)) = todo.pop() {
+            let c = match self.skip_to_runnable(c, grid, stats) {
+                Skipped::Runnable(c) if self.burn_fuel() => c,
+                Skipped::Runnable(c) | Skipped::OutOfFuel(c) => {
+                    todo.push((c, times));
+                    self.unfinished = Some(Unfinished { todo, work });
+                    return Ok(());
                 }
-                self.command(grid.char_at(self.pos), grid, sender.clone(), out, quiet);
+                Skipped::Nothing => {
+                    log::warn!("ip {}: no instructions left to run in its path", self.id);
+                    return Ok(());
+                }
+            };
+            if work == 0 {
+                self.ran = Some((self.pos, c));
+            }
+            if times > 1 {
+                todo.push((c, times - 1));
+            }
+            work += 1;
+            if work > MAX_WORK {
+                log::warn!(
+                    "ip {}: gave up after {MAX_WORK} instructions in one tick",
+                    self.id
+                );
+                self.reflect(Reflection::TooMuchWork, stats);
+                return Ok(());
             }
+            stats.instructions += 1;
+            // Iterate
+            if c == 'k' {
+                let n = self.pop();
+                if n == 0 {
+                    self.walk(grid, stats);
+                    continue;
+                }
+                match grid.runnable_char_ahead(self.pos, self.delta) {
+                    ' ' => log::warn!("ip {}: nothing ahead to iterate", self.id),
+                    c if n > 0 => todo.push((c, n as u32)),
+                    _ => {}
+                }
+                continue;
+            }
+            self.execute(c, grid, &sender, out, env, stats)?;
+            // an IP that died or quit is done, whatever a k had left for it
+            if self.dead || c == 'q' {
+                break;
+            }
+        }
+        Ok(())
+    }
+    /// move past the spaces and ;-regions starting at c, to the
+    /// instruction that actually runs, if there is one in the ip's path,
+    /// using up fuel for every jump to the next non-space cell
+    fn skip_to_runnable(&mut self, mut c: char, grid: &FungeGrid, stats: &mut Stats) -> Skipped {
+        // every cell could be passed once outside and once inside a ;-region
+        // before the path starts repeating
+        let mut steps = (2 * grid.width()).saturating_mul(grid.height()) + 2;
+        loop {
+            match c {
+                ' ' if !self.burn_fuel() => return Skipped::OutOfFuel(c),
+                ' ' => {
+                    if let Err(skipped) = self.skip_spaces(grid, stats) {
+                        return skipped;
+                    }
+                }
+                // starting again from ';' inside a region carries on to its end
+                ';' => loop {
+                    if !self.burn_fuel() {
+                        return Skipped::OutOfFuel(';');
+                    }
+                    // move off of the opening semicolon, and then past the closing one
+                    match self.skip_spaces(grid, stats) {
+                        Err(Skipped::OutOfFuel(_)) => return Skipped::OutOfFuel(';'),
+                        Err(skipped) => return skipped,
+                        Ok(()) => {}
+                    }
+                    let Some(left) = steps.checked_sub(1) else {
+                        return Skipped::Nothing;
+                    };
+                    steps = left;
+                    if grid.char_at(self.pos) == ';' {
+                        self.walk(grid, stats);
+                        break;
+                    }
+                },
+                c => return Skipped::Runnable(c),
+            }
+            let Some(left) = steps.checked_sub(1) else {
+                return Skipped::Nothing;
+            };
+            steps = left;
+            c = grid.char_at(self.pos);
+        }
+    }
+    /// move to the next non-space cell ahead, or turn around at an edge that
+    /// doesn't wrap, with the fuel for the jump already used up and a unit more
+    /// for every step past the first when the delta isn't cardinal
+    fn skip_spaces(&mut self, grid: &FungeGrid, stats: &mut Stats) -> Result<(), Skipped> {
+        let steps = self.fuel.map_or(u64::MAX, |fuel| fuel as u64 + 1);
+        match grid.seek_non_space(self.pos, self.delta, steps) {
+            Seek::Found(pos, taken) => {
+                self.pos = pos;
+                if let Some(fuel) = &mut self.fuel {
+                    *fuel -= (taken - 1) as u32;
+                }
+            }
+            Seek::Stopped(pos) => {
+                self.pos = pos;
+                self.fuel = Some(0);
+                return Err(Skipped::OutOfFuel(' '));
+            }
+            Seek::Edge(_) => self.bounce(grid, stats),
+            Seek::Nothing => return Err(Skipped::Nothing),
+        }
+        Ok(())
+    }
+    /// in string mode, push a single space for a run of them, ending up on the
+    /// last one so the next step lands past it, using up a unit of fuel per
+    /// step (or jump) there; false if it ran out on the way
+    pub(crate) fn skip_string_spaces(&mut self, grid: &FungeGrid, stats: &mut Stats) -> bool {
+        let steps = self.fuel.map_or(u64::MAX, u64::from);
+        match grid.seek_non_space(self.pos, self.delta, steps) {
+            Seek::Found(pos, taken) => {
+                self.pos = pos;
+                if let Some(fuel) = &mut self.fuel {
+                    *fuel -= taken as u32;
+                }
+                self.walk_reverse(grid);
+            }
+            Seek::Stopped(pos) => {
+                self.pos = pos;
+                self.fuel = Some(0);
+                return false;
+            }
+            // bounce back onto the cell at the edge, to head back the other way
+            Seek::Edge(pos) => {
+                self.pos = pos;
+                self.bounce(grid, stats);
+                self.walk_reverse(grid);
+            }
+            Seek::Nothing => {}
+        }
+        self.push(32);
+        true
+    }
+    /// run a single instruction, with spaces, ; and k already dealt with
+    fn execute(
+        &mut self,
+        c: char,
+        grid: &mut FungeGrid,
+        sender: &mpsc::Sender<Event>,
+        out: &mut Output,
+        env: &mut Environment,
+        stats: &mut Stats,
+    ) -> Result<()> {
+        match c {
             // Logical Not
             '!' => stack_op!(self; n; if n == 0 {1} else {0}),
             // Enable String mode
             '"' => self.string_mode = true,
             // Trampoline
-            '#' => self.walk(grid),
+            '#' => self.walk(grid, stats),
             // Pop
             '$' => stack_op!(self; _del; ),
             // Remainder
             '%' => stack_op!(self; x, y; y.checked_rem(x).unwrap_or_default()),
             // Input Integer
-            '&' => sender
-                .send(Event::Input(InputType::Number, self.id))
-                .unwrap(),
+            '&' => send(sender, Event::Input(InputType::Number, self.id))?,
             // Fetch Character
             '\'' => {
-                self.walk(grid);
+                self.walk(grid, stats);
                 self.push(grid.char_at(self.pos));
             }
-            // '(' { Fingerprints: Load Semantics }
-            // ')' { Fingerprints: Unload Semantics }
+            // Load Semantics
+            '(' => {
+                let id = self.pop_fingerprint_id();
+                match fingerprint::find(id).filter(|_| self.fingerprints.allows(id)) {
+                    Some(fp) => {
+                        for &(letter, _) in fp.semantics {
+                            self.semantics[letter_index(letter)].push(fp);
+                        }
+                        self.push(id);
+                        self.push(1);
+                    }
+                    None => {
+                        log::debug!("ip {}: cannot load fingerprint {id:#x}", self.id);
+                        self.reflect(Reflection::Failed, stats)
+                    }
+                }
+            }
+            // Unload Semantics
+            ')' => {
+                let id = self.pop_fingerprint_id();
+                match fingerprint::find(id) {
+                    Some(fp) => {
+                        for &(letter, _) in fp.semantics {
+                            self.semantics[letter_index(letter)].pop();
+                        }
+                    }
+                    None => self.reflect(Reflection::Failed, stats),
+                }
+            }
             // Multiply
             '*' => stack_op!(self; x, y; x.saturating_mul(y)),
             // Add
@@ -115,22 +552,14 @@ impl InstructionPointer {
             // Output Character
             ',' => {
                 let c: char = self.pop_t();
-                if quiet {
-                    print!("{c}");
-                } else {
-                    out.push(c);
-                }
+                out.write_char(c).unwrap_or(());
             }
             // Subtract
             '-' => stack_op!(self; x, y; y.saturating_sub(x)),
             // Output Integer
             '.' => {
                 let n = self.pop();
-                if quiet {
-                    print!("{n} ");
-                } else {
-                    out.push_str(&format!("{n} "));
-                }
+                write!(out, "{n} ").unwrap_or(());
             }
             // Divide
             '/' => stack_op!(self; x, y; y.checked_div(x).unwrap_or_default()),
@@ -138,36 +567,47 @@ impl InstructionPointer {
             '0'..='9' => stack_op!(self; ; c.to_digit(10).unwrap() as i32),
             // Duplicate
             ':' => stack_op!(self; n; n, n),
-            // Jump Over
-            ';' => {
-                self.walk(grid); // move off of current semicolon
-                while grid.char_at(self.pos) != ';' {
-                    self.walk(grid);
-                }
-                self.walk(grid);
-                self.command(grid.char_at(self.pos), grid, sender.clone(), out, quiet);
-            }
             // Go West
             '<' => self.delta = directions::WEST,
             // Execute
             '=' => {
                 let cmd: String = self.pop_t();
-                self.push(
-                    Command::new("cmd.exe")
-                        .args(vec!["/c", &cmd])
-                        .status()
-                        .expect("failed to execute")
-                        .code()
-                        .unwrap_or_default(),
-                );
+                match env.platform().execute(&cmd) {
+                    Ok(code) => self.push(code),
+                    Err(err) => {
+                        #[cfg(feature = "tracing")]
+                        tracing::warn!(ip = self.id, command = %cmd, error = %err, "execute failed, reflecting");
+                        let error = RefungeError::Exec {
+                            command: cmd,
+                            error: err,
+                        };
+                        self.fail(error, stats)
+                    }
+                }
             }
             // Go East
             '>' => self.delta = directions::EAST,
             // Go Away
-            '?' => self.delta = rand::random(),
+            '?' => self.delta = env.rng().gen(),
             // Stop
             '@' => self.dead = true,
-            // 'A'...'Z' { Fingerprints }
+            // Fingerprint Semantics
+            'A'..='Z' => match self.semantics[letter_index(c)]
+                .last()
+                .and_then(|fp| fp.semantic(c))
+            {
+                Some(semantic) => semantic(
+                    self,
+                    &mut fingerprint::Context {
+                        grid,
+                        out,
+                        env,
+                        stats,
+                        sender,
+                    },
+                ),
+                None => self.reflect(Reflection::Unknown(c), stats),
+            },
             // Turn Left
             '[' => self.delta.turn_left(),
             // Swap
@@ -189,48 +629,59 @@ impl InstructionPointer {
             // Hexadecimal Literals
             'a'..='f' => stack_op!(self; ; c.to_digit(16).unwrap() as i32),
             // Get
-            'g' => stack_op!(self; y, x; grid.char_at(FungeVector(x, y))),
+            'g' => stack_op!(self; y, x; grid.cell_at(FungeVector(x, y))),
             // 'h' { Trefunge: Go High }
             // Input File
             'i' => {
                 let filename: String = self.pop_t();
                 let flags = self.pop();
                 let pos: FungeVector = self.pop_t();
-                if !Path::new(&filename).exists() {
-                    self.delta.invert()
-                } else {
-                    let text = read_to_string(filename).unwrap_or_default();
-                    grid.place(text, pos, flags & 1 != 0);
-                }
-            }
-            // Jump Forward
-            'j' => {
-                let n = self.pop();
-                if n < 0 {
-                    for _ in 0..n.abs() {
-                        self.walk_reverse(grid)
+                #[cfg(feature = "tracing")]
+                tracing::debug!(ip = self.id, file = %filename, x = pos.0, y = pos.1, flags, "input file");
+                match env.platform().read_file(&filename) {
+                    Ok(text) => {
+                        let cells = text.chars().filter(|c| !matches!(c, '\n' | '\r')).count();
+                        if grid.place(text, pos, flags & 1 != 0) {
+                            send(sender, Event::ReadFile(filename, cells))?
+                        } else {
+                            let what = format!("{filename:?} at {pos}");
+                            self.fail(RefungeError::DoesNotFit { what }, stats)
+                        }
                     }
-                } else {
-                    for _ in 0..n {
-                        self.walk(grid)
+                    Err(err) => {
+                        #[cfg(feature = "tracing")]
+                        tracing::warn!(ip = self.id, file = %filename, error = %err, "input file failed, reflecting");
+                        let error = RefungeError::Io {
+                            action: "read",
+                            path: filename,
+                            error: err,
+                        };
+                        self.fail(error, stats)
                     }
                 }
             }
-            // Iterate
-            'k' => {
+            // Jump Forward
+            'j' => {
                 let n = self.pop();
-                if n == 0 {
-                    return self.walk(grid);
+                if self.delta == directions::ORIGIN && n != 0 && !self.zero_jump_logged {
+                    self.zero_jump_logged = true;
+                    log::warn!("ip {}: cannot jump {n} cells with a zero delta", self.id);
                 }
-                let c = grid.runnable_char_ahead(self.pos, self.delta);
-                for _ in 0..n {
-                    self.command(c, grid, sender.clone(), out, quiet)
+                match grid.step(self.pos, self.delta, n as i64) {
+                    Some(pos) => self.pos = pos,
+                    None => {
+                        log::warn!("ip {}: cannot jump {n} cells off the edge", self.id);
+                        self.reflect(Reflection::OffEdge, stats)
+                    }
                 }
             }
             // Lehmer Code Permutation
-            'l' => {
+            'l' if self.extensions => {
                 let n = self.pop();
-                self.stacks[0].permute(n as usize);
+                if let Err(err) = self.stacks[0].permute(n) {
+                    log::warn!("ip {}: cannot permute: {err}", self.id);
+                    self.reflect(Reflection::Failed, stats)
+                }
             }
             // 'm' { Trefunge: High-Low If }
             // Clear Stack
@@ -238,42 +689,48 @@ impl InstructionPointer {
             // Output File
             'o' => {
                 let filename: String = self.pop_t();
-                let path = Path::new(&filename);
                 let flags = self.pop();
                 let v_a: FungeVector = self.pop_t();
                 let v_b: FungeVector = self.pop_t();
-                let mut text = grid.read_from(v_a, v_b);
-                if flags & 1 != 0 {
-                    text = text
-                        .lines()
-                        .map(|l| l.strip_suffix("\r\n").or(l.strip_suffix("\n")).unwrap_or(l))
-                        .collect();
-                    text = text.trim_end().to_string();
-                }
-                if path.exists() && !path.metadata().unwrap().permissions().readonly() {
-                    File::open(path)
-                        .unwrap()
-                        .write_all(text.as_bytes())
-                        .unwrap();
-                } else if !path.exists() {
-                    File::create(path)
-                        .unwrap()
-                        .write_all(text.as_bytes())
-                        .unwrap();
+                #[cfg(feature = "tracing")]
+                tracing::debug!(ip = self.id, file = %filename, flags, "output file");
+                let text = if flags & 1 != 0 {
+                    grid.read_linear(v_a, v_b)
                 } else {
-                    self.delta.invert();
+                    grid.read_from(v_a, v_b)
+                };
+                match env.platform().write_file(&filename, &text) {
+                    Ok(()) => {
+                        let cells = text.chars().filter(|c| *c != '\n').count();
+                        send(sender, Event::WroteFile(filename, cells))?
+                    }
+                    Err(err) => {
+                        #[cfg(feature = "tracing")]
+                        tracing::warn!(ip = self.id, file = %filename, error = %err, "output file failed, reflecting");
+                        let error = RefungeError::Io {
+                            action: "write",
+                            path: filename,
+                            error: err,
+                        };
+                        self.fail(error, stats);
+                    }
                 }
             }
             // Put
             'p' => {
                 let pos: FungeVector = self.pop_t();
-                let c: char = self.pop_t();
-                grid.set_char(pos + self.offset, c);
+                let value = self.pop();
+                // negative cells are dropped, but too far the other way reflects
+                let pos = pos + self.offset;
+                if !pos.is_negative() && !grid.set_cell(pos, value) {
+                    let what = format!("a cell at {pos}");
+                    self.fail(RefungeError::DoesNotFit { what }, stats)
+                }
             }
             // Quit
             'q' => {
                 let code = self.pop();
-                sender.send(Event::Kill(code)).unwrap()
+                send(sender, Event::Kill(code))?
             }
             // Reflect
             'r' => self.delta.invert(),
@@ -282,26 +739,25 @@ impl InstructionPointer {
                 let c: char = self.pop_t();
                 let pos = grid.cell_ahead_ip(self);
                 grid.set_char(pos, c);
-                self.walk(grid);
+                self.walk(grid, stats);
             }
             // Split
-            't' => sender.send(Event::Spawn(self.id)).unwrap(),
+            't' => send(sender, Event::Spawn(self.id))?,
             // Stack under Stack
             'u' => {
                 if self.stacks.len() == 1 {
-                    return self.delta.invert();
+                    self.reflect(Reflection::Failed, stats);
+                    return Ok(());
                 }
+                // cells move over one at a time, so they end up reversed
                 let count = self.pop();
+                let moved = count.unsigned_abs() as usize;
                 if count > 0 {
-                    for _ in 0..count {
-                        let elem = self.stacks[1].pop();
-                        self.push(elem);
-                    }
+                    let elems = self.stacks[1].pop_many(moved);
+                    self.stacks[0].push_iter(elems);
                 } else if count < 0 {
-                    for _ in 0..count.abs() {
-                        let elem = self.pop();
-                        self.stacks[1].push(elem);
-                    }
+                    let elems = self.stacks[0].pop_many(moved);
+                    self.stacks[1].push_iter(elems);
                 }
             }
             // Go South
@@ -309,33 +765,37 @@ impl InstructionPointer {
             // Compare
             'w' => {
                 let (b, a) = (self.pop(), self.pop());
-                if a < b {
-                    self.delta.turn_left()
-                } else if a > b {
-                    self.delta.turn_right()
-                };
+                match a.cmp(&b) {
+                    Ordering::Less => self.delta.turn_left(),
+                    Ordering::Greater => self.delta.turn_right(),
+                    Ordering::Equal => {}
+                }
             }
             // Absolute Delta
             'x' => self.delta = self.pop_t(),
             // Get SysInfo
             'y' => {
                 let n = self.pop();
-                let info: Vec<Box<fn(&FungeGrid, &mut InstructionPointer)>> = vec![
-                    // 1: flags: getch, =, o, i, t
-                    Box::new(|_, ip| ip.push(0b11111)),
+                // only read the clock when asked for it so recordings stay small
+                let now = match n {
+                    ..=0 | 15 | 16 => env.now(),
+                    _ => DateTime::UNIX_EPOCH,
+                };
+                let args = [env.args().to_vec(), env.platform().args()].concat();
+                let vars = env.platform().env();
+                let (handprint, version) = env.identity();
+                let flags = 0b1111 | (env.unbuffered_input() as i32) << 4;
+                // taken before anything gets pushed, so the full report doesn't count itself
+                let stack_sizes = self.stack_sizes();
+                let info: Vec<SysInfoCell> = vec![
+                    // 1: flags: unbuffered ~, =, o, i, t
+                    Box::new(move |_, ip| ip.push(flags)),
                     // 2: bytes per cell
                     Box::new(|_, ip| ip.push(std::mem::size_of::<i32>() as i32)),
-                    // 3: handprint           R  F  N  G
-                    Box::new(|_, ip| ip.push(0x52_46_4E_47)),
+                    // 3: handprint
+                    Box::new(move |_, ip| ip.push(handprint)),
                     // 4: version number
-                    Box::new(|_, ip| {
-                        ip.push(
-                            env!("CARGO_PKG_VERSION")
-                                .replace(".", "")
-                                .parse::<i32>()
-                                .unwrap(),
-                        )
-                    }),
+                    Box::new(move |_, ip| ip.push(version)),
                     // 5: how does "=" work
                     Box::new(|_, ip| ip.push(1)),
                     // 6: path separator
@@ -357,8 +817,7 @@ impl InstructionPointer {
                     // 14: greatest point
                     Box::new(|g, ip| ip.push(FungeVector(g.width() as i32, g.height() as i32 + 1))),
                     // 15: ((year - 1900) * 256 * 256) + (month * 256) + (day of month)
-                    Box::new(|_, ip| {
-                        let now = chrono::Utc::now();
+                    Box::new(move |_, ip| {
                         ip.push(
                             ((now.year() - 1900) * 256 * 256)
                                 + (now.month() as i32 * 256)
@@ -366,8 +825,7 @@ impl InstructionPointer {
                         )
                     }),
                     // 16: (hour * 256 * 256) + (minute * 256) + (second)
-                    Box::new(|_, ip| {
-                        let now = chrono::Utc::now();
+                    Box::new(move |_, ip| {
                         ip.push(
                             now.hour() as i32 * 256 * 256
                                 + now.minute() as i32 * 256
@@ -376,25 +834,24 @@ impl InstructionPointer {
                     }),
                     // 17: size of stack-stack
                     Box::new(|_, ip| ip.push(ip.stacks.len() as i32)),
-                    // 18: size of stack
-                    Box::new(|_, ip| {
-                        let stack_lens = ip
-                            .stacks
-                            .iter()
-                            .map(|s| s.len() as i32)
-                            .collect::<Vec<i32>>();
-                        for len in stack_lens {
-                            ip.push(len);
-                        }
+                    // 18: size of each stack, TOSS first
+                    Box::new(move |_, ip| {
+                        ip.stacks[0].push_iter(stack_sizes.iter().rev().copied())
                     }),
-                    // 19: program arguments as 0gnirts, with another nul at end
-                    Box::new(|_, ip| {
-                        ip.push(args().collect::<Vec<String>>().join("\x00") + "\x00\x00")
+                    // 19: program name and args as 0gnirts, with another nul at end
+                    Box::new(move |g, ip| {
+                        ip.push(
+                            std::iter::once(g.name().to_string())
+                                .chain(args.iter().cloned())
+                                .collect::<Vec<String>>()
+                                .join("\x00")
+                                + "\x00\x00",
+                        )
                     }),
                     // 20: env vars as key=val 0nigrts, with another null at end
-                    Box::new(|_, ip| {
+                    Box::new(move |_, ip| {
                         ip.push(
-                            vars()
+                            vars.iter()
                                 .map(|(k, v)| format!("{k}={v}"))
                                 .collect::<Vec<String>>()
                                 .join("\x00")
@@ -405,9 +862,11 @@ impl InstructionPointer {
                 match n {
                     ..=0 => info.iter().rev().for_each(|i| i(grid, self)),
                     1..=20 => info[n as usize - 1](grid, self),
-                    21.. => (0..n - 20).for_each(|_| {
-                        self.pop();
-                    }),
+                    // popping an empty stack does nothing, so stop there
+                    21.. => {
+                        let count = (n as usize - 20).min(self.stacks[0].len());
+                        self.stacks[0].pop_many(count);
+                    }
                 }
             }
             // No-Op
@@ -416,18 +875,13 @@ impl InstructionPointer {
             '{' => {
                 let n = self.pop();
                 self.stacks.push_front(FungeStack::default());
+                let count = n.unsigned_abs() as usize;
                 match n.signum() {
                     1 => {
-                        let elems: Vec<i32> = (0..n).map(|_| self.stacks[1].pop()).collect();
-                        for val in elems.iter().rev() {
-                            self.push(*val)
-                        }
-                    }
-                    -1 => {
-                        for _ in 0..n.abs() {
-                            self.stacks[1].push(0)
-                        }
+                        let elems = self.stacks[1].pop_many(count);
+                        self.stacks[0].push_iter(elems.into_iter().rev());
                     }
+                    -1 => self.stacks[1].push_iter(std::iter::repeat_n(0, count)),
                     _ => {}
                 }
                 self.stacks[1].push(self.offset.0);
@@ -445,27 +899,67 @@ impl InstructionPointer {
             // End Block
             '}' => {
                 if self.stacks.len() == 1 {
-                    return self.delta.invert();
+                    self.reflect(Reflection::Failed, stats);
+                    return Ok(());
                 }
                 let n = self.pop();
                 (self.offset.1, self.offset.0) = (self.stacks[1].pop(), self.stacks[1].pop());
+                let count = n.unsigned_abs() as usize;
                 if n > 0 {
-                    let elems: Vec<i32> = (0..n).map(|_| self.pop()).collect();
-                    for val in elems.iter().rev() {
-                        self.stacks[1].push(*val)
-                    }
+                    let elems = self.stacks[0].pop_many(count);
+                    self.stacks[1].push_iter(elems.into_iter().rev());
                 } else if n < 0 {
-                    for _ in 0..n.abs() {
-                        self.stacks[1].pop();
-                    }
+                    self.stacks[1].pop_many(count);
                 }
                 self.stacks.pop_front();
             }
             // Input Character
-            '~' => sender
-                .send(Event::Input(InputType::Character, self.id))
-                .unwrap(),
-            _ => self.delta.invert(),
+            '~' => send(sender, Event::Input(InputType::Character, self.id))?,
+            _ => self.reflect(Reflection::Unknown(c), stats),
         }
+        Ok(())
     }
 }
+
+/// most instructions one ip can run in a single tick, counting each round of k
+pub const MAX_WORK: u32 = 1 << 22;
+
+/// hand an event to the interpreter, which only fails once it's gone
+fn send(sender: &mpsc::Sender<Event>, event: Event) -> Result<()> {
+    sender
+        .send(event)
+        .context("interpreter stopped listening for events")
+}
+
+/// the handprint 'y' reports unless --handprint says otherwise
+///                          R  F  N  G
+pub const HANDPRINT: i32 = 0x52_46_4E_47;
+
+/// a version like 1.2.3 as 'y' reports it, major * 1000000 + minor * 1000 + patch,
+/// with anything after a - or + left off and each part capped so it can't run
+/// into the next one (or past i32)
+///
+/// ```
+/// use refunge::pointer::version_number;
+///
+/// assert_eq!(version_number("0.2.2"), 2_002);
+/// assert_eq!(version_number("0.10.0"), 10_000);
+/// assert_eq!(version_number("1.2.3-beta.4+build.5"), 1_002_003);
+/// assert_eq!(version_number("3.1"), 3_001_000);
+/// assert_eq!(version_number("7.2000.4"), 7_999_004);
+/// assert_eq!(version_number("99999.999.999"), 2_146_999_999);
+/// assert_eq!(version_number("not a version"), 0);
+/// ```
+pub fn version_number(version: &str) -> i32 {
+    let release = version.split(['-', '+']).next().unwrap_or_default();
+    let mut parts = release
+        .split('.')
+        .map(|part| part.parse::<i32>().unwrap_or_default());
+    let mut part = |max: i32| parts.next().unwrap_or_default().clamp(0, max);
+    part((i32::MAX - 999_999) / 1_000_000) * 1_000_000 + part(999) * 1000 + part(999)
+}
+
+/// position of an uppercase letter in the alphabet
+fn letter_index(c: char) -> usize {
+    (c as u8 - b'A') as usize
+}
@@ -0,0 +1,76 @@
+use crate::stats::ReflectionRecord;
+use crate::vector::FungeVector;
+use std::collections::HashMap;
+use std::io::{self, Write};
+
+/// execution counts per cell and per instruction, written out by --profile
+#[derive(Debug, Default)]
+pub struct Profiler {
+    /// last character seen and times executed for each cell
+    cells: HashMap<(i32, i32), (char, u64)>,
+    /// times each instruction was executed anywhere
+    instructions: HashMap<char, u64>,
+}
+impl Profiler {
+    /// count one execution of c at pos
+    pub fn record(&mut self, pos: FungeVector, c: char) {
+        let cell = self.cells.entry((pos.0, pos.1)).or_insert((c, 0));
+        *cell = (c, cell.1 + 1);
+        *self.instructions.entry(c).or_insert(0) += 1;
+    }
+    /// write the cell and instruction tables as csv, most executed first,
+    /// then the cells reflected off, most reflected first
+    pub fn write(
+        &self,
+        reflected: &HashMap<FungeVector, ReflectionRecord>,
+        mut w: impl Write,
+    ) -> io::Result<()> {
+        let mut cells: Vec<_> = self.cells.iter().collect();
+        cells.sort_by(|a, b| {
+            b.1 .1
+                .cmp(&a.1 .1)
+                .then(a.0 .1.cmp(&b.0 .1))
+                .then(a.0 .0.cmp(&b.0 .0))
+        });
+        writeln!(w, "x,y,instruction,count,reflections")?;
+        for (&(x, y), (c, count)) in cells {
+            let reflections = reflected
+                .get(&FungeVector(x, y))
+                .map_or(0, |record| record.count);
+            writeln!(w, "{x},{y},{},{count},{reflections}", csv_char(*c))?;
+        }
+        let mut instructions: Vec<_> = self.instructions.iter().collect();
+        instructions.sort_by(|a, b| b.1.cmp(a.1).then(a.0.cmp(b.0)));
+        writeln!(w)?;
+        writeln!(w, "instruction,count")?;
+        for (c, count) in instructions {
+            writeln!(w, "{},{count}", csv_char(*c))?;
+        }
+        let mut reflected: Vec<_> = reflected.iter().collect();
+        reflected.sort_by(|a, b| {
+            b.1.count
+                .cmp(&a.1.count)
+                .then(a.0 .1.cmp(&b.0 .1))
+                .then(a.0 .0.cmp(&b.0 .0))
+        });
+        writeln!(w)?;
+        writeln!(w, "x,y,instruction,first_tick,reflections")?;
+        for (pos, record) in reflected {
+            let c = csv_char(record.instruction);
+            writeln!(
+                w,
+                "{},{},{c},{},{}",
+                pos.0, pos.1, record.first_tick, record.count
+            )?;
+        }
+        Ok(())
+    }
+}
+
+/// quote a character so commas and quotes survive in csv
+fn csv_char(c: char) -> String {
+    match c {
+        '"' => "\"\"\"\"".to_string(),
+        c => format!("\"{c}\""),
+    }
+}
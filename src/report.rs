@@ -0,0 +1,94 @@
+use crate::input::InputType;
+use crate::vector::FungeVector;
+use serde::{Deserialize, Serialize};
+
+/// exit status of a quiet run stopped by --time-limit
+pub const TIMEOUT_EXIT_CODE: i32 = 124;
+/// exit status of a quiet run stopped by --break-on-output
+pub const OUTPUT_MATCH_EXIT_CODE: i32 = 3;
+/// exit status of a quiet run that wrote more than --max-output
+pub const OUTPUT_LIMIT_EXIT_CODE: i32 = 4;
+
+/// everything that happened during one tick, returned by Befunge::step
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TickReport {
+    /// which tick this was, counting from 0
+    pub tick: u64,
+    /// one step for each IP that was alive at the start of the tick
+    pub steps: Vec<IpStep>,
+}
+
+/// how far [Befunge::tick_bounded](crate::Befunge::tick_bounded) got
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TickOutcome {
+    /// the tick is over, with everything that happened during it
+    Completed(TickReport),
+    /// the fuel ran out partway, and the next call carries on from there
+    OutOfFuel,
+}
+
+/// what a single IP did during a tick
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct IpStep {
+    /// id of the IP at the start of the tick
+    pub id: usize,
+    /// the character that was run (or pushed, in string mode)
+    pub instruction: char,
+    /// where the character was
+    pub pos: FungeVector,
+    /// delta after running it
+    pub delta: FungeVector,
+    /// was the character pushed as part of a string
+    pub string_mode: bool,
+    /// cells changed in the grid, in order
+    pub writes: Vec<(FungeVector, char)>,
+    /// text written by , and .
+    pub output: String,
+    /// requests made to the interpreter
+    pub events: Vec<StepEvent>,
+    /// did the IP stop here, with @ or an error
+    #[serde(default)]
+    pub died: bool,
+}
+
+/// a request an IP made while running its instruction
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum StepEvent {
+    /// split off a new IP with t
+    Spawned,
+    /// ended the program with q
+    Quit(i32),
+    /// asked for input with & or ~
+    InputRequested(InputType),
+    /// jumped through space and time with TRDS
+    Jumped,
+    /// stopped time for everyone else with TRDS, or started it again
+    TimeStopped,
+    TimeResumed,
+    /// loaded a file into the grid with i, as (name, cells read)
+    ReadFile(String, usize),
+    /// saved part of the grid with o, as (name, cells written)
+    WroteFile(String, usize),
+}
+
+/// why Befunge::run_until_break stopped
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum RunOutcome {
+    /// every IP has stopped
+    Ended,
+    /// an IP reached a breakpoint (or a pause hook) and is waiting on it
+    BreakpointHit { ip: usize, pos: FungeVector },
+    /// ran out of ticks, or something else like --max-ticks or --time-limit stopped the run
+    MaxTicks,
+    /// an IP is waiting on & or ~, answer it with Befunge::answer_input
+    InputRequested { ip: usize, input: InputType },
+}
+
+/// what Befunge::ticks yields each time it's asked for the next tick
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Tick {
+    /// a tick ran
+    Ran(TickReport),
+    /// an IP is waiting on & or ~, answer it with Befunge::answer_input
+    Input(InputType),
+}
@@ -0,0 +1,286 @@
+use crate::platform::{default_platform, Platform};
+use crate::pointer::{version_number, HANDPRINT};
+use chrono::{DateTime, Utc};
+use rand::SeedableRng;
+use rand_chacha::ChaCha12Rng;
+use serde::{Deserialize, Serialize};
+use std::io;
+use std::net::TcpStream;
+
+/// everything nondeterministic about a run, written by --record and fed back by --replay
+#[derive(Debug, Default, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Session {
+    pub seed: u64,
+    /// values given to & and ~, None where the input was cancelled and reflected
+    pub inputs: Vec<Option<i32>>,
+    /// clock readings taken by y, as unix timestamps
+    pub clock: Vec<i64>,
+}
+
+/// a saved position in the rng stream
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RngState {
+    pub seed: [u8; 32],
+    pub word_pos: u128,
+}
+
+/// where instructions get randomness, the time, and input answers from,
+/// so a whole run can be recorded or replayed
+#[derive(Debug)]
+pub struct Environment {
+    rng: ChaCha12Rng,
+    session: Session,
+    /// keeping inputs and clock readings for --record
+    recording: bool,
+    replaying: bool,
+    next_input: usize,
+    next_clock: usize,
+    diverged: Option<String>,
+    platform: Box<dyn Platform>,
+    /// the handprint and version number 'y' reports
+    identity: (i32, i32),
+    /// whether ~ gets keys as they're pressed, which 'y' reports too
+    unbuffered_input: bool,
+    /// how many ticks back TRDS can jump, which its 'P' reports
+    history: usize,
+    /// the program's arguments from the command line, which 'y' reports after its name
+    args: Vec<String>,
+    /// sockets SCKE can look at, by the number programs know them as
+    sockets: Vec<TcpStream>,
+}
+impl Default for Environment {
+    fn default() -> Environment {
+        Environment::new(0)
+    }
+}
+impl Environment {
+    /// a live environment, keeping track of what it hands out
+    pub fn new(seed: u64) -> Environment {
+        Environment {
+            rng: ChaCha12Rng::seed_from_u64(seed),
+            session: Session {
+                seed,
+                ..Default::default()
+            },
+            recording: false,
+            replaying: false,
+            next_input: 0,
+            next_clock: 0,
+            diverged: None,
+            platform: default_platform(),
+            identity: (HANDPRINT, version_number(env!("CARGO_PKG_VERSION"))),
+            unbuffered_input: false,
+            history: 0,
+            args: vec![],
+            sockets: vec![],
+        }
+    }
+    /// keep every input and clock reading handed out, for saving with --record
+    ///
+    /// ```
+    /// use refunge::session::Environment;
+    ///
+    /// let mut live = Environment::new(3);
+    /// live.record_input(Some(7));
+    /// live.now();
+    /// assert_eq!((live.session().inputs.len(), live.session().clock.len()), (0, 0));
+    /// let mut recording = Environment::new(3).recording(true);
+    /// recording.record_input(Some(7));
+    /// recording.now();
+    /// assert_eq!((recording.session().inputs.len(), recording.session().clock.len()), (1, 1));
+    /// ```
+    pub fn recording(mut self, recording: bool) -> Environment {
+        self.recording = recording;
+        self
+    }
+    /// an environment that plays back a recorded session
+    pub fn replay(session: Session) -> Environment {
+        Environment {
+            rng: ChaCha12Rng::seed_from_u64(session.seed),
+            session,
+            replaying: true,
+            ..Environment::new(0)
+        }
+    }
+    /// start over from the beginning of the run
+    pub fn restart(&mut self) {
+        self.rng = ChaCha12Rng::seed_from_u64(self.session.seed);
+        if self.recording {
+            self.session.inputs.clear();
+            self.session.clock.clear();
+        }
+        self.next_input = 0;
+        self.next_clock = 0;
+        self.diverged = None;
+    }
+    /// the rng behind ?, shared by every IP and seeded by --seed
+    ///
+    /// ```
+    /// use refunge::report::Tick;
+    /// use refunge::{Arguments, Befunge, FungeVector};
+    ///
+    /// // wander between ?s, pushing digits on the way and printing some
+    /// let run = |seed| {
+    ///     let args = Arguments { quiet: true, seed: Some(seed), max_ticks: Some(300), ..Default::default() };
+    ///     let mut befunge = Befunge::from_text("?1?.\n3?4?\n.5?6", args).unwrap();
+    ///     befunge.capture_output();
+    ///     let path: Vec<FungeVector> = befunge
+    ///         .ticks()
+    ///         .filter_map(|tick| match tick {
+    ///             Tick::Ran(report) => Some(report.steps[0].pos),
+    ///             Tick::Input(_) => None,
+    ///         })
+    ///         .collect();
+    ///     (path, befunge.run_result())
+    /// };
+    /// let (path, result) = run(7);
+    /// assert_eq!(path.len(), 300);
+    /// assert!(!result.output.is_empty());
+    /// // the same seed takes the same path, leaving the same output and stacks
+    /// assert_eq!(run(7), (path.clone(), result));
+    /// assert_ne!(run(8).0, path);
+    /// ```
+    pub fn rng(&mut self) -> &mut ChaCha12Rng {
+        &mut self.rng
+    }
+    /// where the rng is, so a snapshot can pick up from the same place
+    pub fn rng_state(&self) -> RngState {
+        RngState {
+            seed: self.rng.get_seed(),
+            word_pos: self.rng.get_word_pos(),
+        }
+    }
+    /// move the rng to a saved state
+    pub fn set_rng_state(&mut self, state: &RngState) {
+        self.rng = ChaCha12Rng::from_seed(state.seed);
+        self.rng.set_word_pos(state.word_pos);
+    }
+    /// files, commands and the environment, as far as the platform allows
+    pub fn platform(&mut self) -> &mut dyn Platform {
+        self.platform.as_mut()
+    }
+    /// swap out what instructions can reach outside of funge-space
+    pub fn set_platform(&mut self, platform: impl Platform + 'static) {
+        self.platform = Box::new(platform);
+    }
+    /// hand over a socket for SCKE, returning the number programs know it as
+    pub fn add_socket(&mut self, socket: TcpStream) -> i32 {
+        self.sockets.push(socket);
+        self.sockets.len() as i32 - 1
+    }
+    /// whether socket n has data waiting, as far as the platform allows
+    pub fn socket_ready(&mut self, n: i32) -> io::Result<bool> {
+        let socket = usize::try_from(n)
+            .ok()
+            .and_then(|n| self.sockets.get(n))
+            .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, format!("no socket {n}")))?;
+        self.platform.peek(socket)
+    }
+    /// does ~ get keys as they're pressed instead of a line at a time
+    pub fn unbuffered_input(&self) -> bool {
+        self.unbuffered_input
+    }
+    /// say whether ~ gets keys as they're pressed, for 'y' to report
+    pub fn set_unbuffered_input(&mut self, unbuffered: bool) {
+        self.unbuffered_input = unbuffered;
+    }
+    /// how many ticks back TRDS can jump
+    pub fn history(&self) -> usize {
+        self.history
+    }
+    /// say how many ticks back TRDS can jump, for its 'P' to report
+    pub fn set_history(&mut self, ticks: usize) {
+        self.history = ticks;
+    }
+    /// the program's arguments, without its name
+    pub fn args(&self) -> &[String] {
+        &self.args
+    }
+    /// give the program arguments, for 'y' to report after its name
+    pub fn set_args(&mut self, args: Vec<String>) {
+        self.args = args;
+    }
+    /// the handprint and version number 'y' reports, as (handprint, version)
+    pub fn identity(&self) -> (i32, i32) {
+        self.identity
+    }
+    /// report a different handprint or version number to 'y', where given
+    ///
+    /// ```
+    /// use refunge::{Arguments, Befunge};
+    ///
+    /// let run = |handprint, report_version| {
+    ///     let args = Arguments { quiet: true, handprint, report_version, ..Default::default() };
+    ///     let mut befunge = Befunge::from_text("3y.4y.@", args).unwrap();
+    ///     befunge.capture_output();
+    ///     befunge.run(|| true);
+    ///     befunge.output().to_string()
+    /// };
+    /// let version = refunge::pointer::version_number(env!("CARGO_PKG_VERSION"));
+    /// assert_eq!(run(None, None), format!("{} {version} ", 0x52464e47));
+    /// assert_eq!(run(Some(0x46424746), None), format!("{} {version} ", 0x46424746));
+    /// assert_eq!(run(None, Some(100)), format!("{} 100 ", 0x52464e47));
+    /// ```
+    pub fn set_identity(&mut self, handprint: Option<i32>, version: Option<i32>) {
+        self.identity = (
+            handprint.unwrap_or(self.identity.0),
+            version.unwrap_or(self.identity.1),
+        );
+    }
+    /// the current time, or the recorded one when replaying
+    pub fn now(&mut self) -> DateTime<Utc> {
+        if !self.replaying {
+            let now = self.platform.clock();
+            if self.recording {
+                self.session.clock.push(now.timestamp());
+            }
+            return now;
+        }
+        let recorded = self.session.clock.get(self.next_clock).copied();
+        self.next_clock += 1;
+        match recorded.and_then(|t| DateTime::from_timestamp(t, 0)) {
+            Some(time) => time,
+            None => {
+                self.diverge("y read the clock more often than recorded");
+                DateTime::UNIX_EPOCH
+            }
+        }
+    }
+    /// is input coming from a recording instead of the user
+    pub fn replaying(&self) -> bool {
+        self.replaying
+    }
+    /// the next recorded input, with the outer None meaning there are none left
+    pub fn replay_input(&mut self) -> Option<Option<i32>> {
+        let recorded = self.session.inputs.get(self.next_input).copied();
+        self.next_input += 1;
+        if recorded.is_none() {
+            self.diverge("the program asked for more input than recorded");
+        }
+        recorded
+    }
+    /// note down an input answer for the recording
+    pub fn record_input(&mut self, val: Option<i32>) {
+        if self.recording {
+            self.session.inputs.push(val);
+        }
+    }
+    /// recorded inputs and clock readings that were never used
+    pub fn unused(&self) -> (usize, usize) {
+        (
+            self.session.inputs.len().saturating_sub(self.next_input),
+            self.session.clock.len().saturating_sub(self.next_clock),
+        )
+    }
+    /// the seed, and everything handed out so far when recording
+    pub fn session(&self) -> &Session {
+        &self.session
+    }
+    /// why the replay stopped matching the recording, if it has
+    pub fn diverged(&self) -> Option<&str> {
+        self.diverged.as_deref()
+    }
+    fn diverge(&mut self, msg: &str) {
+        self.diverged.get_or_insert(msg.to_string());
+    }
+}
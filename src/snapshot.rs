@@ -0,0 +1,96 @@
+use crate::grid::FungeGrid;
+use crate::pointer::InstructionPointer;
+use crate::session::RngState;
+use anyhow::{bail, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+
+/// bumped whenever the snapshot layout changes
+pub const SNAPSHOT_VERSION: u32 = 3;
+
+/// everything needed to pick a run back up where it left off
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BefungeSnapshot {
+    /// SNAPSHOT_VERSION at the time of saving
+    pub version: u32,
+    pub grid: FungeGrid,
+    pub ips: VecDeque<InstructionPointer>,
+    /// program output so far (tui and captured output only)
+    pub output: String,
+    pub ticks: u64,
+    pub rng: RngState,
+    pub exit_code: Option<i32>,
+}
+impl BefungeSnapshot {
+    /// write the snapshot as json
+    pub fn to_json(&self) -> Result<String> {
+        Ok(serde_json::to_string(self)?)
+    }
+    /// read a snapshot back from json, checking the version before anything else
+    ///
+    /// ```
+    /// use refunge::snapshot::BefungeSnapshot;
+    /// use refunge::{Arguments, Befunge, FungeVector};
+    ///
+    /// // a crab outside the basic multilingual plane, in the grid, on the stack and in the output
+    /// let program = "\"\u{1f980}\":,\"!\u{3bb}\",,,@";
+    /// let args = || Arguments { quiet: true, ..Default::default() };
+    /// let mut first = Befunge::from_text(program, args()).unwrap();
+    /// first.capture_output();
+    /// for _ in 0..5 {
+    ///     first.tick();
+    /// }
+    /// let json = first.snapshot().to_json().unwrap();
+    /// let saved = BefungeSnapshot::from_json(&json).unwrap();
+    /// assert!(saved == first.snapshot());
+    /// assert_eq!(saved.output, "\u{1f980}");
+    /// assert_eq!(saved.ips[0].stacks[0].len(), 1);
+    /// assert_eq!(saved.grid.char_at(FungeVector(1, 0)), '\u{1f980}');
+    ///
+    /// let mut second = Befunge::from_text("@", args()).unwrap();
+    /// second.capture_output();
+    /// second.restore(saved).unwrap();
+    /// first.run(|| true);
+    /// second.run(|| true);
+    /// assert_eq!(second.output(), "\u{1f980}\u{3bb}!\u{1f980}");
+    /// assert_eq!(second.output(), first.output());
+    ///
+    /// let mut newer: serde_json::Value = serde_json::from_str(&json).unwrap();
+    /// newer["version"] = 99.into();
+    /// let error = BefungeSnapshot::from_json(&newer.to_string()).unwrap_err();
+    /// assert!(error.to_string().starts_with("snapshot format version 99 is not supported"));
+    /// ```
+    pub fn from_json(text: &str) -> Result<BefungeSnapshot> {
+        #[derive(Deserialize)]
+        struct Version {
+            version: u32,
+        }
+        check_version(serde_json::from_str::<Version>(text)?.version)?;
+        Ok(serde_json::from_str(text)?)
+    }
+}
+
+/// snapshots are equal when they'd carry on the same way: the same cells in
+/// the grid (however it's kept), the same IPs down to their stacks, and the
+/// same output, tick, rng and exit code
+impl PartialEq for BefungeSnapshot {
+    fn eq(&self, other: &BefungeSnapshot) -> bool {
+        let ips = |snapshot: &BefungeSnapshot| serde_json::to_string(&snapshot.ips).ok();
+        self.version == other.version
+            && self.grid == other.grid
+            && self.output == other.output
+            && self.ticks == other.ticks
+            && self.rng == other.rng
+            && self.exit_code == other.exit_code
+            && ips(self) == ips(other)
+    }
+}
+impl Eq for BefungeSnapshot {}
+
+/// complain about snapshots from another version of refunge
+pub fn check_version(version: u32) -> Result<()> {
+    if version != SNAPSHOT_VERSION {
+        bail!("snapshot format version {version} is not supported (expected {SNAPSHOT_VERSION})");
+    }
+    Ok(())
+}
@@ -0,0 +1,84 @@
+use crate::arguments::Arguments;
+use std::fmt::{Display, Formatter};
+use std::fs::{metadata, read_to_string};
+use std::io::{self, Read};
+use std::time::SystemTime;
+
+/// where the program text gets loaded from
+#[derive(Debug, Clone, Default)]
+pub enum Source {
+    /// a file on disk
+    File(String),
+    /// standard input, read up to EOF
+    #[default]
+    Stdin,
+    /// code given directly with -e, one line per flag
+    Eval(String),
+}
+impl Source {
+    /// pick the source named by the command line arguments
+    ///
+    /// ```
+    /// use refunge::source::Source;
+    /// use refunge::{Arguments, Befunge};
+    ///
+    /// // piped in with -
+    /// let args = Arguments { file: Some("-".to_string()), ..Default::default() };
+    /// let source = Source::new(&args);
+    /// assert_eq!(source.to_string(), "<stdin>");
+    /// assert_eq!(source.read_from(&b"64+\"!ih\">:#,_@\n"[..]).unwrap(), "64+\"!ih\">:#,_@\n");
+    ///
+    /// // read from a file
+    /// let path = std::env::temp_dir().join("refunge-doctest-source.bf");
+    /// std::fs::write(&path, "7.@").unwrap();
+    /// let file = path.to_string_lossy().into_owned();
+    /// let args = Arguments { quiet: true, file: Some(file.clone()), ..Default::default() };
+    /// assert_eq!(Source::new(&args).to_string(), file);
+    /// let mut befunge = Befunge::new(args).unwrap();
+    /// befunge.capture_output();
+    /// befunge.run(|| true);
+    /// assert_eq!(befunge.output(), "7 ");
+    /// ```
+    pub fn new(args: &Arguments) -> Source {
+        if !args.eval.is_empty() {
+            return Source::Eval(args.eval.join("\n"));
+        }
+        match args.file.as_deref() {
+            Some("-") => Source::Stdin,
+            path => Source::File(path.unwrap_or_default().to_string()),
+        }
+    }
+    /// read the entire program text
+    pub fn read(&self) -> io::Result<String> {
+        self.read_from(io::stdin())
+    }
+    /// read the entire program text, taking it from stdin when that's where
+    /// it comes from
+    pub fn read_from(&self, mut stdin: impl Read) -> io::Result<String> {
+        match self {
+            Source::File(path) => read_to_string(path),
+            Source::Stdin => {
+                let mut text = String::new();
+                stdin.read_to_string(&mut text)?;
+                Ok(text)
+            }
+            Source::Eval(code) => Ok(code.clone()),
+        }
+    }
+    /// when the source file was last changed, none for stdin and eval
+    pub fn modified(&self) -> Option<SystemTime> {
+        match self {
+            Source::File(path) => metadata(path).and_then(|m| m.modified()).ok(),
+            _ => None,
+        }
+    }
+}
+impl Display for Source {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Source::File(path) => write!(f, "{path}"),
+            Source::Stdin => write!(f, "<stdin>"),
+            Source::Eval(_) => write!(f, "<eval>"),
+        }
+    }
+}
@@ -0,0 +1,392 @@
+//! where the cells of a [FungeGrid](crate::FungeGrid) are kept: rows of cells
+//! for ordinary programs, or a map of just the non-space ones for programs
+//! that write far out into funge-space
+
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::sync::OnceLock;
+
+/// storage for the cells of a grid, which are spaces until set
+///
+/// the bounds start out as the size of the loaded text and grow to take in
+/// every cell that gets set, and anything outside of them is a space
+///
+/// ```
+/// use refunge::space::{DenseGrid, FungeSpace, SparseGrid};
+///
+/// // both backings have to agree on everything
+/// fn check(space: &mut impl FungeSpace) -> Vec<String> {
+///     let mut seen = vec![];
+///     space.reset("ab\n\n  c");
+///     seen.push(format!("{:?} {:?}", space.bounds(), space.read_region(0, 2, 0, 2)));
+///     seen.push(format!("{} {:?}", space.set(6, 1, 'x'), space.bounds()));
+///     seen.push(format!("{:?}", space.place(1, 2, "yz ")));
+///     seen.push(format!("{:?}", space.read_region(1, 3, 1, 2)));
+///     seen.push(format!("{}{}{}", space.get(0, 0), space.get(6, 1), space.get(50, 50)));
+///     for (x, y) in [(0, 0), (1, 0), (6, 1), (3, 3)] {
+///         seen.push(format!(
+///             "{:?} {:?} {:?} {:?}",
+///             space.next_in_row(x, y, true),
+///             space.next_in_row(x, y, false),
+///             space.next_in_col(x, y, true),
+///             space.next_in_col(x, y, false),
+///         ));
+///     }
+///     space.set(0, 0, ' ');
+///     seen.push(format!("{:?} {:?}", space.next_in_row(0, 0, true), space.bounds()));
+///     space.reset("q");
+///     seen.push(format!("{:?} {:?}", space.bounds(), space.read_region(0, 0, 0, 0)));
+///     seen
+/// }
+/// assert_eq!(check(&mut DenseGrid::default()), check(&mut SparseGrid::default()));
+/// ```
+pub trait FungeSpace {
+    /// the cell at (x, y), which is a space outside the bounds
+    fn get(&self, x: usize, y: usize) -> char;
+    /// change the cell at (x, y), growing the bounds to take it in,
+    /// and hand back what was there
+    fn set(&mut self, x: usize, y: usize, c: char) -> char;
+    /// the width and height of the area cells can have been set in
+    fn bounds(&self) -> (usize, usize);
+    /// the rows top..=bottom of the cells left..=right
+    fn read_region(&self, left: usize, right: usize, top: usize, bottom: usize) -> Vec<Vec<char>>;
+    /// write a line of text from (x, y) going right, handing back what each cell held
+    fn place(&mut self, x: usize, y: usize, line: &str) -> Vec<char> {
+        line.chars()
+            .enumerate()
+            .map(|(dx, c)| self.set(x + dx, y, c))
+            .collect()
+    }
+    /// throw everything away and load some text, a row for each line
+    fn reset(&mut self, text: &str);
+    /// x of the next non-space cell on row y after x (or before it, going
+    /// backwards), wrapping around, which is x itself if it's the only one
+    fn next_in_row(&self, x: usize, y: usize, forwards: bool) -> Option<usize>;
+    /// y of the next non-space cell in column x, like next_in_row
+    fn next_in_col(&self, x: usize, y: usize, forwards: bool) -> Option<usize>;
+    /// how many cells would be kept in memory once (x, y) is set, if that's
+    /// a number that fits at all
+    fn footprint_with(&self, x: usize, y: usize) -> Option<usize>;
+    /// drop every cell past width x height, going back to a smaller size
+    fn truncate(&mut self, width: usize, height: usize);
+    /// every cell that isn't a space as (x, y), row by row
+    fn non_spaces(&self) -> Vec<(usize, usize)> {
+        let (width, height) = self.bounds();
+        let mut cells = vec![];
+        for y in 0..height {
+            let mut next = self.next_in_row(width, y, true);
+            while let Some(x) = next {
+                cells.push((x, y));
+                next = self.next_in_row(x, y, true).filter(|next| *next > x);
+            }
+        }
+        cells
+    }
+}
+
+/// every cell of the bounds, row by row, which is quickest for small programs
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+pub struct DenseGrid {
+    chars: Vec<Vec<char>>,
+    width: usize,
+    height: usize,
+    /// where the non-space cells are, built the first time something skips
+    #[serde(skip)]
+    skips: OnceLock<SkipIndex>,
+}
+impl DenseGrid {
+    /// load some text, a row for each line padded out to the longest
+    pub fn new(text: &str) -> DenseGrid {
+        let mut grid = DenseGrid::default();
+        grid.reset(text);
+        grid
+    }
+    /// make room for (x, y), padding with spaces
+    fn grow(&mut self, x: usize, y: usize) {
+        if x < self.width && y < self.height {
+            return;
+        }
+        let (width, height) = (self.width.max(x + 1), self.height.max(y + 1));
+        for row in &mut self.chars {
+            row.resize(width, ' ');
+        }
+        self.chars.resize(height, vec![' '; width]);
+        (self.width, self.height) = (width, height);
+        if let Some(skips) = self.skips.get_mut() {
+            skips.resize(width, height);
+        }
+    }
+    fn skips(&self) -> &SkipIndex {
+        self.skips
+            .get_or_init(|| SkipIndex::new(self.width, self.height))
+    }
+}
+impl FungeSpace for DenseGrid {
+    fn get(&self, x: usize, y: usize) -> char {
+        self.chars
+            .get(y)
+            .and_then(|row| row.get(x))
+            .copied()
+            .unwrap_or(' ')
+    }
+    fn set(&mut self, x: usize, y: usize, c: char) -> char {
+        self.grow(x, y);
+        if let Some(skips) = self.skips.get_mut() {
+            skips.set(x, y, c);
+        }
+        std::mem::replace(&mut self.chars[y][x], c)
+    }
+    fn bounds(&self) -> (usize, usize) {
+        (self.width, self.height)
+    }
+    fn read_region(&self, left: usize, right: usize, top: usize, bottom: usize) -> Vec<Vec<char>> {
+        (top..=bottom)
+            .map(|y| (left..=right).map(|x| self.get(x, y)).collect())
+            .collect()
+    }
+    fn place(&mut self, x: usize, y: usize, line: &str) -> Vec<char> {
+        let len = line.chars().count();
+        if len > 0 {
+            self.grow(x + len - 1, y);
+        }
+        let skips = self.skips.get_mut();
+        let old = self.chars[y][x..]
+            .iter_mut()
+            .zip(line.chars())
+            .map(|(cell, c)| std::mem::replace(cell, c))
+            .collect();
+        if let Some(skips) = skips {
+            for (dx, c) in line.chars().enumerate() {
+                skips.set(x + dx, y, c);
+            }
+        }
+        old
+    }
+    fn reset(&mut self, text: &str) {
+        self.width = text
+            .lines()
+            .map(|line| line.chars().count())
+            .max()
+            .unwrap_or(0);
+        self.chars = text
+            .lines()
+            .map(|line| {
+                let mut row = Vec::with_capacity(self.width);
+                row.extend(line.chars());
+                row.resize(self.width, ' ');
+                row
+            })
+            .collect();
+        self.height = self.chars.len();
+        self.skips = OnceLock::new();
+    }
+    fn next_in_row(&self, x: usize, y: usize, forwards: bool) -> Option<usize> {
+        next_in(self.skips().row(&self.chars, y)?, x, forwards)
+    }
+    fn next_in_col(&self, x: usize, y: usize, forwards: bool) -> Option<usize> {
+        next_in(self.skips().col(&self.chars, x)?, y, forwards)
+    }
+    fn footprint_with(&self, x: usize, y: usize) -> Option<usize> {
+        self.width
+            .max(x.checked_add(1)?)
+            .checked_mul(self.height.max(y.checked_add(1)?))
+    }
+    fn truncate(&mut self, width: usize, height: usize) {
+        let (width, height) = (self.width.min(width), self.height.min(height));
+        self.chars.truncate(height);
+        for row in &mut self.chars {
+            row.truncate(width);
+        }
+        (self.width, self.height) = (width, height);
+        self.skips = OnceLock::new();
+    }
+}
+
+/// only the non-space cells, for programs that spread out over a huge area
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+#[serde(from = "SavedSparse", into = "SavedSparse")]
+pub struct SparseGrid {
+    cells: HashMap<(usize, usize), char>,
+    /// x of every non-space cell, per row that has any
+    rows: BTreeMap<usize, BTreeSet<usize>>,
+    /// y of every non-space cell, per column that has any
+    cols: BTreeMap<usize, BTreeSet<usize>>,
+    width: usize,
+    height: usize,
+}
+impl SparseGrid {
+    /// load some text, keeping only the cells that aren't spaces
+    pub fn new(text: &str) -> SparseGrid {
+        let mut grid = SparseGrid::default();
+        grid.reset(text);
+        grid
+    }
+}
+impl FungeSpace for SparseGrid {
+    fn get(&self, x: usize, y: usize) -> char {
+        self.cells.get(&(x, y)).copied().unwrap_or(' ')
+    }
+    fn set(&mut self, x: usize, y: usize, c: char) -> char {
+        (self.width, self.height) = (self.width.max(x + 1), self.height.max(y + 1));
+        if c == ' ' {
+            let line_emptied = |lines: &mut BTreeMap<usize, BTreeSet<usize>>, at, n| {
+                if let Some(set) = lines.get_mut(&at) {
+                    set.remove(&n);
+                    if set.is_empty() {
+                        lines.remove(&at);
+                    }
+                }
+            };
+            line_emptied(&mut self.rows, y, x);
+            line_emptied(&mut self.cols, x, y);
+            return self.cells.remove(&(x, y)).unwrap_or(' ');
+        }
+        self.rows.entry(y).or_default().insert(x);
+        self.cols.entry(x).or_default().insert(y);
+        self.cells.insert((x, y), c).unwrap_or(' ')
+    }
+    fn bounds(&self) -> (usize, usize) {
+        (self.width, self.height)
+    }
+    fn read_region(&self, left: usize, right: usize, top: usize, bottom: usize) -> Vec<Vec<char>> {
+        (top..=bottom)
+            .map(|y| {
+                let mut row = vec![' '; right + 1 - left];
+                for &x in self
+                    .rows
+                    .get(&y)
+                    .into_iter()
+                    .flat_map(|xs| xs.range(left..=right))
+                {
+                    row[x - left] = self.cells[&(x, y)];
+                }
+                row
+            })
+            .collect()
+    }
+    fn reset(&mut self, text: &str) {
+        *self = SparseGrid::default();
+        self.width = text
+            .lines()
+            .map(|line| line.chars().count())
+            .max()
+            .unwrap_or(0);
+        self.height = text.lines().count();
+        for (y, line) in text.lines().enumerate() {
+            for (x, c) in line.chars().enumerate().filter(|(_, c)| *c != ' ') {
+                self.set(x, y, c);
+            }
+        }
+    }
+    fn next_in_row(&self, x: usize, y: usize, forwards: bool) -> Option<usize> {
+        next_in(self.rows.get(&y)?, x, forwards)
+    }
+    fn next_in_col(&self, x: usize, y: usize, forwards: bool) -> Option<usize> {
+        next_in(self.cols.get(&x)?, y, forwards)
+    }
+    fn footprint_with(&self, x: usize, y: usize) -> Option<usize> {
+        x.checked_add(1)?;
+        y.checked_add(1)?;
+        Some(self.cells.len() + !self.cells.contains_key(&(x, y)) as usize)
+    }
+    fn truncate(&mut self, width: usize, height: usize) {
+        let (width, height) = (self.width.min(width), self.height.min(height));
+        self.cells.retain(|&(x, y), _| x < width && y < height);
+        self.rows.split_off(&height);
+        self.cols.split_off(&width);
+        for xs in self.rows.values_mut() {
+            xs.split_off(&width);
+        }
+        for ys in self.cols.values_mut() {
+            ys.split_off(&height);
+        }
+        self.rows.retain(|_, xs| !xs.is_empty());
+        self.cols.retain(|_, ys| !ys.is_empty());
+        (self.width, self.height) = (width, height);
+    }
+}
+
+/// a sparse grid as it's saved in a snapshot, with the indexes left to be rebuilt
+#[derive(Serialize, Deserialize)]
+struct SavedSparse {
+    width: usize,
+    height: usize,
+    /// every non-space cell, top to bottom
+    cells: Vec<((usize, usize), char)>,
+}
+impl From<SparseGrid> for SavedSparse {
+    fn from(grid: SparseGrid) -> SavedSparse {
+        let mut cells: Vec<_> = grid.cells.into_iter().collect();
+        cells.sort_by_key(|((x, y), _)| (*y, *x));
+        SavedSparse {
+            width: grid.width,
+            height: grid.height,
+            cells,
+        }
+    }
+}
+impl From<SavedSparse> for SparseGrid {
+    fn from(saved: SavedSparse) -> SparseGrid {
+        let mut grid = SparseGrid::default();
+        for ((x, y), c) in saved.cells {
+            grid.set(x, y, c);
+        }
+        (grid.width, grid.height) = (saved.width, saved.height);
+        grid
+    }
+}
+
+/// the non-space cells of each row and column of a dense grid, so skipping
+/// over a wide gap of spaces is a lookup instead of a walk
+///
+/// each row and column is only indexed once something skips along it,
+/// so dense grids that never skip far don't pay for it
+#[derive(Debug, Default, Clone)]
+struct SkipIndex {
+    /// x of every non-space cell, per row
+    rows: Vec<OnceLock<BTreeSet<usize>>>,
+    /// y of every non-space cell, per column
+    cols: Vec<OnceLock<BTreeSet<usize>>>,
+}
+impl SkipIndex {
+    fn new(width: usize, height: usize) -> SkipIndex {
+        let mut index = SkipIndex::default();
+        index.resize(width, height);
+        index
+    }
+    fn resize(&mut self, width: usize, height: usize) {
+        self.rows.resize_with(height, OnceLock::new);
+        self.cols.resize_with(width, OnceLock::new);
+    }
+    fn row(&self, chars: &[Vec<char>], y: usize) -> Option<&BTreeSet<usize>> {
+        let row = self.rows.get(y)?;
+        Some(row.get_or_init(|| {
+            (0..chars[y].len())
+                .filter(|&x| chars[y][x] != ' ')
+                .collect()
+        }))
+    }
+    fn col(&self, chars: &[Vec<char>], x: usize) -> Option<&BTreeSet<usize>> {
+        let col = self.cols.get(x)?;
+        Some(col.get_or_init(|| (0..chars.len()).filter(|&y| chars[y][x] != ' ').collect()))
+    }
+    /// keep whichever of the cell's row and column are indexed up to date
+    fn set(&mut self, x: usize, y: usize, c: char) {
+        for (set, n) in [(self.rows[y].get_mut(), x), (self.cols[x].get_mut(), y)] {
+            match set {
+                Some(set) if c == ' ' => set.remove(&n),
+                Some(set) => set.insert(n),
+                None => false,
+            };
+        }
+    }
+}
+
+/// the first entry after n going forwards (or backwards), wrapping around
+fn next_in(set: &BTreeSet<usize>, n: usize, forwards: bool) -> Option<usize> {
+    if forwards {
+        set.range(n + 1..).next().or(set.first()).copied()
+    } else {
+        set.range(..n).next_back().or(set.last()).copied()
+    }
+}
@@ -1,47 +1,88 @@
+use anyhow::{bail, Result};
+#[cfg(feature = "tui")]
 use ratatui::prelude::{Constraint, Layout, Rect};
-use ratatui::text::Line;
-use ratatui::widgets::{Block, Borders, Paragraph};
+#[cfg(feature = "tui")]
+use ratatui::text::{Line, Span};
+#[cfg(feature = "tui")]
+use ratatui::widgets::{Block, Paragraph};
+#[cfg(feature = "tui")]
 use ratatui::Frame;
+use serde::{Deserialize, Serialize};
 use std::collections::{vec_deque, VecDeque};
+#[cfg(feature = "tui")]
+use std::fmt::Display;
 use std::fmt::{Debug, Formatter};
 use std::ops::{Index, IndexMut};
 
 /// a wrapper around VecDeque with queue/invert mode functionality
 /// additionality defaults all pops to zero on empty stacks
-#[derive(Default, Clone)]
-pub struct FungeStack {
-    inner: VecDeque<i32>,
+///
+/// cells are i32 unless asked otherwise, anything with a zero-like default works:
+///
+/// ```
+/// use refunge::FungeStack;
+///
+/// let mut stack: FungeStack<i64> = FungeStack::from([i64::MAX, 1]);
+/// stack.roll(1);
+/// assert_eq!(stack.pop_many(3), [i64::MAX, 1, 0]);
+/// ```
+#[derive(Default, Clone, Serialize, Deserialize)]
+pub struct FungeStack<T = i32> {
+    inner: VecDeque<T>,
     /// toggles which end of the stack is popped from
     pub queue_mode: bool,
     /// toggles which end of the stack is pushed to
     pub invert_mode: bool,
 }
 
-impl FungeStack {
+impl<T> FungeStack<T> {
     /// clear the entire stack
     pub fn clear(&mut self) {
         self.inner.clear();
     }
     /// push a value onto the stack
-    pub fn push(&mut self, val: i32) {
+    pub fn push(&mut self, val: T) {
         if self.invert_mode {
             self.inner.push_front(val)
         } else {
             self.inner.push_back(val)
         }
     }
-
     /// returns the number of values in the stack
     pub fn len(&self) -> usize {
         self.inner.len()
     }
+    /// returns true if there are no values in the stack
+    pub fn is_empty(&self) -> bool {
+        self.inner.is_empty()
+    }
     /// returns a bottom-to-top iterator
-    pub fn iter(&self) -> vec_deque::Iter<'_, i32> {
+    pub fn iter(&self) -> vec_deque::Iter<'_, T> {
         self.inner.iter()
     }
+    /// the value n below the top, if the stack is that deep
+    pub fn get(&self, n: usize) -> Option<&T> {
+        self.pick_index(n).map(|idx| &self.inner[idx])
+    }
+    /// push every value in order, so the last one ends up on top
+    pub fn push_iter(&mut self, values: impl IntoIterator<Item = T>) {
+        values.into_iter().for_each(|val| self.push(val));
+    }
+    /// where the value n below the top lives in the inner deque
+    fn pick_index(&self, n: usize) -> Option<usize> {
+        if n >= self.len() {
+            None
+        } else if self.queue_mode {
+            Some(n)
+        } else {
+            Some(self.len() - 1 - n)
+        }
+    }
+}
 
+impl<T: Clone + Default> FungeStack<T> {
     /// pop a value from the stack (0 when empty)
-    pub fn pop(&mut self) -> i32 {
+    pub fn pop(&mut self) -> T {
         if self.queue_mode {
             self.inner.pop_front().unwrap_or_default()
         } else {
@@ -49,15 +90,225 @@ impl FungeStack {
         }
     }
 
-    /// rearrange the stack based on a lehmer code
-    pub fn permute(&mut self, p: usize) {
-        let perm = lehmer::Lehmer::from_decimal(p, self.len()).to_permutation();
-        let og = self.inner.clone();
-        self.inner = perm.iter().map(|idx| og[*idx as usize]).collect();
+    /// pop n values, top first (missing values are zero)
+    ///
+    /// ```
+    /// use refunge::FungeStack;
+    ///
+    /// let mut stack = FungeStack::default();
+    /// stack.push_iter([1, 2, 3]);
+    /// assert_eq!(stack.pop_many(4), [3, 2, 1, 0]);
+    ///
+    /// // whatever gets pushed comes back reversed, in every mode
+    /// for (queue_mode, invert_mode) in [(false, false), (true, false), (false, true), (true, true)] {
+    ///     let mut stack = FungeStack::default();
+    ///     (stack.queue_mode, stack.invert_mode) = (queue_mode, invert_mode);
+    ///     let values: Vec<i32> = (0..20).map(|n| n * 7 - 50).collect();
+    ///     stack.push_iter(values.clone());
+    ///     let mut popped = stack.pop_many(values.len());
+    ///     if queue_mode == invert_mode {
+    ///         popped.reverse();
+    ///     }
+    ///     assert_eq!(popped, values);
+    /// }
+    /// ```
+    pub fn pop_many(&mut self, n: usize) -> Vec<T> {
+        (0..n).map(|_| self.pop()).collect()
+    }
+    /// the top value without popping it (0 when empty)
+    pub fn peek(&self) -> T {
+        self.pick_index(0)
+            .map(|idx| self.inner[idx].clone())
+            .unwrap_or_default()
+    }
+    /// push a copy of the value n below the top, so `pick(0)` duplicates it
+    ///
+    /// ```
+    /// use refunge::FungeStack;
+    ///
+    /// let mut stack = FungeStack::from([1, 2, 3]);
+    /// stack.pick(2);
+    /// stack.pick(0);
+    /// stack.pick(9);
+    /// assert_eq!(stack.pop_many(6), [0, 1, 1, 3, 2, 1]);
+    /// ```
+    pub fn pick(&mut self, n: usize) {
+        let val = self
+            .pick_index(n)
+            .map(|idx| self.inner[idx].clone())
+            .unwrap_or_default();
+        self.push(val);
+    }
+    /// move the value n below the top up to the top, shifting the rest down,
+    /// or with a negative n sink the top value down to that depth instead
+    ///
+    /// rolling past the bottom brings up an implied 0 or sinks to the bottom,
+    /// so `roll(n)` only undoes itself after n + 1 rolls when the stack is deep enough
+    ///
+    /// ```
+    /// use refunge::FungeStack;
+    ///
+    /// let mut stack = FungeStack::from([1, 2, 3, 4]);
+    /// stack.roll(2);
+    /// assert_eq!(stack.iter().copied().collect::<Vec<_>>(), [1, 3, 4, 2]);
+    /// stack.roll(-2);
+    /// assert_eq!(stack.iter().copied().collect::<Vec<_>>(), [1, 2, 3, 4]);
+    ///
+    /// for queue_mode in [false, true] {
+    ///     for n in 0..8 {
+    ///         let mut stack = FungeStack::from([5, 6, 7, 8, 9, 10, 11, 12]);
+    ///         stack.queue_mode = queue_mode;
+    ///         let before: Vec<i32> = stack.iter().copied().collect();
+    ///         (0..=n).for_each(|_| stack.roll(n as i32));
+    ///         assert_eq!(stack.iter().copied().collect::<Vec<_>>(), before);
+    ///     }
+    /// }
+    /// ```
+    pub fn roll(&mut self, n: i32) {
+        let depth = n.unsigned_abs() as usize;
+        if self.is_empty() {
+            if n > 0 {
+                self.inner.push_back(T::default());
+            }
+            return;
+        }
+        let deepest = self.len() - 1;
+        let top = self.pick_index(0).unwrap_or_default();
+        if n >= 0 {
+            let val = match self.pick_index(depth) {
+                Some(idx) => self.inner.remove(idx).unwrap_or_default(),
+                None => T::default(),
+            };
+            if self.queue_mode {
+                self.inner.push_front(val)
+            } else {
+                self.inner.push_back(val)
+            }
+        } else {
+            let val = self.inner.remove(top).unwrap_or_default();
+            let depth = depth.min(deepest);
+            let idx = if self.queue_mode {
+                depth
+            } else {
+                self.len() - depth
+            };
+            self.inner.insert(idx, val);
+        }
+    }
+
+    /// rearrange the stack based on the nth lehmer code,
+    /// failing (and leaving the stack alone) for stacks deeper than
+    /// [MAX_PERMUTE_LEN] or codes past the last permutation
+    ///
+    /// ```
+    /// use refunge::FungeStack;
+    ///
+    /// let mut stack = FungeStack::from([1, 2, 3, 4, 5]);
+    /// stack.permute(0).unwrap();
+    /// assert_eq!(stack.iter().copied().collect::<Vec<_>>(), [1, 2, 3, 4, 5]);
+    /// stack.permute(1).unwrap();
+    /// assert_eq!(stack.iter().copied().collect::<Vec<_>>(), [1, 2, 3, 5, 4]);
+    /// let mut stack = FungeStack::from([1, 2, 3, 4, 5]);
+    /// stack.permute(100).unwrap();
+    /// assert_eq!(stack.iter().copied().collect::<Vec<_>>(), [5, 1, 4, 2, 3]);
+    /// assert!(stack.permute(120).is_err());
+    /// assert!(stack.permute(-1).is_err());
+    ///
+    /// let mut deep = FungeStack::from([0; 25]);
+    /// assert!(deep.permute(0).is_err());
+    /// ```
+    pub fn permute(&mut self, p: i32) -> Result<()> {
+        let n = self.len();
+        if n > MAX_PERMUTE_LEN {
+            bail!("can't permute {n} values, at most {MAX_PERMUTE_LEN} can be");
+        }
+        let Ok(mut code) = u128::try_from(p) else {
+            bail!("lehmer code {p} is negative");
+        };
+        if factorial(n).is_none_or(|count| code >= count) {
+            bail!("lehmer code {p} is past the last permutation of {n} values");
+        }
+        let mut left: Vec<T> = self.inner.drain(..).collect();
+        for i in (0..n).rev() {
+            let place = factorial(i).unwrap_or(1);
+            self.inner.push_back(left.remove((code / place) as usize));
+            code %= place;
+        }
+        Ok(())
     }
+}
+
+/// how deep a stack can be and still be permuted
+pub const MAX_PERMUTE_LEN: usize = 20;
+
+/// n!, if it fits
+fn factorial(n: usize) -> Option<u128> {
+    (1..=n as u128).try_fold(1u128, |acc, i| acc.checked_mul(i))
+}
 
-    /// render to a vertical list, bottom to top
-    pub fn render(&self, frame: &mut Frame, area: Rect, max_height: u16, title: impl Into<String>) {
+/// how [FungeStack::render] lays out a stack
+#[cfg(feature = "tui")]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StackLayout {
+    /// one value per line, top first, taking up at least height lines
+    Vertical { height: u16 },
+    /// one line of values, top at the right, scrolled back from there by scroll columns
+    Horizontal { scroll: usize },
+}
+
+#[cfg(feature = "tui")]
+impl<T: Display> FungeStack<T> {
+    /// render inside the given block, one value per line or all of them on one
+    ///
+    /// ```
+    /// use ratatui::widgets::{Block, Borders};
+    /// use ratatui::{backend::TestBackend, Terminal};
+    /// use refunge::stack::StackLayout;
+    /// use refunge::FungeStack;
+    ///
+    /// let stack = FungeStack::from((1..=50).collect::<Vec<i32>>());
+    /// let mut rows = |width: u16, height: u16, layout: StackLayout| {
+    ///     let mut terminal = Terminal::new(TestBackend::new(width, height)).unwrap();
+    ///     terminal
+    ///         .draw(|f| stack.render(f, f.size(), layout, Block::default().borders(Borders::ALL)))
+    ///         .unwrap();
+    ///     let buffer = terminal.backend().buffer();
+    ///     (0..height)
+    ///         .map(|y| (0..width).map(|x| buffer.get(x, y).symbol.as_str()).collect::<String>())
+    ///         .collect::<Vec<_>>()
+    /// };
+    /// // the top of the stack is at the top, and what doesn't fit is cut off below
+    /// let rows_shown = rows(8, 6, StackLayout::Vertical { height: 6 });
+    /// assert_eq!(rows_shown[1..5], ["│50    │", "│49    │", "│48    │", "│47    │"]);
+    /// // or on the right, with whatever doesn't fit scrolled off to the left
+    /// let row = |scroll| rows(20, 3, StackLayout::Horizontal { scroll })[1].clone();
+    /// assert_eq!(row(0), "│ 47 │ 48 │ 49 │ 50│");
+    /// assert_eq!(row(5), "│ 46 │ 47 │ 48 │ 49│");
+    /// assert_eq!(row(500), "│1 │ 2 │ 3 │ 4 │ 5 │");
+    ///
+    /// // rows longer than a u16 can count still end at the top
+    /// let stack = FungeStack::from((1..=15000).collect::<Vec<i32>>());
+    /// let mut terminal = Terminal::new(TestBackend::new(42, 3)).unwrap();
+    /// let layout = StackLayout::Horizontal { scroll: 0 };
+    /// terminal
+    ///     .draw(|f| stack.render(f, f.size(), layout, Block::default().borders(Borders::ALL)))
+    ///     .unwrap();
+    /// let buffer = terminal.backend().buffer();
+    /// let row: String = (1..41).map(|x| buffer.get(x, 1).symbol.as_str()).collect();
+    /// assert_eq!(row, " │ 14996 │ 14997 │ 14998 │ 14999 │ 15000");
+    /// ```
+    pub fn render(&self, frame: &mut Frame, area: Rect, layout: StackLayout, block: Block) {
+        let height = match layout {
+            StackLayout::Vertical { height } => height,
+            StackLayout::Horizontal { scroll } => {
+                let width = block.inner(area).width as usize;
+                let tail = self.row_tail(scroll.saturating_add(width));
+                let end = tail.len() - scroll.min(tail.len().saturating_sub(width));
+                let shown: String = tail[end.saturating_sub(width)..end].iter().collect();
+                frame.render_widget(Paragraph::new(shown).block(block), area);
+                return;
+            }
+        };
         let widget = Paragraph::new(
             self.inner
                 .iter()
@@ -65,52 +316,100 @@ impl FungeStack {
                 .map(|val| Line::from(val.to_string()))
                 .collect::<Vec<Line>>(),
         )
-        .block(Block::default().borders(Borders::ALL).title(title.into()));
+        .block(block);
         let bits = Layout::new()
             .constraints(vec![
-                Constraint::Length((self.len() as u16).max(max_height)),
+                Constraint::Length((self.len() as u16).max(height)),
                 Constraint::Min(0),
             ])
             .split(area);
         frame.render_widget(widget, bits[0]);
     }
+    /// the last len characters of the values on one line, bottom to top, as
+    /// [StackLayout::Horizontal] shows them, formatting only the values that
+    /// reach into them
+    fn row_tail(&self, len: usize) -> Vec<char> {
+        let mut tail = vec![];
+        for (i, val) in self.inner.iter().rev().enumerate() {
+            if tail.len() >= len {
+                break;
+            }
+            if i > 0 {
+                tail.extend(" │ ".chars().rev());
+            }
+            tail.extend(val.to_string().chars().rev());
+        }
+        tail.truncate(len);
+        tail.reverse();
+        tail
+    }
+    /// how many characters long the values are on one line, as
+    /// [StackLayout::Horizontal] shows them
+    ///
+    /// ```
+    /// use refunge::FungeStack;
+    ///
+    /// assert_eq!(FungeStack::from([1, -20, 300]).row_width(), "1 │ -20 │ 300".chars().count());
+    /// assert_eq!(FungeStack::<i32>::default().row_width(), 0);
+    /// ```
+    pub fn row_width(&self) -> usize {
+        let values: usize = self.inner.iter().map(|val| val.to_string().chars().count()).sum();
+        values + 3 * self.len().saturating_sub(1)
+    }
+    /// compact one-line version of [FungeStack::render], the title and then the values top first
+    ///
+    /// ```
+    /// use refunge::FungeStack;
+    ///
+    /// let stack = FungeStack::from([1, 2, 3]);
+    /// let line = stack.render_line("SOSS (3)");
+    /// let text: String = line.spans.iter().map(|span| span.content.as_ref()).collect();
+    /// assert_eq!(text, "SOSS (3): 3 2 1");
+    /// ```
+    pub fn render_line<'t>(&self, title: impl Into<Span<'t>>) -> Line<'t> {
+        let values: Vec<String> = self.inner.iter().rev().map(|val| val.to_string()).collect();
+        Line::from(vec![
+            title.into(),
+            Span::raw(format!(": {}", values.join(" "))),
+        ])
+    }
 }
 
-impl Debug for FungeStack {
+impl<T: Debug> Debug for FungeStack<T> {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
         f.debug_list().entries(&self.inner).finish()
     }
 }
-impl IntoIterator for FungeStack {
-    type Item = i32;
-    type IntoIter = vec_deque::IntoIter<i32>;
+impl<T> IntoIterator for FungeStack<T> {
+    type Item = T;
+    type IntoIter = vec_deque::IntoIter<T>;
 
     fn into_iter(self) -> Self::IntoIter {
         self.inner.into_iter()
     }
 }
-impl<'a> IntoIterator for &'a FungeStack {
-    type Item = &'a i32;
-    type IntoIter = vec_deque::Iter<'a, i32>;
+impl<'a, T> IntoIterator for &'a FungeStack<T> {
+    type Item = &'a T;
+    type IntoIter = vec_deque::Iter<'a, T>;
 
-    fn into_iter(self) -> vec_deque::Iter<'a, i32> {
-        self.iter()
+    fn into_iter(self) -> vec_deque::Iter<'a, T> {
+        self.inner.iter()
     }
 }
-impl Index<usize> for FungeStack {
-    type Output = i32;
+impl<T> Index<usize> for FungeStack<T> {
+    type Output = T;
 
     fn index(&self, index: usize) -> &Self::Output {
         &self.inner[index]
     }
 }
-impl IndexMut<usize> for FungeStack {
+impl<T> IndexMut<usize> for FungeStack<T> {
     fn index_mut(&mut self, index: usize) -> &mut Self::Output {
         &mut self.inner[index]
     }
 }
-impl From<Vec<i32>> for FungeStack {
-    fn from(value: Vec<i32>) -> Self {
+impl<T> From<Vec<T>> for FungeStack<T> {
+    fn from(value: Vec<T>) -> Self {
         FungeStack {
             inner: value.into(),
             queue_mode: false,
@@ -118,8 +417,8 @@ impl From<Vec<i32>> for FungeStack {
         }
     }
 }
-impl<const N: usize> From<[i32; N]> for FungeStack {
-    fn from(value: [i32; N]) -> Self {
+impl<T, const N: usize> From<[T; N]> for FungeStack<T> {
+    fn from(value: [T; N]) -> Self {
         FungeStack {
             inner: value.into(),
             queue_mode: false,
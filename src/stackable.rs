@@ -1,8 +1,9 @@
 use crate::{stack::FungeStack, vector::FungeVector};
 
-pub trait Stackable {
-    fn pop(stack: &mut FungeStack) -> Self;
-    fn push(stack: &mut FungeStack, val: Self);
+/// a value that can be moved on and off a stack of `T` cells
+pub trait Stackable<T = i32> {
+    fn pop(stack: &mut FungeStack<T>) -> Self;
+    fn push(stack: &mut FungeStack<T>, val: Self);
 }
 
 impl Stackable for i32 {
@@ -52,3 +53,90 @@ impl Stackable for FungeVector {
         stack.push(val.1);
     }
 }
+/// two cells, the high half pushed first so the low half ends up on top
+///
+/// ```
+/// use refunge::stackable::Stackable;
+/// use refunge::FungeStack;
+///
+/// let mut stack = FungeStack::default();
+/// i64::push(&mut stack, 0x1_0000_0002);
+/// assert_eq!(stack.iter().copied().collect::<Vec<_>>(), [1, 2]);
+/// assert_eq!(i64::pop(&mut stack), 0x1_0000_0002);
+///
+/// i64::push(&mut stack, -5);
+/// assert_eq!(i64::pop(&mut stack), -5);
+///
+/// // a missing high half reads as 0
+/// stack.push(-1);
+/// assert_eq!(i64::pop(&mut stack), u32::MAX as i64);
+/// ```
+impl Stackable for i64 {
+    fn pop(stack: &mut FungeStack) -> Self {
+        let low = stack.pop() as u32;
+        let high = stack.pop();
+        ((high as i64) << 32) | low as i64
+    }
+
+    fn push(stack: &mut FungeStack, val: Self) {
+        stack.push((val >> 32) as i32);
+        stack.push(val as i32);
+    }
+}
+/// one cell, 0 for false and 1 for true, popping anything nonzero as true
+///
+/// ```
+/// use refunge::stackable::Stackable;
+/// use refunge::FungeStack;
+///
+/// let mut stack = FungeStack::from([7, 0]);
+/// assert!(!bool::pop(&mut stack));
+/// assert!(bool::pop(&mut stack));
+/// assert!(!bool::pop(&mut stack));
+///
+/// bool::push(&mut stack, true);
+/// assert_eq!(stack.pop(), 1);
+/// ```
+impl Stackable for bool {
+    fn pop(stack: &mut FungeStack) -> Self {
+        stack.pop() != 0
+    }
+
+    fn push(stack: &mut FungeStack, val: Self) {
+        stack.push(val as i32)
+    }
+}
+/// the values in order followed by their count on top,
+/// popping gives them back in the order they were pushed
+///
+/// a negative count pops as empty, and a count longer than the stack
+/// is clamped so that only the cells actually there get popped
+///
+/// ```
+/// use refunge::stackable::Stackable;
+/// use refunge::FungeStack;
+///
+/// let mut stack = FungeStack::default();
+/// Stackable::push(&mut stack, vec![4, 5, 6]);
+/// assert_eq!(stack.iter().copied().collect::<Vec<_>>(), [4, 5, 6, 3]);
+/// assert_eq!(<Vec<i32> as Stackable>::pop(&mut stack), [4, 5, 6]);
+/// assert!(stack.is_empty());
+///
+/// let mut short = FungeStack::from([8, 9, 5]);
+/// assert_eq!(<Vec<i32> as Stackable>::pop(&mut short), [8, 9]);
+/// assert!(<Vec<i32> as Stackable>::pop(&mut short).is_empty());
+/// ```
+impl Stackable for Vec<i32> {
+    fn pop(stack: &mut FungeStack) -> Self {
+        let count = (stack.pop().max(0) as usize).min(stack.len());
+        let mut values: Vec<i32> = (0..count).map(|_| stack.pop()).collect();
+        values.reverse();
+        values
+    }
+
+    fn push(stack: &mut FungeStack, val: Self) {
+        let count = val.len() as i32;
+        val.into_iter().for_each(|n| stack.push(n));
+        stack.push(count);
+    }
+}
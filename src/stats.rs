@@ -0,0 +1,111 @@
+use std::fmt::{Display, Formatter};
+use std::time::{Duration, Instant};
+
+/// counters collected while interpreting, reported by --stats
+///
+/// ```
+/// use refunge::{Arguments, Befunge};
+///
+/// let count = |program: &str| {
+///     let args = Arguments { quiet: true, ..Default::default() };
+///     let mut befunge = Befunge::from_text(program, args).unwrap();
+///     befunge.capture_output();
+///     befunge.run(|| true);
+///     let stats = befunge.stats();
+///     (stats.ticks, stats.instructions, stats.max_stack_depth, stats.spawned, stats.reflections)
+/// };
+/// assert_eq!(count("12+.@"), (5, 5, 2, 0, 0));
+/// // spaces and ;-regions take no ticks
+/// assert_eq!(count("1   ;xx; 2+.@"), (5, 5, 2, 0, 0));
+/// // two IPs share each tick once t splits them
+/// assert_eq!(count("0t#X1.@"), (6, 8, 2, 1, 0));
+/// // the unknown X reflects back onto the @
+/// assert_eq!(count("#@X"), (3, 3, 0, 0, 1));
+/// ```
+#[derive(Debug, Clone)]
+pub struct Stats {
+    /// number of calls to tick
+    pub ticks: u64,
+    /// instructions run across all IPs (not counting skipped spaces and ;)
+    pub instructions: u64,
+    /// deepest any single stack has been
+    pub max_stack_depth: usize,
+    /// IPs created by t
+    pub spawned: u64,
+    /// reflections caused by unknown instructions, or ones that failed
+    pub reflections: u64,
+    /// different cells those reflections happened at
+    pub reflecting_cells: usize,
+    /// seed the rng was started with
+    pub seed: u64,
+    /// there's no clock to read on wasm32, so no time there
+    started: Option<Instant>,
+}
+impl Stats {
+    /// fresh counters for a run using the given seed
+    pub fn new(seed: u64) -> Stats {
+        Stats {
+            seed,
+            ..Default::default()
+        }
+    }
+    /// time since the run started
+    pub fn elapsed(&self) -> Duration {
+        self.started
+            .map_or(Duration::ZERO, |started| started.elapsed())
+    }
+    /// average ticks per second since the run started
+    pub fn ticks_per_second(&self) -> f64 {
+        self.ticks as f64 / self.elapsed().as_secs_f64().max(f64::EPSILON)
+    }
+    /// the reflections in a few words, like "3 cells caused 48210 reflections"
+    pub fn reflection_summary(&self) -> String {
+        let plural = |n: u64| if n == 1 { "" } else { "s" };
+        format!(
+            "{} cell{} caused {} reflection{}",
+            self.reflecting_cells,
+            plural(self.reflecting_cells as u64),
+            self.reflections,
+            plural(self.reflections)
+        )
+    }
+}
+impl Default for Stats {
+    fn default() -> Stats {
+        Stats {
+            ticks: 0,
+            instructions: 0,
+            max_stack_depth: 0,
+            spawned: 0,
+            reflections: 0,
+            reflecting_cells: 0,
+            seed: 0,
+            started: (!cfg!(target_arch = "wasm32")).then(Instant::now),
+        }
+    }
+}
+impl Display for Stats {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "ticks:           {}", self.ticks)?;
+        writeln!(f, "instructions:    {}", self.instructions)?;
+        writeln!(f, "time:            {:.3?}", self.elapsed())?;
+        writeln!(f, "ticks/second:    {:.0}", self.ticks_per_second())?;
+        writeln!(f, "max stack depth: {}", self.max_stack_depth)?;
+        writeln!(f, "IPs spawned:     {}", self.spawned)?;
+        writeln!(f, "reflections:     {}", self.reflections)?;
+        writeln!(f, "reflecting cells: {}", self.reflecting_cells)?;
+        write!(f, "seed:            {}", self.seed)
+    }
+}
+
+/// how often IPs reflected off one cell, so a loop that keeps hitting it
+/// only gets warned about once
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ReflectionRecord {
+    /// the tick it first happened on
+    pub first_tick: u64,
+    /// times it's happened, that first one included
+    pub count: u64,
+    /// what was in the cell the first time
+    pub instruction: char,
+}
@@ -0,0 +1,149 @@
+//! newline-delimited json events for `--json`, so tools wrapping refunge can
+//! follow a quiet run without scraping its output
+//!
+//! every line on stdout is one [StreamEvent]. When an IP asks for input, an
+//! `input` event goes out and the next line on stdin answers it: a number for
+//! `&`, a character code for `~`, or `null` (or the end of stdin) to reflect.
+
+use crate::input::InputType;
+use crate::report::{StepEvent, Tick, TickReport};
+use crate::Befunge;
+use anyhow::{anyhow, Result};
+use serde::{Deserialize, Serialize};
+use std::io::BufRead;
+
+/// one line of the --json stream, tagged by its "event" field
+///
+/// IP ids are the ones they had at the start of the tick, and every spawn
+/// shifts the ids from the parent's onwards up by one, like in [TickReport]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum StreamEvent {
+    /// text written by , and .
+    Output { tick: u64, ip: usize, text: String },
+    /// an IP is waiting on & or ~, answered by the next line of stdin
+    Input {
+        tick: u64,
+        ip: usize,
+        kind: InputType,
+    },
+    /// t split off a new IP, which took the parent's id
+    Spawn { tick: u64, ip: usize },
+    /// an IP stopped, with @ or an error
+    Kill { tick: u64, ip: usize },
+    /// the counters --stats would print
+    Stats {
+        ticks: u64,
+        instructions: u64,
+        max_stack_depth: usize,
+        spawned: u64,
+        reflections: u64,
+        reflecting_cells: usize,
+        seed: u64,
+        seconds: f64,
+    },
+    /// always the last event, with the code given to q (or 0), and whether the
+    /// program ended rather than being stopped by something like --max-ticks
+    Exit { code: i32, ended: bool },
+}
+impl StreamEvent {
+    /// the events in a tick, in the order the IPs ran
+    pub fn from_report(report: &TickReport) -> Vec<StreamEvent> {
+        let tick = report.tick;
+        let mut events = vec![];
+        for step in &report.steps {
+            let ip = step.id;
+            if !step.output.is_empty() {
+                let text = step.output.clone();
+                events.push(StreamEvent::Output { tick, ip, text });
+            }
+            for event in &step.events {
+                if *event == StepEvent::Spawned {
+                    events.push(StreamEvent::Spawn { tick, ip });
+                }
+            }
+            if step.died {
+                events.push(StreamEvent::Kill { tick, ip });
+            }
+        }
+        events
+    }
+    /// the event as one line of json
+    pub fn to_json(&self) -> String {
+        serde_json::to_string(self).expect("stream events are plain numbers and strings")
+    }
+}
+
+/// run a program until it stops, handing each event to emit and answering
+/// input from answers, one line at a time
+///
+/// ```
+/// use refunge::stream::{self, StreamEvent};
+/// use refunge::{Arguments, Befunge};
+///
+/// // 't' sends a new ip 0 west around to the '@' while ip 1 asks for a number
+/// let args = Arguments { quiet: true, json: true, ..Default::default() };
+/// let mut befunge = Befunge::from_text("t&.@", args).unwrap();
+/// let mut lines = vec![];
+/// stream::run(&mut befunge, &b"41\n"[..], |event| lines.push(event.to_json())).unwrap();
+///
+/// let events: Vec<StreamEvent> = lines.iter().map(|line| serde_json::from_str(line).unwrap()).collect();
+/// let output: String = events
+///     .iter()
+///     .filter_map(|event| match event {
+///         StreamEvent::Output { text, .. } => Some(text.as_str()),
+///         _ => None,
+///     })
+///     .collect();
+/// assert_eq!(output, "41 ");
+/// assert_eq!(lines[0], r#"{"event":"spawn","tick":0,"ip":0}"#);
+/// assert_eq!(lines[1], r#"{"event":"kill","tick":1,"ip":0}"#);
+/// assert_eq!(lines[2], r#"{"event":"input","tick":1,"ip":1,"kind":"Number"}"#);
+/// assert!(matches!(events[events.len() - 2], StreamEvent::Stats { ticks: 4, spawned: 1, .. }));
+/// assert_eq!(lines.last().unwrap(), r#"{"event":"exit","code":0,"ended":true}"#);
+/// ```
+pub fn run(
+    befunge: &mut Befunge,
+    mut answers: impl BufRead,
+    mut emit: impl FnMut(&StreamEvent),
+) -> Result<()> {
+    loop {
+        for tick in befunge.ticks() {
+            if let Tick::Ran(report) = tick {
+                StreamEvent::from_report(&report).iter().for_each(&mut emit);
+            }
+        }
+        let Some((ip, kind)) = befunge.waiting_for_input() else {
+            break;
+        };
+        let tick = befunge.stats().ticks.saturating_sub(1);
+        emit(&StreamEvent::Input { tick, ip, kind });
+        let mut line = String::new();
+        let answer = match answers.read_line(&mut line)? {
+            0 => None,
+            _ => serde_json::from_str::<Option<i32>>(line.trim()).map_err(|_| {
+                anyhow!(
+                    "expected a number or null to answer input, got {:?}",
+                    line.trim()
+                )
+            })?,
+        };
+        befunge.answer_input(answer);
+    }
+    let stats = befunge.stats();
+    emit(&StreamEvent::Stats {
+        ticks: stats.ticks,
+        instructions: stats.instructions,
+        max_stack_depth: stats.max_stack_depth,
+        spawned: stats.spawned,
+        reflections: stats.reflections,
+        reflecting_cells: stats.reflecting_cells,
+        seed: stats.seed,
+        seconds: stats.elapsed().as_secs_f64(),
+    });
+    emit(&StreamEvent::Exit {
+        code: befunge.exit_code.unwrap_or(0),
+        ended: befunge.ended(),
+    });
+    Ok(())
+}
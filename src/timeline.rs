@@ -0,0 +1,113 @@
+use crate::pointer::Reflection;
+use crate::vector::FungeVector;
+use std::collections::VecDeque;
+use std::fmt::{Display, Formatter};
+
+/// how many entries the event log holds before dropping the oldest
+pub const EVENT_LOG_LIMIT: usize = 1000;
+
+/// something an IP did that's worth seeing on a timeline
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Happening {
+    /// split off the IP with this id, starting where the t was
+    Spawned(usize, FungeVector),
+    /// stopped at this cell, by @ or an error
+    Died(FungeVector),
+    /// ended the program with q
+    Quit(i32),
+    /// loaded a file into the grid with i, as (name, cells read)
+    ReadFile(String, usize),
+    /// saved part of the grid with o, as (name, cells written)
+    WroteFile(String, usize),
+    /// bounced off an instruction it couldn't run at this cell, and why
+    Reflected(FungeVector, Reflection),
+    /// the error behind an instruction failing, like a file i couldn't read
+    ///
+    /// ```
+    /// use refunge::timeline::Happening;
+    /// use refunge::{Arguments, Befunge};
+    ///
+    /// let mut befunge = Befunge::from_text("0000\"fb.gnissim\"i@", Arguments::default()).unwrap();
+    /// for _ in 0..20 {
+    ///     befunge.step();
+    /// }
+    /// let failure = befunge.event_log().iter().find(|e| matches!(e.what, Happening::Failed(_)));
+    /// assert_eq!(
+    ///     failure.unwrap().to_string(),
+    ///     "tick 16: IP 0 failed: failed to read missing.bf: No such file or directory (os error 2)"
+    /// );
+    /// ```
+    Failed(String),
+}
+
+impl Display for Happening {
+    fn fmt(&self, f: &mut Formatter) -> std::fmt::Result {
+        match self {
+            Happening::Spawned(id, pos) => write!(f, "spawned IP {id} at {pos}"),
+            Happening::Died(pos) => write!(f, "died at {pos}"),
+            Happening::Quit(code) => write!(f, "quit with exit code {code}"),
+            Happening::ReadFile(name, cells) => write!(f, "read file {name} ({cells} cells)"),
+            Happening::WroteFile(name, cells) => write!(f, "wrote file {name} ({cells} cells)"),
+            Happening::Reflected(pos, why) => write!(f, "reflected at {pos}: {why}"),
+            Happening::Failed(error) => write!(f, "failed: {error}"),
+        }
+    }
+}
+
+/// a happening, along with when and to whom
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LogEntry {
+    pub tick: u64,
+    /// id of the IP at the time
+    pub ip: usize,
+    pub what: Happening,
+}
+impl Display for LogEntry {
+    fn fmt(&self, f: &mut Formatter) -> std::fmt::Result {
+        write!(f, "tick {}: IP {} {}", self.tick, self.ip, self.what)
+    }
+}
+
+/// the most recent spawns, deaths, file I/O and reflections, oldest first,
+/// allocated once up front so recording doesn't cost anything at full speed
+#[derive(Debug)]
+pub struct EventLog {
+    entries: VecDeque<LogEntry>,
+    /// entries ever pushed, counting dropped ones
+    total: u64,
+}
+impl Default for EventLog {
+    fn default() -> EventLog {
+        EventLog {
+            entries: VecDeque::with_capacity(EVENT_LOG_LIMIT),
+            total: 0,
+        }
+    }
+}
+impl EventLog {
+    /// add an entry, dropping the oldest one if the log is full
+    pub fn push(&mut self, tick: u64, ip: usize, what: Happening) {
+        if self.entries.len() == EVENT_LOG_LIMIT {
+            self.entries.pop_front();
+        }
+        self.entries.push_back(LogEntry { tick, ip, what });
+        self.total += 1;
+    }
+    /// how many entries were ever pushed, counting the ones since dropped
+    pub fn total(&self) -> u64 {
+        self.total
+    }
+    pub fn iter(&self) -> impl DoubleEndedIterator<Item = &LogEntry> + ExactSizeIterator {
+        self.entries.iter()
+    }
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+    pub fn clear(&mut self) {
+        self.entries.clear();
+        self.total = 0;
+    }
+}
@@ -1,38 +1,121 @@
 use rand::distributions::{Distribution, Standard};
 use rand::Rng;
-use std::ops::{Add, AddAssign};
+use serde::{Deserialize, Serialize};
+use std::fmt::{Display, Formatter};
+use std::ops::{Add, AddAssign, Mul, Neg, Sub, SubAssign};
 
 /// represents a 2-dimensional vector with integer coordinates
-#[derive(Debug, Clone, Copy, PartialEq)]
+///
+/// arithmetic wraps around on overflow, like funge cells do
+///
+/// ```
+/// use refunge::FungeVector;
+///
+/// let v = FungeVector(3, -2);
+/// assert_eq!(v + FungeVector(1, 1), FungeVector(4, -1));
+/// assert_eq!(v - FungeVector(1, 1), FungeVector(2, -3));
+/// assert_eq!(-v, FungeVector(-3, 2));
+/// assert_eq!(v * -2, FungeVector(-6, 4));
+/// assert_eq!(-v * 3, FungeVector(-9, 6));
+/// assert_eq!(v.to_string(), "(3,-2)");
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub struct FungeVector(pub i32, pub i32);
 impl FungeVector {
     /// negate each dimension
     pub fn invert(&mut self) {
-        self.0 *= -1;
-        self.1 *= -1;
+        *self = -*self;
     }
     /// return the result of rotating this vector 90 degrees counterclockwise
+    ///
+    /// ```
+    /// use refunge::FungeVector;
+    ///
+    /// let v = FungeVector(3, -2);
+    /// assert_eq!(v.left().left().left().left(), v);
+    /// assert_eq!(v.right().right().right().right(), v);
+    /// assert_eq!(v.left().right(), v);
+    /// assert_eq!(v.left().left(), -v);
+    /// ```
     pub fn left(&self) -> Self {
-        FungeVector(self.1, -self.0)
+        FungeVector(self.1, self.0.wrapping_neg())
     }
     /// rotate this vector 90 degrees counterclockwise
     pub fn turn_left(&mut self) {
-        std::mem::swap(&mut self.0, &mut self.1);
-        self.1 *= -1;
+        *self = self.left();
     }
     /// return the result of rotating this vector 90 degrees clockwise
     pub fn right(&self) -> Self {
-        FungeVector(-self.1, self.0)
+        FungeVector(self.1.wrapping_neg(), self.0)
     }
     /// rotate this vector 90 degrees clockwise
     pub fn turn_right(&mut self) {
-        std::mem::swap(&mut self.0, &mut self.1);
-        self.0 *= -1;
+        *self = self.right();
     }
     /// returns true if either coordinate is less than zero
     pub fn is_negative(&self) -> bool {
         self.0 < 0 || self.1 < 0
     }
+    /// returns true for the four unit directions
+    ///
+    /// ```
+    /// use refunge::vector::directions::{EAST, NORTH, ORIGIN};
+    /// use refunge::FungeVector;
+    ///
+    /// assert!(NORTH.is_cardinal() && EAST.is_cardinal());
+    /// assert!(!ORIGIN.is_cardinal());
+    /// assert!(!FungeVector(1, 1).is_cardinal() && !(EAST * 2).is_cardinal());
+    /// ```
+    pub fn is_cardinal(&self) -> bool {
+        self.0.unsigned_abs() + self.1.unsigned_abs() == 1
+    }
+    /// an arrow pointing the way this goes, one of the eight compass
+    /// directions by the signs of x and y, or a dot for none at all
+    ///
+    /// ```
+    /// use refunge::vector::directions::{EAST, NORTH, ORIGIN, SOUTH, WEST};
+    /// use refunge::FungeVector;
+    ///
+    /// let arrows: String = [EAST, WEST, NORTH, SOUTH].iter().map(|v| v.arrow()).collect();
+    /// assert_eq!(arrows, "→←↑↓");
+    /// assert_eq!(FungeVector(3, -1).arrow(), '↗');
+    /// assert_eq!(ORIGIN.arrow(), '·');
+    /// ```
+    pub fn arrow(&self) -> char {
+        match (self.0.signum(), self.1.signum()) {
+            (1, 0) => '→',
+            (-1, 0) => '←',
+            (0, -1) => '↑',
+            (0, 1) => '↓',
+            (1, -1) => '↗',
+            (-1, -1) => '↖',
+            (1, 1) => '↘',
+            (-1, 1) => '↙',
+            _ => '·',
+        }
+    }
+    /// [FungeVector::arrow] in plain ASCII, for --ascii
+    ///
+    /// ```
+    /// use refunge::vector::directions::{EAST, NORTH, ORIGIN, SOUTH, WEST};
+    /// use refunge::FungeVector;
+    ///
+    /// let arrows: String = [EAST, WEST, NORTH, SOUTH].iter().map(|v| v.ascii_arrow()).collect();
+    /// assert_eq!(arrows, "><^v");
+    /// assert_eq!(FungeVector(3, -1).ascii_arrow(), '/');
+    /// assert_eq!(ORIGIN.ascii_arrow(), '.');
+    /// ```
+    pub fn ascii_arrow(&self) -> char {
+        match (self.0.signum(), self.1.signum()) {
+            (1, 0) => '>',
+            (-1, 0) => '<',
+            (0, -1) => '^',
+            (0, 1) => 'v',
+            (1, -1) | (-1, 1) => '/',
+            (-1, -1) | (1, 1) => '\\',
+            _ => '.',
+        }
+    }
 }
 impl Distribution<FungeVector> for Standard {
     fn sample<R: Rng + ?Sized>(&self, rng: &mut R) -> FungeVector {
@@ -52,13 +135,40 @@ impl Default for FungeVector {
 impl Add<FungeVector> for FungeVector {
     type Output = FungeVector;
     fn add(self, rhs: FungeVector) -> FungeVector {
-        FungeVector(self.0 + rhs.0, self.1 + rhs.1)
+        FungeVector(self.0.wrapping_add(rhs.0), self.1.wrapping_add(rhs.1))
     }
 }
 impl AddAssign for FungeVector {
     fn add_assign(&mut self, rhs: Self) {
-        self.0 += rhs.0;
-        self.1 += rhs.1;
+        *self = *self + rhs;
+    }
+}
+impl Sub<FungeVector> for FungeVector {
+    type Output = FungeVector;
+    fn sub(self, rhs: FungeVector) -> FungeVector {
+        FungeVector(self.0.wrapping_sub(rhs.0), self.1.wrapping_sub(rhs.1))
+    }
+}
+impl SubAssign for FungeVector {
+    fn sub_assign(&mut self, rhs: Self) {
+        *self = *self - rhs;
+    }
+}
+impl Neg for FungeVector {
+    type Output = FungeVector;
+    fn neg(self) -> FungeVector {
+        FungeVector(self.0.wrapping_neg(), self.1.wrapping_neg())
+    }
+}
+impl Mul<i32> for FungeVector {
+    type Output = FungeVector;
+    fn mul(self, rhs: i32) -> FungeVector {
+        FungeVector(self.0.wrapping_mul(rhs), self.1.wrapping_mul(rhs))
+    }
+}
+impl Display for FungeVector {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "({},{})", self.0, self.1)
     }
 }
 
@@ -0,0 +1,35 @@
+//! `refunge bench --json` run as a binary, with its report parsed back
+
+use serde_json::Value;
+use std::process::Command;
+
+#[test]
+fn bench_json() {
+    let path = std::env::temp_dir().join("refunge-test-bench.bf");
+    std::fs::write(&path, "\"!iH\">:#,_@").unwrap();
+    let output = Command::new(env!("CARGO_BIN_EXE_refunge"))
+        .args(["bench", "--json", "--iterations", "3"])
+        .arg(&path)
+        .output()
+        .unwrap();
+    std::fs::remove_file(&path).unwrap_or(());
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+
+    let report: Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(report["program"], path.to_str().unwrap());
+    let iterations = report["iterations"].as_array().unwrap();
+    assert_eq!(iterations.len(), 3);
+    for iteration in iterations {
+        assert_eq!(iteration["ticks"], 28);
+        assert_eq!(iteration["ended"], true);
+        assert!(iteration["seconds"].as_f64().unwrap() >= 0.0);
+    }
+    assert_eq!(report["ticks"]["mean"], 28.0);
+    let seconds = &report["seconds"];
+    assert!(seconds["min"].as_f64() <= seconds["mean"].as_f64());
+    assert!(seconds["mean"].as_f64() <= seconds["max"].as_f64());
+}